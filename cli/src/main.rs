@@ -2,13 +2,18 @@
 //!
 //! A fast, parallel file/directory copy command powered by parcopy.
 
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
 use indicatif::{ProgressBar, ProgressStyle};
 use parcopy::{
-    CopyOptions, CopyStats, Error as ParcopyError, ErrorCode, OnConflict, copy_dir, copy_file,
-    is_no_space_error,
+    BackupMode, CopyEngine, CopyOptions, CopyPlan, CopyPolicy, CopySession, CopyStats, DedupMode,
+    Difference, Digest, EntryKind, Error as ParcopyError, ErrorCode, ItemOutcome, ItemReport,
+    Journal, Manifest, ManifestCheck, ManifestFormat, OnConflict, PlannedItemKind, RollbackReport,
+    RuntimeOptions, SourceChangePolicy, StaleTemp, TreeDiff, TreeDifference, clean_temp_files,
+    compare_trees, copy_dir, copy_file_with_stats, execute_plan, find_stale_temp_files,
+    is_no_space_error, move_dir, move_file, plan_copy, plan_item, verify_manifest,
 };
 use serde_json::{Value, json};
+use std::collections::HashMap;
 use std::fs::Metadata;
 use std::io;
 use std::path::{Path, PathBuf};
@@ -26,13 +31,131 @@ use thiserror::Error;
 ///   pcp SOURCE DEST
 ///   pcp SOURCE... DIRECTORY
 ///   pcp -t DIRECTORY SOURCE...
+///   pcp mv SOURCE... DEST
+///   pcp verify SOURCE DEST
 #[derive(Parser, Debug)]
-#[command(name = "pcp", version, about, long_about = None)]
+#[command(
+    name = "pcp",
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    disable_help_subcommand = true,
+    subcommand_negates_reqs = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    args: Args,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Move files and directories
+    ///
+    /// Renames sources on the same filesystem. Otherwise copies them and
+    /// removes each source entry once its copy is in place; re-run an
+    /// interrupted move to resume it.
+    Mv(Box<Args>),
+
+    /// Remove temp files that crashed runs left under a directory
+    ///
+    /// Finds parcopy's own temporary files, backups being put in place,
+    /// partial files of resumable copies, and staging directories of atomic
+    /// copies that were last modified at least --min-age ago and are owned by
+    /// the current user, and removes them. Files saved by transactional copies
+    /// are never removed.
+    Clean(CleanArgs),
+
+    /// Compare a copy with its source without copying anything
+    ///
+    /// Walks both trees as a recursive copy would and reports missing and
+    /// extra entries and entries whose kind, size, mtime, permissions,
+    /// symlink target, or (with --checksum) contents differ. Exits with an
+    /// error if the trees differ.
+    ///
+    /// With --manifest, checks a tree against a checksum manifest instead:
+    /// re-hashes every listed file and reports corrupted, missing, and
+    /// unlisted files.
+    Verify(VerifyArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct VerifyArgs {
+    /// Source directory, or with --manifest the tree to check (default: the
+    /// manifest's directory)
+    #[arg(required_unless_present = "manifest")]
+    source: Option<PathBuf>,
+
+    /// Copy of the source directory to check
+    #[arg(required_unless_present = "manifest", conflicts_with = "manifest")]
+    dest: Option<PathBuf>,
+
+    /// Check the tree against a SHA256SUMS or JSONL checksum manifest
+    #[arg(long, value_name = "FILE")]
+    manifest: Option<PathBuf>,
+
+    /// Also compare file contents by SHA-256 digest
+    #[arg(long)]
+    checksum: bool,
+
+    /// Do not compare modification times
+    #[arg(long)]
+    no_times: bool,
+
+    /// Do not compare permissions
+    #[arg(long)]
+    no_perms: bool,
+
+    /// Follow symlinks instead of comparing them as links
+    #[arg(short = 'L', long)]
+    follow_symlinks: bool,
+
+    /// Ignore symlinks that escape upward with ".." components, as
+    /// --block-escaping-symlinks does when copying
+    #[arg(long)]
+    block_escaping_symlinks: bool,
+
+    /// Maximum directory depth (default: unlimited)
+    #[arg(long)]
+    max_depth: Option<usize>,
+
+    /// Number of files compared in parallel
+    #[arg(short = 'j', long, default_value = "16")]
+    jobs: usize,
+
+    /// Output format
+    #[arg(long, value_enum, default_value = "human")]
+    output: OutputMode,
+}
+
+#[derive(clap::Args, Debug)]
+struct CleanArgs {
+    /// Directory to sweep
+    dir: PathBuf,
+
+    /// Only remove leftovers last modified at least this long ago (e.g. 30m,
+    /// 12h, 7d)
+    #[arg(long, value_name = "AGE", default_value = "24h", value_parser = parse_age)]
+    min_age: Duration,
+
+    /// List what would be removed without removing anything
+    #[arg(short = 'n', long = "plan", alias = "dry-run")]
+    plan: bool,
+
+    /// Output format
+    #[arg(long, value_enum, default_value = "human")]
+    output: OutputMode,
+}
+
+#[derive(clap::Args, Debug)]
 struct Args {
     /// Source file(s) or directory(ies)
     ///
     /// When multiple sources are given, the destination must be a directory.
-    #[arg(required = true)]
+    #[arg(required_unless_present = "apply")]
     sources: Vec<PathBuf>,
 
     /// Target directory (copy all sources into this directory)
@@ -45,9 +168,13 @@ struct Args {
     #[arg(short = 'r', long)]
     recursive: bool,
 
-    /// Number of parallel copy operations
-    #[arg(short = 'j', long, default_value = "16")]
-    jobs: usize,
+    /// Number of parallel copy operations, or `auto` / `auto:MAX` to tune it
+    ///
+    /// `auto` measures throughput and per-file latency while copying and
+    /// adjusts the number of files copied at once between 1 and MAX
+    /// [default MAX: 64].
+    #[arg(short = 'j', long, value_name = "N|auto[:MAX]", default_value = "16", value_parser = parse_jobs)]
+    jobs: Jobs,
 
     /// Conflict resolution strategy
     #[arg(short = 'c', long, value_enum)]
@@ -95,10 +222,184 @@ struct Args {
     #[arg(long)]
     max_depth: Option<usize>,
 
+    /// Back up destination files before replacing them
+    ///
+    /// CONTROL is `simple` (append a suffix, the default) or `numbered`
+    /// (keep every version as `NAME.~N~`).
+    #[arg(
+        long,
+        value_enum,
+        value_name = "CONTROL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "simple"
+    )]
+    backup: Option<BackupControl>,
+
+    /// Suffix for simple backups (default: "~"); implies --backup
+    #[arg(short = 'S', long, value_name = "SUFFIX")]
+    suffix: Option<String>,
+
+    /// Keep backups in DIR, mirroring the destination tree
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["backup", "suffix"])]
+    backup_dir: Option<PathBuf>,
+
+    /// Hard-link files that are unchanged in DIR instead of copying them
+    ///
+    /// DIR is an earlier copy of the same source. May be given several
+    /// times; earlier directories take precedence.
+    #[arg(long, value_name = "DIR")]
+    link_dest: Vec<PathBuf>,
+
+    /// Skip files that are unchanged in DIR, copying only the delta
+    ///
+    /// May be given several times.
+    #[arg(long, value_name = "DIR")]
+    compare_dest: Vec<PathBuf>,
+
+    /// Compare file contents instead of size and mtime against
+    /// --link-dest and --compare-dest
+    #[arg(long)]
+    checksum: bool,
+
+    /// Update replaced files of at least SIZE bytes block by block,
+    /// writing only the blocks that changed (e.g. 64M)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    delta_threshold: Option<u64>,
+
+    /// Block size for --delta-threshold updates [default: 1M]
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    delta_block_size: Option<u64>,
+
+    /// Copy files of at least SIZE bytes through a partial file that an
+    /// interrupted run resumes from (e.g. 1G)
+    ///
+    /// The partial file `.NAME.parcopy-partial` is kept next to the
+    /// destination if the copy is cancelled or fails, and is continued by
+    /// the next run as long as the source is unchanged.
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    resume_threshold: Option<u64>,
+
+    /// What to do when a source file changes while being copied
+    ///
+    /// POLICY is `warn` (keep the copy and warn, the default), `fail`, or
+    /// `retry[:N]` (copy again up to N times, default 3, then fail).
+    #[arg(long, value_name = "POLICY", value_parser = parse_source_change)]
+    on_source_change: Option<SourceChangePolicy>,
+
+    /// Build copied directories in a hidden staging directory and swap them
+    /// into place once complete, so readers never see a partial tree
+    ///
+    /// Applies to directory copies; `pcp mv` rejects it.
+    #[arg(long)]
+    atomic: bool,
+
+    /// If a directory copy fails, remove everything it created and restore
+    /// everything it replaced
+    ///
+    /// `pcp mv` rejects it.
+    #[arg(long)]
+    transactional: bool,
+
+    /// Read each copied file back and compare its SHA-256 digest with the
+    /// source's; mismatching copies are discarded and reported
+    #[arg(long)]
+    verify: bool,
+
+    /// Write a checksum manifest of every copied file to PATH
+    ///
+    /// Digests are SHA-256, computed while copying where possible. Paths
+    /// are relative to the manifest's directory, so a manifest placed in the
+    /// destination can be checked there with `sha256sum -c`.
+    #[arg(long, value_name = "PATH")]
+    manifest: Option<PathBuf>,
+
+    /// Format of the --manifest file
+    #[arg(long, value_name = "FORMAT", default_value = "sha256sums")]
+    manifest_format: ManifestFormatArg,
+
+    /// Link files identical to one already copied in this run to that copy
+    /// instead of writing them again
+    ///
+    /// Files whose size matches an earlier file are hashed (SHA-256) first.
+    /// Hard links share the earlier copy's permissions and timestamps;
+    /// reflinks fall back to a normal copy where unsupported.
+    #[arg(long, value_name = "MODE")]
+    dedup: Option<DedupArg>,
+
+    /// Record copied files in a completion journal at PATH, and skip files
+    /// it lists whose source is unchanged
+    ///
+    /// Journaled files are skipped without checking the destination, so
+    /// re-running an interrupted copy of many files does not stat each one.
+    /// Moves and --atomic runs ignore the journal; --transactional runs
+    /// only read it.
+    #[arg(long, value_name = "PATH")]
+    journal: Option<PathBuf>,
+
+    /// Before copying a directory, remove temp files that crashed runs left
+    /// in the destination and that are at least AGE old (e.g. 24h)
+    ///
+    /// See `pcp clean`. Partial files of resumable copies are kept for the
+    /// copy to resume from. Files that cannot be removed are reported as
+    /// warnings and do not fail the copy.
+    #[arg(long, value_name = "AGE", value_parser = parse_age)]
+    clean_stale_temps: Option<Duration>,
+
+    /// How file contents are copied
+    ///
+    /// io-uring submits file operations in batches through io_uring (Linux),
+    /// which helps with many small files on fast NVMe and NFS: small files
+    /// are stat-ed, opened, read, written, synced and renamed 32 at a time.
+    /// Falls back to standard where io_uring is unavailable.
+    #[arg(long, value_enum, value_name = "ENGINE", default_value = "standard")]
+    engine: EngineArg,
+
+    /// Split files of at least SIZE bytes into chunks that the -j workers
+    /// copy concurrently (e.g. 1G)
+    ///
+    /// Speeds up single huge files on parallel filesystems and NFS with
+    /// nconnect. --resume-threshold and --delta-threshold take precedence.
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    parallel_chunk_threshold: Option<u64>,
+
+    /// Chunk size for --parallel-chunk-threshold copies [default: 64M]
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    parallel_chunk_size: Option<u64>,
+
+    /// Limit file data to SIZE bytes per second across all jobs (e.g. 200M)
+    ///
+    /// Data is copied in small slices that each wait for their share, so
+    /// large files are limited too. Reflinks and hard links are not limited.
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    bwlimit: Option<u64>,
+
+    /// Limit metadata operations (stat, create, rename, chmod, utimes) to OPS per second
+    ///
+    /// Shared by all jobs; keeps parallel small-file copies from overloading
+    /// an NFS server's metadata path.
+    #[arg(long, value_name = "OPS")]
+    metadata_limit: Option<u64>,
+
     /// Print what would be copied without actually copying
     #[arg(short = 'n', long = "plan", alias = "dry-run")]
     plan: bool,
 
+    /// Plan the copy and save the plan as JSON to FILE instead of copying
+    ///
+    /// The plan records the absolute paths of every item with its planned
+    /// action and the copy options. Run it later with --apply.
+    #[arg(long, value_name = "FILE", conflicts_with = "apply")]
+    plan_out: Option<PathBuf>,
+
+    /// Execute a plan saved with --plan-out
+    ///
+    /// Copy options come from the plan; only --output and -v apply. Each
+    /// item is checked first and fails with `plan_drift`, without being
+    /// run, if its source or destination changed since it was planned.
+    #[arg(long, value_name = "FILE", conflicts_with = "sources")]
+    apply: Option<PathBuf>,
+
     /// Verbose output
     #[arg(short = 'v', long)]
     verbose: bool,
@@ -127,6 +428,14 @@ impl ConflictStrategy {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum BackupControl {
+    /// Append a suffix, replacing any older backup
+    Simple,
+    /// Keep every version as NAME.~N~
+    Numbered,
+}
+
 impl From<ConflictStrategy> for OnConflict {
     fn from(s: ConflictStrategy) -> Self {
         match s {
@@ -138,6 +447,124 @@ impl From<ConflictStrategy> for OnConflict {
     }
 }
 
+impl From<OnConflict> for ConflictStrategy {
+    fn from(policy: OnConflict) -> Self {
+        match policy {
+            OnConflict::Skip => ConflictStrategy::Skip,
+            OnConflict::Overwrite => ConflictStrategy::Overwrite,
+            OnConflict::Error => ConflictStrategy::Error,
+            OnConflict::UpdateNewer => ConflictStrategy::Update,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ManifestFormatArg {
+    /// `<sha256>  <path>` lines, as used by `sha256sum -c`
+    Sha256sums,
+    /// One JSON object per file with path, size, mtime, and sha256
+    Jsonl,
+}
+
+impl ManifestFormatArg {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Sha256sums => "sha256sums",
+            Self::Jsonl => "jsonl",
+        }
+    }
+}
+
+impl From<ManifestFormatArg> for ManifestFormat {
+    fn from(format: ManifestFormatArg) -> Self {
+        match format {
+            ManifestFormatArg::Sha256sums => ManifestFormat::Sha256Sums,
+            ManifestFormatArg::Jsonl => ManifestFormat::Jsonl,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum EngineArg {
+    /// copy_file_range on Linux, buffered reads and writes elsewhere
+    Standard,
+    /// Batched stats, opens, reads, writes, fsyncs and renames through io_uring (Linux)
+    IoUring,
+}
+
+impl EngineArg {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::IoUring => "io_uring",
+        }
+    }
+}
+
+impl From<EngineArg> for CopyEngine {
+    fn from(engine: EngineArg) -> Self {
+        match engine {
+            EngineArg::Standard => CopyEngine::Standard,
+            EngineArg::IoUring => CopyEngine::IoUring,
+        }
+    }
+}
+
+impl From<CopyEngine> for EngineArg {
+    fn from(engine: CopyEngine) -> Self {
+        match engine {
+            CopyEngine::Standard => EngineArg::Standard,
+            CopyEngine::IoUring => EngineArg::IoUring,
+        }
+    }
+}
+
+/// The engine that will copy file contents when `engine` is asked for.
+///
+/// Bandwidth-limited copies always use the standard engine.
+fn effective_engine(engine: CopyEngine, bwlimit: bool) -> EngineArg {
+    if engine.is_available() && !bwlimit {
+        engine.into()
+    } else {
+        EngineArg::Standard
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum DedupArg {
+    /// Hard-link duplicates to the earlier copy
+    Hardlink,
+    /// Reflink duplicates to the earlier copy (copy-on-write filesystems)
+    Reflink,
+}
+
+impl DedupArg {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Hardlink => "hardlink",
+            Self::Reflink => "reflink",
+        }
+    }
+}
+
+impl From<DedupArg> for DedupMode {
+    fn from(mode: DedupArg) -> Self {
+        match mode {
+            DedupArg::Hardlink => DedupMode::HardLink,
+            DedupArg::Reflink => DedupMode::Reflink,
+        }
+    }
+}
+
+impl From<DedupMode> for DedupArg {
+    fn from(mode: DedupMode) -> Self {
+        match mode {
+            DedupMode::HardLink => DedupArg::Hardlink,
+            DedupMode::Reflink => DedupArg::Reflink,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, ValueEnum)]
 enum ProfileName {
     Modern,
@@ -172,6 +599,21 @@ impl OutputMode {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operation {
+    Copy,
+    Move,
+}
+
+impl Operation {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Copy => "copy",
+            Self::Move => "move",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 struct ProfileDefaults {
     on_conflict: ConflictStrategy,
@@ -245,8 +687,63 @@ enum CliError {
     #[error("Failed to copy file: {path}: {source}")]
     CopyFile { path: PathBuf, source: ParcopyError },
 
+    #[error("Failed to move: {path}: {source}")]
+    Move { path: PathBuf, source: ParcopyError },
+
+    #[error("Failed to sweep {path}: {source}")]
+    Clean { path: PathBuf, source: ParcopyError },
+
+    #[error("Failed to remove {count} stale temp files")]
+    CleanFailed { count: usize },
+
+    #[error("Failed to compare {path}: {source}")]
+    Compare { path: PathBuf, source: ParcopyError },
+
+    #[error("Trees differ: {count} differences")]
+    TreesDiffer { count: usize },
+
+    #[error("Failed to write manifest: {path}: {source}")]
+    Manifest { path: PathBuf, source: io::Error },
+
+    #[error("Failed to use journal: {path}: {source}")]
+    Journal { path: PathBuf, source: io::Error },
+
+    #[error("Failed to check manifest {path}: {source}")]
+    CheckManifest { path: PathBuf, source: ParcopyError },
+
+    #[error("Tree does not match manifest: {count} problems")]
+    ManifestMismatch { count: usize },
+
+    #[error("Failed to read {count} listed files")]
+    ManifestUnreadable { count: usize },
+
     #[error("Failed to serialize JSON output: {source}")]
     JsonSerialize { source: serde_json::Error },
+
+    #[error("Plans cannot be saved or applied for moves")]
+    PlanMove,
+
+    #[error("{flag} is not supported for moves")]
+    UnsupportedMoveFlag { flag: &'static str },
+
+    #[error("Failed to plan copy to {path}: {source}")]
+    Plan { path: PathBuf, source: ParcopyError },
+
+    #[error("Failed to access plan file {path}: {source}")]
+    PlanFile { path: PathBuf, source: io::Error },
+
+    #[error("Invalid plan file {path}: {source}")]
+    InvalidPlan {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[error("{failed} of {total} planned items failed")]
+    PlanFailed {
+        failed: usize,
+        total: usize,
+        code: ErrorCode,
+    },
 }
 
 impl CliError {
@@ -256,25 +753,56 @@ impl CliError {
             | Self::TargetNotDirectory { .. }
             | Self::MissingDestinationOperand { .. }
             | Self::MultiSourceTargetNotDirectory { .. }
-            | Self::SourceHasNoFilename { .. } => ErrorCode::InvalidInput,
+            | Self::SourceHasNoFilename { .. }
+            | Self::PlanMove
+            | Self::UnsupportedMoveFlag { .. }
+            | Self::InvalidPlan { .. } => ErrorCode::InvalidInput,
             Self::SourceNotFound { .. } => ErrorCode::SourceNotFound,
-            Self::SourceMetadata { source, .. } | Self::CreateDirectory { source, .. } => {
-                io_error_code(source)
+            Self::SourceMetadata { source, .. }
+            | Self::CreateDirectory { source, .. }
+            | Self::Manifest { source, .. }
+            | Self::Journal { source, .. }
+            | Self::PlanFile { source, .. } => io_error_code(source),
+            Self::CopyDirectory { source, .. }
+            | Self::CopyFile { source, .. }
+            | Self::Move { source, .. }
+            | Self::Compare { source, .. }
+            | Self::Clean { source, .. }
+            | Self::CheckManifest { source, .. }
+            | Self::Plan { source, .. } => source.code(),
+            Self::TreesDiffer { .. } | Self::ManifestMismatch { .. } => {
+                ErrorCode::VerificationFailed
             }
-            Self::CopyDirectory { source, .. } | Self::CopyFile { source, .. } => source.code(),
+            Self::ManifestUnreadable { .. } | Self::CleanFailed { .. } => ErrorCode::IoError,
             Self::JsonSerialize { .. } => ErrorCode::Internal,
+            Self::PlanFailed { code, .. } => *code,
         }
     }
 }
 
 #[derive(Debug, Clone)]
 struct EffectiveConfig {
+    operation: Operation,
     profile: ProfileName,
     conflict_policy: ConflictStrategy,
     preserve_timestamps: bool,
     preserve_permissions: bool,
     fsync: bool,
     symlink_mode: &'static str,
+    backup_mode: &'static str,
+    source_change_policy: &'static str,
+    atomic_publish: bool,
+    transactional: bool,
+    verify: bool,
+    manifest_format: Option<ManifestFormatArg>,
+    dedup: Option<DedupArg>,
+    journal: bool,
+    clean_stale_temps: Option<Duration>,
+    engine: EngineArg,
+    auto_parallel: bool,
+    parallel_chunk_threshold: Option<u64>,
+    bwlimit: Option<u64>,
+    metadata_limit: Option<u64>,
     output_mode: OutputMode,
     verbose: bool,
 }
@@ -282,24 +810,76 @@ struct EffectiveConfig {
 impl EffectiveConfig {
     fn to_json_value(&self) -> Value {
         json!({
+            "operation": self.operation.as_str(),
             "profile": self.profile.as_str(),
             "conflict_policy": self.conflict_policy.as_str(),
             "preserve_timestamps": self.preserve_timestamps,
             "preserve_permissions": self.preserve_permissions,
             "fsync": self.fsync,
             "symlink_mode": self.symlink_mode,
+            "backup_mode": self.backup_mode,
+            "source_change_policy": self.source_change_policy,
+            "atomic_publish": self.atomic_publish,
+            "transactional": self.transactional,
+            "verify": self.verify,
+            "manifest_format": self.manifest_format.map(ManifestFormatArg::as_str),
+            "dedup": self.dedup.map(DedupArg::as_str),
+            "journal": self.journal,
+            "clean_stale_temps_secs": self.clean_stale_temps.map(|age| age.as_secs()),
+            "engine": self.engine.as_str(),
+            "auto_parallel": self.auto_parallel,
+            "parallel_chunk_threshold": self.parallel_chunk_threshold,
+            "bwlimit": self.bwlimit,
+            "metadata_limit": self.metadata_limit,
             "output_mode": self.output_mode.as_str(),
         })
     }
 
     fn print_human_stderr(&self) {
         eprintln!("Effective configuration:");
+        eprintln!("  operation: {}", self.operation.as_str());
         eprintln!("  profile: {}", self.profile.as_str());
         eprintln!("  conflict_policy: {}", self.conflict_policy.as_str());
         eprintln!("  preserve_timestamps: {}", self.preserve_timestamps);
         eprintln!("  preserve_permissions: {}", self.preserve_permissions);
         eprintln!("  fsync: {}", self.fsync);
         eprintln!("  symlink_mode: {}", self.symlink_mode);
+        eprintln!("  backup_mode: {}", self.backup_mode);
+        eprintln!("  source_change_policy: {}", self.source_change_policy);
+        eprintln!("  atomic_publish: {}", self.atomic_publish);
+        eprintln!("  transactional: {}", self.transactional);
+        eprintln!("  verify: {}", self.verify);
+        eprintln!(
+            "  manifest_format: {}",
+            self.manifest_format
+                .map_or("none", ManifestFormatArg::as_str)
+        );
+        eprintln!("  dedup: {}", self.dedup.map_or("none", DedupArg::as_str));
+        eprintln!("  journal: {}", self.journal);
+        eprintln!(
+            "  clean_stale_temps: {}",
+            self.clean_stale_temps
+                .map_or_else(|| "none".to_owned(), |age| format!("{}s", age.as_secs()))
+        );
+        eprintln!("  engine: {}", self.engine.as_str());
+        eprintln!("  auto_parallel: {}", self.auto_parallel);
+        eprintln!(
+            "  parallel_chunk_threshold: {}",
+            self.parallel_chunk_threshold
+                .map_or_else(|| "none".to_owned(), format_bytes)
+        );
+        eprintln!(
+            "  bwlimit: {}",
+            self.bwlimit.map_or_else(
+                || "none".to_owned(),
+                |rate| format!("{}/s", format_bytes(rate))
+            )
+        );
+        eprintln!(
+            "  metadata_limit: {}",
+            self.metadata_limit
+                .map_or_else(|| "none".to_owned(), |rate| format!("{rate} ops/s"))
+        );
         eprintln!("  output_mode: {}", self.output_mode.as_str());
     }
 }
@@ -343,6 +923,7 @@ struct ExecuteItem {
     bytes_copied: Option<u64>,
     error_code: Option<String>,
     error_message: Option<String>,
+    digest: Option<Digest>,
 }
 
 impl ExecuteItem {
@@ -354,6 +935,42 @@ impl ExecuteItem {
             bytes_copied: Some(bytes_copied),
             error_code: None,
             error_message: None,
+            digest: None,
+        }
+    }
+
+    fn from_report(item: &ItemReport) -> Self {
+        let outcome = match item.outcome {
+            ItemOutcome::Copied => "copied",
+            ItemOutcome::Linked => "linked",
+            ItemOutcome::Skipped => "skipped",
+            ItemOutcome::Failed => "failed",
+        };
+        Self {
+            source: display_path(&item.source),
+            destination: display_path(&item.destination),
+            outcome,
+            bytes_copied: item.bytes_copied,
+            error_code: item.error_code.map(|code| code.as_str().to_owned()),
+            error_message: item.error_message.clone(),
+            digest: item.digest,
+        }
+    }
+
+    fn with_digest(mut self, digest: Option<Digest>) -> Self {
+        self.digest = digest;
+        self
+    }
+
+    fn linked(source: &Path, destination: &Path) -> Self {
+        Self {
+            source: display_path(source),
+            destination: display_path(destination),
+            outcome: "linked",
+            bytes_copied: Some(0),
+            error_code: None,
+            error_message: None,
+            digest: None,
         }
     }
 
@@ -365,6 +982,7 @@ impl ExecuteItem {
             bytes_copied: None,
             error_code: None,
             error_message: None,
+            digest: None,
         }
     }
 
@@ -381,6 +999,7 @@ impl ExecuteItem {
             bytes_copied: None,
             error_code: Some(code.as_str().to_owned()),
             error_message: Some(message),
+            digest: None,
         }
     }
 
@@ -402,6 +1021,9 @@ impl ExecuteItem {
         if let Some(ref message) = self.error_message {
             obj.insert("error_message".to_owned(), Value::String(message.clone()));
         }
+        if let Some(digest) = self.digest {
+            obj.insert("sha256".to_owned(), Value::String(digest.to_string()));
+        }
 
         Value::Object(obj)
     }
@@ -429,6 +1051,9 @@ impl ExecuteItem {
         if let Some(ref message) = self.error_message {
             obj.insert("error_message".to_owned(), Value::String(message.clone()));
         }
+        if let Some(digest) = self.digest {
+            obj.insert("sha256".to_owned(), Value::String(digest.to_string()));
+        }
 
         Value::Object(obj)
     }
@@ -444,6 +1069,33 @@ fn io_error_code(error: &io::Error) -> ErrorCode {
     ErrorCode::IoError
 }
 
+fn rollback_report(error: &CliError) -> Option<&RollbackReport> {
+    match error {
+        CliError::CopyDirectory {
+            source: ParcopyError::RolledBack { report, .. },
+            ..
+        } => Some(report),
+        _ => None,
+    }
+}
+
+fn print_rollback_report(report: &RollbackReport) {
+    eprintln!(
+        "Rolled back {} created and {} replaced entries:",
+        report.removed.len(),
+        report.restored.len()
+    );
+    for path in &report.removed {
+        eprintln!("  removed {}", path.display());
+    }
+    for path in &report.restored {
+        eprintln!("  restored {}", path.display());
+    }
+    for (path, error) in &report.failed {
+        eprintln!("  failed to roll back {}: {}", path.display(), error);
+    }
+}
+
 fn cancellation_stats(error: &CliError) -> Option<(u64, u64)> {
     match error {
         CliError::CopyDirectory {
@@ -463,6 +1115,15 @@ fn cancellation_stats(error: &CliError) -> Option<(u64, u64)> {
                     ..
                 },
             ..
+        }
+        | CliError::Move {
+            source:
+                ParcopyError::Cancelled {
+                    files_copied,
+                    bytes_copied,
+                    ..
+                },
+            ..
         } => Some((*files_copied, *bytes_copied)),
         _ => None,
     }
@@ -487,12 +1148,40 @@ fn main() {
             std::process::exit(130);
         }
         eprintln!("error[{}]: {}", error.code(), error);
+        if let Some(report) = rollback_report(&error) {
+            print_rollback_report(report);
+        }
         std::process::exit(exit_code_for(error.code()));
     }
 }
 
 fn run() -> CliResult<()> {
-    let args = Args::parse();
+    let cli = Cli::parse();
+    let (args, operation) = match cli.command {
+        Some(Command::Mv(args)) => (*args, Operation::Move),
+        Some(Command::Clean(args)) => return run_clean(&args),
+        Some(Command::Verify(args)) => return run_verify(&args),
+        None => (cli.args, Operation::Copy),
+    };
+
+    if operation == Operation::Move {
+        if args.atomic {
+            return Err(CliError::UnsupportedMoveFlag { flag: "--atomic" });
+        }
+        if args.transactional {
+            return Err(CliError::UnsupportedMoveFlag {
+                flag: "--transactional",
+            });
+        }
+    }
+    if args.apply.is_some() || args.plan_out.is_some() {
+        if operation == Operation::Move {
+            return Err(CliError::PlanMove);
+        }
+        if let Some(plan_file) = &args.apply {
+            return run_apply(&args, plan_file);
+        }
+    }
 
     let (sources, dest) = resolve_sources_and_dest(&args)?;
 
@@ -500,7 +1189,8 @@ fn run() -> CliResult<()> {
     for src in sources {
         match src.metadata() {
             Ok(meta) => {
-                if meta.is_dir() && !args.recursive {
+                // Moving a directory needs no -r, like mv
+                if meta.is_dir() && !args.recursive && operation == Operation::Copy {
                     return Err(CliError::SourceIsDirectoryWithoutRecursive { path: src });
                 }
                 sources_with_meta.push((src, meta));
@@ -514,32 +1204,19 @@ fn run() -> CliResult<()> {
         }
     }
 
-    let (mut options, effective_config) = build_options_and_effective_config(&args);
-
-    let cancel = Arc::new(AtomicBool::new(false));
-    {
-        let cancel_clone = cancel.clone();
-        ctrlc::set_handler(move || {
-            if cancel_clone.load(Ordering::Relaxed) {
-                eprintln!("\nForce quit.");
-                std::process::exit(130);
-            }
-            cancel_clone.store(true, Ordering::Relaxed);
-            eprintln!(
-                "\nCancelling... finishing in-flight files. Press Ctrl+C again to abort immediately."
-            );
-        })
-        .ok();
-    }
-    options = options.with_cancel_token(cancel);
+    let (mut options, effective_config) = build_options_and_effective_config(&args, operation);
+    options = options.with_cancel_token(install_cancel_handler());
 
-    let plan_items = build_plan_items(&sources_with_meta, &dest, options.on_conflict)?;
+    let plan_items = build_plan_items(&sources_with_meta, &dest, &options)?;
 
     if effective_config.output_mode == OutputMode::Human && effective_config.verbose {
         effective_config.print_human_stderr();
     }
 
-    if args.plan {
+    if let Some(plan_file) = &args.plan_out {
+        save_plan(&sources_with_meta, &dest, &options, plan_file)?;
+    }
+    if args.plan || args.plan_out.is_some() {
         emit_plan_output(effective_config.output_mode, &effective_config, &plan_items)?;
         return Ok(());
     }
@@ -550,10 +1227,14 @@ fn run() -> CliResult<()> {
         if let Ok(style) = style {
             pb.set_style(style);
             pb.enable_steady_tick(Duration::from_millis(100));
+            let verb = match operation {
+                Operation::Copy => "Copying",
+                Operation::Move => "Moving",
+            };
             if sources_with_meta.len() == 1 {
-                pb.set_message(format!("Copying {}...", sources_with_meta[0].0.display()));
+                pb.set_message(format!("{verb} {}...", sources_with_meta[0].0.display()));
             } else {
-                pb.set_message(format!("Copying {} items...", sources_with_meta.len()));
+                pb.set_message(format!("{verb} {} items...", sources_with_meta.len()));
             }
             Some(pb)
         } else {
@@ -563,9 +1244,39 @@ fn run() -> CliResult<()> {
         None
     };
 
+    let manifest = args
+        .manifest
+        .as_ref()
+        .map(|path| Arc::new(Manifest::new(path, args.manifest_format.into())));
+    if let Some(manifest) = &manifest {
+        options = options.with_manifest(manifest.clone());
+    }
+    let journal = match &args.journal {
+        Some(path) => Some(Arc::new(Journal::open(path).map_err(|source| {
+            CliError::Journal {
+                path: path.clone(),
+                source,
+            }
+        })?)),
+        None => None,
+    };
+    if let Some(journal) = &journal {
+        options = options.with_journal(journal.clone());
+    }
+
     let start_time = Instant::now();
-    let copy_result = copy_sources(&sources_with_meta, &dest, &options);
+    let mut digests = HashMap::new();
+    // One pool serves every source rather than one per source
+    let session = CopySession::new(options.clone());
+    let copy_result = copy_sources(&sources_with_meta, &dest, &session, operation, &mut digests);
     let total_duration = start_time.elapsed();
+    // Keep what completed even if the run failed, so a re-run can skip it
+    let journal_synced = journal.as_ref().map_or(Ok(()), |journal| {
+        journal.sync().map_err(|source| CliError::Journal {
+            path: journal.path().to_path_buf(),
+            source,
+        })
+    });
 
     if let Some(pb) = pb {
         pb.finish_and_clear();
@@ -577,14 +1288,31 @@ fn run() -> CliResult<()> {
                 stats.duration = total_duration;
             }
 
+            if let Some(manifest) = &manifest {
+                manifest.finish().map_err(|source| CliError::Manifest {
+                    path: manifest.path().to_path_buf(),
+                    source,
+                })?;
+            }
+            journal_synced?;
+
+            if stats.files_changed > 0 {
+                eprintln!(
+                    "warning[{}]: {} files changed while being copied; their copies may be inconsistent",
+                    ErrorCode::SourceChanged,
+                    stats.files_changed
+                );
+            }
+
             if effective_config.output_mode == OutputMode::Human {
-                print_stats(&stats, effective_config.verbose);
+                print_stats(&stats, effective_config.verbose, operation);
             } else {
-                let execute_items = build_execute_items_from_success_plan(&plan_items);
+                let execute_items = build_execute_items_from_success_plan(&plan_items, &digests);
                 emit_execute_output(
                     effective_config.output_mode,
                     &effective_config,
                     &execute_items,
+                    &stats.parallel_levels,
                 )?;
             }
             Ok(())
@@ -598,6 +1326,7 @@ fn run() -> CliResult<()> {
                     effective_config.output_mode,
                     &effective_config,
                     &[failure_item],
+                    &[],
                 )?;
             }
             Err(error)
@@ -605,12 +1334,571 @@ fn run() -> CliResult<()> {
     }
 }
 
-fn resolve_sources_and_dest(args: &Args) -> CliResult<(Vec<PathBuf>, PathBuf)> {
-    if let Some(ref target_dir) = args.target_directory {
-        if !target_dir.is_dir() && target_dir.exists() {
-            return Err(CliError::TargetNotDirectory {
-                path: target_dir.clone(),
-            });
+/// Cancel the run on the first Ctrl+C and exit on the second.
+fn install_cancel_handler() -> Arc<AtomicBool> {
+    let cancel = Arc::new(AtomicBool::new(false));
+    let cancel_clone = cancel.clone();
+    ctrlc::set_handler(move || {
+        if cancel_clone.load(Ordering::Relaxed) {
+            eprintln!("\nForce quit.");
+            std::process::exit(130);
+        }
+        cancel_clone.store(true, Ordering::Relaxed);
+        eprintln!(
+            "\nCancelling... finishing in-flight files. Press Ctrl+C again to abort immediately."
+        );
+    })
+    .ok();
+    cancel
+}
+
+/// Plan copying the sources to `dest` and write the plan to `plan_file`.
+fn save_plan(
+    sources_with_meta: &[(PathBuf, Metadata)],
+    dest: &Path,
+    options: &CopyOptions,
+    plan_file: &Path,
+) -> CliResult<()> {
+    // Absolute paths, so the plan means the same wherever it is applied from
+    let absolute = |path: &Path| {
+        std::path::absolute(path).map_err(|source| CliError::SourceMetadata {
+            path: path.to_path_buf(),
+            source,
+        })
+    };
+    let sources = sources_with_meta
+        .iter()
+        .map(|(src, _)| absolute(src))
+        .collect::<CliResult<Vec<_>>>()?;
+    let dest = absolute(dest)?;
+
+    let plan = plan_copy(
+        sources,
+        dest.clone(),
+        CopyPolicy::from(options),
+        RuntimeOptions::from(options),
+    )
+    .map_err(|source| CliError::Plan { path: dest, source })?;
+    let mut text =
+        serde_json::to_string_pretty(&plan).map_err(|source| CliError::JsonSerialize { source })?;
+    text.push('\n');
+    std::fs::write(plan_file, text).map_err(|source| CliError::PlanFile {
+        path: plan_file.to_path_buf(),
+        source,
+    })
+}
+
+/// Execute the plan saved in `plan_file`.
+fn run_apply(args: &Args, plan_file: &Path) -> CliResult<()> {
+    let text = std::fs::read_to_string(plan_file).map_err(|source| CliError::PlanFile {
+        path: plan_file.to_path_buf(),
+        source,
+    })?;
+    let mut plan: CopyPlan =
+        serde_json::from_str(&text).map_err(|source| CliError::InvalidPlan {
+            path: plan_file.to_path_buf(),
+            source,
+        })?;
+    plan.runtime.cancel_token = Some(install_cancel_handler());
+
+    let effective_config = effective_config_for_plan(&plan, args);
+    if effective_config.output_mode == OutputMode::Human && effective_config.verbose {
+        effective_config.print_human_stderr();
+    }
+
+    let report = execute_plan(&plan, None);
+
+    if effective_config.output_mode == OutputMode::Human {
+        for item in &report.items {
+            if let (Some(code), Some(message)) = (item.error_code, &item.error_message) {
+                eprintln!("error[{code}]: {}: {message}", item.source.display());
+            }
+        }
+        print_stats(&report.stats, effective_config.verbose, Operation::Copy);
+    } else {
+        let execute_items: Vec<ExecuteItem> =
+            report.items.iter().map(ExecuteItem::from_report).collect();
+        emit_execute_output(
+            effective_config.output_mode,
+            &effective_config,
+            &execute_items,
+            &report.stats.parallel_levels,
+        )?;
+    }
+
+    let failed: Vec<&ItemReport> = report
+        .items
+        .iter()
+        .filter(|item| item.outcome == ItemOutcome::Failed)
+        .collect();
+    match failed.first() {
+        Some(first) => Err(CliError::PlanFailed {
+            failed: failed.len(),
+            total: report.items.len(),
+            code: first.error_code.unwrap_or(ErrorCode::Internal),
+        }),
+        None => Ok(()),
+    }
+}
+
+fn run_clean(args: &CleanArgs) -> CliResult<()> {
+    let clean_error = |source| CliError::Clean {
+        path: args.dir.clone(),
+        source,
+    };
+    let mut records = Vec::new();
+    let mut failed = 0;
+    let mut bytes = 0;
+    if args.plan {
+        for stale in find_stale_temp_files(&args.dir, args.min_age).map_err(clean_error)? {
+            bytes += stale.bytes;
+            records.push(stale_temp_to_json(&stale, "would_remove", None));
+        }
+    } else {
+        let report = clean_temp_files(&args.dir, args.min_age).map_err(clean_error)?;
+        bytes = report.bytes_removed;
+        for stale in &report.removed {
+            records.push(stale_temp_to_json(stale, "removed", None));
+        }
+        for (stale, e) in &report.failed {
+            records.push(stale_temp_to_json(stale, "failed", Some(e)));
+        }
+        failed = report.failed.len();
+    }
+    let summary = json!({
+        "entries": records.len() - failed,
+        "bytes": bytes,
+        "failed": failed,
+    });
+
+    match args.output {
+        OutputMode::Human => {
+            for record in &records {
+                let error = record["error"]
+                    .as_str()
+                    .map_or_else(String::new, |e| format!(": {e}"));
+                println!(
+                    "{:<12} {}{error}",
+                    record["status"].as_str().unwrap_or_default(),
+                    record["path"].as_str().unwrap_or_default(),
+                );
+            }
+            let verb = if args.plan { "Would remove" } else { "Removed" };
+            println!(
+                "{verb} {} stale temp files ({})",
+                records.len() - failed,
+                format_bytes(bytes)
+            );
+        }
+        OutputMode::Json => {
+            let payload = json!({
+                "schema_version": "1.0",
+                "mode": "clean",
+                "root": display_path(&args.dir),
+                "dry_run": args.plan,
+                "min_age_secs": args.min_age.as_secs(),
+                "summary": summary,
+                "entries": records,
+            });
+            print_json_value(&payload)?;
+        }
+        OutputMode::Jsonl => {
+            for mut record in records {
+                if let Value::Object(obj) = &mut record {
+                    obj.insert("schema_version".to_owned(), json!("1.0"));
+                    obj.insert("record_type".to_owned(), json!("stale_temp"));
+                }
+                print_json_value(&record)?;
+            }
+            let mut summary = summary;
+            if let Value::Object(obj) = &mut summary {
+                obj.insert("schema_version".to_owned(), json!("1.0"));
+                obj.insert("record_type".to_owned(), json!("clean_summary"));
+                obj.insert("dry_run".to_owned(), json!(args.plan));
+            }
+            print_json_value(&summary)?;
+        }
+    }
+
+    if failed > 0 {
+        Err(CliError::CleanFailed { count: failed })
+    } else {
+        Ok(())
+    }
+}
+
+fn stale_temp_to_json(stale: &StaleTemp, status: &str, error: Option<&io::Error>) -> Value {
+    json!({
+        "path": display_path(&stale.path),
+        "kind": stale.kind.as_str(),
+        "bytes": stale.bytes,
+        "age_secs": stale.age.as_secs(),
+        "status": status,
+        "error": error.map(ToString::to_string),
+    })
+}
+
+fn run_verify(args: &VerifyArgs) -> CliResult<()> {
+    let mut options = CopyOptions::default().with_parallel(args.jobs);
+    if args.checksum {
+        options = options.with_checksum();
+    }
+    if args.no_times {
+        options = options.without_timestamps();
+    }
+    if args.no_perms {
+        options.preserve_permissions = false;
+        options.preserve_dir_permissions = false;
+    }
+    if args.follow_symlinks {
+        options.preserve_symlinks = false;
+    }
+    if args.block_escaping_symlinks {
+        options = options.with_block_escaping_symlinks();
+    }
+    if let Some(depth) = args.max_depth {
+        options = options.with_max_depth(depth);
+    }
+    if args.output == OutputMode::Human {
+        options = options.with_warn_handler(|msg| {
+            eprintln!("warning: {}", msg);
+        });
+    }
+
+    if let Some(manifest) = &args.manifest {
+        return run_verify_manifest(args, manifest, &options);
+    }
+    // clap requires both operands without --manifest
+    let (Some(source), Some(dest)) = (&args.source, &args.dest) else {
+        return Err(CliError::MissingDestinationOperand {
+            operand: args.source.clone().unwrap_or_default(),
+        });
+    };
+
+    let diff = compare_trees(source, dest, &options).map_err(|e| CliError::Compare {
+        path: source.clone(),
+        source: e,
+    })?;
+
+    match args.output {
+        OutputMode::Human => {
+            for entry in &diff.differences {
+                println!(
+                    "{:<8} {}{}",
+                    difference_kind(&entry.difference),
+                    display_tree_path(&entry.path),
+                    describe_difference(&entry.difference)
+                );
+            }
+            let compared = format!(
+                "{} files, {} directories, {} symlinks compared",
+                diff.files_compared, diff.dirs_compared, diff.symlinks_compared
+            );
+            if diff.is_identical() {
+                println!("Trees are identical ({compared})");
+            } else {
+                println!("{} differences ({compared})", diff.differences.len());
+            }
+        }
+        OutputMode::Json => {
+            let payload = json!({
+                "schema_version": "1.0",
+                "mode": "verify",
+                "source": display_path(source),
+                "destination": display_path(dest),
+                "identical": diff.is_identical(),
+                "summary": verify_summary(&diff),
+                "differences": diff
+                    .differences
+                    .iter()
+                    .map(difference_to_json)
+                    .collect::<Vec<Value>>(),
+            });
+            print_json_value(&payload)?;
+        }
+        OutputMode::Jsonl => {
+            for entry in &diff.differences {
+                let mut record = difference_to_json(entry);
+                if let Value::Object(obj) = &mut record {
+                    obj.insert("schema_version".to_owned(), json!("1.0"));
+                    obj.insert("record_type".to_owned(), json!("difference"));
+                }
+                print_json_value(&record)?;
+            }
+            let mut summary = verify_summary(&diff);
+            if let Value::Object(obj) = &mut summary {
+                obj.insert("schema_version".to_owned(), json!("1.0"));
+                obj.insert("record_type".to_owned(), json!("verify_summary"));
+                obj.insert("identical".to_owned(), json!(diff.is_identical()));
+            }
+            print_json_value(&summary)?;
+        }
+    }
+
+    if diff.is_identical() {
+        Ok(())
+    } else {
+        Err(CliError::TreesDiffer {
+            count: diff.differences.len(),
+        })
+    }
+}
+
+fn run_verify_manifest(args: &VerifyArgs, manifest: &Path, options: &CopyOptions) -> CliResult<()> {
+    let root = match &args.source {
+        Some(root) => root.clone(),
+        None => match manifest.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        },
+    };
+
+    let check =
+        verify_manifest(manifest, &root, options).map_err(|source| CliError::CheckManifest {
+            path: manifest.to_path_buf(),
+            source,
+        })?;
+    let problems = manifest_problems(&check);
+
+    match args.output {
+        OutputMode::Human => {
+            for problem in &problems {
+                println!(
+                    "{:<10} {}{}",
+                    problem["kind"].as_str().unwrap_or_default(),
+                    problem["path"].as_str().unwrap_or_default(),
+                    describe_manifest_problem(problem)
+                );
+            }
+            let checked = format!(
+                "{} files, {} checked",
+                check.files_checked,
+                format_bytes(check.bytes_checked)
+            );
+            if check.is_clean() {
+                println!("Manifest matches ({checked})");
+            } else {
+                println!("{} problems ({checked})", problems.len());
+            }
+        }
+        OutputMode::Json => {
+            let payload = json!({
+                "schema_version": "1.0",
+                "mode": "verify_manifest",
+                "manifest": display_path(manifest),
+                "root": display_path(&root),
+                "clean": check.is_clean(),
+                "summary": manifest_check_summary(&check),
+                "problems": problems,
+            });
+            print_json_value(&payload)?;
+        }
+        OutputMode::Jsonl => {
+            for mut record in problems {
+                if let Value::Object(obj) = &mut record {
+                    obj.insert("schema_version".to_owned(), json!("1.0"));
+                    obj.insert("record_type".to_owned(), json!("manifest_problem"));
+                }
+                print_json_value(&record)?;
+            }
+            let mut summary = manifest_check_summary(&check);
+            if let Value::Object(obj) = &mut summary {
+                obj.insert("schema_version".to_owned(), json!("1.0"));
+                obj.insert("record_type".to_owned(), json!("verify_manifest_summary"));
+                obj.insert("clean".to_owned(), json!(check.is_clean()));
+            }
+            print_json_value(&summary)?;
+        }
+    }
+
+    let mismatches = check.corrupted.len() + check.missing.len() + check.unexpected.len();
+    if mismatches > 0 {
+        Err(CliError::ManifestMismatch {
+            count: mismatches + check.unreadable.len(),
+        })
+    } else if !check.unreadable.is_empty() {
+        Err(CliError::ManifestUnreadable {
+            count: check.unreadable.len(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// One JSON record per problem found, in the order they are reported.
+fn manifest_problems(check: &ManifestCheck) -> Vec<Value> {
+    let problem = |path: &Path, kind: &str| {
+        json!({
+            "path": display_path(path),
+            "kind": kind,
+            "expected": Value::Null,
+            "actual": Value::Null,
+            "error": Value::Null,
+        })
+    };
+    let mut problems = Vec::new();
+    for mismatch in &check.corrupted {
+        let mut record = problem(&mismatch.path, "corrupted");
+        record["expected"] = json!(mismatch.expected.to_string());
+        record["actual"] = json!(mismatch.actual.to_string());
+        problems.push(record);
+    }
+    problems.extend(check.missing.iter().map(|path| problem(path, "missing")));
+    problems.extend(
+        check
+            .unexpected
+            .iter()
+            .map(|path| problem(path, "unexpected")),
+    );
+    for (path, error) in &check.unreadable {
+        let mut record = problem(path, "unreadable");
+        record["error"] = json!(error.to_string());
+        problems.push(record);
+    }
+    problems
+}
+
+fn describe_manifest_problem(problem: &Value) -> String {
+    match (
+        problem["expected"].as_str(),
+        problem["actual"].as_str(),
+        problem["error"].as_str(),
+    ) {
+        (Some(expected), Some(actual), _) => {
+            format!(" (expected sha256 {expected}, read {actual})")
+        }
+        (_, _, Some(error)) => format!(" ({error})"),
+        _ => String::new(),
+    }
+}
+
+fn manifest_check_summary(check: &ManifestCheck) -> Value {
+    json!({
+        "files_checked": check.files_checked,
+        "bytes_checked": check.bytes_checked,
+        "corrupted": check.corrupted.len(),
+        "missing": check.missing.len(),
+        "unexpected": check.unexpected.len(),
+        "unreadable": check.unreadable.len(),
+        "duration_ms": u64::try_from(check.duration.as_millis()).unwrap_or(u64::MAX),
+    })
+}
+
+fn verify_summary(diff: &TreeDiff) -> Value {
+    json!({
+        "files_compared": diff.files_compared,
+        "dirs_compared": diff.dirs_compared,
+        "symlinks_compared": diff.symlinks_compared,
+        "differences": diff.differences.len(),
+        "duration_ms": u64::try_from(diff.duration.as_millis()).unwrap_or(u64::MAX),
+    })
+}
+
+fn difference_to_json(entry: &TreeDifference) -> Value {
+    let (source, destination) = match &entry.difference {
+        Difference::Missing | Difference::Extra => (Value::Null, Value::Null),
+        Difference::Kind { src, dst } => (json!(entry_kind_str(*src)), json!(entry_kind_str(*dst))),
+        Difference::Size { src, dst } => (json!(src), json!(dst)),
+        Difference::Mtime { src, dst } => (json!(unix_seconds(*src)), json!(unix_seconds(*dst))),
+        Difference::Content { src, dst } => (json!(src.to_string()), json!(dst.to_string())),
+        Difference::Permissions { src, dst } => {
+            (json!(permissions_str(src)), json!(permissions_str(dst)))
+        }
+        Difference::SymlinkTarget { src, dst } => {
+            (json!(display_path(src)), json!(display_path(dst)))
+        }
+    };
+    json!({
+        "path": display_tree_path(&entry.path),
+        "kind": difference_kind(&entry.difference),
+        "source": source,
+        "destination": destination,
+    })
+}
+
+fn difference_kind(difference: &Difference) -> &'static str {
+    match difference {
+        Difference::Missing => "missing",
+        Difference::Extra => "extra",
+        Difference::Kind { .. } => "type",
+        Difference::Size { .. } => "size",
+        Difference::Mtime { .. } => "mtime",
+        Difference::Content { .. } => "content",
+        Difference::Permissions { .. } => "perms",
+        Difference::SymlinkTarget { .. } => "symlink",
+    }
+}
+
+fn describe_difference(difference: &Difference) -> String {
+    match difference {
+        Difference::Missing => " (only in source)".to_owned(),
+        Difference::Extra => " (only in destination)".to_owned(),
+        Difference::Kind { src, dst } => {
+            format!(" ({} -> {})", entry_kind_str(*src), entry_kind_str(*dst))
+        }
+        Difference::Size { src, dst } => format!(" ({src} -> {dst} bytes)"),
+        Difference::Mtime { src, dst } => {
+            format!(" ({} -> {})", unix_seconds(*src), unix_seconds(*dst))
+        }
+        Difference::Content { src, dst } => format!(" (sha256 {src} -> {dst})"),
+        Difference::Permissions { src, dst } => {
+            format!(" ({} -> {})", permissions_str(src), permissions_str(dst))
+        }
+        Difference::SymlinkTarget { src, dst } => {
+            format!(" ({} -> {})", src.display(), dst.display())
+        }
+    }
+}
+
+fn entry_kind_str(kind: EntryKind) -> &'static str {
+    match kind {
+        EntryKind::File => "file",
+        EntryKind::Dir => "directory",
+        EntryKind::Symlink => "symlink",
+        EntryKind::Other => "other",
+    }
+}
+
+/// Permissions as octal mode bits on Unix, or `readonly`/`writable` elsewhere.
+fn permissions_str(permissions: &std::fs::Permissions) -> String {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        format!("{:o}", permissions.mode() & 0o7777)
+    }
+    #[cfg(not(unix))]
+    {
+        if permissions.readonly() {
+            "readonly".to_owned()
+        } else {
+            "writable".to_owned()
+        }
+    }
+}
+
+/// Seconds since the Unix epoch, negative for earlier times.
+fn unix_seconds(time: std::time::SystemTime) -> f64 {
+    match time.duration_since(std::time::UNIX_EPOCH) {
+        Ok(d) => d.as_secs_f64(),
+        Err(e) => -e.duration().as_secs_f64(),
+    }
+}
+
+/// Tree-relative path for display, `.` for the root.
+fn display_tree_path(path: &Path) -> String {
+    if path.as_os_str().is_empty() {
+        ".".to_owned()
+    } else {
+        display_path(path)
+    }
+}
+
+fn resolve_sources_and_dest(args: &Args) -> CliResult<(Vec<PathBuf>, PathBuf)> {
+    if let Some(ref target_dir) = args.target_directory {
+        if !target_dir.is_dir() && target_dir.exists() {
+            return Err(CliError::TargetNotDirectory {
+                path: target_dir.clone(),
+            });
         }
         Ok((args.sources.clone(), target_dir.clone()))
     } else if args.sources.len() == 1 {
@@ -633,7 +1921,10 @@ fn resolve_sources_and_dest(args: &Args) -> CliResult<(Vec<PathBuf>, PathBuf)> {
     }
 }
 
-fn build_options_and_effective_config(args: &Args) -> (CopyOptions, EffectiveConfig) {
+fn build_options_and_effective_config(
+    args: &Args,
+    operation: Operation,
+) -> (CopyOptions, EffectiveConfig) {
     let defaults = profile_defaults(args.profile);
 
     let conflict = args.on_conflict.unwrap_or(defaults.on_conflict);
@@ -663,9 +1954,11 @@ fn build_options_and_effective_config(args: &Args) -> (CopyOptions, EffectiveCon
         verbose = true;
     }
 
-    let mut options = CopyOptions::default()
-        .with_parallel(args.jobs)
-        .with_on_conflict(conflict.into());
+    let mut options = CopyOptions::default().with_on_conflict(conflict.into());
+    options = match args.jobs {
+        Jobs::Fixed(n) => options.with_parallel(n),
+        Jobs::Auto { max } => options.with_auto_parallel(max),
+    };
 
     if !preserve_timestamps {
         options = options.without_timestamps();
@@ -689,14 +1982,73 @@ fn build_options_and_effective_config(args: &Args) -> (CopyOptions, EffectiveCon
     if let Some(depth) = args.max_depth {
         options = options.with_max_depth(depth);
     }
+    let backup = backup_mode(args);
+    let backup_label = backup_label(backup.as_ref());
+    if let Some(mode) = backup {
+        options = options.with_backup(mode);
+    }
+    for dir in &args.link_dest {
+        options = options.with_link_dest(dir);
+    }
+    for dir in &args.compare_dest {
+        options = options.with_compare_dest(dir);
+    }
+    if args.checksum {
+        options = options.with_checksum();
+    }
+    if let Some(min_size) = args.delta_threshold {
+        options = options.with_delta(min_size);
+    }
+    let source_change = args.on_source_change.unwrap_or_default();
+    options = options.with_on_source_change(source_change);
+    if let Some(size) = args.delta_block_size {
+        options = options.with_delta_block_size(usize::try_from(size).unwrap_or(usize::MAX));
+    }
+    if let Some(min_size) = args.resume_threshold {
+        options = options.with_resume(min_size);
+    }
+    if args.atomic {
+        options = options.with_atomic_publish();
+    }
+    if args.transactional {
+        options = options.with_transactional();
+    }
+    if args.verify {
+        options = options.with_verify();
+    }
+    if let Some(mode) = args.dedup {
+        options = options.with_dedup(mode.into());
+    }
+    if let Some(min_age) = args.clean_stale_temps {
+        options = options.with_clean_stale_temps(min_age);
+    }
+    options = options.with_engine(args.engine.into());
+    if let Some(min_size) = args.parallel_chunk_threshold {
+        options = options.with_parallel_chunks(min_size);
+    }
+    if let Some(size) = args.parallel_chunk_size {
+        options = options.with_parallel_chunk_size(size);
+    }
+    if let Some(rate) = args.bwlimit {
+        options = options.with_bwlimit(rate);
+    }
+    if let Some(rate) = args.metadata_limit {
+        options = options.with_metadata_limit(rate);
+    }
 
     if verbose && args.output == OutputMode::Human {
         options = options.with_warn_handler(|msg| {
             eprintln!("warning: {}", msg);
         });
+        if !options.engine.is_available() {
+            eprintln!("warning: io_uring is unavailable, using the standard engine");
+        } else if options.engine == CopyEngine::IoUring && options.bwlimit.is_some() {
+            eprintln!("warning: --bwlimit copies with the standard engine");
+        }
     }
 
     let effective_config = EffectiveConfig {
+        operation,
         profile: args.profile,
         conflict_policy: conflict,
         preserve_timestamps,
@@ -707,6 +2059,23 @@ fn build_options_and_effective_config(args: &Args) -> (CopyOptions, EffectiveCon
         } else {
             "follow"
         },
+        backup_mode: backup_label,
+        source_change_policy: source_change_label(source_change),
+        atomic_publish: args.atomic,
+        transactional: args.transactional,
+        verify: args.verify,
+        manifest_format: args.manifest.as_ref().map(|_| args.manifest_format),
+        dedup: args.dedup,
+        journal: args.journal.is_some(),
+        clean_stale_temps: args.clean_stale_temps,
+        engine: effective_engine(options.engine, options.bwlimit.is_some()),
+        auto_parallel: options.auto_parallel,
+        parallel_chunk_threshold: options.parallel_chunk_threshold,
+        bwlimit: options.bwlimit.as_ref().map(|limiter| limiter.rate()),
+        metadata_limit: options
+            .metadata_limit
+            .as_ref()
+            .map(|limiter| limiter.rate()),
         output_mode: args.output,
         verbose,
     };
@@ -714,10 +2083,76 @@ fn build_options_and_effective_config(args: &Args) -> (CopyOptions, EffectiveCon
     (options, effective_config)
 }
 
+/// Effective configuration of a saved plan, as it will be applied.
+fn effective_config_for_plan(plan: &CopyPlan, args: &Args) -> EffectiveConfig {
+    let policy = &plan.policy;
+    EffectiveConfig {
+        operation: Operation::Copy,
+        profile: args.profile,
+        conflict_policy: policy.on_conflict.into(),
+        preserve_timestamps: policy.preserve_timestamps,
+        preserve_permissions: policy.preserve_permissions,
+        fsync: policy.fsync,
+        symlink_mode: if policy.preserve_symlinks {
+            "preserve"
+        } else {
+            "follow"
+        },
+        backup_mode: backup_label(policy.backup.as_ref()),
+        source_change_policy: source_change_label(policy.on_source_change),
+        atomic_publish: policy.atomic_publish,
+        transactional: policy.transactional,
+        verify: policy.verify,
+        manifest_format: None,
+        dedup: policy.dedup.map(DedupArg::from),
+        journal: false,
+        clean_stale_temps: plan.runtime.clean_stale_temps,
+        engine: effective_engine(plan.runtime.engine, plan.runtime.bwlimit.is_some()),
+        auto_parallel: plan.runtime.auto_parallel,
+        parallel_chunk_threshold: plan.runtime.parallel_chunk_threshold,
+        bwlimit: plan.runtime.bwlimit,
+        metadata_limit: plan.runtime.metadata_limit,
+        output_mode: args.output,
+        verbose: args.verbose,
+    }
+}
+
+fn backup_label(backup: Option<&BackupMode>) -> &'static str {
+    match backup {
+        None => "none",
+        Some(BackupMode::Simple { .. }) => "simple",
+        Some(BackupMode::Numbered) => "numbered",
+        Some(BackupMode::Directory(_)) => "directory",
+    }
+}
+
+fn source_change_label(policy: SourceChangePolicy) -> &'static str {
+    match policy {
+        SourceChangePolicy::Warn => "warn",
+        SourceChangePolicy::Fail => "fail",
+        SourceChangePolicy::Retry { .. } => "retry",
+    }
+}
+
+fn backup_mode(args: &Args) -> Option<BackupMode> {
+    if let Some(dir) = &args.backup_dir {
+        return Some(BackupMode::Directory(dir.clone()));
+    }
+    match (args.backup, &args.suffix) {
+        (Some(BackupControl::Numbered), _) => Some(BackupMode::Numbered),
+        (Some(BackupControl::Simple), suffix) | (None, suffix @ Some(_)) => {
+            Some(BackupMode::Simple {
+                suffix: suffix.clone().unwrap_or_else(|| "~".to_string()),
+            })
+        }
+        (None, None) => None,
+    }
+}
+
 fn build_plan_items(
     sources_with_meta: &[(PathBuf, Metadata)],
     dest: &PathBuf,
-    on_conflict: OnConflict,
+    options: &CopyOptions,
 ) -> CliResult<Vec<PlanItem>> {
     let mut items = Vec::with_capacity(sources_with_meta.len());
     let (dest_is_dir, mut dest_created) = match dest.metadata() {
@@ -725,6 +2160,7 @@ fn build_plan_items(
         Err(_) => (false, false),
     };
     let multi_source = sources_with_meta.len() > 1;
+    let policy = CopyPolicy::from(options);
 
     for (src, src_meta) in sources_with_meta {
         let actual_dest = resolve_actual_destination_path(
@@ -736,50 +2172,29 @@ fn build_plan_items(
             false,
         )?;
 
-        let (action, reason) = classify_plan_action(src_meta, &actual_dest, on_conflict);
+        let kind = if src_meta.is_dir() {
+            PlannedItemKind::Directory
+        } else {
+            PlannedItemKind::File
+        };
+        let planned =
+            plan_item(src, &actual_dest, dest, kind, &policy).map_err(|source| CliError::Plan {
+                path: dest.clone(),
+                source,
+            })?;
 
         items.push(PlanItem {
             source: src.clone(),
             destination: actual_dest,
             source_size: src_meta.len(),
-            action,
-            reason,
+            action: planned.action.as_str(),
+            reason: planned.reason.as_str(),
         });
     }
 
     Ok(items)
 }
 
-fn classify_plan_action(
-    source_meta: &Metadata,
-    destination: &Path,
-    on_conflict: OnConflict,
-) -> (&'static str, &'static str) {
-    if !destination.exists() {
-        return ("copy", "not_exists");
-    }
-
-    match on_conflict {
-        OnConflict::Skip => ("skip", "exists"),
-        OnConflict::Overwrite => ("overwrite", "exists"),
-        OnConflict::Error => ("error", "exists"),
-        OnConflict::UpdateNewer => {
-            let destination_meta = match destination.metadata() {
-                Ok(meta) => meta,
-                Err(_) => return ("copy", "exists"),
-            };
-
-            let src_mtime = source_meta.modified();
-            let dst_mtime = destination_meta.modified();
-
-            match (src_mtime, dst_mtime) {
-                (Ok(src), Ok(dst)) if src <= dst => ("skip", "newer_or_same"),
-                _ => ("copy", "exists"),
-            }
-        }
-    }
-}
-
 fn emit_plan_output(
     output_mode: OutputMode,
     effective_config: &EffectiveConfig,
@@ -824,12 +2239,19 @@ fn emit_plan_output(
     }
 }
 
-fn build_execute_items_from_success_plan(plan_items: &[PlanItem]) -> Vec<ExecuteItem> {
+fn build_execute_items_from_success_plan(
+    plan_items: &[PlanItem],
+    digests: &HashMap<PathBuf, Digest>,
+) -> Vec<ExecuteItem> {
     plan_items
         .iter()
-        .map(|item| match item.action {
-            "skip" => ExecuteItem::skipped(&item.source, &item.destination),
-            _ => ExecuteItem::copied(&item.source, &item.destination, item.source_size),
+        .map(|item| {
+            let execute_item = match item.action {
+                "skip" => ExecuteItem::skipped(&item.source, &item.destination),
+                "link" => ExecuteItem::linked(&item.source, &item.destination),
+                _ => ExecuteItem::copied(&item.source, &item.destination, item.source_size),
+            };
+            execute_item.with_digest(digests.get(&item.source).copied())
         })
         .collect()
 }
@@ -862,16 +2284,22 @@ fn emit_execute_output(
     output_mode: OutputMode,
     effective_config: &EffectiveConfig,
     items: &[ExecuteItem],
+    parallel_levels: &[usize],
 ) -> CliResult<()> {
     match output_mode {
         OutputMode::Human => Ok(()),
         OutputMode::Json => {
-            let payload = json!({
+            let mut payload = json!({
                 "schema_version": "1.0",
                 "mode": "execute",
                 "effective_config": effective_config.to_json_value(),
                 "items": items.iter().map(ExecuteItem::to_json_value).collect::<Vec<Value>>(),
             });
+            if !parallel_levels.is_empty() {
+                if let Value::Object(obj) = &mut payload {
+                    obj.insert("parallel_levels".to_owned(), json!(parallel_levels));
+                }
+            }
             print_json_value(&payload)
         }
         OutputMode::Jsonl => {
@@ -885,6 +2313,13 @@ fn emit_execute_output(
             for item in items {
                 print_json_value(&item.to_jsonl_record())?;
             }
+            if !parallel_levels.is_empty() {
+                print_json_value(&json!({
+                    "schema_version": "1.0",
+                    "record_type": "parallelism",
+                    "levels": parallel_levels,
+                }))?;
+            }
             Ok(())
         }
     }
@@ -919,10 +2354,39 @@ fn resolve_actual_destination_path(
     }
 }
 
+/// `options` with reference trees narrowed to the subtree `actual_dest` is in.
+///
+/// Reference trees mirror `dest`, while a directory copied into `dest` looks
+/// its files up relative to its own destination.
+fn narrow_references(
+    options: &CopyOptions,
+    dest: &Path,
+    actual_dest: &Path,
+) -> Option<CopyOptions> {
+    if options.link_dest.is_empty() && options.compare_dest.is_empty() {
+        return None;
+    }
+    let rel = actual_dest
+        .strip_prefix(dest)
+        .ok()
+        .filter(|rel| !rel.as_os_str().is_empty())?;
+    let mut options = options.clone();
+    for reference in options
+        .link_dest
+        .iter_mut()
+        .chain(&mut options.compare_dest)
+    {
+        *reference = reference.join(rel);
+    }
+    Some(options)
+}
+
 fn copy_sources(
     sources_with_meta: &[(PathBuf, Metadata)],
     dest: &PathBuf,
-    options: &CopyOptions,
+    session: &CopySession,
+    operation: Operation,
+    digests: &mut HashMap<PathBuf, Digest>,
 ) -> CliResult<CopyStats> {
     let mut total_stats = CopyStats::default();
     let start_time = Instant::now();
@@ -935,7 +2399,6 @@ fn copy_sources(
 
     for (src, src_meta) in sources_with_meta {
         let is_dir = src_meta.is_dir();
-        let file_size = src_meta.len();
 
         let actual_dest = resolve_actual_destination_path(
             src,
@@ -946,26 +2409,47 @@ fn copy_sources(
             true,
         )?;
 
-        if is_dir {
-            let stats =
-                copy_dir(src, &actual_dest, options).map_err(|source| CliError::CopyDirectory {
+        let narrowed = if is_dir {
+            narrow_references(session.options(), dest, &actual_dest)
+        } else {
+            None
+        };
+        let options = narrowed.as_ref().unwrap_or(session.options());
+
+        if operation == Operation::Move {
+            // A symlink to a directory is moved as the link itself
+            let stats = if is_dir && !src.is_symlink() {
+                session.install(|| move_dir(src, &actual_dest, options))
+            } else {
+                session.install(|| move_file(src, &actual_dest, options))
+            }
+            .map_err(|source| CliError::Move {
+                path: src.clone(),
+                source,
+            })?;
+            if let Some(digest) = stats.digest {
+                digests.insert(src.clone(), digest);
+            }
+            total_stats = merge_stats(total_stats, stats);
+        } else if is_dir {
+            let stats = session
+                .install(|| copy_dir(src, &actual_dest, options))
+                .map_err(|source| CliError::CopyDirectory {
                     path: src.clone(),
                     source,
                 })?;
             total_stats = merge_stats(total_stats, stats);
         } else {
-            let copied =
-                copy_file(src, &actual_dest, options).map_err(|source| CliError::CopyFile {
+            let stats = session
+                .install(|| copy_file_with_stats(src, &actual_dest, options))
+                .map_err(|source| CliError::CopyFile {
                     path: src.clone(),
                     source,
                 })?;
-
-            if copied {
-                total_stats.files_copied += 1;
-                total_stats.bytes_copied += file_size;
-            } else {
-                total_stats.files_skipped += 1;
+            if let Some(digest) = stats.digest {
+                digests.insert(src.clone(), digest);
             }
+            total_stats = merge_stats(total_stats, stats);
         }
     }
 
@@ -975,16 +2459,28 @@ fn copy_sources(
 
 fn merge_stats(mut a: CopyStats, b: CopyStats) -> CopyStats {
     a.files_copied += b.files_copied;
+    a.files_linked += b.files_linked;
     a.files_skipped += b.files_skipped;
     a.symlinks_copied += b.symlinks_copied;
     a.symlinks_skipped += b.symlinks_skipped;
     a.dirs_created += b.dirs_created;
     a.bytes_copied += b.bytes_copied;
+    a.bytes_written += b.bytes_written;
+    a.renamed += b.renamed;
+    a.files_changed += b.files_changed;
+    a.files_deduplicated += b.files_deduplicated;
+    a.bytes_deduplicated += b.bytes_deduplicated;
+    a.parallel_levels.extend(b.parallel_levels);
     a
 }
 
-fn print_stats(stats: &CopyStats, verbose: bool) {
-    if stats.files_copied == 0 && stats.symlinks_copied == 0 && stats.dirs_created == 0 {
+fn print_stats(stats: &CopyStats, verbose: bool, operation: Operation) {
+    if stats.files_copied == 0
+        && stats.files_linked == 0
+        && stats.renamed == 0
+        && stats.symlinks_copied == 0
+        && stats.dirs_created == 0
+    {
         if stats.files_skipped > 0 {
             println!(
                 "Nothing to copy ({} files already exist)",
@@ -999,12 +2495,36 @@ fn print_stats(stats: &CopyStats, verbose: bool) {
     let bytes_str = format_bytes(stats.bytes_copied);
 
     if verbose {
-        println!("Copy completed in {:?}", stats.duration);
+        match operation {
+            Operation::Copy => println!("Copy completed in {:?}", stats.duration),
+            Operation::Move => println!("Move completed in {:?}", stats.duration),
+        }
         println!("  Files copied:   {}", stats.files_copied);
+        println!("  Files linked:   {}", stats.files_linked);
+        if stats.renamed > 0 {
+            println!("  Renamed:        {}", stats.renamed);
+        }
         println!("  Files skipped:  {}", stats.files_skipped);
         println!("  Symlinks:       {}", stats.symlinks_copied);
         println!("  Directories:    {}", stats.dirs_created);
         println!("  Total size:     {}", bytes_str);
+        if stats.bytes_written != stats.bytes_copied {
+            println!("  Bytes written:  {}", format_bytes(stats.bytes_written));
+        }
+        if stats.files_deduplicated > 0 {
+            println!(
+                "  Deduplicated:   {} files ({} saved)",
+                stats.files_deduplicated,
+                format_bytes(stats.bytes_deduplicated)
+            );
+        }
+
+        if !stats.parallel_levels.is_empty() {
+            println!(
+                "  Parallelism:    {} (auto)",
+                format_levels(&stats.parallel_levels)
+            );
+        }
 
         if stats.duration.as_secs_f64() > 0.0 {
             let speed = stats.bytes_copied as f64 / stats.duration.as_secs_f64();
@@ -1015,6 +2535,15 @@ fn print_stats(stats: &CopyStats, verbose: bool) {
         if stats.files_copied > 0 {
             parts.push(format!("{} files", stats.files_copied));
         }
+        if stats.files_linked > 0 {
+            parts.push(format!("{} linked", stats.files_linked));
+        }
+        if stats.files_deduplicated > 0 {
+            parts.push(format!("{} deduplicated", stats.files_deduplicated));
+        }
+        if stats.renamed > 0 {
+            parts.push(format!("{} by rename", stats.renamed));
+        }
         if stats.symlinks_copied > 0 {
             parts.push(format!("{} symlinks", stats.symlinks_copied));
         }
@@ -1025,7 +2554,15 @@ fn print_stats(stats: &CopyStats, verbose: bool) {
         if parts.is_empty() {
             println!("Done");
         } else {
-            println!("Copied {} ({})", parts.join(", "), bytes_str);
+            let verb = match operation {
+                Operation::Copy => "Copied",
+                Operation::Move => "Moved",
+            };
+            if stats.renamed > 0 && stats.bytes_copied == 0 {
+                println!("{verb} {}", parts.join(", "));
+            } else {
+                println!("{verb} {} ({})", parts.join(", "), bytes_str);
+            }
         }
     }
 }
@@ -1041,6 +2578,103 @@ fn display_path(path: &Path) -> String {
     path.display().to_string()
 }
 
+/// Parse a byte size such as `4096`, `64K`, `1M` or `2G` (binary units).
+fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (digits, unit) = s.split_at(split);
+    let value: u64 = digits.parse().map_err(|_| format!("invalid size '{s}'"))?;
+    let shift = match unit.to_ascii_uppercase().trim_end_matches(['B', 'I']) {
+        "" => 0,
+        "K" => 10,
+        "M" => 20,
+        "G" => 30,
+        "T" => 40,
+        _ => {
+            return Err(format!(
+                "invalid size unit in '{s}' (expected K, M, G or T)"
+            ));
+        }
+    };
+    value
+        .checked_mul(1 << shift)
+        .ok_or_else(|| format!("size '{s}' is too large"))
+}
+
+/// Parse an age such as `90`, `90s`, `30m`, `12h` or `7d`.
+fn parse_age(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (digits, unit) = s.split_at(split);
+    let value: u64 = digits.parse().map_err(|_| format!("invalid age '{s}'"))?;
+    let unit_secs = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => {
+            return Err(format!("invalid age unit in '{s}' (expected s, m, h or d)"));
+        }
+    };
+    value
+        .checked_mul(unit_secs)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("age '{s}' is too large"))
+}
+
+/// Number of parallel copy operations given to `-j`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Jobs {
+    Fixed(usize),
+    Auto { max: usize },
+}
+
+/// Upper bound of `-j auto`
+const AUTO_JOBS_MAX: usize = 64;
+
+/// Parse a job count: `N`, `auto` or `auto:MAX`.
+fn parse_jobs(s: &str) -> Result<Jobs, String> {
+    match s.split_once(':') {
+        None if s == "auto" => Ok(Jobs::Auto { max: AUTO_JOBS_MAX }),
+        None => s
+            .parse()
+            .map(Jobs::Fixed)
+            .map_err(|_| format!("invalid job count '{s}' (expected N, auto or auto:MAX)")),
+        Some(("auto", max)) => max
+            .parse()
+            .map(|max| Jobs::Auto { max })
+            .map_err(|_| format!("invalid maximum job count '{max}'")),
+        _ => Err(format!(
+            "invalid job count '{s}' (expected N, auto or auto:MAX)"
+        )),
+    }
+}
+
+/// Levels chosen by `-j auto`, such as `8 -> 9 -> 6`.
+fn format_levels(levels: &[usize]) -> String {
+    levels
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+/// Parse a source change policy: `warn`, `fail`, `retry` or `retry:N`.
+fn parse_source_change(s: &str) -> Result<SourceChangePolicy, String> {
+    match s.split_once(':') {
+        None if s == "warn" => Ok(SourceChangePolicy::Warn),
+        None if s == "fail" => Ok(SourceChangePolicy::Fail),
+        None if s == "retry" => Ok(SourceChangePolicy::Retry { attempts: 3 }),
+        Some(("retry", n)) => n
+            .parse()
+            .map(|attempts| SourceChangePolicy::Retry { attempts })
+            .map_err(|_| format!("invalid retry count '{n}'")),
+        _ => Err(format!(
+            "invalid policy '{s}' (expected warn, fail, retry or retry:N)"
+        )),
+    }
+}
+
 fn format_bytes(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = KB * 1024;
@@ -101,6 +101,599 @@ fn test_overwrite_existing_file() {
     );
 }
 
+#[test]
+fn test_overwrite_with_simple_backup() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+
+    fs::write(src.path().join("app.conf"), "v2").unwrap();
+    fs::write(dst.path().join("app.conf"), "v1").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["-c", "overwrite", "--backup"])
+        .arg(src.path().join("app.conf"))
+        .arg(dst.path().join("app.conf"))
+        .assert()
+        .success();
+
+    assert_eq!(
+        fs::read_to_string(dst.path().join("app.conf")).unwrap(),
+        "v2"
+    );
+    assert_eq!(
+        fs::read_to_string(dst.path().join("app.conf~")).unwrap(),
+        "v1"
+    );
+}
+
+#[test]
+fn test_overwrite_with_custom_suffix() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+
+    fs::write(src.path().join("app.conf"), "v2").unwrap();
+    fs::write(dst.path().join("app.conf"), "v1").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["-c", "overwrite", "-S", ".bak"])
+        .arg(src.path().join("app.conf"))
+        .arg(dst.path().join("app.conf"))
+        .assert()
+        .success();
+
+    assert_eq!(
+        fs::read_to_string(dst.path().join("app.conf.bak")).unwrap(),
+        "v1"
+    );
+}
+
+#[test]
+fn test_overwrite_with_delta_updates() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+
+    let old = vec![7u8; 256 * 1024];
+    let mut new = old.clone();
+    new[100_000] = 8;
+    fs::write(src.path().join("vm.img"), &new).unwrap();
+    fs::write(dst.path().join("vm.img"), &old).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    let assert = cmd
+        .args(["-c", "overwrite", "-v", "--delta-threshold", "64K"])
+        .args(["--delta-block-size", "4K"])
+        .arg(src.path().join("vm.img"))
+        .arg(dst.path().join("vm.img"))
+        .assert()
+        .success();
+    // Delta updates need the old file cloned; otherwise it is copied in full
+    if supports_clone(dst.path()) {
+        assert.stdout(predicate::str::contains("Bytes written:  4.00 KB"));
+    } else {
+        assert.stdout(predicate::str::contains("Bytes written").not());
+    }
+
+    assert_eq!(fs::read(dst.path().join("vm.img")).unwrap(), new);
+}
+
+/// Whether files in `dir` can be cloned with `FICLONE`.
+fn supports_clone(dir: &std::path::Path) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::io::AsRawFd;
+        let base = dir.join(".clone-probe-base");
+        fs::write(&base, "probe").unwrap();
+        let base = fs::File::open(base).unwrap();
+        let out = fs::File::create(dir.join(".clone-probe-out")).unwrap();
+        // SAFETY: both descriptors are open for the duration of the call
+        let result = unsafe { libc::ioctl(out.as_raw_fd(), libc::FICLONE, base.as_raw_fd()) };
+        let _ = fs::remove_file(dir.join(".clone-probe-base"));
+        let _ = fs::remove_file(dir.join(".clone-probe-out"));
+        result == 0
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = dir;
+        false
+    }
+}
+
+#[test]
+fn test_delta_threshold_rejects_bad_size() {
+    let src = TempDir::new().unwrap();
+    fs::write(src.path().join("a"), "a").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["--delta-threshold", "12Q"])
+        .arg(src.path().join("a"))
+        .arg(src.path().join("b"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid size unit"));
+}
+
+#[test]
+fn test_transactional_rolls_back_failed_copy() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+
+    fs::create_dir(src.path().join("data")).unwrap();
+    fs::write(src.path().join("data/a.txt"), "a").unwrap();
+    fs::write(src.path().join("data/b.txt"), "b").unwrap();
+    fs::create_dir(dst.path().join("data")).unwrap();
+    fs::write(dst.path().join("data/b.txt"), "existing").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["-r", "-c", "error", "--transactional"])
+        .arg(src.path().join("data"))
+        .arg(dst.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Rolled back 1 created and 0 replaced entries",
+        ))
+        .stderr(predicate::str::contains("removed"));
+
+    assert!(!dst.path().join("data/a.txt").exists());
+    assert_eq!(
+        fs::read_to_string(dst.path().join("data/b.txt")).unwrap(),
+        "existing"
+    );
+}
+
+#[test]
+fn test_atomic_publish_replaces_tree() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let site = dst.path().join("site");
+
+    fs::create_dir(src.path().join("site")).unwrap();
+    fs::write(src.path().join("site/index.html"), "v2").unwrap();
+    fs::create_dir(&site).unwrap();
+    fs::write(site.join("index.html"), "v1").unwrap();
+    fs::write(site.join("old.html"), "old").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["-r", "-c", "overwrite", "--atomic"])
+        .arg(src.path().join("site"))
+        .arg(dst.path())
+        .assert()
+        .success();
+
+    assert_eq!(fs::read_to_string(site.join("index.html")).unwrap(), "v2");
+    assert_eq!(fs::read_to_string(site.join("old.html")).unwrap(), "old");
+    let leftovers: Vec<_> = fs::read_dir(dst.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .filter(|name| name != "site")
+        .collect();
+    assert!(leftovers.is_empty(), "staging left behind: {leftovers:?}");
+}
+
+#[test]
+fn test_on_source_change_policy() {
+    let src = TempDir::new().unwrap();
+    fs::write(src.path().join("a"), "a").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["--on-source-change", "retry:2", "--output", "json"])
+        .arg(src.path().join("a"))
+        .arg(src.path().join("b"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#""source_change_policy":"retry""#,
+        ));
+    assert_eq!(fs::read_to_string(src.path().join("b")).unwrap(), "a");
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["--on-source-change", "retry:x"])
+        .arg(src.path().join("a"))
+        .arg(src.path().join("c"))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid retry count"));
+}
+
+#[test]
+fn test_verify_copies_directory() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+
+    fs::create_dir(src.path().join("data")).unwrap();
+    fs::write(src.path().join("data/a.txt"), "a").unwrap();
+    fs::write(src.path().join("data/b.txt"), "b".repeat(100_000)).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["-r", "--verify", "--output", "json"])
+        .arg(src.path().join("data"))
+        .arg(dst.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""verify":true"#));
+
+    assert_eq!(
+        fs::read_to_string(dst.path().join("data/b.txt")).unwrap(),
+        "b".repeat(100_000)
+    );
+}
+
+#[test]
+fn test_execute_items_include_digests_when_hashing() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+
+    fs::write(src.path().join("a.txt"), "abc").unwrap();
+    fs::create_dir(src.path().join("dir")).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    let output = cmd
+        .args(["-r", "--verify", "--output", "jsonl"])
+        .arg(src.path().join("a.txt"))
+        .arg(src.path().join("dir"))
+        .arg(dst.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let items: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .filter(|record: &serde_json::Value| record["record_type"] == "execute_item")
+        .collect();
+    assert_eq!(items.len(), 2);
+    assert_eq!(
+        items[0]["sha256"],
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert!(items[1].get("sha256").is_none());
+}
+
+#[test]
+fn test_dedup_links_duplicate_files() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+
+    fs::create_dir(src.path().join("data")).unwrap();
+    for name in ["a.bin", "b.bin", "c.bin"] {
+        fs::write(src.path().join("data").join(name), "same shard").unwrap();
+    }
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["-r", "-j", "1", "--dedup", "hardlink"])
+        .arg(src.path().join("data"))
+        .arg(dst.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Copied 3 files, 2 deduplicated"));
+
+    for name in ["a.bin", "b.bin", "c.bin"] {
+        assert_eq!(
+            fs::read_to_string(dst.path().join("data").join(name)).unwrap(),
+            "same shard"
+        );
+    }
+}
+
+#[test]
+fn test_resume_threshold_copies_through_partial_file() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let data = vec![42u8; 4096];
+    fs::write(src.path().join("disk.img"), &data).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["--resume-threshold", "1K"])
+        .arg(src.path().join("disk.img"))
+        .arg(dst.path())
+        .assert()
+        .success();
+
+    assert_eq!(fs::read(dst.path().join("disk.img")).unwrap(), data);
+    // The partial file and its sidecar are gone once the copy is in place
+    assert_eq!(fs::read_dir(dst.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn test_parallel_chunk_copy_of_large_file() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let data: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+    fs::write(src.path().join("disk.img"), &data).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args([
+        "--parallel-chunk-threshold",
+        "64K",
+        "--parallel-chunk-size",
+        "16K",
+        "--verify",
+    ])
+    .arg(src.path().join("disk.img"))
+    .arg(dst.path())
+    .assert()
+    .success();
+
+    assert_eq!(fs::read(dst.path().join("disk.img")).unwrap(), data);
+}
+
+#[test]
+fn test_bwlimit_paces_copy() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let data: Vec<u8> = (0..400_000u32).map(|i| (i % 251) as u8).collect();
+    fs::write(src.path().join("disk.img"), &data).unwrap();
+
+    let start = std::time::Instant::now();
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["--bwlimit", "1M"])
+        .arg(src.path().join("disk.img"))
+        .arg(dst.path())
+        .assert()
+        .success();
+
+    // About 0.3s once the first 100 KiB burst is spent
+    assert!(start.elapsed() >= std::time::Duration::from_millis(250));
+    assert_eq!(fs::read(dst.path().join("disk.img")).unwrap(), data);
+}
+
+#[test]
+fn test_metadata_limit_paces_small_files() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    fs::create_dir(src.path().join("data")).unwrap();
+    for i in 0..10 {
+        fs::write(src.path().join(format!("data/{i}.txt")), "x").unwrap();
+    }
+
+    let start = std::time::Instant::now();
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["-r", "--metadata-limit", "100"])
+        .arg(src.path().join("data"))
+        .arg(dst.path())
+        .assert()
+        .success();
+
+    // Over 60 operations at 100 per second, after a burst of 10
+    assert!(start.elapsed() >= std::time::Duration::from_millis(350));
+    assert_eq!(fs::read(dst.path().join("data/9.txt")).unwrap(), b"x");
+}
+
+#[test]
+fn test_journal_skips_completed_files_on_rerun() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let journal = dst.path().join("copy.journal");
+
+    fs::create_dir(src.path().join("data")).unwrap();
+    fs::write(src.path().join("data/a.txt"), "a").unwrap();
+    fs::write(src.path().join("data/b.txt"), "b").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["-r", "--journal"])
+        .arg(&journal)
+        .arg(src.path().join("data"))
+        .arg(dst.path())
+        .assert()
+        .success();
+
+    fs::write(src.path().join("data/b.txt"), "b2").unwrap();
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["-r", "-v", "-c", "overwrite", "--journal"])
+        .arg(&journal)
+        .arg(src.path().join("data"))
+        .arg(dst.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Files copied:   1"))
+        .stdout(predicate::str::contains("Files skipped:  1"));
+
+    assert_eq!(
+        fs::read_to_string(dst.path().join("data/b.txt")).unwrap(),
+        "b2"
+    );
+}
+
+#[test]
+fn test_manifest_lists_copied_files() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let out = dst.path().join("out");
+
+    fs::create_dir_all(src.path().join("data/sub")).unwrap();
+    fs::write(src.path().join("data/a.txt"), "abc").unwrap();
+    fs::write(src.path().join("data/sub/b.txt"), "abc").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["-r", "--manifest"])
+        .arg(out.join("SHA256SUMS"))
+        .arg(src.path().join("data"))
+        .arg(&out)
+        .assert()
+        .success();
+
+    let digest = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+    assert_eq!(
+        fs::read_to_string(out.join("SHA256SUMS")).unwrap(),
+        format!("{digest}  a.txt\n{digest}  sub/b.txt\n")
+    );
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args([
+        "-r",
+        "--manifest-format",
+        "jsonl",
+        "--output",
+        "json",
+        "--manifest",
+    ])
+    .arg(dst.path().join("MANIFEST.jsonl"))
+    .arg(src.path().join("data"))
+    .arg(dst.path().join("again"))
+    .assert()
+    .success()
+    .stdout(predicate::str::contains(r#""manifest_format":"jsonl""#));
+
+    let jsonl = fs::read_to_string(dst.path().join("MANIFEST.jsonl")).unwrap();
+    assert_eq!(jsonl.lines().count(), 2);
+    assert!(jsonl.starts_with(r#"{"path":"again/a.txt","size":3,"mtime":"#));
+    assert!(jsonl.contains(&format!(r#""sha256":"{digest}""#)));
+}
+
+#[test]
+fn test_verify_compares_trees() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let copy = dst.path().join("data");
+
+    fs::create_dir_all(src.path().join("data/sub")).unwrap();
+    fs::write(src.path().join("data/a.txt"), "a").unwrap();
+    fs::write(src.path().join("data/sub/b.txt"), "b").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.arg("-r")
+        .arg(src.path().join("data"))
+        .arg(&copy)
+        .assert()
+        .success();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["verify", "--checksum"])
+        .arg(src.path().join("data"))
+        .arg(&copy)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Trees are identical"));
+
+    fs::remove_file(copy.join("sub/b.txt")).unwrap();
+    fs::write(copy.join("extra.txt"), "x").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["verify", "--output", "json"])
+        .arg(src.path().join("data"))
+        .arg(&copy)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            r#"{"destination":null,"kind":"extra","path":"extra.txt","source":null}"#,
+        ))
+        .stdout(predicate::str::contains(
+            r#""kind":"missing","path":"sub/b.txt""#,
+        ))
+        .stderr(predicate::str::contains("error[verification_failed]"));
+}
+
+#[test]
+fn test_verify_against_manifest() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let out = dst.path().join("out");
+
+    fs::create_dir_all(src.path().join("data/sub")).unwrap();
+    fs::write(src.path().join("data/a.txt"), "abc").unwrap();
+    fs::write(src.path().join("data/sub/b.txt"), "abc").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["-r", "--manifest"])
+        .arg(out.join("SHA256SUMS"))
+        .arg(src.path().join("data"))
+        .arg(&out)
+        .assert()
+        .success();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["verify", "--manifest"])
+        .arg(out.join("SHA256SUMS"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Manifest matches (2 files"));
+
+    fs::write(out.join("a.txt"), "abd").unwrap();
+    fs::remove_file(out.join("sub/b.txt")).unwrap();
+    fs::write(out.join("new.txt"), "new").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["verify", "--output", "jsonl", "--manifest"])
+        .arg(out.join("SHA256SUMS"))
+        .arg(&out)
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            r#""kind":"corrupted","path":"a.txt""#,
+        ))
+        .stdout(predicate::str::contains(
+            r#""kind":"missing","path":"sub/b.txt""#,
+        ))
+        .stdout(predicate::str::contains(
+            r#""kind":"unexpected","path":"new.txt""#,
+        ))
+        .stderr(predicate::str::contains("error[verification_failed]"));
+}
+
+#[test]
+fn test_overwrite_with_numbered_backups() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+
+    fs::write(dst.path().join("app.conf"), "v1").unwrap();
+
+    for version in ["v2", "v3"] {
+        fs::write(src.path().join("app.conf"), version).unwrap();
+        let mut cmd = cargo_bin_cmd!("pcp");
+        cmd.args(["-c", "overwrite", "--backup=numbered"])
+            .arg(src.path().join("app.conf"))
+            .arg(dst.path().join("app.conf"))
+            .assert()
+            .success();
+    }
+
+    assert_eq!(
+        fs::read_to_string(dst.path().join("app.conf")).unwrap(),
+        "v3"
+    );
+    assert_eq!(
+        fs::read_to_string(dst.path().join("app.conf.~1~")).unwrap(),
+        "v1"
+    );
+    assert_eq!(
+        fs::read_to_string(dst.path().join("app.conf.~2~")).unwrap(),
+        "v2"
+    );
+}
+
+#[test]
+fn test_recursive_overwrite_with_backup_dir() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let backups = TempDir::new().unwrap();
+
+    fs::create_dir_all(src.path().join("site/etc")).unwrap();
+    fs::create_dir_all(dst.path().join("site/etc")).unwrap();
+    fs::write(src.path().join("site/etc/app.conf"), "new").unwrap();
+    fs::write(src.path().join("site/etc/added.conf"), "added").unwrap();
+    fs::write(dst.path().join("site/etc/app.conf"), "old").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["-r", "-c", "overwrite", "--backup-dir"])
+        .arg(backups.path())
+        .arg(src.path().join("site"))
+        .arg(dst.path())
+        .assert()
+        .success();
+
+    assert_eq!(
+        fs::read_to_string(dst.path().join("site/etc/app.conf")).unwrap(),
+        "new"
+    );
+    assert_eq!(
+        fs::read_to_string(backups.path().join("etc/app.conf")).unwrap(),
+        "old"
+    );
+    // New files have nothing to back up
+    assert!(!backups.path().join("etc/added.conf").exists());
+}
+
 #[test]
 fn test_skip_existing_file() {
     let src = TempDir::new().unwrap();
@@ -171,3 +764,147 @@ fn test_version_flag() {
         .success()
         .stdout(predicate::str::contains("pcp"));
 }
+
+#[test]
+fn test_mv_renames_directory() {
+    let tmp = TempDir::new().unwrap();
+    let src = tmp.path().join("project");
+    fs::create_dir_all(src.join("sub")).unwrap();
+    fs::write(src.join("sub/main.rs"), "fn main() {}").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.arg("mv")
+        .arg(&src)
+        .arg(tmp.path().join("moved"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Moved 1 by rename"));
+
+    assert!(!src.exists());
+    assert_eq!(
+        fs::read_to_string(tmp.path().join("moved/sub/main.rs")).unwrap(),
+        "fn main() {}"
+    );
+}
+
+#[test]
+fn test_mv_merges_into_existing_directory() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let tree = src.path().join("site");
+    fs::create_dir_all(&tree).unwrap();
+    fs::create_dir_all(dst.path().join("site")).unwrap();
+    fs::write(tree.join("index.html"), "new page").unwrap();
+    fs::write(tree.join("kept.html"), "source version").unwrap();
+    fs::write(dst.path().join("site/kept.html"), "dest").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.arg("mv").arg(&tree).arg(dst.path()).assert().success();
+
+    assert_eq!(
+        fs::read_to_string(dst.path().join("site/index.html")).unwrap(),
+        "new page"
+    );
+    assert!(!tree.join("index.html").exists());
+    // Skipped on conflict, so the source copy stays
+    assert_eq!(
+        fs::read_to_string(tree.join("kept.html")).unwrap(),
+        "source version"
+    );
+}
+
+#[test]
+fn test_mv_into_itself_is_rejected() {
+    let tmp = TempDir::new().unwrap();
+    let src = tmp.path().join("data");
+    fs::create_dir_all(&src).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.arg("mv")
+        .arg(&src)
+        .arg(src.join("inner"))
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("error[invalid_input]"));
+
+    assert!(src.exists());
+}
+
+#[test]
+fn test_mv_rejects_atomic_and_transactional() {
+    let tmp = TempDir::new().unwrap();
+    let src = tmp.path().join("data");
+    fs::create_dir_all(&src).unwrap();
+    fs::write(src.join("f.txt"), "data").unwrap();
+
+    for flag in ["--atomic", "--transactional"] {
+        let mut cmd = cargo_bin_cmd!("pcp");
+        cmd.arg("mv")
+            .arg(flag)
+            .arg(&src)
+            .arg(tmp.path().join("moved"))
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("error[invalid_input]"))
+            .stderr(predicate::str::contains(flag));
+    }
+
+    assert!(src.join("f.txt").exists());
+    assert!(!tmp.path().join("moved").exists());
+}
+
+#[test]
+fn test_clean_removes_stale_temp_files() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir_all(dir.path().join("sub")).unwrap();
+    fs::write(dir.path().join("sub/.parcopy-tmp-abc123"), "partial").unwrap();
+    fs::write(dir.path().join("keep.txt"), "keep").unwrap();
+
+    // Leftovers newer than the default minimum age are kept
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.arg("clean")
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Removed 0 stale temp files"));
+    assert!(dir.path().join("sub/.parcopy-tmp-abc123").exists());
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["clean", "--plan", "--min-age", "0", "--output", "jsonl"])
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""kind":"temp_file","path":""#))
+        .stdout(predicate::str::contains(r#""status":"would_remove""#))
+        .stdout(predicate::str::contains(r#""record_type":"clean_summary""#));
+    assert!(dir.path().join("sub/.parcopy-tmp-abc123").exists());
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["clean", "--min-age", "0s"])
+        .arg(dir.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Removed 1 stale temp files (7 B)"));
+    assert!(!dir.path().join("sub/.parcopy-tmp-abc123").exists());
+    assert!(dir.path().join("keep.txt").exists());
+}
+
+#[test]
+fn test_copy_cleans_stale_temps_in_destination() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let copy = dst.path().join("data");
+    fs::create_dir_all(src.path().join("data")).unwrap();
+    fs::create_dir_all(&copy).unwrap();
+    fs::write(src.path().join("data/a.txt"), "a").unwrap();
+    fs::write(copy.join(".parcopy-tmp-crashed"), "partial").unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["-r", "--clean-stale-temps", "0"])
+        .arg(src.path().join("data"))
+        .arg(dst.path())
+        .assert()
+        .success();
+    assert!(!copy.join(".parcopy-tmp-crashed").exists());
+    assert_eq!(fs::read_to_string(copy.join("a.txt")).unwrap(), "a");
+}
@@ -162,3 +162,306 @@ fn test_fast_profile_effective_config() {
     assert_eq!(effective["fsync"], false);
     assert_eq!(effective["output_mode"], "json");
 }
+
+#[test]
+fn test_plan_link_dest_action() {
+    let src = TempDir::new().unwrap();
+    let snapshots = TempDir::new().unwrap();
+
+    let source_file = src.path().join("data.bin");
+    fs::write(&source_file, "snapshot me").unwrap();
+
+    let previous = snapshots.path().join("previous");
+    fs::create_dir(&previous).unwrap();
+    cargo_bin_cmd!("pcp")
+        .arg(&source_file)
+        .arg(&previous)
+        .assert()
+        .success();
+
+    let current = snapshots.path().join("current");
+    fs::create_dir(&current).unwrap();
+    let output = cargo_bin_cmd!("pcp")
+        .args(["--plan", "--output", "json", "--link-dest"])
+        .arg(&previous)
+        .arg(&source_file)
+        .arg(&current)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let payload: Value = serde_json::from_slice(&output).unwrap();
+    let items = payload["items"].as_array().unwrap();
+    assert_eq!(items[0]["action"], "link");
+    assert_eq!(items[0]["reason"], "link_dest_match");
+
+    let output = cargo_bin_cmd!("pcp")
+        .args(["--output", "json", "--link-dest"])
+        .arg(&previous)
+        .arg(&source_file)
+        .arg(&current)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let payload: Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(payload["items"][0]["outcome"], "linked");
+    assert_eq!(
+        fs::read_to_string(current.join("data.bin")).unwrap(),
+        "snapshot me"
+    );
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let linked = fs::metadata(current.join("data.bin")).unwrap();
+        let reference = fs::metadata(previous.join("data.bin")).unwrap();
+        assert_eq!(linked.ino(), reference.ino());
+    }
+}
+
+#[test]
+fn test_link_dest_directory_into_existing_destination() {
+    let src = TempDir::new().unwrap();
+    let snapshots = TempDir::new().unwrap();
+
+    let tree = src.path().join("tree");
+    fs::create_dir_all(tree.join("sub")).unwrap();
+    fs::write(tree.join("sub/data.bin"), "snapshot me").unwrap();
+
+    // Both snapshots hold the tree under its own name: previous/tree/sub/data.bin
+    let previous = snapshots.path().join("previous");
+    fs::create_dir(&previous).unwrap();
+    cargo_bin_cmd!("pcp")
+        .arg("-r")
+        .arg(&tree)
+        .arg(&previous)
+        .assert()
+        .success();
+
+    let current = snapshots.path().join("current");
+    fs::create_dir(&current).unwrap();
+    cargo_bin_cmd!("pcp")
+        .args(["-r", "--link-dest"])
+        .arg(&previous)
+        .arg(&tree)
+        .arg(&current)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(current.join("tree/sub/data.bin")).unwrap(),
+        "snapshot me"
+    );
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let linked = fs::metadata(current.join("tree/sub/data.bin")).unwrap();
+        let reference = fs::metadata(previous.join("tree/sub/data.bin")).unwrap();
+        assert_eq!(linked.ino(), reference.ino());
+    }
+}
+
+#[test]
+fn test_plan_compare_dest_reason() {
+    let src = TempDir::new().unwrap();
+    let reference = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+
+    let source_file = src.path().join("same.txt");
+    fs::write(&source_file, "identical").unwrap();
+    fs::write(reference.path().join("same.txt"), "identical").unwrap();
+
+    let output = cargo_bin_cmd!("pcp")
+        .args(["--plan", "--output", "json", "--checksum", "--compare-dest"])
+        .arg(reference.path())
+        .arg(&source_file)
+        .arg(dst.path())
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let payload: Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(payload["items"][0]["action"], "skip");
+    assert_eq!(payload["items"][0]["reason"], "compare_dest_match");
+
+    cargo_bin_cmd!("pcp")
+        .args(["--checksum", "--compare-dest"])
+        .arg(reference.path())
+        .arg(&source_file)
+        .arg(dst.path())
+        .assert()
+        .success();
+    assert!(!dst.path().join("same.txt").exists());
+}
+
+#[test]
+fn test_plan_out_then_apply_copies() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let work = TempDir::new().unwrap();
+    let source_file = src.path().join("a.txt");
+    let destination_file = dst.path().join("a.txt");
+    let plan_file = work.path().join("plan.json");
+    fs::write(&source_file, "saved plan").unwrap();
+
+    cargo_bin_cmd!("pcp")
+        .arg("--plan-out")
+        .arg(&plan_file)
+        .arg(&source_file)
+        .arg(&destination_file)
+        .assert()
+        .success();
+    assert!(!destination_file.exists(), "--plan-out must not copy");
+
+    let plan: Value = serde_json::from_str(&fs::read_to_string(&plan_file).unwrap()).unwrap();
+    assert_eq!(plan["schema_version"], 1);
+    assert_eq!(plan["items"][0]["action"], "copy");
+    assert_eq!(plan["items"][0]["reason"], "not_exists");
+
+    let output = cargo_bin_cmd!("pcp")
+        .arg("--apply")
+        .arg(&plan_file)
+        .arg("--output")
+        .arg("json")
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let payload: Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(payload["mode"], "execute");
+    assert_eq!(payload["items"][0]["outcome"], "copied");
+    assert_eq!(fs::read_to_string(&destination_file).unwrap(), "saved plan");
+}
+
+#[test]
+fn test_apply_reports_drift() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    let work = TempDir::new().unwrap();
+    let source_file = src.path().join("a.txt");
+    let destination_file = dst.path().join("a.txt");
+    let plan_file = work.path().join("plan.json");
+    fs::write(&source_file, "planned").unwrap();
+
+    cargo_bin_cmd!("pcp")
+        .arg("--plan-out")
+        .arg(&plan_file)
+        .arg(&source_file)
+        .arg(&destination_file)
+        .assert()
+        .success();
+    fs::write(&source_file, "changed after planning").unwrap();
+
+    let output = cargo_bin_cmd!("pcp")
+        .arg("--apply")
+        .arg(&plan_file)
+        .arg("--output")
+        .arg("jsonl")
+        .assert()
+        .code(1)
+        .get_output()
+        .stdout
+        .clone();
+    let lines: Vec<Value> = String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let item = lines
+        .iter()
+        .find(|line| line["outcome"].is_string())
+        .unwrap();
+    assert_eq!(item["outcome"], "failed");
+    assert_eq!(item["error_code"], "plan_drift");
+    assert!(!destination_file.exists());
+}
+
+#[test]
+fn test_apply_rejects_invalid_plan() {
+    let work = TempDir::new().unwrap();
+    let plan_file = work.path().join("plan.json");
+    fs::write(&plan_file, r#"{"schema_version": 99}"#).unwrap();
+
+    cargo_bin_cmd!("pcp")
+        .arg("--apply")
+        .arg(&plan_file)
+        .assert()
+        .code(2)
+        .stderr(predicates::str::contains("error[invalid_input]"));
+}
+
+#[test]
+fn test_engine_io_uring_copies_and_is_reported() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    fs::create_dir_all(src.path().join("data/sub")).unwrap();
+    fs::write(src.path().join("data/a.txt"), "a").unwrap();
+    fs::write(src.path().join("data/sub/b.txt"), vec![b'b'; 1 << 20]).unwrap();
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    let output = cmd
+        .args(["-r", "--engine", "io-uring", "--output", "json"])
+        .arg(src.path().join("data"))
+        .arg(dst.path())
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let payload: Value = serde_json::from_slice(&output).unwrap();
+    let engine = payload["effective_config"]["engine"].as_str().unwrap();
+    assert!(matches!(engine, "io_uring" | "standard"), "{engine}");
+    assert_eq!(fs::read(dst.path().join("data/a.txt")).unwrap(), b"a");
+    assert_eq!(
+        fs::read(dst.path().join("data/sub/b.txt")).unwrap(),
+        vec![b'b'; 1 << 20]
+    );
+}
+
+#[test]
+fn test_jobs_auto_reports_parallel_levels() {
+    let src = TempDir::new().unwrap();
+    let dst = TempDir::new().unwrap();
+    fs::create_dir_all(src.path().join("data")).unwrap();
+    for i in 0..40 {
+        fs::write(src.path().join(format!("data/{i}.txt")), i.to_string()).unwrap();
+    }
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    let output = cmd
+        .args(["-r", "-j", "auto:6", "--output", "json"])
+        .arg(src.path().join("data"))
+        .arg(dst.path())
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let payload: Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(payload["effective_config"]["auto_parallel"], true);
+    let levels = payload["parallel_levels"].as_array().unwrap();
+    assert!(!levels.is_empty());
+    assert!(
+        levels
+            .iter()
+            .all(|level| (1..=6).contains(&level.as_u64().unwrap()))
+    );
+    assert_eq!(fs::read(dst.path().join("data/39.txt")).unwrap(), b"39");
+
+    let mut cmd = cargo_bin_cmd!("pcp");
+    cmd.args(["-r", "-j", "sometimes"])
+        .arg(src.path().join("data"))
+        .arg(dst.path())
+        .assert()
+        .failure();
+}
@@ -46,12 +46,15 @@
 //! # Ok::<(), parcopy::Error>(())
 //! ```
 
-use crate::copy::{CopyStats, copy_dir, copy_file};
+use crate::copy::{CopyStats, Journal, Manifest, copy_dir, copy_file_with_stats};
 use crate::error::Result;
-use crate::options::{CopyOptions, OnConflict};
+use crate::options::{
+    BackupMode, CopyEngine, CopyOptions, DedupMode, OnConflict, SourceChangePolicy,
+};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
+use std::time::Duration;
 
 /// A builder for configuring and executing copy operations.
 ///
@@ -118,6 +121,29 @@ impl CopyBuilder {
         self
     }
 
+    /// Tune the number of files copied at once, up to `max`.
+    ///
+    /// Throughput and per-file latency are measured while copying and the
+    /// level is adjusted within `1..=max`; the levels chosen are reported in
+    /// [`CopyStats::parallel_levels`](crate::CopyStats::parallel_levels).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use parcopy::CopyBuilder;
+    ///
+    /// let stats = CopyBuilder::new("src", "dst")
+    ///     .auto_parallel(64)
+    ///     .run()?;
+    /// println!("levels: {:?}", stats.parallel_levels);
+    /// # Ok::<(), parcopy::Error>(())
+    /// ```
+    #[must_use]
+    pub fn auto_parallel(mut self, max: usize) -> Self {
+        self.options = self.options.with_auto_parallel(max);
+        self
+    }
+
     /// Skip files that already exist at the destination (default behavior).
     ///
     /// # Example
@@ -323,6 +349,384 @@ impl CopyBuilder {
         self
     }
 
+    /// Keep the previous version of destination files that get replaced.
+    ///
+    /// Only has an effect together with [`overwrite`](Self::overwrite) or
+    /// [`update_newer`](Self::update_newer).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use parcopy::{BackupMode, CopyBuilder};
+    ///
+    /// let stats = CopyBuilder::new("config", "/etc/myapp")
+    ///     .overwrite()
+    ///     .backup(BackupMode::Numbered)
+    ///     .run()?;
+    /// # Ok::<(), parcopy::Error>(())
+    /// ```
+    #[must_use]
+    pub fn backup(mut self, mode: BackupMode) -> Self {
+        self.options = self.options.with_backup(mode);
+        self
+    }
+
+    /// Hard-link files that are unchanged in an earlier snapshot.
+    ///
+    /// May be called several times; earlier snapshots take precedence.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use parcopy::CopyBuilder;
+    ///
+    /// let stats = CopyBuilder::new("data", "/backups/2026-10-18")
+    ///     .link_dest("/backups/2026-10-17")
+    ///     .run()?;
+    /// println!("{} files linked", stats.files_linked);
+    /// # Ok::<(), parcopy::Error>(())
+    /// ```
+    #[must_use]
+    pub fn link_dest<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.options = self.options.with_link_dest(dir.as_ref());
+        self
+    }
+
+    /// Skip files that are unchanged in a reference tree.
+    ///
+    /// The destination then only receives files that differ from `dir`.
+    /// May be called several times.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use parcopy::CopyBuilder;
+    ///
+    /// let stats = CopyBuilder::new("release-1.5", "patch-1.5")
+    ///     .compare_dest("release-1.4")
+    ///     .run()?;
+    /// # Ok::<(), parcopy::Error>(())
+    /// ```
+    #[must_use]
+    pub fn compare_dest<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.options = self.options.with_compare_dest(dir.as_ref());
+        self
+    }
+
+    /// Match reference files by contents instead of size and mtime.
+    #[must_use]
+    pub fn checksum(mut self) -> Self {
+        self.options = self.options.with_checksum();
+        self
+    }
+
+    /// Update replaced files of at least `min_size` bytes block by block.
+    ///
+    /// Only blocks that differ from the existing destination are written.
+    /// Takes effect with [`overwrite`](Self::overwrite) or
+    /// [`update_newer`](Self::update_newer).
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use parcopy::CopyBuilder;
+    ///
+    /// CopyBuilder::new("images", "mirror")
+    ///     .update_newer()
+    ///     .delta(64 * 1024 * 1024)
+    ///     .run()?;
+    /// # Ok::<(), parcopy::Error>(())
+    /// ```
+    #[must_use]
+    pub fn delta(mut self, min_size: u64) -> Self {
+        self.options = self.options.with_delta(min_size);
+        self
+    }
+
+    /// Set the block size for delta updates.
+    #[must_use]
+    pub fn delta_block_size(mut self, size: usize) -> Self {
+        self.options = self.options.with_delta_block_size(size);
+        self
+    }
+
+    /// Keep partial copies of files of at least `min_size` bytes so that an
+    /// interrupted copy can be resumed.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use parcopy::CopyBuilder;
+    ///
+    /// // Re-running after an interruption continues the image where it stopped
+    /// CopyBuilder::new("disk.img", "/mnt/nfs/disk.img")
+    ///     .resume(1024 * 1024 * 1024)
+    ///     .run()?;
+    /// # Ok::<(), parcopy::Error>(())
+    /// ```
+    #[must_use]
+    pub fn resume(mut self, min_size: u64) -> Self {
+        self.options = self.options.with_resume(min_size);
+        self
+    }
+
+    /// Set what happens when a source file changes while it is being copied.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use parcopy::{CopyBuilder, SourceChangePolicy};
+    ///
+    /// CopyBuilder::new("/srv/app", "/backup/app")
+    ///     .on_source_change(SourceChangePolicy::Retry { attempts: 3 })
+    ///     .run()?;
+    /// # Ok::<(), parcopy::Error>(())
+    /// ```
+    #[must_use]
+    pub fn on_source_change(mut self, policy: SourceChangePolicy) -> Self {
+        self.options = self.options.with_on_source_change(policy);
+        self
+    }
+
+    /// Build the directory tree in a staging directory and swap it into
+    /// place once complete.
+    ///
+    /// Readers of the destination see either the old tree or the new one.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use parcopy::CopyBuilder;
+    ///
+    /// CopyBuilder::new("build/site", "/var/www/site")
+    ///     .overwrite()
+    ///     .atomic_publish()
+    ///     .run()?;
+    /// # Ok::<(), parcopy::Error>(())
+    /// ```
+    #[must_use]
+    pub fn atomic_publish(mut self) -> Self {
+        self.options = self.options.with_atomic_publish();
+        self
+    }
+
+    /// Undo everything the copy created or replaced if it fails.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use parcopy::{CopyBuilder, Error};
+    ///
+    /// match CopyBuilder::new("dataset", "/srv/dataset").transactional().run() {
+    ///     Err(Error::RolledBack { report, .. }) => {
+    ///         eprintln!("rolled back {} entries", report.removed.len());
+    ///     }
+    ///     result => {
+    ///         result?;
+    ///     }
+    /// }
+    /// # Ok::<(), parcopy::Error>(())
+    /// ```
+    #[must_use]
+    pub fn transactional(mut self) -> Self {
+        self.options = self.options.with_transactional();
+        self
+    }
+
+    /// Read back each copied file and compare its hash with the source.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use parcopy::CopyBuilder;
+    ///
+    /// CopyBuilder::new("results", "/mnt/nfs/results").verify().run()?;
+    /// # Ok::<(), parcopy::Error>(())
+    /// ```
+    #[must_use]
+    pub fn verify(mut self) -> Self {
+        self.options = self.options.with_verify();
+        self
+    }
+
+    /// Compute the SHA-256 digest of every copied file.
+    ///
+    /// For a single file, the digest is reported in
+    /// [`CopyStats::digest`](crate::CopyStats::digest).
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use parcopy::CopyBuilder;
+    ///
+    /// let stats = CopyBuilder::new("image.iso", "/mnt/usb/image.iso").digests().run()?;
+    /// if let Some(digest) = stats.digest {
+    ///     println!("sha256 {digest}");
+    /// }
+    /// # Ok::<(), parcopy::Error>(())
+    /// ```
+    #[must_use]
+    pub fn digests(mut self) -> Self {
+        self.options = self.options.with_digests();
+        self
+    }
+
+    /// List every copied file with its SHA-256 digest in a manifest.
+    ///
+    /// Call [`Manifest::finish`](crate::Manifest::finish) after the run to
+    /// write it.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use parcopy::{CopyBuilder, Manifest, ManifestFormat};
+    /// use std::sync::Arc;
+    ///
+    /// let manifest = Arc::new(Manifest::new("/data/set/SHA256SUMS", ManifestFormat::Sha256Sums));
+    /// CopyBuilder::new("set", "/data/set")
+    ///     .manifest(manifest.clone())
+    ///     .run()?;
+    /// manifest.finish()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    pub fn manifest(mut self, manifest: Arc<Manifest>) -> Self {
+        self.options = self.options.with_manifest(manifest);
+        self
+    }
+
+    /// Link files identical to one already copied in this run to that copy.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use parcopy::{CopyBuilder, DedupMode};
+    ///
+    /// let stats = CopyBuilder::new("shards", "/mnt/nfs/shards")
+    ///     .dedup(DedupMode::HardLink)
+    ///     .run()?;
+    /// println!("{} bytes not written", stats.bytes_deduplicated);
+    /// # Ok::<(), parcopy::Error>(())
+    /// ```
+    #[must_use]
+    pub fn dedup(mut self, mode: DedupMode) -> Self {
+        self.options = self.options.with_dedup(mode);
+        self
+    }
+
+    /// Resume from a completion journal, skipping files it lists as done.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use parcopy::{CopyBuilder, Journal};
+    /// use std::sync::Arc;
+    ///
+    /// let journal = Arc::new(Journal::open("/var/tmp/shards.journal")?);
+    /// CopyBuilder::new("shards", "/mnt/nfs/shards")
+    ///     .journal(journal.clone())
+    ///     .run()?;
+    /// journal.sync()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[must_use]
+    pub fn journal(mut self, journal: Arc<Journal>) -> Self {
+        self.options = self.options.with_journal(journal);
+        self
+    }
+
+    /// Remove temp files older than `min_age` that crashed runs left in the
+    /// destination before copying.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use parcopy::CopyBuilder;
+    /// use std::time::Duration;
+    ///
+    /// CopyBuilder::new("shards", "/mnt/nfs/shards")
+    ///     .clean_stale_temps(Duration::from_secs(24 * 3600))
+    ///     .run()?;
+    /// # Ok::<(), parcopy::Error>(())
+    /// ```
+    #[must_use]
+    pub fn clean_stale_temps(mut self, min_age: Duration) -> Self {
+        self.options = self.options.with_clean_stale_temps(min_age);
+        self
+    }
+
+    /// Set how file contents are copied.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use parcopy::{CopyBuilder, CopyEngine};
+    ///
+    /// CopyBuilder::new("small-files", "/mnt/nvme/small-files")
+    ///     .engine(CopyEngine::IoUring)
+    ///     .run()?;
+    /// # Ok::<(), parcopy::Error>(())
+    /// ```
+    #[must_use]
+    pub fn engine(mut self, engine: CopyEngine) -> Self {
+        self.options = self.options.with_engine(engine);
+        self
+    }
+
+    /// Copy files of at least `min_size` bytes in concurrent chunks.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use parcopy::CopyBuilder;
+    ///
+    /// CopyBuilder::new("dataset.tar", "/mnt/lustre/dataset.tar")
+    ///     .parallel_chunks(1024 * 1024 * 1024)
+    ///     .run()?;
+    /// # Ok::<(), parcopy::Error>(())
+    /// ```
+    #[must_use]
+    pub fn parallel_chunks(mut self, min_size: u64) -> Self {
+        self.options = self.options.with_parallel_chunks(min_size);
+        self
+    }
+
+    /// Limit the bandwidth of file data to `bytes_per_second` across all workers.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use parcopy::CopyBuilder;
+    ///
+    /// CopyBuilder::new("data", "/mnt/nfs/data")
+    ///     .bwlimit(200 * 1024 * 1024)
+    ///     .run()?;
+    /// # Ok::<(), parcopy::Error>(())
+    /// ```
+    #[must_use]
+    pub fn bwlimit(mut self, bytes_per_second: u64) -> Self {
+        self.options = self.options.with_bwlimit(bytes_per_second);
+        self
+    }
+
+    /// Limit metadata operations to `ops_per_second` across all workers.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use parcopy::CopyBuilder;
+    ///
+    /// CopyBuilder::new("small-files", "/mnt/nfs/small-files")
+    ///     .metadata_limit(2000)
+    ///     .run()?;
+    /// # Ok::<(), parcopy::Error>(())
+    /// ```
+    #[must_use]
+    pub fn metadata_limit(mut self, ops_per_second: u64) -> Self {
+        self.options = self.options.with_metadata_limit(ops_per_second);
+        self
+    }
+
     /// Set a cancellation token for cooperative cancellation.
     ///
     /// When the token is set to `true`, the copy operation stops starting new
@@ -427,19 +831,7 @@ impl CopyBuilder {
         if self.src.is_dir() {
             copy_dir(&self.src, &self.dst, &self.options)
         } else {
-            let start = std::time::Instant::now();
-            let copied = copy_file(&self.src, &self.dst, &self.options)?;
-            let file_len = self.src.metadata().map(|m| m.len()).unwrap_or(0);
-
-            Ok(CopyStats {
-                files_copied: if copied { 1 } else { 0 },
-                files_skipped: if copied { 0 } else { 1 },
-                symlinks_copied: 0,
-                symlinks_skipped: 0,
-                dirs_created: 0,
-                bytes_copied: if copied { file_len } else { 0 },
-                duration: start.elapsed(),
-            })
+            copy_file_with_stats(&self.src, &self.dst, &self.options)
         }
     }
 
@@ -472,19 +864,7 @@ impl CopyBuilder {
     /// # Ok::<(), parcopy::Error>(())
     /// ```
     pub fn run_file(self) -> Result<CopyStats> {
-        let start = std::time::Instant::now();
-        let copied = copy_file(&self.src, &self.dst, &self.options)?;
-        let file_len = self.src.metadata().map(|m| m.len()).unwrap_or(0);
-
-        Ok(CopyStats {
-            files_copied: if copied { 1 } else { 0 },
-            files_skipped: if copied { 0 } else { 1 },
-            symlinks_copied: 0,
-            symlinks_skipped: 0,
-            dirs_created: 0,
-            bytes_copied: if copied { file_len } else { 0 },
-            duration: start.elapsed(),
-        })
+        copy_file_with_stats(&self.src, &self.dst, &self.options)
     }
 }
 
@@ -0,0 +1,309 @@
+//! Adaptive parallelism for Phase 3 of directory copies.
+//!
+//! With [`CopyOptions::auto_parallel`](crate::CopyOptions::auto_parallel) the
+//! files are copied as tasks on the current rayon pool, of which at most
+//! `limit` are in flight at a time. After every window of completed files the
+//! limit is adjusted AIMD-style from the measured throughput and per-file
+//! latency: it grows by one while throughput holds up, and shrinks by a
+//! quarter when throughput drops or latency grows without a throughput gain.
+//!
+//! Only the calling thread waits for a free slot, running queued files while
+//! it does. Tasks themselves never block on the limit, so a thread that steals
+//! a file while waiting on another file's parallel chunks cannot deadlock.
+
+use std::sync::{Condvar, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Level the limit starts from, capped by the number of workers
+const START_LEVEL: usize = 8;
+
+/// Bytes a file counts for on top of its size, for the open, create, rename
+/// and metadata operations that every file costs
+const PER_FILE_COST: u64 = 64 * 1024;
+
+/// Fewest files in a window
+const MIN_WINDOW_FILES: u64 = 8;
+
+/// Shortest window, so that small files do not make throughput noisy
+const MIN_WINDOW: Duration = Duration::from_millis(100);
+
+/// A window also closes after this long, however few files it has
+const MAX_WINDOW: Duration = Duration::from_secs(1);
+
+/// Throughput this much lower than the previous window's is a drop
+const DROP_RATIO: f64 = 0.9;
+
+/// Throughput at least this much higher than the previous window's is a gain
+const GAIN_RATIO: f64 = 1.05;
+
+/// Latency this much higher than the previous window's is a rise
+const LATENCY_RISE_RATIO: f64 = 1.25;
+
+/// Throughput and mean per-file latency of one window.
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    throughput: f64,
+    latency: f64,
+}
+
+/// AIMD controller for the number of files copied at once.
+#[derive(Debug)]
+struct Tuner {
+    min: usize,
+    max: usize,
+    limit: usize,
+    /// The starting level followed by every level changed to
+    levels: Vec<usize>,
+    window_start: Instant,
+    files: u64,
+    bytes: u64,
+    latency: Duration,
+    last: Option<Sample>,
+}
+
+impl Tuner {
+    fn new(max: usize) -> Self {
+        let max = max.max(1);
+        let limit = START_LEVEL.min(max);
+        Self {
+            min: 1,
+            max,
+            limit,
+            levels: vec![limit],
+            window_start: Instant::now(),
+            files: 0,
+            bytes: 0,
+            latency: Duration::ZERO,
+            last: None,
+        }
+    }
+
+    /// Record a finished file; returns whether the limit changed.
+    fn record(&mut self, bytes: u64, latency: Duration) -> bool {
+        self.files += 1;
+        self.bytes += bytes;
+        self.latency += latency;
+
+        let elapsed = self.window_start.elapsed();
+        let window_files = MIN_WINDOW_FILES.max(2 * self.limit as u64);
+        let full = self.files >= window_files && elapsed >= MIN_WINDOW;
+        if !full && elapsed < MAX_WINDOW {
+            return false;
+        }
+
+        let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+        #[allow(clippy::cast_precision_loss)]
+        let sample = Sample {
+            throughput: (self.bytes + self.files * PER_FILE_COST) as f64 / secs,
+            latency: self.latency.as_secs_f64() / self.files as f64,
+        };
+        self.window_start = Instant::now();
+        self.files = 0;
+        self.bytes = 0;
+        self.latency = Duration::ZERO;
+        self.adjust(sample)
+    }
+
+    /// Move the limit after a window; returns whether it changed.
+    fn adjust(&mut self, sample: Sample) -> bool {
+        let congested = self.last.is_some_and(|last| {
+            sample.throughput < last.throughput * DROP_RATIO
+                || (sample.latency > last.latency * LATENCY_RISE_RATIO
+                    && sample.throughput < last.throughput * GAIN_RATIO)
+        });
+        self.last = Some(sample);
+
+        let limit = if congested {
+            // Multiplicative decrease by a quarter, by at least one
+            (self.limit * 3 / 4).min(self.limit - 1).max(self.min)
+        } else {
+            (self.limit + 1).min(self.max)
+        };
+        if limit == self.limit {
+            return false;
+        }
+        self.limit = limit;
+        self.levels.push(limit);
+        true
+    }
+}
+
+struct State {
+    in_flight: usize,
+    tuner: Tuner,
+}
+
+/// A slot taken by one file; freed on drop, even if the file panics.
+struct Slot<'a> {
+    state: &'a Mutex<State>,
+    freed: &'a Condvar,
+    /// Bytes copied and latency, once the file is done
+    sample: Option<(u64, Duration)>,
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        let mut guard = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        guard.in_flight -= 1;
+        if let Some((bytes, latency)) = self.sample {
+            guard.tuner.record(bytes, latency);
+        }
+        self.freed.notify_one();
+    }
+}
+
+/// Run `f` over `items` on the current rayon pool, tuning how many run at
+/// once up to `max`.
+///
+/// `f` returns its result and the bytes it copied. Results are returned in
+/// the order of `items`, with the levels chosen along the way.
+pub(crate) fn run_adaptive<I, T, F>(items: &[I], max: usize, f: F) -> (Vec<T>, Vec<usize>)
+where
+    I: Sync,
+    T: Send,
+    F: Fn(&I) -> (T, u64) + Sync,
+{
+    let state = Mutex::new(State {
+        in_flight: 0,
+        tuner: Tuner::new(max.clamp(1, items.len().max(1))),
+    });
+    let freed = Condvar::new();
+    let results = Mutex::new(Vec::with_capacity(items.len()));
+    let lock = || state.lock().unwrap_or_else(PoisonError::into_inner);
+
+    rayon::scope(|scope| {
+        for (index, item) in items.iter().enumerate() {
+            loop {
+                let mut guard = lock();
+                if guard.in_flight < guard.tuner.limit {
+                    guard.in_flight += 1;
+                    break;
+                }
+                drop(guard);
+                // Run a queued file rather than idle; a pool of one thread
+                // would otherwise never get to them
+                if rayon::yield_now() == Some(rayon::Yield::Executed) {
+                    continue;
+                }
+                let guard = lock();
+                if guard.in_flight >= guard.tuner.limit {
+                    drop(freed.wait(guard).unwrap_or_else(PoisonError::into_inner));
+                }
+            }
+
+            let (state, freed, results, f) = (&state, &freed, &results, &f);
+            scope.spawn(move |_| {
+                let mut slot = Slot {
+                    state,
+                    freed,
+                    sample: None,
+                };
+                let start = Instant::now();
+                let (result, bytes) = f(item);
+                slot.sample = Some((bytes, start.elapsed()));
+                results
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .push((index, result));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap_or_else(PoisonError::into_inner);
+    results.sort_unstable_by_key(|(index, _)| *index);
+
+    let levels = state
+        .into_inner()
+        .unwrap_or_else(PoisonError::into_inner)
+        .tuner
+        .levels;
+    (
+        results.into_iter().map(|(_, result)| result).collect(),
+        levels,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn sample(throughput: f64, latency: f64) -> Sample {
+        Sample {
+            throughput,
+            latency,
+        }
+    }
+
+    #[test]
+    fn test_tuner_increases_additively_and_decreases_multiplicatively() {
+        let mut tuner = Tuner::new(64);
+        assert_eq!(tuner.limit, START_LEVEL);
+
+        // Throughput keeps up as the limit grows
+        assert!(tuner.adjust(sample(100.0, 1.0)));
+        assert!(tuner.adjust(sample(120.0, 1.0)));
+        assert!(tuner.adjust(sample(121.0, 1.05)));
+        assert_eq!(tuner.limit, START_LEVEL + 3);
+
+        // Throughput drops: back off by a quarter
+        assert!(tuner.adjust(sample(80.0, 1.0)));
+        assert_eq!(tuner.limit, (START_LEVEL + 3) * 3 / 4);
+
+        // Latency rises with no throughput gain: back off again
+        let before = tuner.limit;
+        assert!(tuner.adjust(sample(81.0, 2.0)));
+        assert_eq!(tuner.limit, before * 3 / 4);
+
+        assert_eq!(
+            tuner.levels,
+            vec![8, 9, 10, 11, 8, 6],
+            "every level is recorded in order"
+        );
+    }
+
+    #[test]
+    fn test_tuner_stays_within_bounds() {
+        let mut tuner = Tuner::new(2);
+        assert_eq!(tuner.limit, 2);
+        assert!(!tuner.adjust(sample(100.0, 1.0)));
+        assert!(tuner.adjust(sample(10.0, 1.0)));
+        assert_eq!(tuner.limit, 1);
+        assert!(!tuner.adjust(sample(1.0, 1.0)));
+        assert_eq!(tuner.limit, 1);
+        assert_eq!(tuner.levels, vec![2, 1]);
+    }
+
+    #[test]
+    fn test_run_adaptive_runs_every_item_once_in_order() {
+        let items: Vec<u64> = (0..200).collect();
+        let (results, levels) = run_adaptive(&items, 16, |&item| {
+            thread::sleep(Duration::from_micros(200));
+            (item * 2, item)
+        });
+        assert_eq!(
+            results,
+            items.iter().map(|item| item * 2).collect::<Vec<_>>()
+        );
+        assert_eq!(levels[0], START_LEVEL);
+        assert!(levels.iter().all(|&level| (1..=16).contains(&level)));
+    }
+
+    #[test]
+    fn test_run_adaptive_uses_the_current_pool() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let items: Vec<u64> = (0..50).collect();
+        let (results, _) = pool.install(|| {
+            run_adaptive(&items, 4, |&item| {
+                // A single-thread pool still gets through every file
+                assert_eq!(rayon::current_num_threads(), 1);
+                assert!(rayon::current_thread_index().is_some());
+                (item, item)
+            })
+        });
+        assert_eq!(results, items);
+    }
+}
@@ -0,0 +1,245 @@
+//! Backups of destination files that are about to be replaced.
+//!
+//! A backup is created by hard-linking the existing destination to its backup
+//! name (falling back to a copy when hard links are not possible), and only then
+//! is the new content persisted over the destination. At every point in time
+//! at least one of the old and new versions is present on disk.
+
+use crate::options::BackupMode;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::rollback::Rollback;
+
+/// Prefix for temporary names used while placing a backup
+pub(crate) const BACKUP_TEMP_PREFIX: &str = ".parcopy-bak-";
+
+/// Upper bound on attempts to claim a free numbered backup slot
+const MAX_NUMBERED_ATTEMPTS: usize = 64;
+
+/// Back up the existing destination `dst` according to `mode`.
+///
+/// `rel` is the path of `dst` relative to the destination root; it is used by
+/// [`BackupMode::Directory`] to mirror the tree.
+///
+/// In a transactional run, the backup and any directories created for it are
+/// recorded in `rollback`, and an older backup it replaces is kept, so that a
+/// rollback leaves the backups as they were.
+///
+/// Returns the backup path, or `None` if `dst` no longer exists.
+pub(crate) fn make_backup(
+    dst: &Path,
+    rel: &Path,
+    mode: &BackupMode,
+    rollback: Option<&Rollback>,
+) -> io::Result<Option<PathBuf>> {
+    match fs::symlink_metadata(dst) {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let backup = match mode {
+        BackupMode::Simple { suffix } => {
+            let target = with_suffix(dst, suffix);
+            place_backup(dst, &target, rollback)?;
+            target
+        }
+        BackupMode::Numbered => {
+            let target = numbered_backup(dst)?;
+            if let Some(rollback) = rollback {
+                rollback.created(&target);
+            }
+            target
+        }
+        BackupMode::Directory(dir) => {
+            let target = dir.join(rel);
+            if let Some(parent) = target.parent() {
+                match rollback {
+                    Some(rollback) => rollback.create_dir_all(parent)?,
+                    None => fs::create_dir_all(parent)?,
+                }
+            }
+            place_backup(dst, &target, rollback)?;
+            target
+        }
+    };
+
+    Ok(Some(backup))
+}
+
+/// Append `suffix` to the final component of `path`.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name: OsString = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Back up `dst` at `target` with [`replace_with_link`], recording the change
+/// in `rollback`.
+fn place_backup(dst: &Path, target: &Path, rollback: Option<&Rollback>) -> io::Result<()> {
+    let Some(rollback) = rollback else {
+        return replace_with_link(dst, target);
+    };
+    match fs::symlink_metadata(target) {
+        // An older backup is kept, to be put back by a rollback
+        Ok(_) => {
+            rollback.keep_original(target)?;
+            replace_with_link(dst, target)
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            replace_with_link(dst, target)?;
+            rollback.created(target);
+            Ok(())
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Place a link to (or copy of) `dst` at `target`, atomically replacing any
+/// older backup that already lives there.
+fn replace_with_link(dst: &Path, target: &Path) -> io::Result<()> {
+    let dir = target.parent().unwrap_or(Path::new("."));
+    let temp = tempfile::Builder::new()
+        .prefix(BACKUP_TEMP_PREFIX)
+        .make_in(dir, |path| link_or_copy(dst, path))?;
+    temp.into_temp_path().persist(target).map_err(|e| e.error)
+}
+
+/// Claim the next free `name.~N~` slot for `dst`.
+fn numbered_backup(dst: &Path) -> io::Result<PathBuf> {
+    let dir = dst.parent().unwrap_or(Path::new("."));
+    let name = dst.file_name().map(OsString::from).unwrap_or_default();
+    let mut next = highest_backup_number(dir, &name)? + 1;
+
+    for _ in 0..MAX_NUMBERED_ATTEMPTS {
+        let target = with_suffix(dst, &format!(".~{next}~"));
+        match link_or_copy(dst, &target) {
+            Ok(()) => return Ok(target),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => next += 1,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("no free numbered backup slot for {}", dst.display()),
+    ))
+}
+
+/// Highest `N` among existing `name.~N~` entries in `dir` (0 if none).
+fn highest_backup_number(dir: &Path, name: &OsString) -> io::Result<u64> {
+    let prefix = format!("{}.~", name.to_string_lossy());
+    let mut highest = 0;
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let file_name = file_name.to_string_lossy();
+        let number = file_name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix('~'))
+            .and_then(|digits| digits.parse::<u64>().ok());
+        if let Some(number) = number {
+            highest = highest.max(number);
+        }
+    }
+
+    Ok(highest)
+}
+
+/// Create `target` as a hard link to `src`, or as a copy if linking fails
+/// (e.g. across devices). Fails with `AlreadyExists` if `target` exists.
+fn link_or_copy(src: &Path, target: &Path) -> io::Result<()> {
+    match fs::hard_link(src, target) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(e),
+        Err(_) => copy_preserving(src, target),
+    }
+}
+
+/// Copy `src` to a new path `target`, preserving symlinks and timestamps.
+fn copy_preserving(src: &Path, target: &Path) -> io::Result<()> {
+    let meta = fs::symlink_metadata(src)?;
+    if meta.file_type().is_symlink() {
+        return super::utils::symlink(&fs::read_link(src)?, target);
+    }
+
+    let mut reader = fs::File::open(src)?;
+    let mut writer = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(target)?;
+    io::copy(&mut reader, &mut writer)?;
+    fs::set_permissions(target, meta.permissions())?;
+    let _ = super::utils::preserve_timestamps(&meta, target);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_simple_backup_replaces_older_backup() {
+        let dir = tempdir().unwrap();
+        let dst = dir.path().join("config.toml");
+        fs::write(&dst, "v1").unwrap();
+        fs::write(dir.path().join("config.toml~"), "v0").unwrap();
+
+        let mode = BackupMode::Simple {
+            suffix: "~".to_string(),
+        };
+        let backup = make_backup(&dst, Path::new("config.toml"), &mode, None)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(backup, dir.path().join("config.toml~"));
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "v1");
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "v1");
+    }
+
+    #[test]
+    fn test_numbered_backup_increments() {
+        let dir = tempdir().unwrap();
+        let dst = dir.path().join("app.conf");
+        fs::write(&dst, "v3").unwrap();
+        fs::write(dir.path().join("app.conf.~1~"), "v1").unwrap();
+        fs::write(dir.path().join("app.conf.~2~"), "v2").unwrap();
+
+        let backup = make_backup(&dst, Path::new("app.conf"), &BackupMode::Numbered, None)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(backup, dir.path().join("app.conf.~3~"));
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "v3");
+    }
+
+    #[test]
+    fn test_directory_backup_mirrors_tree() {
+        let dir = tempdir().unwrap();
+        let backups = tempdir().unwrap();
+        let dst = dir.path().join("etc/app.conf");
+        fs::create_dir_all(dst.parent().unwrap()).unwrap();
+        fs::write(&dst, "old").unwrap();
+
+        let mode = BackupMode::Directory(backups.path().to_path_buf());
+        let backup = make_backup(&dst, Path::new("etc/app.conf"), &mode, None)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(backup, backups.path().join("etc/app.conf"));
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "old");
+    }
+
+    #[test]
+    fn test_backup_missing_destination() {
+        let dir = tempdir().unwrap();
+        let dst = dir.path().join("missing");
+        let result = make_backup(&dst, Path::new("missing"), &BackupMode::Numbered, None).unwrap();
+        assert!(result.is_none());
+    }
+}
@@ -0,0 +1,689 @@
+//! Batched copies of small files through io_uring (Linux, `io_uring` feature).
+//!
+//! With the io_uring engine, directory copies hand their files to
+//! [`copy_batch`] up to [`BATCH_FILES`] at a time. Each step io_uring offers
+//! is submitted for the whole batch at once, with a single `io_uring_enter`:
+//!
+//! 1. a `statx` of every source and destination;
+//! 2. an `openat` of every source and of a new temp file next to each
+//!    destination;
+//! 3. per file, a read linked to a `statx` of the open source (to catch
+//!    changes made during the copy), to the write of the same buffer and,
+//!    with `fsync`, to an fsync of the temp file;
+//! 4. a `renameat` of every temp file into place, once permissions and
+//!    timestamps have been set on the open temp files.
+//!
+//! Files the batch does not handle are left to the standard per-file path:
+//! files too large for a batch buffer, destinations that exist (other than
+//! skipped ones), and any file whose source changed or whose step failed
+//! along the way. That path applies conflict policies, backups, delta
+//! updates and source change policies as usual.
+
+use filetime::FileTime;
+use io_uring::{IoUring, Probe, opcode, squeue, types};
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::fs::{File, Permissions};
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tempfile::TempPath;
+
+use crate::error::{Error, Result};
+use crate::options::{CopyEngine, CopyOptions, OnConflict};
+
+use super::clean::TEMP_FILE_PREFIX;
+use super::context::CopyContext;
+use super::digest::Hasher;
+use super::file::{FileCopyResult, finish_file, verify_before_persist};
+use super::journal::SourceStamp;
+#[cfg(feature = "reflink")]
+use super::reflink;
+use super::uring::{self, submit_all};
+
+/// Files copied together in one batch
+pub(crate) const BATCH_FILES: usize = 32;
+
+/// Largest file copied in a batch; larger ones are copied on their own
+const MAX_FILE_SIZE: u64 = 64 * 1024;
+
+/// Submission queue entries: up to four per file (read, statx, write, fsync)
+#[allow(clippy::cast_possible_truncation)]
+const QUEUE_DEPTH: u32 = 4 * BATCH_FILES as u32;
+
+/// Distinguishes the temp files of one process
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+struct Batch {
+    ring: IoUring,
+    /// One buffer per file
+    buffers: Vec<Vec<u8>>,
+    /// Per file: the source before it is opened, the destination, and the
+    /// open source after it is read
+    stats: Vec<libc::statx>,
+    /// Paths the entries point at
+    paths: Vec<CString>,
+}
+
+thread_local! {
+    static BATCH: RefCell<Option<Batch>> = const { RefCell::new(None) };
+}
+
+/// A file of the batch on its way through the steps.
+struct Job<'a> {
+    /// Position in the batch
+    index: usize,
+    src: &'a Path,
+    dst: &'a Path,
+    /// Indexes into `Batch::paths` of the source and destination
+    src_path: usize,
+    dst_path: usize,
+}
+
+/// A job whose source and temp file are open.
+struct Open<'a> {
+    job: Job<'a>,
+    size: u64,
+    before: libc::statx,
+    src_file: File,
+    temp_file: File,
+    temp: TempPath,
+    temp_path: usize,
+}
+
+/// Whether io_uring offers every operation batches use.
+fn is_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        let Ok(ring) = IoUring::new(QUEUE_DEPTH) else {
+            return false;
+        };
+        let mut probe = Probe::new();
+        uring::is_available()
+            && ring.submitter().register_probe(&mut probe).is_ok()
+            && [
+                opcode::Statx::CODE,
+                opcode::OpenAt::CODE,
+                opcode::RenameAt::CODE,
+            ]
+            .into_iter()
+            .all(|code| probe.is_supported(code))
+    })
+}
+
+/// Whether the files of a directory copy with `options` are copied in batches.
+///
+/// Reference trees, deduplication and bandwidth limits decide per file how
+/// it is written, so they keep every file on the standard path.
+pub(crate) fn applies(options: &CopyOptions) -> bool {
+    options.engine == CopyEngine::IoUring
+        && options.bwlimit.is_none()
+        && options.link_dest.is_empty()
+        && options.compare_dest.is_empty()
+        && options.dedup.is_none()
+        && is_available()
+}
+
+/// Copy up to [`BATCH_FILES`] of `files` through io_uring.
+///
+/// Returns the result of each file, or `None` for the files left to the
+/// standard path. Results are finished as by `copy_file_internal`: recorded
+/// in the manifest and journal, and their sources removed when moving.
+pub(crate) fn copy_batch(
+    files: &[(PathBuf, PathBuf)],
+    options: &CopyOptions,
+    ctx: &CopyContext,
+) -> Vec<Option<Result<FileCopyResult>>> {
+    let mut results: Vec<Option<Result<FileCopyResult>>> = files.iter().map(|_| None).collect();
+    BATCH.with(|batch| {
+        let Ok(mut batch) = batch.try_borrow_mut() else {
+            return;
+        };
+        if batch.is_none() {
+            let Ok(ring) = IoUring::new(QUEUE_DEPTH) else {
+                return;
+            };
+            *batch = Some(Batch {
+                ring,
+                buffers: (0..BATCH_FILES)
+                    .map(|_| vec![0u8; MAX_FILE_SIZE as usize])
+                    .collect(),
+                // SAFETY: `statx` is plain data, for which all zeroes is valid
+                stats: vec![unsafe { std::mem::zeroed() }; 3 * BATCH_FILES],
+                paths: Vec::new(),
+            });
+        }
+        let Some(engine) = batch.as_mut() else {
+            return;
+        };
+        if engine.copy(files, options, ctx, &mut results).is_err() {
+            // Entries of the failed submission may still be in flight, and
+            // their completions would be taken for those of the next batch
+            *batch = None;
+        }
+    });
+
+    results
+        .into_iter()
+        .zip(files)
+        .map(|(result, (src, dst))| {
+            result.map(|result| result.and_then(|r| finish_file(src, dst, r, options, ctx)))
+        })
+        .collect()
+}
+
+impl Batch {
+    /// Run the steps of a batch, filling in `results`.
+    ///
+    /// Fails only if a submission failed, leaving the ring unusable.
+    fn copy(
+        &mut self,
+        files: &[(PathBuf, PathBuf)],
+        options: &CopyOptions,
+        ctx: &CopyContext,
+        results: &mut [Option<Result<FileCopyResult>>],
+    ) -> io::Result<()> {
+        self.paths.clear();
+        let mut jobs = Vec::new();
+        for (index, (src, dst)) in files.iter().enumerate().take(BATCH_FILES) {
+            let (Some(src_c), Some(dst_c)) = (c_path(src), c_path(dst)) else {
+                continue;
+            };
+            jobs.push(Job {
+                index,
+                src,
+                dst,
+                src_path: self.paths.len(),
+                dst_path: self.paths.len() + 1,
+            });
+            self.paths.push(src_c);
+            self.paths.push(dst_c);
+        }
+
+        // Step 1: stat every source and destination
+        let mut entries = Vec::with_capacity(2 * jobs.len());
+        for (j, job) in jobs.iter().enumerate() {
+            let entry = opcode::Statx::new(
+                types::Fd(libc::AT_FDCWD),
+                self.paths[job.src_path].as_ptr(),
+                (&raw mut self.stats[3 * j]).cast(),
+            )
+            .flags(libc::AT_STATX_SYNC_AS_STAT)
+            .mask(libc::STATX_BASIC_STATS)
+            .build();
+            push(&mut entries, entry);
+            let entry = opcode::Statx::new(
+                types::Fd(libc::AT_FDCWD),
+                self.paths[job.dst_path].as_ptr(),
+                (&raw mut self.stats[3 * j + 1]).cast(),
+            )
+            .flags(libc::AT_STATX_SYNC_AS_STAT | libc::AT_SYMLINK_NOFOLLOW)
+            .mask(libc::STATX_BASIC_STATS)
+            .build();
+            push(&mut entries, entry);
+        }
+        options.throttle_metadata(entries.len() as u64);
+        let done = self.submit(&entries)?;
+
+        let mut ready = Vec::new();
+        for (j, job) in jobs.into_iter().enumerate() {
+            let before = self.stats[3 * j];
+            if done[2 * j] < 0 || u32::from(before.stx_mode) & libc::S_IFMT != libc::S_IFREG {
+                // The standard path reports the error or rejects the entry
+                continue;
+            }
+            if let Some(journal) = &options.journal {
+                if ctx.uses_journal() && journal.lists(job.src, job.dst, stamp(&before)) {
+                    results[job.index] = Some(Ok(FileCopyResult::skipped()));
+                    continue;
+                }
+            }
+            match done[2 * j + 1] {
+                0 => {
+                    if options.on_conflict == OnConflict::Skip {
+                        results[job.index] = Some(Ok(FileCopyResult::skipped()));
+                    }
+                    continue;
+                }
+                res if res == -libc::ENOENT => {}
+                _ => continue,
+            }
+            let size = before.stx_size;
+            if size > MAX_FILE_SIZE
+                || options.resume_threshold.is_some_and(|t| size >= t)
+                || (options.parallel > 1
+                    && options.parallel_chunk_threshold.is_some_and(|t| size >= t))
+            {
+                continue;
+            }
+            // Clones beat copies where the destination supports them
+            #[cfg(feature = "reflink")]
+            if reflink::supports_reflink(job.dst.parent().unwrap_or(Path::new("."))) {
+                continue;
+            }
+            ready.push((job, before));
+        }
+
+        if options.is_cancelled() {
+            for (job, _) in ready {
+                results[job.index] = Some(Err(cancelled()));
+            }
+            return Ok(());
+        }
+
+        // Step 2: open every source and create a temp file for it
+        let mode = if options.preserve_permissions {
+            // Owner only until the source's permissions are set
+            0o600
+        } else {
+            0o666
+        };
+        let mut entries = Vec::with_capacity(2 * ready.len());
+        let mut temps = Vec::with_capacity(ready.len());
+        for (job, _) in &ready {
+            let temp_path = temp_name(job.dst);
+            let Some(temp_c) = c_path(&temp_path) else {
+                temps.push(None);
+                continue;
+            };
+            let entry =
+                opcode::OpenAt::new(types::Fd(libc::AT_FDCWD), self.paths[job.src_path].as_ptr())
+                    .flags(libc::O_RDONLY | libc::O_CLOEXEC)
+                    .build();
+            push(&mut entries, entry);
+            let entry = opcode::OpenAt::new(types::Fd(libc::AT_FDCWD), temp_c.as_ptr())
+                .flags(libc::O_WRONLY | libc::O_CREAT | libc::O_EXCL | libc::O_CLOEXEC)
+                .mode(mode)
+                .build();
+            push(&mut entries, entry);
+            temps.push(Some((temp_path, self.paths.len())));
+            self.paths.push(temp_c);
+        }
+        options.throttle_metadata(ready.len() as u64);
+        let done = self.submit(&entries)?;
+
+        let mut open = Vec::with_capacity(ready.len());
+        let mut entry = 0;
+        for ((job, before), temp) in ready.into_iter().zip(temps) {
+            let Some((temp, temp_path)) = temp else {
+                continue;
+            };
+            let (src_fd, temp_fd) = (done[entry], done[entry + 1]);
+            entry += 2;
+            // SAFETY: Descriptors returned by the kernel are open and owned by
+            // nothing else
+            let src_file =
+                (src_fd >= 0).then(|| File::from(unsafe { OwnedFd::from_raw_fd(src_fd) }));
+            let temp_file =
+                (temp_fd >= 0).then(|| File::from(unsafe { OwnedFd::from_raw_fd(temp_fd) }));
+            // Created by this run, so removed when dropped unless renamed into place
+            let temp = temp_file.is_some().then(|| TempPath::from_path(temp));
+            let (Some(src_file), Some(temp_file), Some(temp)) = (src_file, temp_file, temp) else {
+                continue;
+            };
+            open.push(Open {
+                job,
+                size: before.stx_size,
+                before,
+                src_file,
+                temp_file,
+                temp,
+                temp_path,
+            });
+        }
+
+        // Step 3: read each source, stat it, then write and sync its copy
+        let mut entries = Vec::with_capacity(4 * open.len());
+        let mut chains = Vec::with_capacity(open.len());
+        for (k, file) in open.iter().enumerate() {
+            let src_fd = types::Fd(file.src_file.as_raw_fd());
+            let temp_fd = types::Fd(file.temp_file.as_raw_fd());
+            #[allow(clippy::cast_possible_truncation)] // size <= MAX_FILE_SIZE
+            let len = file.size as u32;
+            let read = push(
+                &mut entries,
+                opcode::Read::new(src_fd, self.buffers[k].as_mut_ptr(), len)
+                    .offset(0)
+                    .build()
+                    .flags(squeue::Flags::IO_LINK),
+            );
+            push(
+                &mut entries,
+                opcode::Statx::new(
+                    src_fd,
+                    c"".as_ptr(),
+                    (&raw mut self.stats[3 * k + 2]).cast(),
+                )
+                .flags(libc::AT_EMPTY_PATH | libc::AT_STATX_SYNC_AS_STAT)
+                .mask(libc::STATX_BASIC_STATS)
+                .build()
+                .flags(squeue::Flags::IO_LINK),
+            );
+            let write = opcode::Write::new(temp_fd, self.buffers[k].as_ptr(), len)
+                .offset(0)
+                .build();
+            if options.fsync {
+                push(&mut entries, write.flags(squeue::Flags::IO_LINK));
+                push(&mut entries, opcode::Fsync::new(temp_fd).build());
+            } else {
+                push(&mut entries, write);
+            }
+            chains.push(read);
+        }
+        let done = self.submit(&entries)?;
+
+        let mut written = Vec::with_capacity(open.len());
+        for (k, (file, read)) in open.into_iter().zip(chains).enumerate() {
+            let (read_res, stat_res, write_res) = (done[read], done[read + 1], done[read + 2]);
+            let after = self.stats[3 * k + 2];
+            // Anything unexpected, including a source changed meanwhile, is
+            // copied again on the standard path
+            if read_res < 0
+                || u64::from(read_res.unsigned_abs()) != file.size
+                || stat_res < 0
+                || !same_file(&file.before, &after)
+            {
+                continue;
+            }
+            if write_res < 0 {
+                results[file.job.index] =
+                    Some(Err(io::Error::from_raw_os_error(-write_res).into()));
+                continue;
+            }
+            if u64::from(write_res.unsigned_abs()) != file.size {
+                continue;
+            }
+            if options.fsync && done[read + 3] < 0 {
+                results[file.job.index] =
+                    Some(Err(io::Error::from_raw_os_error(-done[read + 3]).into()));
+                continue;
+            }
+            #[allow(clippy::cast_possible_truncation)]
+            let data = &self.buffers[k][..file.size as usize];
+            let digest = options.hashes_files().then(|| {
+                let mut hasher = Hasher::default();
+                hasher.update(data);
+                hasher.finalize()
+            });
+
+            match finish_temp(&file, digest, options) {
+                Ok(digest) => written.push((file, digest)),
+                Err(e) => results[file.job.index] = Some(Err(e)),
+            }
+        }
+
+        // Step 4: rename every copy into place
+        let flags = match options.on_conflict {
+            OnConflict::Overwrite | OnConflict::UpdateNewer => 0,
+            OnConflict::Skip | OnConflict::Error => libc::RENAME_NOREPLACE,
+        };
+        let mut entries = Vec::with_capacity(written.len());
+        for (file, _) in &written {
+            let entry = opcode::RenameAt::new(
+                types::Fd(libc::AT_FDCWD),
+                self.paths[file.temp_path].as_ptr(),
+                types::Fd(libc::AT_FDCWD),
+                self.paths[file.job.dst_path].as_ptr(),
+            )
+            .flags(flags)
+            .build();
+            push(&mut entries, entry);
+        }
+        options.throttle_metadata(entries.len() as u64);
+        let done = self.submit(&entries)?;
+
+        for ((file, digest), res) in written.into_iter().zip(done) {
+            let dst = file.job.dst;
+            results[file.job.index] = match res {
+                0 => {
+                    // Renamed: nothing left to remove
+                    let _ = file.temp.keep();
+                    ctx.record_created(dst);
+                    Some(Ok(FileCopyResult {
+                        digest,
+                        source: Some(stamp(&file.before)),
+                        ..FileCopyResult::copied(file.size, file.size)
+                    }))
+                }
+                res if res == -libc::EEXIST => {
+                    // Destination was created by another process
+                    if options.on_conflict == OnConflict::Skip {
+                        Some(Ok(FileCopyResult::skipped()))
+                    } else {
+                        Some(Err(Error::AlreadyExists(dst.to_path_buf())))
+                    }
+                }
+                // e.g. a filesystem without RENAME_NOREPLACE
+                res if res == -libc::EINVAL => None,
+                res => Some(Err(Error::Persist {
+                    path: dst.to_path_buf(),
+                    source: io::Error::from_raw_os_error(-res),
+                })),
+            };
+        }
+        Ok(())
+    }
+
+    /// Submit `entries` and wait for all of them.
+    ///
+    /// Returns the result of each entry, by position.
+    fn submit(&mut self, entries: &[squeue::Entry]) -> io::Result<Vec<i32>> {
+        // SAFETY: Every entry points into `self.buffers`, `self.stats` or
+        // `self.paths`, or at descriptors owned by the caller, and all three
+        // are leaked below if an entry may still be in flight.
+        match unsafe { submit_all(&mut self.ring, entries) } {
+            Ok(completions) => {
+                let mut results = vec![-libc::ECANCELED; entries.len()];
+                for (user_data, res) in completions {
+                    if let Some(slot) = usize::try_from(user_data)
+                        .ok()
+                        .and_then(|i| results.get_mut(i))
+                    {
+                        *slot = res;
+                    }
+                }
+                Ok(results)
+            }
+            Err(e) => {
+                // The kernel may still use them, so they must never be reused
+                // or freed; the caller drops the ring
+                std::mem::forget(std::mem::take(&mut self.buffers));
+                std::mem::forget(std::mem::take(&mut self.stats));
+                std::mem::forget(std::mem::take(&mut self.paths));
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Give a written temp file the source's metadata and check it.
+///
+/// Returns the digest, as by `verify_before_persist`.
+fn finish_temp(
+    file: &Open<'_>,
+    digest: Option<super::digest::Digest>,
+    options: &CopyOptions,
+) -> Result<Option<super::digest::Digest>> {
+    if options.preserve_permissions {
+        options.throttle_metadata(1);
+        file.temp_file.set_permissions(Permissions::from_mode(
+            u32::from(file.before.stx_mode) & 0o7777,
+        ))?;
+    }
+    // Checked before the timestamps are set, which reading it could change
+    let digest = verify_before_persist(file.job.src, &file.temp, file.job.dst, digest, options)?;
+    if options.preserve_timestamps {
+        // Ignore timestamp errors - they're not critical
+        options.throttle_metadata(1);
+        let atime =
+            FileTime::from_unix_time(file.before.stx_atime.tv_sec, file.before.stx_atime.tv_nsec);
+        let mtime =
+            FileTime::from_unix_time(file.before.stx_mtime.tv_sec, file.before.stx_mtime.tv_nsec);
+        let _ = filetime::set_file_handle_times(&file.temp_file, Some(atime), Some(mtime));
+    }
+    Ok(digest)
+}
+
+/// Queue `entry`, identified by its position.
+fn push(entries: &mut Vec<squeue::Entry>, entry: squeue::Entry) -> usize {
+    let index = entries.len();
+    entries.push(entry.user_data(index as u64));
+    index
+}
+
+fn c_path(path: &Path) -> Option<CString> {
+    CString::new(path.as_os_str().as_bytes()).ok()
+}
+
+/// A fresh temp file name next to `dst`.
+fn temp_name(dst: &Path) -> PathBuf {
+    let name = format!(
+        "{TEMP_FILE_PREFIX}{:x}-{:x}",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    dst.with_file_name(name)
+}
+
+/// Whether `after` describes the same, unmodified file as `before`.
+fn same_file(before: &libc::statx, after: &libc::statx) -> bool {
+    let time = |t: &libc::statx_timestamp| (t.tv_sec, t.tv_nsec);
+    (before.stx_ino, before.stx_dev_major, before.stx_dev_minor)
+        == (after.stx_ino, after.stx_dev_major, after.stx_dev_minor)
+        && before.stx_size == after.stx_size
+        && time(&before.stx_mtime) == time(&after.stx_mtime)
+        && time(&before.stx_ctime) == time(&after.stx_ctime)
+}
+
+/// Size and mtime of a source, as the journal stores them.
+fn stamp(stat: &libc::statx) -> SourceStamp {
+    let nanos =
+        i128::from(stat.stx_mtime.tv_sec) * 1_000_000_000 + i128::from(stat.stx_mtime.tv_nsec);
+    (stat.stx_size, nanos)
+}
+
+fn cancelled() -> Error {
+    Error::Cancelled {
+        files_copied: 0,
+        bytes_copied: 0,
+        files_skipped: 0,
+        dirs_created: 0,
+        remaining_work: Box::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CopyOptions, copy_dir};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_copy_dir_batches_small_files() {
+        if !is_available() {
+            return;
+        }
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::create_dir_all(&dst).unwrap();
+        let sizes = [
+            0,
+            1,
+            4096,
+            MAX_FILE_SIZE as usize,
+            MAX_FILE_SIZE as usize + 1,
+        ];
+        for i in 0..80 {
+            let data: Vec<u8> = (0..sizes[i % sizes.len()])
+                .map(|b| ((b + i) % 251) as u8)
+                .collect();
+            let path = src.join(format!("sub/f{i}"));
+            fs::write(&path, data).unwrap();
+            fs::set_permissions(&path, Permissions::from_mode(0o640)).unwrap();
+            filetime::set_file_mtime(&path, FileTime::from_unix_time(1_600_000_000, 0)).unwrap();
+        }
+        fs::write(src.join("kept"), "new").unwrap();
+        fs::write(dst.join("kept"), "old").unwrap();
+
+        let options = CopyOptions::default()
+            .with_engine(CopyEngine::IoUring)
+            .with_verify();
+        let stats = copy_dir(&src, &dst, &options).unwrap();
+
+        assert_eq!(stats.files_copied, 80);
+        assert_eq!(stats.files_skipped, 1);
+        assert_eq!(fs::read(dst.join("kept")).unwrap(), b"old");
+        for i in 0..80 {
+            let rel = format!("sub/f{i}");
+            assert_eq!(
+                fs::read(dst.join(&rel)).unwrap(),
+                fs::read(src.join(&rel)).unwrap()
+            );
+            let meta = fs::metadata(dst.join(&rel)).unwrap();
+            assert_eq!(meta.permissions().mode() & 0o7777, 0o640);
+            assert_eq!(
+                FileTime::from_last_modification_time(&meta),
+                FileTime::from_unix_time(1_600_000_000, 0)
+            );
+        }
+        let leftovers = fs::read_dir(dst.join("sub"))
+            .unwrap()
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .unwrap()
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with(TEMP_FILE_PREFIX)
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[test]
+    fn test_copy_batch_leaves_other_files_to_the_standard_path() {
+        if !is_available() {
+            return;
+        }
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("small"), "small").unwrap();
+        fs::write(
+            dir.path().join("large"),
+            vec![1u8; MAX_FILE_SIZE as usize + 1],
+        )
+        .unwrap();
+        fs::write(dir.path().join("replaced"), "new").unwrap();
+        fs::write(dir.path().join("replaced.out"), "old").unwrap();
+        let files: Vec<(PathBuf, PathBuf)> = ["small", "large", "missing", "replaced"]
+            .iter()
+            .map(|name| {
+                (
+                    dir.path().join(name),
+                    dir.path().join(format!("{name}.out")),
+                )
+            })
+            .collect();
+
+        let options = CopyOptions::default()
+            .with_engine(CopyEngine::IoUring)
+            .with_on_conflict(OnConflict::Overwrite);
+        let ctx = CopyContext::new(dir.path());
+        let results = copy_batch(&files, &options, &ctx);
+
+        assert_eq!(results.len(), 4);
+        let copied = results[0].as_ref().unwrap().as_ref().unwrap();
+        assert!(copied.copied);
+        assert_eq!(copied.bytes, 5);
+        assert_eq!(fs::read(dir.path().join("small.out")).unwrap(), b"small");
+        assert!(results[1..].iter().all(Option::is_none));
+        assert!(!dir.path().join("large.out").exists());
+        assert_eq!(fs::read(dir.path().join("replaced.out")).unwrap(), b"old");
+    }
+}
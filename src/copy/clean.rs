@@ -0,0 +1,393 @@
+//! Sweeping temporary files left behind by interrupted runs.
+//!
+//! Files are written under a temporary name next to their destination and
+//! renamed into place once complete. A process that is killed, or a machine
+//! that crashes, leaves those temporary files behind. They all carry a
+//! recognizable name, so [`clean_temp_files`] can find and remove them later.
+
+use crate::error::Result;
+use crate::options::CopyOptions;
+use std::ffi::OsStr;
+use std::fs::{self, FileType, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use super::backup::BACKUP_TEMP_PREFIX;
+use super::partial::{PARTIAL_SUFFIX, STATE_SUFFIX};
+use super::publish::STAGING_PREFIX;
+use super::rollback::ROLLBACK_PREFIX;
+
+/// Prefix of the temporary files parcopy writes next to their destination.
+pub const TEMP_FILE_PREFIX: &str = ".parcopy-tmp-";
+
+/// Builder for a temporary file named with [`TEMP_FILE_PREFIX`].
+pub(crate) fn temp_builder() -> tempfile::Builder<'static, 'static> {
+    let mut builder = tempfile::Builder::new();
+    builder.prefix(TEMP_FILE_PREFIX);
+    builder
+}
+
+/// Kind of a leftover found by [`find_stale_temp_files`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TempKind {
+    /// A file being copied, named with [`TEMP_FILE_PREFIX`]
+    TempFile,
+    /// A backup being put in place
+    Backup,
+    /// The partial file or checkpoint of a resumable copy (see
+    /// [`CopyOptions::resume_threshold`](crate::CopyOptions::resume_threshold))
+    Partial,
+    /// The staging directory of an atomic publish (see
+    /// [`CopyOptions::atomic_publish`](crate::CopyOptions::atomic_publish))
+    Staging,
+}
+
+impl TempKind {
+    /// Stable lowercase name of this kind.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::TempFile => "temp_file",
+            Self::Backup => "backup",
+            Self::Partial => "partial",
+            Self::Staging => "staging",
+        }
+    }
+}
+
+/// A leftover temporary file or directory from an earlier run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleTemp {
+    /// Path of the leftover
+    pub path: PathBuf,
+    /// What the leftover is
+    pub kind: TempKind,
+    /// Size in bytes (of every file inside, for a staging directory)
+    pub bytes: u64,
+    /// Time since the leftover was last modified
+    pub age: Duration,
+}
+
+/// Result of [`clean_temp_files`].
+#[derive(Debug, Default)]
+pub struct CleanReport {
+    /// Leftovers removed
+    pub removed: Vec<StaleTemp>,
+    /// Bytes removed
+    pub bytes_removed: u64,
+    /// Leftovers that could not be removed
+    pub failed: Vec<(StaleTemp, io::Error)>,
+    /// Duration of the sweep
+    pub duration: Duration,
+}
+
+/// Find the stale temporary files parcopy left under `root`.
+///
+/// Only entries named like parcopy's own temporary files are considered:
+/// files starting with [`TEMP_FILE_PREFIX`] or `.parcopy-bak-`, partial
+/// files of resumable copies (`.NAME.parcopy-partial` and their `.state`
+/// checkpoints), and `.parcopy-staging-*` directories of atomic publishes. An
+/// entry is stale if it was last modified at least `min_age` ago and, on
+/// Unix, is owned by the current user. Entries replaced by a transactional
+/// run (`.parcopy-rollback-*`) may hold the only copy of a file and are never
+/// considered.
+///
+/// The tree is walked without following symlinks. Nothing is modified.
+///
+/// `min_age` must be longer than any copy that may still be running, since
+/// the temporary files of a running copy look just like stale ones.
+///
+/// # Errors
+///
+/// Returns an error if `root` or a directory under it cannot be read
+/// ([`Error::Io`](crate::Error::Io)).
+pub fn find_stale_temp_files(root: &Path, min_age: Duration) -> Result<Vec<StaleTemp>> {
+    let mut found = Vec::new();
+    walk(root, SystemTime::now(), min_age, &mut found)?;
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(found)
+}
+
+/// Remove the stale temporary files parcopy left under `root`.
+///
+/// Leftovers are found as by [`find_stale_temp_files`]; staging directories
+/// are removed with everything in them. Leftovers that cannot be removed are
+/// reported in the returned [`CleanReport`].
+///
+/// # Errors
+///
+/// Returns an error if `root` or a directory under it cannot be read
+/// ([`Error::Io`](crate::Error::Io)).
+///
+/// # Example
+///
+/// ```no_run
+/// use parcopy::clean_temp_files;
+/// use std::path::Path;
+/// use std::time::Duration;
+///
+/// let report = clean_temp_files(Path::new("/mnt/nfs"), Duration::from_secs(24 * 3600))?;
+/// println!("removed {} files ({} bytes)", report.removed.len(), report.bytes_removed);
+/// # Ok::<(), parcopy::Error>(())
+/// ```
+pub fn clean_temp_files(root: &Path, min_age: Duration) -> Result<CleanReport> {
+    let start = Instant::now();
+    let mut report = remove_stale(find_stale_temp_files(root, min_age)?);
+    report.duration = start.elapsed();
+    Ok(report)
+}
+
+/// Remove the leftovers `found`, reporting those that cannot be removed.
+fn remove_stale(found: impl IntoIterator<Item = StaleTemp>) -> CleanReport {
+    let mut report = CleanReport::default();
+    for stale in found {
+        let result = match stale.kind {
+            TempKind::Staging => fs::remove_dir_all(&stale.path),
+            TempKind::TempFile | TempKind::Backup | TempKind::Partial => {
+                fs::remove_file(&stale.path)
+            }
+        };
+        match result {
+            Ok(()) => {
+                report.bytes_removed += stale.bytes;
+                report.removed.push(stale);
+            }
+            // Removed by someone else in the meantime
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => report.failed.push((stale, e)),
+        }
+    }
+    report
+}
+
+/// Remove stale temp files under `dst` if
+/// [`clean_stale_temps`](CopyOptions::clean_stale_temps) is set.
+///
+/// Partial files of resumable copies are kept, since the copy that follows
+/// resumes from them. Best effort: failures are reported as warnings.
+pub(crate) fn sweep_stale_temps(dst: &Path, options: &CopyOptions) {
+    let Some(min_age) = options.clean_stale_temps else {
+        return;
+    };
+    if !dst.is_dir() {
+        return;
+    }
+    let found = match find_stale_temp_files(dst, min_age) {
+        Ok(found) => found,
+        Err(e) => {
+            options.warn(&format!(
+                "cannot sweep stale temp files in {}: {e}",
+                dst.display()
+            ));
+            return;
+        }
+    };
+    let report = remove_stale(
+        found
+            .into_iter()
+            .filter(|stale| stale.kind != TempKind::Partial),
+    );
+    for stale in &report.removed {
+        options.verbose(&format!("removed stale temp file {}", stale.path.display()));
+    }
+    for (stale, e) in &report.failed {
+        options.warn(&format!(
+            "cannot remove stale temp file {}: {e}",
+            stale.path.display()
+        ));
+    }
+}
+
+fn walk(dir: &Path, now: SystemTime, min_age: Duration, found: &mut Vec<StaleTemp>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let name = entry.file_name();
+
+        match temp_kind(&name, file_type) {
+            Some(kind) => {
+                // Entries may disappear while the tree is walked
+                let Ok(meta) = entry.metadata() else {
+                    continue;
+                };
+                let age = meta
+                    .modified()
+                    .ok()
+                    .and_then(|mtime| now.duration_since(mtime).ok())
+                    .unwrap_or_default();
+                if age >= min_age && owned_by_current_user(&meta) {
+                    let path = entry.path();
+                    let bytes = match kind {
+                        TempKind::Staging => tree_size(&path),
+                        _ => meta.len(),
+                    };
+                    found.push(StaleTemp {
+                        path,
+                        kind,
+                        bytes,
+                        age,
+                    });
+                }
+            }
+            None if file_type.is_dir() && !starts_with(&name, ROLLBACK_PREFIX) => {
+                walk(&entry.path(), now, min_age, found)?;
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+/// Kind of leftover an entry named `name` is, if it is one.
+fn temp_kind(name: &OsStr, file_type: FileType) -> Option<TempKind> {
+    if file_type.is_dir() {
+        return starts_with(name, STAGING_PREFIX).then_some(TempKind::Staging);
+    }
+    if !file_type.is_file() {
+        return None;
+    }
+    let name = name.to_str()?;
+    if name.starts_with(TEMP_FILE_PREFIX) {
+        Some(TempKind::TempFile)
+    } else if name.starts_with(BACKUP_TEMP_PREFIX) {
+        Some(TempKind::Backup)
+    } else if name.starts_with('.')
+        && (name.ends_with(PARTIAL_SUFFIX)
+            || name
+                .strip_suffix(STATE_SUFFIX)
+                .is_some_and(|name| name.ends_with(PARTIAL_SUFFIX)))
+    {
+        Some(TempKind::Partial)
+    } else {
+        None
+    }
+}
+
+fn starts_with(name: &OsStr, prefix: &str) -> bool {
+    name.as_encoded_bytes().starts_with(prefix.as_bytes())
+}
+
+/// Total size of the files under `dir`, not following symlinks.
+fn tree_size(dir: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .filter_map(std::result::Result::ok)
+        .map(|entry| match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => tree_size(&entry.path()),
+            Ok(_) => entry.metadata().map_or(0, |meta| meta.len()),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+#[cfg(unix)]
+fn owned_by_current_user(meta: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    // SAFETY: geteuid has no preconditions and always succeeds
+    meta.uid() == unsafe { libc::geteuid() }
+}
+
+#[cfg(not(unix))]
+fn owned_by_current_user(_meta: &Metadata) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use filetime::FileTime;
+    use tempfile::tempdir;
+
+    fn age(path: &Path, secs: i64) {
+        let mtime = FileTime::from_unix_time(FileTime::now().unix_seconds() - secs, 0);
+        filetime::set_file_mtime(path, mtime).unwrap();
+    }
+
+    #[test]
+    fn test_clean_temp_files_removes_stale_leftovers() {
+        let dir = tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::create_dir_all(root.join(".parcopy-staging-abc/tree")).unwrap();
+        fs::create_dir_all(root.join(".parcopy-rollback-abc")).unwrap();
+        let files = [
+            ".parcopy-tmp-old",
+            "sub/.parcopy-tmp-recent",
+            "sub/.parcopy-bak-x",
+            "sub/.big.iso.parcopy-partial",
+            "sub/.big.iso.parcopy-partial.state",
+            ".parcopy-staging-abc/tree/a.txt",
+            ".parcopy-rollback-abc/.parcopy-tmp-kept",
+            ".tmpABC123",
+            "data.txt",
+        ];
+        for file in files {
+            fs::write(root.join(file), "1234").unwrap();
+            if file != "sub/.parcopy-tmp-recent" {
+                age(&root.join(file), 7200);
+            }
+        }
+        age(&root.join(".parcopy-staging-abc"), 7200);
+
+        let stale = find_stale_temp_files(root, Duration::from_secs(3600)).unwrap();
+        let kinds: Vec<_> = stale
+            .iter()
+            .map(|s| (s.path.strip_prefix(root).unwrap().to_path_buf(), s.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (PathBuf::from(".parcopy-staging-abc"), TempKind::Staging),
+                (PathBuf::from(".parcopy-tmp-old"), TempKind::TempFile),
+                (
+                    PathBuf::from("sub/.big.iso.parcopy-partial"),
+                    TempKind::Partial
+                ),
+                (
+                    PathBuf::from("sub/.big.iso.parcopy-partial.state"),
+                    TempKind::Partial
+                ),
+                (PathBuf::from("sub/.parcopy-bak-x"), TempKind::Backup),
+            ]
+        );
+
+        let report = clean_temp_files(root, Duration::from_secs(3600)).unwrap();
+        assert_eq!(report.removed.len(), 5);
+        assert_eq!(report.bytes_removed, 20);
+        assert!(report.failed.is_empty());
+        assert!(!root.join(".parcopy-staging-abc").exists());
+        assert!(root.join("sub/.parcopy-tmp-recent").exists());
+        assert!(
+            root.join(".parcopy-rollback-abc/.parcopy-tmp-kept")
+                .exists()
+        );
+        assert!(root.join(".tmpABC123").exists());
+        assert!(root.join("data.txt").exists());
+    }
+
+    #[test]
+    fn test_copy_dir_sweeps_stale_temps() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(&src).unwrap();
+        fs::create_dir_all(&dst).unwrap();
+        fs::write(src.join("a.txt"), "a").unwrap();
+        fs::write(dst.join(".parcopy-tmp-crashed"), "partial").unwrap();
+        age(&dst.join(".parcopy-tmp-crashed"), 7200);
+        fs::write(dst.join(".big.iso.parcopy-partial"), "resume me").unwrap();
+        age(&dst.join(".big.iso.parcopy-partial"), 7200);
+
+        crate::copy_dir(&src, &dst, &CopyOptions::default()).unwrap();
+        assert!(dst.join(".parcopy-tmp-crashed").exists());
+
+        let options = CopyOptions::default().with_clean_stale_temps(Duration::from_secs(3600));
+        crate::copy_dir(&src, &dst, &options).unwrap();
+        assert!(!dst.join(".parcopy-tmp-crashed").exists());
+        assert!(dst.join(".big.iso.parcopy-partial").exists());
+        assert!(dst.join("a.txt").exists());
+    }
+}
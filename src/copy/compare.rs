@@ -0,0 +1,592 @@
+//! Comparing a source tree with its copy.
+//!
+//! [`compare_trees`] walks both trees with the same traversal rules as
+//! [`copy_dir`](crate::copy_dir) and reports every entry the copy would not
+//! reproduce as-is, without writing anything.
+
+use crate::error::{Error, Result};
+use crate::options::CopyOptions;
+use filetime::FileTime;
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::fs::{self, Metadata, Permissions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+use super::digest::{Digest, hash_file};
+use super::dir::collect_entries;
+use super::utils::{DirEntry, is_escaping_symlink, run_in_pool};
+
+/// Kind of a directory entry, as seen when comparing trees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    /// Regular file
+    File,
+    /// Directory
+    Dir,
+    /// Symbolic link (only when symlinks are preserved)
+    Symlink,
+    /// Anything else (socket, device, ...)
+    Other,
+}
+
+/// How an entry of the destination tree differs from the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Difference {
+    /// The entry exists in the source but not in the destination
+    Missing,
+    /// The entry exists in the destination but not in the source
+    Extra,
+    /// The entry is of a different kind on each side
+    Kind {
+        /// Kind in the source
+        src: EntryKind,
+        /// Kind in the destination
+        dst: EntryKind,
+    },
+    /// The files have different sizes
+    Size {
+        /// Size of the source
+        src: u64,
+        /// Size of the destination
+        dst: u64,
+    },
+    /// The files have different modification times
+    Mtime {
+        /// Modification time of the source
+        src: SystemTime,
+        /// Modification time of the destination
+        dst: SystemTime,
+    },
+    /// The files have the same size but different contents
+    Content {
+        /// SHA-256 digest of the source
+        src: Digest,
+        /// SHA-256 digest of the destination
+        dst: Digest,
+    },
+    /// The entries have different permissions
+    Permissions {
+        /// Permissions of the source
+        src: Permissions,
+        /// Permissions of the destination
+        dst: Permissions,
+    },
+    /// The symlinks point to different targets
+    SymlinkTarget {
+        /// Target of the source symlink
+        src: PathBuf,
+        /// Target of the destination symlink
+        dst: PathBuf,
+    },
+}
+
+/// One difference found by [`compare_trees`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeDifference {
+    /// Path relative to the tree roots (empty for the roots themselves)
+    pub path: PathBuf,
+    /// What differs
+    pub difference: Difference,
+}
+
+/// Result of [`compare_trees`].
+#[derive(Debug, Clone, Default)]
+pub struct TreeDiff {
+    /// Every difference found, sorted by path
+    pub differences: Vec<TreeDifference>,
+    /// Number of source files compared
+    pub files_compared: u64,
+    /// Number of source directories compared
+    pub dirs_compared: u64,
+    /// Number of source symlinks compared
+    pub symlinks_compared: u64,
+    /// Duration of the comparison
+    pub duration: std::time::Duration,
+}
+
+impl TreeDiff {
+    /// Whether the destination matches the source.
+    #[must_use]
+    pub fn is_identical(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+/// Compare the tree at `dst` with the tree at `src` without copying anything.
+///
+/// Both trees are walked with the traversal rules `copy_dir` applies for the
+/// same `options` (`preserve_symlinks`, `max_depth`, skipped special files,
+/// blocked escaping symlinks). What is compared follows the options too:
+///
+/// - presence and kind of every entry, and symlink targets;
+/// - file sizes, and modification times if `preserve_timestamps` is set;
+/// - file contents (SHA-256) if `checksum` is set;
+/// - permissions of files if `preserve_permissions` is set, and of
+///   directories if `preserve_dir_permissions` is set.
+///
+/// Only the topmost entry of a missing or extra subtree is reported. A
+/// missing `dst` is reported as a single [`Difference::Missing`] with an
+/// empty path.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Source does not exist ([`Error::SourceNotFound`])
+/// - Source or destination is not a directory ([`Error::NotADirectory`])
+/// - Symlink loop detected ([`Error::SymlinkLoop`])
+/// - Max depth exceeded ([`Error::MaxDepthExceeded`])
+/// - IO operations fail ([`Error::Io`])
+///
+/// # Example
+///
+/// ```no_run
+/// use parcopy::{CopyOptions, compare_trees};
+/// use std::path::Path;
+///
+/// let diff = compare_trees(Path::new("data"), Path::new("/mnt/new/data"), &CopyOptions::default())?;
+/// for entry in &diff.differences {
+///     println!("{}: {:?}", entry.path.display(), entry.difference);
+/// }
+/// # Ok::<(), parcopy::Error>(())
+/// ```
+pub fn compare_trees(src: &Path, dst: &Path, options: &CopyOptions) -> Result<TreeDiff> {
+    let start = Instant::now();
+
+    if !src.exists() {
+        return Err(Error::SourceNotFound(src.to_path_buf()));
+    }
+    if !src.is_dir() {
+        return Err(Error::NotADirectory(src.to_path_buf()));
+    }
+    match fs::metadata(dst) {
+        Ok(meta) if meta.is_dir() => {}
+        Ok(_) => return Err(Error::NotADirectory(dst.to_path_buf())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Ok(TreeDiff {
+                differences: vec![TreeDifference {
+                    path: PathBuf::new(),
+                    difference: Difference::Missing,
+                }],
+                duration: start.elapsed(),
+                ..TreeDiff::default()
+            });
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    let ours = Tree::collect(src, dst, options)?;
+    let theirs = Tree::collect(dst, dst, options)?;
+    let expected: HashSet<&Path> = ours.paths().collect();
+
+    let mut differences = Vec::new();
+    // Paths whose subtree is reported as a whole
+    let mut reported: HashSet<PathBuf> = HashSet::new();
+    let covered = |reported: &HashSet<PathBuf>, rel: &Path| {
+        rel.ancestors().skip(1).any(|a| reported.contains(a))
+    };
+
+    let mut dir_pairs = Vec::new();
+    for dir in &ours.dirs {
+        let rel = relative(dst, &dir.dst);
+        if covered(&reported, rel) {
+            continue;
+        }
+        match entry_kind(&dir.dst, options)? {
+            None => {
+                reported.insert(rel.to_path_buf());
+                differences.push(difference(rel, Difference::Missing));
+            }
+            Some(EntryKind::Dir) => dir_pairs.push((rel, dir)),
+            Some(kind) => {
+                reported.insert(rel.to_path_buf());
+                differences.push(difference(
+                    rel,
+                    Difference::Kind {
+                        src: EntryKind::Dir,
+                        dst: kind,
+                    },
+                ));
+            }
+        }
+    }
+
+    if options.preserve_dir_permissions {
+        for (rel, dir) in &dir_pairs {
+            let src_perm = fs::metadata(&dir.src)?.permissions();
+            let dst_perm = fs::metadata(&dir.dst)?.permissions();
+            if src_perm != dst_perm {
+                differences.push(difference(
+                    rel,
+                    Difference::Permissions {
+                        src: src_perm,
+                        dst: dst_perm,
+                    },
+                ));
+            }
+        }
+    }
+
+    let files: Vec<_> = ours
+        .files
+        .iter()
+        .filter(|(_, dst_file)| !covered(&reported, relative(dst, dst_file)))
+        .collect();
+    let file_results = run_in_pool(options, || {
+        files
+            .par_iter()
+            .map(|(src_file, dst_file)| {
+                compare_file(src_file, dst_file, options)
+                    .map(|found| (relative(dst, dst_file), found))
+            })
+            .collect::<Vec<_>>()
+    });
+    for result in file_results {
+        let (rel, found) = result?;
+        if found.iter().any(|d| matches!(d, Difference::Kind { .. })) {
+            reported.insert(rel.to_path_buf());
+        }
+        differences.extend(found.into_iter().map(|d| difference(rel, d)));
+    }
+
+    for (src_link, dst_link) in &ours.symlinks {
+        let rel = relative(dst, dst_link);
+        if covered(&reported, rel) {
+            continue;
+        }
+        let src_target = fs::read_link(src_link)?;
+        match entry_kind(dst_link, options)? {
+            None => differences.push(difference(rel, Difference::Missing)),
+            Some(EntryKind::Symlink) => {
+                let dst_target = fs::read_link(dst_link)?;
+                if dst_target != src_target {
+                    differences.push(difference(
+                        rel,
+                        Difference::SymlinkTarget {
+                            src: src_target,
+                            dst: dst_target,
+                        },
+                    ));
+                }
+            }
+            Some(kind) => {
+                reported.insert(rel.to_path_buf());
+                differences.push(difference(
+                    rel,
+                    Difference::Kind {
+                        src: EntryKind::Symlink,
+                        dst: kind,
+                    },
+                ));
+            }
+        }
+    }
+
+    // Entries only in the destination, topmost first
+    let mut extra: HashSet<&Path> = HashSet::new();
+    for rel in theirs.paths() {
+        if expected.contains(rel)
+            || reported.contains(rel)
+            || rel
+                .ancestors()
+                .skip(1)
+                .any(|a| extra.contains(a) || reported.contains(a))
+        {
+            continue;
+        }
+        extra.insert(rel);
+        differences.push(difference(rel, Difference::Extra));
+    }
+
+    differences.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(TreeDiff {
+        differences,
+        files_compared: ours.files.len() as u64,
+        dirs_compared: ours.dirs.len() as u64,
+        symlinks_compared: ours.symlinks.len() as u64,
+        duration: start.elapsed(),
+    })
+}
+
+/// Entries of one tree, mapped to their paths under the destination root.
+struct Tree {
+    dirs: Vec<DirEntry>,
+    files: Vec<(PathBuf, PathBuf)>,
+    symlinks: Vec<(PathBuf, PathBuf)>,
+    root: PathBuf,
+}
+
+impl Tree {
+    /// Walk `root` as `copy_dir` would when copying it to `dst`.
+    fn collect(root: &Path, dst: &Path, options: &CopyOptions) -> Result<Self> {
+        let mut dirs = Vec::new();
+        let mut files = Vec::new();
+        let mut symlinks = Vec::new();
+        let mut visited = HashSet::new();
+        collect_entries(
+            root,
+            dst,
+            &mut dirs,
+            &mut files,
+            &mut symlinks,
+            options,
+            0,
+            &mut visited,
+        )?;
+
+        // Escaping symlinks are not copied when they are blocked
+        if options.block_escaping_symlinks {
+            symlinks.retain(|(link, _)| {
+                fs::read_link(link).map_or(true, |t| !(t.is_relative() && is_escaping_symlink(&t)))
+            });
+        }
+
+        Ok(Self {
+            dirs,
+            files,
+            symlinks,
+            root: dst.to_path_buf(),
+        })
+    }
+
+    /// Every entry's path relative to the destination root, parents first.
+    fn paths(&self) -> impl Iterator<Item = &Path> {
+        let dirs = self.dirs.iter().map(|d| d.dst.as_path());
+        let files = self.files.iter().map(|(_, d)| d.as_path());
+        let symlinks = self.symlinks.iter().map(|(_, d)| d.as_path());
+        dirs.chain(files)
+            .chain(symlinks)
+            .map(|path| relative(&self.root, path))
+            .filter(|rel| !rel.as_os_str().is_empty())
+    }
+}
+
+/// Compare one source file with its copy.
+fn compare_file(src: &Path, dst: &Path, options: &CopyOptions) -> Result<Vec<Difference>> {
+    let Some(kind) = entry_kind(dst, options)? else {
+        return Ok(vec![Difference::Missing]);
+    };
+    if kind != EntryKind::File {
+        return Ok(vec![Difference::Kind {
+            src: EntryKind::File,
+            dst: kind,
+        }]);
+    }
+
+    let src_meta = fs::metadata(src)?;
+    let dst_meta = fs::metadata(dst)?;
+    let mut found = Vec::new();
+
+    if src_meta.len() != dst_meta.len() {
+        found.push(Difference::Size {
+            src: src_meta.len(),
+            dst: dst_meta.len(),
+        });
+    } else if options.checksum {
+        let (src_digest, dst_digest) = (hash_file(src)?, hash_file(dst)?);
+        if src_digest != dst_digest {
+            found.push(Difference::Content {
+                src: src_digest,
+                dst: dst_digest,
+            });
+        }
+    }
+    if options.preserve_timestamps && !same_mtime(&src_meta, &dst_meta) {
+        found.push(Difference::Mtime {
+            src: src_meta.modified()?,
+            dst: dst_meta.modified()?,
+        });
+    }
+    if options.preserve_permissions && src_meta.permissions() != dst_meta.permissions() {
+        found.push(Difference::Permissions {
+            src: src_meta.permissions(),
+            dst: dst_meta.permissions(),
+        });
+    }
+
+    Ok(found)
+}
+
+/// Kind of the entry at `path`, or `None` if there is none.
+///
+/// Symlinks are followed unless `preserve_symlinks` is set, as when copying.
+fn entry_kind(path: &Path, options: &CopyOptions) -> io::Result<Option<EntryKind>> {
+    let meta = if options.preserve_symlinks {
+        fs::symlink_metadata(path)
+    } else {
+        fs::metadata(path)
+    };
+    let file_type = match meta {
+        Ok(meta) => meta.file_type(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    Ok(Some(if file_type.is_symlink() {
+        EntryKind::Symlink
+    } else if file_type.is_dir() {
+        EntryKind::Dir
+    } else if file_type.is_file() {
+        EntryKind::File
+    } else {
+        EntryKind::Other
+    }))
+}
+
+fn same_mtime(a: &Metadata, b: &Metadata) -> bool {
+    FileTime::from_last_modification_time(a) == FileTime::from_last_modification_time(b)
+}
+
+fn relative<'p>(root: &Path, path: &'p Path) -> &'p Path {
+    path.strip_prefix(root).unwrap_or(path)
+}
+
+fn difference(path: &Path, difference: Difference) -> TreeDifference {
+    TreeDifference {
+        path: path.to_path_buf(),
+        difference,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::copy_dir;
+    use tempfile::tempdir;
+
+    fn kinds(diff: &TreeDiff) -> Vec<(String, Difference)> {
+        diff.differences
+            .iter()
+            .map(|d| (d.path.to_string_lossy().into_owned(), d.difference.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_compare_trees_identical_after_copy() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("a.txt"), "a").unwrap();
+        fs::write(src.join("sub/b.txt"), "b").unwrap();
+        copy_dir(&src, &dst, &CopyOptions::default()).unwrap();
+
+        let options = CopyOptions::default().with_checksum();
+        let diff = compare_trees(&src, &dst, &options).unwrap();
+
+        assert!(diff.is_identical(), "{:?}", diff.differences);
+        assert_eq!(diff.files_compared, 2);
+        assert_eq!(diff.dirs_compared, 2);
+    }
+
+    #[test]
+    fn test_compare_trees_reports_differences() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(src.join("gone")).unwrap();
+        fs::write(src.join("gone/x.txt"), "x").unwrap();
+        fs::write(src.join("missing.txt"), "m").unwrap();
+        fs::write(src.join("size.txt"), "short").unwrap();
+        fs::write(src.join("content.txt"), "aaaa").unwrap();
+        fs::write(src.join("kind"), "file").unwrap();
+        copy_dir(&src, &dst, &CopyOptions::default()).unwrap();
+
+        fs::remove_dir_all(dst.join("gone")).unwrap();
+        fs::remove_file(dst.join("missing.txt")).unwrap();
+        fs::write(dst.join("size.txt"), "longer").unwrap();
+        fs::remove_file(dst.join("kind")).unwrap();
+        fs::create_dir(dst.join("kind")).unwrap();
+        fs::write(dst.join("kind/inner"), "i").unwrap();
+        fs::create_dir_all(dst.join("extra/deep")).unwrap();
+        fs::write(dst.join("extra/deep/e.txt"), "e").unwrap();
+        // Same size and mtime, different bytes
+        fs::write(dst.join("content.txt"), "bbbb").unwrap();
+        let mtime =
+            FileTime::from_last_modification_time(&fs::metadata(src.join("content.txt")).unwrap());
+        filetime::set_file_mtime(dst.join("content.txt"), mtime).unwrap();
+
+        let options = CopyOptions::default().without_timestamps().with_checksum();
+        let diff = compare_trees(&src, &dst, &options).unwrap();
+        let found = kinds(&diff);
+
+        assert_eq!(found.len(), 6, "{found:?}");
+        assert!(matches!(&found[0], (p, Difference::Content { .. }) if p == "content.txt"));
+        assert_eq!(found[1], ("extra".to_owned(), Difference::Extra));
+        assert_eq!(found[2], ("gone".to_owned(), Difference::Missing));
+        assert_eq!(
+            found[3],
+            (
+                "kind".to_owned(),
+                Difference::Kind {
+                    src: EntryKind::File,
+                    dst: EntryKind::Dir
+                }
+            )
+        );
+        assert_eq!(found[4], ("missing.txt".to_owned(), Difference::Missing));
+        assert_eq!(
+            found[5],
+            ("size.txt".to_owned(), Difference::Size { src: 5, dst: 6 })
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_compare_trees_symlinks_and_permissions() {
+        use std::os::unix::fs::{PermissionsExt, symlink};
+
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("a.txt"), "a").unwrap();
+        symlink("a.txt", src.join("link")).unwrap();
+        copy_dir(&src, &dst, &CopyOptions::default()).unwrap();
+
+        fs::remove_file(dst.join("link")).unwrap();
+        symlink("elsewhere", dst.join("link")).unwrap();
+        fs::set_permissions(dst.join("a.txt"), fs::Permissions::from_mode(0o600)).unwrap();
+        fs::set_permissions(src.join("a.txt"), fs::Permissions::from_mode(0o644)).unwrap();
+
+        let diff = compare_trees(&src, &dst, &CopyOptions::default()).unwrap();
+        let found = kinds(&diff);
+
+        assert_eq!(found.len(), 2, "{found:?}");
+        assert!(matches!(&found[0], (p, Difference::Permissions { .. }) if p == "a.txt"));
+        assert_eq!(
+            found[1],
+            (
+                "link".to_owned(),
+                Difference::SymlinkTarget {
+                    src: PathBuf::from("a.txt"),
+                    dst: PathBuf::from("elsewhere")
+                }
+            )
+        );
+
+        let options = CopyOptions {
+            preserve_permissions: false,
+            ..CopyOptions::default()
+        };
+        assert_eq!(
+            compare_trees(&src, &dst, &options)
+                .unwrap()
+                .differences
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_compare_trees_missing_destination() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+
+        let diff = compare_trees(&src, &dir.path().join("none"), &CopyOptions::default()).unwrap();
+
+        assert_eq!(kinds(&diff), vec![(String::new(), Difference::Missing)]);
+    }
+}
@@ -0,0 +1,167 @@
+//! Per-run state shared by every file in one copy operation.
+//!
+//! A [`CopyContext`] is created once per [`copy_file`](crate::copy_file),
+//! [`copy_dir`](crate::copy_dir), or move call and passed down to each file copy.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::dedup::DedupIndex;
+use super::rollback::Rollback;
+
+/// State shared by all files copied in a single run.
+pub(crate) struct CopyContext {
+    /// Destination root of the run, used to derive tree-relative paths
+    dst_root: PathBuf,
+    /// Remove each source entry once its destination is in place (moves)
+    remove_source: bool,
+    /// Journal of destination changes to undo if the run fails (transactional runs)
+    rollback: Option<Rollback>,
+    /// Where the tree at `dst_root` is moved once complete (atomic publish)
+    publish_root: Option<PathBuf>,
+    /// Files written so far, to link duplicates to (with `dedup`)
+    dedup: DedupIndex,
+}
+
+impl CopyContext {
+    /// Create a context for a run whose destination tree starts at `dst_root`.
+    pub(crate) fn new(dst_root: &Path) -> Self {
+        Self {
+            dst_root: dst_root.to_path_buf(),
+            remove_source: false,
+            rollback: None,
+            publish_root: None,
+            dedup: DedupIndex::default(),
+        }
+    }
+
+    /// Mark this run's tree as staged, to be moved to `root` once complete.
+    pub(crate) fn published_at(mut self, root: &Path) -> Self {
+        self.publish_root = Some(root.to_path_buf());
+        self
+    }
+
+    /// Path `dst` will have once the run is complete.
+    pub(crate) fn final_path(&self, dst: &Path) -> PathBuf {
+        match (&self.publish_root, dst.strip_prefix(&self.dst_root)) {
+            (Some(root), Ok(rel)) => root.join(rel),
+            _ => dst.to_path_buf(),
+        }
+    }
+
+    /// Turn this run into a move that removes sources as they are copied.
+    pub(crate) fn moving(mut self) -> Self {
+        self.remove_source = true;
+        self
+    }
+
+    /// Whether a completion journal may be consulted for this run.
+    ///
+    /// Moves must remove every source and a staged tree must hold every file,
+    /// so neither can skip files the destination may not have.
+    pub(crate) fn uses_journal(&self) -> bool {
+        !self.remove_source && self.publish_root.is_none()
+    }
+
+    /// Whether source entries are removed after they are copied.
+    pub(crate) fn removes_source(&self) -> bool {
+        self.remove_source
+    }
+
+    /// Turn this run into a transaction whose changes can be rolled back.
+    pub(crate) fn transactional(mut self) -> Self {
+        self.rollback = Some(Rollback::default());
+        self
+    }
+
+    /// The rollback journal, if this run is transactional.
+    pub(crate) fn rollback(&self) -> Option<&Rollback> {
+        self.rollback.as_ref()
+    }
+
+    /// Take the rollback journal out of the context to finish the run.
+    pub(crate) fn into_rollback(self) -> Option<Rollback> {
+        self.rollback
+    }
+
+    /// Files written by this run, by contents.
+    pub(crate) fn dedup(&self) -> &DedupIndex {
+        &self.dedup
+    }
+
+    /// Record an entry created by this run.
+    pub(crate) fn record_created(&self, path: &Path) {
+        if let Some(rollback) = &self.rollback {
+            rollback.created(path);
+        }
+    }
+
+    /// Keep the file at `path` so it can be restored, before it is replaced.
+    pub(crate) fn keep_original(&self, path: &Path) -> io::Result<()> {
+        match &self.rollback {
+            Some(rollback) => rollback.keep_original(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Create a context for copying a single file to `dst`.
+    ///
+    /// The destination root is the parent directory of `dst`.
+    pub(crate) fn for_file(dst: &Path) -> Self {
+        Self::new(dst.parent().unwrap_or(Path::new("")))
+    }
+
+    /// Path of `dst` relative to the destination root.
+    ///
+    /// Falls back to the file name (or `dst` itself) if `dst` is not inside the root.
+    pub(crate) fn relative<'p>(&self, dst: &'p Path) -> &'p Path {
+        dst.strip_prefix(&self.dst_root)
+            .ok()
+            .filter(|rel| !rel.as_os_str().is_empty())
+            .or_else(|| dst.file_name().map(Path::new))
+            .unwrap_or(dst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_inside_root() {
+        let ctx = CopyContext::new(Path::new("/dst"));
+        assert_eq!(
+            ctx.relative(Path::new("/dst/a/b.txt")),
+            Path::new("a/b.txt")
+        );
+    }
+
+    #[test]
+    fn test_relative_outside_root_uses_file_name() {
+        let ctx = CopyContext::new(Path::new("/dst"));
+        assert_eq!(ctx.relative(Path::new("/other/b.txt")), Path::new("b.txt"));
+    }
+
+    #[test]
+    fn test_final_path_of_staged_tree() {
+        let ctx =
+            CopyContext::new(Path::new("/dst/.staging/tree")).published_at(Path::new("/dst/site"));
+        assert_eq!(
+            ctx.final_path(Path::new("/dst/.staging/tree/a/b.txt")),
+            Path::new("/dst/site/a/b.txt")
+        );
+        assert_eq!(
+            CopyContext::new(Path::new("/dst")).final_path(Path::new("/dst/a")),
+            Path::new("/dst/a")
+        );
+    }
+
+    #[test]
+    fn test_for_file_uses_parent() {
+        let ctx = CopyContext::for_file(Path::new("/dst/file.txt"));
+        assert_eq!(
+            ctx.relative(Path::new("/dst/file.txt")),
+            Path::new("file.txt")
+        );
+    }
+}
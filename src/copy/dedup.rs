@@ -0,0 +1,129 @@
+//! Content-based deduplication of files written in one run.
+//!
+//! Every file a run writes is indexed by size and SHA-256 digest. A later file
+//! with the same contents is hard-linked or reflinked to the earlier copy
+//! instead of being written again.
+
+use crate::options::{CopyOptions, DedupMode};
+use crate::utils::path::safe_path;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tempfile::TempPath;
+
+use super::clean::temp_builder;
+use super::digest::Digest;
+
+/// Files written by a run, by size and then digest.
+#[derive(Debug, Default)]
+pub(crate) struct DedupIndex {
+    files: Mutex<HashMap<u64, HashMap<Digest, PathBuf>>>,
+}
+
+impl DedupIndex {
+    /// Whether a file of `size` bytes has been written, so a new one may be a duplicate.
+    pub(crate) fn has_size(&self, size: u64) -> bool {
+        self.lock().contains_key(&size)
+    }
+
+    /// Earlier copy of a file with this size and digest, if any.
+    pub(crate) fn find(&self, size: u64, digest: Digest) -> Option<PathBuf> {
+        self.lock().get(&size)?.get(&digest).cloned()
+    }
+
+    /// Record a file written at `path`; the first copy of some contents is kept.
+    pub(crate) fn insert(&self, size: u64, digest: Digest, path: &Path) {
+        self.lock()
+            .entry(size)
+            .or_default()
+            .entry(digest)
+            .or_insert_with(|| path.to_path_buf());
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<u64, HashMap<Digest, PathBuf>>> {
+        self.files.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// How to store a duplicate of `earlier` whose source is described by `src_meta`.
+///
+/// A hard link shares the inode, and with it the permissions and timestamps,
+/// of the earlier copy. It is only used if those match what the duplicate
+/// would get, so that neither preservation nor a later
+/// [`UpdateNewer`](crate::OnConflict::UpdateNewer) run tells them apart;
+/// otherwise the duplicate is reflinked.
+pub(crate) fn dedup_mode_for(
+    mode: DedupMode,
+    earlier: &Path,
+    src_meta: &fs::Metadata,
+    options: &CopyOptions,
+) -> DedupMode {
+    if mode != DedupMode::HardLink {
+        return mode;
+    }
+    options.throttle_metadata(1);
+    let Ok(earlier_meta) = fs::metadata(safe_path(earlier)) else {
+        return DedupMode::Reflink;
+    };
+    let same_permissions =
+        !options.preserve_permissions || earlier_meta.permissions() == src_meta.permissions();
+    let same_mtime = !options.preserve_timestamps
+        || matches!(
+            (earlier_meta.modified(), src_meta.modified()),
+            (Ok(a), Ok(b)) if a == b
+        );
+    if same_permissions && same_mtime {
+        DedupMode::HardLink
+    } else {
+        DedupMode::Reflink
+    }
+}
+
+/// Create a temporary file next to `dst` sharing the data of `earlier`.
+///
+/// Fails if the link cannot be made, e.g. without reflink support; the
+/// caller then writes the file normally.
+pub(crate) fn dedup_to_temp(mode: DedupMode, earlier: &Path, dst: &Path) -> io::Result<TempPath> {
+    let dst_parent = dst.parent().unwrap_or(Path::new("."));
+    temp_builder()
+        .make_in(safe_path(dst_parent), |path| match mode {
+            DedupMode::HardLink => fs::hard_link(earlier, path),
+            DedupMode::Reflink => reflink(earlier, path),
+        })
+        .map(tempfile::NamedTempFile::into_temp_path)
+}
+
+#[cfg(all(feature = "reflink", any(target_os = "linux", target_os = "macos")))]
+fn reflink(from: &Path, to: &Path) -> io::Result<()> {
+    reflink_copy::reflink(from, to)
+}
+
+#[cfg(not(all(feature = "reflink", any(target_os = "linux", target_os = "macos"))))]
+fn reflink(_from: &Path, _to: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "reflink support is not enabled",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedup_index_keeps_first_copy() {
+        let index = DedupIndex::default();
+        let digest = Digest::from_bytes([7; 32]);
+        assert!(!index.has_size(3));
+
+        index.insert(3, digest, Path::new("first"));
+        index.insert(3, digest, Path::new("second"));
+
+        assert!(index.has_size(3));
+        assert_eq!(index.find(3, digest), Some(PathBuf::from("first")));
+        assert_eq!(index.find(3, Digest::from_bytes([8; 32])), None);
+        assert_eq!(index.find(4, digest), None);
+    }
+}
@@ -0,0 +1,288 @@
+//! Block-level delta updates of existing destination files.
+//!
+//! Instead of rewriting a large destination file, the existing destination is
+//! cloned into the temp file (`FICLONE`, sharing its extents on CoW
+//! filesystems and cloning server-side on NFS 4.2), and only the fixed-size
+//! blocks that differ from the source are written before the usual atomic
+//! persist. Where the destination cannot be cloned, the file is copied in
+//! full: building the temp file from a copy of the destination would write
+//! it all anyway.
+
+use std::fs::File;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::throttle::RateLimiter;
+use super::utils::{read_at, write_all_at};
+
+/// Make the empty file `out` a clone of `base`, sharing its data.
+///
+/// Fails with [`io::ErrorKind::Unsupported`] on platforms without cloning,
+/// and with the error of the filesystem where it cannot clone.
+#[cfg(target_os = "linux")]
+pub(crate) fn clone_file(base: &File, out: &File) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    // SAFETY: FICLONE takes the source descriptor as its argument; both
+    // descriptors are valid for the duration of the call.
+    let result = unsafe { libc::ioctl(out.as_raw_fd(), libc::FICLONE, base.as_raw_fd()) };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Make the empty file `out` a clone of `base`, sharing its data.
+///
+/// Fails with [`io::ErrorKind::Unsupported`] on platforms without cloning,
+/// and with the error of the filesystem where it cannot clone.
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn clone_file(_base: &File, _out: &File) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "cloning files is not supported on this platform",
+    ))
+}
+
+/// Turn the empty temp file `out` into a copy of `src`, starting from `base`.
+///
+/// `base` is the current destination; `src_len` and `base_len` are the file
+/// lengths. `out` is made a clone of `base`, then blocks of `block_size`
+/// bytes of `src` are compared with `base` and only differing blocks are
+/// written. Returns the number of bytes actually written.
+///
+/// Fails before anything is written if `base` cannot be cloned (see
+/// [`clone_file`]). With a `limiter`, every block written is paced by it.
+///
+/// Cancellation is reported as [`io::ErrorKind::Interrupted`].
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn patch_from_base(
+    src: &File,
+    base: &File,
+    out: &File,
+    src_len: u64,
+    base_len: u64,
+    block_size: usize,
+    limiter: Option<&RateLimiter>,
+    cancel_check: Option<&AtomicBool>,
+) -> io::Result<u64> {
+    clone_file(base, out)
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot clone the destination: {e}")))?;
+    out.set_len(src_len)?;
+    write_changed_blocks(
+        src,
+        base,
+        out,
+        src_len,
+        base_len,
+        block_size,
+        limiter,
+        cancel_check,
+    )
+}
+
+/// Write the blocks of `src` that differ from `base` into `out`, which
+/// already holds the contents of `base` truncated or extended to `src_len`.
+#[allow(clippy::too_many_arguments)]
+fn write_changed_blocks(
+    src: &File,
+    base: &File,
+    out: &File,
+    src_len: u64,
+    base_len: u64,
+    block_size: usize,
+    limiter: Option<&RateLimiter>,
+    cancel_check: Option<&AtomicBool>,
+) -> io::Result<u64> {
+    let block_size = block_size.max(1);
+    let mut src_buf = vec![0u8; block_size];
+    let mut base_buf = vec![0u8; block_size];
+    let mut written = 0u64;
+    let mut offset = 0u64;
+
+    while offset < src_len {
+        if cancel_check.is_some_and(|c| c.load(Ordering::Relaxed)) {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+        }
+
+        let want = (src_len - offset).min(block_size as u64) as usize;
+        let n = read_at_full(src, &mut src_buf[..want], offset)?;
+        if n < want {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "source file shrank during delta update",
+            ));
+        }
+
+        // Blocks past the end of the base are new and always written
+        let differs = offset >= base_len || {
+            let m = read_at_full(base, &mut base_buf[..want], offset)?;
+            m < want || src_buf[..want] != base_buf[..want]
+        };
+        if differs {
+            if let Some(limiter) = limiter {
+                limiter.acquire_cancellable(want as u64, cancel_check)?;
+            }
+            write_all_at(out, &src_buf[..want], offset)?;
+            written += want as u64;
+        }
+
+        offset += want as u64;
+    }
+
+    Ok(written)
+}
+
+/// Read into `buf` at `offset` until it is full or EOF is reached.
+fn read_at_full(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match read_at(file, &mut buf[filled..], offset + filled as u64)? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    /// Patch `base` into `src` as if `base` had been cloned into the temp file.
+    fn patch(src: &[u8], base: &[u8], block_size: usize) -> (Vec<u8>, u64) {
+        let dir = tempdir().unwrap();
+        let src_path = dir.path().join("src");
+        let base_path = dir.path().join("base");
+        let out_path = dir.path().join("out");
+        fs::write(&src_path, src).unwrap();
+        fs::write(&base_path, base).unwrap();
+        fs::write(&out_path, base).unwrap();
+
+        let out = fs::OpenOptions::new().write(true).open(&out_path).unwrap();
+        out.set_len(src.len() as u64).unwrap();
+        let written = write_changed_blocks(
+            &File::open(&src_path).unwrap(),
+            &File::open(&base_path).unwrap(),
+            &out,
+            src.len() as u64,
+            base.len() as u64,
+            block_size,
+            None,
+            None,
+        )
+        .unwrap();
+        (fs::read(&out_path).unwrap(), written)
+    }
+
+    #[test]
+    fn test_patch_writes_only_changed_blocks() {
+        let base = vec![1u8; 4096 * 4];
+        let mut src = base.clone();
+        src[4096 + 10] = 9;
+
+        let (out, written) = patch(&src, &base, 4096);
+        assert_eq!(out, src);
+        assert_eq!(written, 4096);
+    }
+
+    #[test]
+    fn test_patch_preallocated_temp_writes_one_block() {
+        // A plain temp file holding the base, as a clone would, so the block
+        // diff runs on every filesystem
+        let dir = tempdir().unwrap();
+        let src_path = dir.path().join("src");
+        let base_path = dir.path().join("base");
+        let base = vec![2u8; 4096 * 8];
+        let mut src = base.clone();
+        src[4096 * 5 + 1] = 7;
+        fs::write(&src_path, &src).unwrap();
+        fs::write(&base_path, &base).unwrap();
+
+        let mut out = tempfile::tempfile().unwrap();
+        out.set_len(src.len() as u64).unwrap();
+        write_all_at(&out, &base, 0).unwrap();
+        let written = write_changed_blocks(
+            &File::open(&src_path).unwrap(),
+            &File::open(&base_path).unwrap(),
+            &out,
+            src.len() as u64,
+            base.len() as u64,
+            4096,
+            None,
+            None,
+        )
+        .unwrap();
+
+        assert!(written < src.len() as u64);
+        assert_eq!(written, 4096);
+        let mut copied = Vec::new();
+        io::Read::read_to_end(&mut out, &mut copied).unwrap();
+        assert_eq!(copied, src);
+    }
+
+    #[test]
+    fn test_patch_identical_writes_nothing() {
+        let base = vec![3u8; 10_000];
+        let (out, written) = patch(&base, &base, 4096);
+        assert_eq!(out, base);
+        assert_eq!(written, 0);
+    }
+
+    #[test]
+    fn test_patch_grows_and_shrinks() {
+        let base = vec![5u8; 8192];
+
+        let mut longer = base.clone();
+        longer.extend_from_slice(&[6u8; 100]);
+        let (out, written) = patch(&longer, &base, 4096);
+        assert_eq!(out, longer);
+        assert_eq!(written, 100);
+
+        let shorter = vec![5u8; 5000];
+        let (out, written) = patch(&shorter, &base, 4096);
+        assert_eq!(out, shorter);
+        assert_eq!(written, 0);
+    }
+
+    #[test]
+    fn test_patch_from_base_clones_or_writes_nothing() {
+        let dir = tempdir().unwrap();
+        let src_path = dir.path().join("src");
+        let base_path = dir.path().join("base");
+        let out_path = dir.path().join("out");
+        let base = vec![1u8; 4096 * 4];
+        let mut src = base.clone();
+        src[10] = 9;
+        fs::write(&src_path, &src).unwrap();
+        fs::write(&base_path, &base).unwrap();
+
+        let out = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&out_path)
+            .unwrap();
+        let result = patch_from_base(
+            &File::open(&src_path).unwrap(),
+            &File::open(&base_path).unwrap(),
+            &out,
+            src.len() as u64,
+            base.len() as u64,
+            4096,
+            None,
+            None,
+        );
+        match result {
+            Ok(written) => {
+                assert_eq!(written, 4096);
+                assert_eq!(fs::read(&out_path).unwrap(), src);
+            }
+            // Without cloning, nothing is written: the caller copies in full
+            Err(_) => assert_eq!(fs::metadata(&out_path).unwrap().len(), 0),
+        }
+    }
+}
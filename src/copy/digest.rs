@@ -0,0 +1,175 @@
+//! SHA-256 content digests of copied files.
+
+use sha2::{Digest as _, Sha256};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Size of the buffer used to hash files
+const HASH_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// SHA-256 digest of a file's contents.
+///
+/// Displays as lowercase hex, as printed by `sha256sum`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Digest([u8; 32]);
+
+impl Digest {
+    /// A digest with the given raw bytes
+    #[must_use]
+    pub const fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// Parse a digest from 64 hex digits, in either case.
+    pub(crate) fn from_hex(hex: &str) -> Option<Self> {
+        if hex.len() != 64 || !hex.is_ascii() {
+            return None;
+        }
+        let mut bytes = [0u8; 32];
+        for (byte, pair) in bytes.iter_mut().zip(hex.as_bytes().chunks(2)) {
+            let pair = std::str::from_utf8(pair).ok()?;
+            *byte = u8::from_str_radix(pair, 16).ok()?;
+        }
+        Some(Self(bytes))
+    }
+
+    /// The raw digest bytes
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Incremental SHA-256 hasher fed with file contents as they are copied.
+#[derive(Clone, Default)]
+pub(crate) struct Hasher(Sha256);
+
+impl Hasher {
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        self.0.update(data);
+    }
+
+    pub(crate) fn finalize(self) -> Digest {
+        Digest(self.0.finalize().into())
+    }
+
+    /// Feed everything `reader` yields into the hasher.
+    pub(crate) fn update_reader(&mut self, mut reader: impl Read) -> io::Result<u64> {
+        let mut buf = vec![0u8; HASH_BUFFER_SIZE];
+        let mut total = 0u64;
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) => return Ok(total),
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            self.update(&buf[..n]);
+            total += n as u64;
+        }
+    }
+}
+
+/// Hash the contents of the file at `path`.
+pub(crate) fn hash_file(path: &Path) -> io::Result<Digest> {
+    let mut hasher = Hasher::default();
+    hasher.update_reader(File::open(path)?)?;
+    Ok(hasher.finalize())
+}
+
+/// Hash the file at `path` as stored, bypassing the page cache where possible.
+///
+/// On Linux, cached pages of the file are dropped first so that data already
+/// synced to storage is read back from it rather than from memory.
+pub(crate) fn hash_from_storage(path: &Path) -> io::Result<Digest> {
+    let file = File::open(path)?;
+
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::io::AsRawFd;
+        // SAFETY: The descriptor is valid for the lifetime of `file`. The call
+        // is only advice, so its result is ignored.
+        unsafe {
+            libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED);
+        }
+    }
+
+    let mut hasher = Hasher::default();
+    hasher.update_reader(file)?;
+    Ok(hasher.finalize())
+}
+
+/// A reader that hashes everything read through it, if given a hasher.
+pub(crate) struct HashingReader<'h, R> {
+    inner: R,
+    hasher: Option<&'h mut Hasher>,
+}
+
+impl<'h, R> HashingReader<'h, R> {
+    pub(crate) fn new(inner: R, hasher: Option<&'h mut Hasher>) -> Self {
+        Self { inner, hasher }
+    }
+}
+
+impl<R: Read> Read for HashingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(hasher) = self.hasher.as_deref_mut() {
+            hasher.update(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    #[test]
+    fn test_hash_file_matches_sha256sum() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("abc");
+        fs::write(&path, "abc").unwrap();
+
+        assert_eq!(hash_file(&path).unwrap().to_string(), ABC_SHA256);
+        assert_eq!(hash_from_storage(&path).unwrap().to_string(), ABC_SHA256);
+    }
+
+    #[test]
+    fn test_digest_from_hex_roundtrip() {
+        let digest = Digest::from_hex(ABC_SHA256).unwrap();
+        assert_eq!(digest.to_string(), ABC_SHA256);
+        assert_eq!(Digest::from_hex(&ABC_SHA256.to_uppercase()), Some(digest));
+        assert_eq!(Digest::from_hex("abc"), None);
+        assert_eq!(Digest::from_hex(&"zz".repeat(32)), None);
+    }
+
+    #[test]
+    fn test_hashing_reader_hashes_bytes_read() {
+        let mut hasher = Hasher::default();
+        let mut out = Vec::new();
+        io::copy(
+            &mut HashingReader::new(&b"abc"[..], Some(&mut hasher)),
+            &mut out,
+        )
+        .unwrap();
+
+        assert_eq!(out, b"abc");
+        assert_eq!(hasher.finalize().to_string(), ABC_SHA256);
+    }
+}
@@ -12,8 +12,21 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::Instant;
 
-use super::file::copy_file_internal;
+use super::adaptive::run_adaptive;
+use super::backup::make_backup;
+#[cfg(all(feature = "io_uring", target_os = "linux"))]
+use super::batch;
+use super::clean::sweep_stale_temps;
+use super::compare::EntryKind;
+use super::context::CopyContext;
+use super::digest::Digest;
+use super::file::{FileCopyResult, copy_file_internal};
+use super::mv::remove_moved_source;
+use super::publish::copy_dir_atomic;
+use super::remaining::RemainingWork;
+use super::rollback::copy_dir_transactional;
 use super::utils::{DirEntry, get_dir_key, is_escaping_symlink, is_symlink, symlink};
+use super::verify::VerifyMismatch;
 
 /// Outcome of a single file copy operation (internal use)
 /// Used for tracking results in parallel copy operations
@@ -27,6 +40,19 @@ pub(crate) enum FileCopyOutcome {
         dst: PathBuf,
         /// Number of bytes copied
         bytes: u64,
+        /// Number of bytes actually written
+        written: u64,
+        /// Whether the source changed while it was copied
+        changed: bool,
+        /// Whether the file was linked to an identical earlier copy
+        deduplicated: bool,
+    },
+    /// File was hard-linked from a `link_dest` snapshot
+    Linked {
+        /// Source path
+        src: PathBuf,
+        /// Destination path
+        dst: PathBuf,
     },
     /// File was skipped (already existed)
     Skipped {
@@ -35,6 +61,13 @@ pub(crate) enum FileCopyOutcome {
         /// Destination path
         dst: PathBuf,
     },
+    /// File was skipped (unchanged in a `compare_dest` tree)
+    Unchanged {
+        /// Source path
+        src: PathBuf,
+        /// Destination path
+        dst: PathBuf,
+    },
     /// Copy failed
     Failed {
         /// Source path
@@ -45,6 +78,15 @@ pub(crate) enum FileCopyOutcome {
         error_msg: String,
         /// Whether this was a "no space" error
         is_no_space: bool,
+        /// Verification failures, if that is why the file failed
+        mismatches: Vec<VerifyMismatch>,
+    },
+    /// Not copied because the run was cancelled before or while copying it
+    Cancelled {
+        /// Source path
+        src: PathBuf,
+        /// Destination path
+        dst: PathBuf,
     },
 }
 
@@ -67,6 +109,8 @@ pub(crate) enum FileCopyOutcome {
 pub struct CopyStats {
     /// Number of files successfully copied
     pub files_copied: u64,
+    /// Number of files hard-linked from a `link_dest` snapshot instead of copied
+    pub files_linked: u64,
     /// Number of files skipped (already existed)
     pub files_skipped: u64,
     /// Number of symlinks successfully copied
@@ -75,8 +119,35 @@ pub struct CopyStats {
     pub symlinks_skipped: u64,
     /// Number of directories created
     pub dirs_created: u64,
-    /// Total bytes copied
+    /// Total bytes copied (logical size of copied files)
     pub bytes_copied: u64,
+    /// Total bytes of file data actually written
+    ///
+    /// Lower than `bytes_copied` when delta updates or reflinks avoid
+    /// rewriting data that is already in place.
+    pub bytes_written: u64,
+    /// Number of entries moved by renaming them in place (moves only)
+    pub renamed: u64,
+    /// Number of copied files whose source changed while being copied
+    ///
+    /// Only counted under [`SourceChangePolicy::Warn`](crate::SourceChangePolicy::Warn);
+    /// these copies may be inconsistent.
+    pub files_changed: u64,
+    /// Number of copied files linked to an identical file written earlier in the run
+    pub files_deduplicated: u64,
+    /// Bytes not written because files were deduplicated
+    pub bytes_deduplicated: u64,
+    /// SHA-256 digest of the copied file
+    ///
+    /// Only set for single-file copies that hash the file (see
+    /// [`CopyOptions::digests`](crate::CopyOptions::digests)); directory copies
+    /// report digests through a [`Manifest`](crate::Manifest).
+    pub digest: Option<Digest>,
+    /// Numbers of files copied at once chosen by auto parallelism
+    ///
+    /// The starting level followed by every level it was changed to, in
+    /// order; empty unless [`CopyOptions::auto_parallel`] is set.
+    pub parallel_levels: Vec<usize>,
     /// Duration of the copy operation
     pub duration: std::time::Duration,
 }
@@ -109,11 +180,37 @@ pub struct CopyStats {
 /// - Symlink loop detected ([`Error::SymlinkLoop`])
 /// - Max depth exceeded ([`Error::MaxDepthExceeded`])
 /// - Some files failed to copy ([`Error::PartialCopy`])
+/// - Every failed file was a copy that read back differently from its source
+///   ([`Error::VerificationFailed`], with [`verify`](CopyOptions::verify))
 /// - Some symlinks failed to copy ([`Error::PartialSymlinks`])
 /// - Destination exists and `on_conflict` is [`OnConflict::Error`] ([`Error::AlreadyExists`])
 /// - IO operations fail ([`Error::Io`])
-#[allow(clippy::too_many_lines)]
+/// - The copy failed and was undone ([`Error::RolledBack`], with
+///   [`transactional`](CopyOptions::transactional))
+/// - The staged tree cannot be published ([`Error::Publish`], with
+///   [`atomic_publish`](CopyOptions::atomic_publish))
 pub fn copy_dir(src: &Path, dst: &Path, options: &CopyOptions) -> Result<CopyStats> {
+    sweep_stale_temps(dst, options);
+    if options.atomic_publish {
+        return copy_dir_atomic(src, dst, options);
+    }
+    if options.transactional {
+        return copy_dir_transactional(src, dst, options);
+    }
+    copy_tree(src, dst, options, &CopyContext::new(dst))
+}
+
+/// Copy a directory tree within the run described by `ctx`.
+///
+/// For moves, each source file and symlink is removed once its destination is
+/// in place, and source directories left empty are removed at the end.
+#[allow(clippy::too_many_lines)]
+pub(crate) fn copy_tree(
+    src: &Path,
+    dst: &Path,
+    options: &CopyOptions,
+    ctx: &CopyContext,
+) -> Result<CopyStats> {
     let start_time = Instant::now();
 
     if !src.exists() {
@@ -148,19 +245,30 @@ pub fn copy_dir(src: &Path, dst: &Path, options: &CopyOptions) -> Result<CopySta
     let mut dirs_created = 0u64;
     for dir in &dirs {
         if options.is_cancelled() {
+            // No file has been copied yet, so the whole tree remains
             return Err(Error::Cancelled {
                 files_copied: 0,
                 bytes_copied: 0,
                 files_skipped: 0,
                 dirs_created,
+                remaining_work: Box::new(RemainingWork::single(
+                    src,
+                    &ctx.final_path(dst),
+                    EntryKind::Dir,
+                )),
             });
         }
 
         // Check if directory already exists
         // Use extended-length path format on Windows to support long paths
         let safe_dst = safe_path(&dir.dst);
+        options.throttle_metadata(1);
         let created = if !safe_dst.exists() {
-            fs::create_dir_all(&safe_dst)?;
+            options.throttle_metadata(1);
+            match ctx.rollback() {
+                Some(rollback) => rollback.create_dir_all(&safe_dst)?,
+                None => fs::create_dir_all(&safe_dst)?,
+            }
             true
         } else {
             false
@@ -168,6 +276,8 @@ pub fn copy_dir(src: &Path, dst: &Path, options: &CopyOptions) -> Result<CopySta
 
         // Preserve directory permissions from source
         if options.preserve_dir_permissions {
+            // A stat of the source and a chmod of the copy
+            options.throttle_metadata(2);
             match fs::metadata(&dir.src) {
                 Ok(metadata) => {
                     if let Err(e) = fs::set_permissions(&safe_dst, metadata.permissions()) {
@@ -204,61 +314,149 @@ pub fn copy_dir(src: &Path, dst: &Path, options: &CopyOptions) -> Result<CopySta
 
     // Phase 3: Copy files in parallel with controlled concurrency
     let mut files_copied: u64 = 0;
+    let mut files_linked: u64 = 0;
     let mut files_skipped: u64 = 0;
     let mut bytes_copied: u64 = 0;
+    let mut bytes_written: u64 = 0;
+    let mut files_changed: u64 = 0;
+    let mut files_deduplicated: u64 = 0;
+    let mut bytes_deduplicated: u64 = 0;
     let mut failed_count: usize = 0;
+    let mut verify_mismatches: Vec<VerifyMismatch> = Vec::new();
+    let mut unverified_failures: usize = 0;
     let mut no_space_error: Option<(PathBuf, String)> = None;
+    let mut parallel_levels: Vec<usize> = Vec::new();
+    // Files, then symlinks, that this run did not finish, for Cancelled and NoSpace
+    let mut remaining = RemainingWork::new(&ctx.final_path(dst));
 
     if total_files > 0 {
-        let do_copy = |files: &[(PathBuf, PathBuf)]| -> Vec<FileCopyOutcome> {
-            files
-                .par_iter()
-                .map(|(src_file, dst_file)| {
-                    // Check cancellation before starting each file
-                    if options.is_cancelled() {
-                        return FileCopyOutcome::Skipped {
+        let to_outcome = |(src_file, dst_file): &(PathBuf, PathBuf),
+                          result: Result<FileCopyResult>|
+         -> FileCopyOutcome {
+            match result {
+                Ok(result) => {
+                    if result.linked {
+                        FileCopyOutcome::Linked {
                             src: src_file.clone(),
                             dst: dst_file.clone(),
-                        };
-                    }
-
-                    match copy_file_internal(src_file, dst_file, options) {
-                        Ok(result) => {
-                            if result.copied {
-                                FileCopyOutcome::Copied {
-                                    src: src_file.clone(),
-                                    dst: dst_file.clone(),
-                                    bytes: result.bytes,
-                                }
-                            } else {
-                                FileCopyOutcome::Skipped {
-                                    src: src_file.clone(),
-                                    dst: dst_file.clone(),
-                                }
-                            }
                         }
-                        Err(e) => {
-                            let is_no_space = match &e {
-                                Error::Io(io_err) => crate::is_no_space_error(io_err),
-                                Error::TempFile { source, .. } => crate::is_no_space_error(source),
-                                Error::Persist { source, .. } => crate::is_no_space_error(source),
-                                _ => false,
-                            };
-                            options.warn(&format!("Failed to copy {}: {}", src_file.display(), e));
-                            FileCopyOutcome::Failed {
-                                src: src_file.clone(),
-                                dst: dst_file.clone(),
-                                error_msg: e.to_string(),
-                                is_no_space,
-                            }
+                    } else if result.unchanged {
+                        FileCopyOutcome::Unchanged {
+                            src: src_file.clone(),
+                            dst: dst_file.clone(),
+                        }
+                    } else if result.copied {
+                        FileCopyOutcome::Copied {
+                            src: src_file.clone(),
+                            dst: dst_file.clone(),
+                            bytes: result.bytes,
+                            written: result.written,
+                            changed: result.changed,
+                            deduplicated: result.deduplicated,
+                        }
+                    } else {
+                        FileCopyOutcome::Skipped {
+                            src: src_file.clone(),
+                            dst: dst_file.clone(),
                         }
                     }
-                })
-                .collect()
+                }
+                Err(Error::Cancelled { .. }) => FileCopyOutcome::Cancelled {
+                    src: src_file.clone(),
+                    dst: dst_file.clone(),
+                },
+                Err(e) => {
+                    let is_no_space = match &e {
+                        Error::Io(io_err) => crate::is_no_space_error(io_err),
+                        Error::TempFile { source, .. } => crate::is_no_space_error(source),
+                        Error::Persist { source, .. } => crate::is_no_space_error(source),
+                        _ => false,
+                    };
+                    options.warn(&format!("Failed to copy {}: {}", src_file.display(), e));
+                    let error_msg = e.to_string();
+                    let mismatches = match e {
+                        Error::VerificationFailed { mismatches } => mismatches,
+                        _ => Vec::new(),
+                    };
+                    FileCopyOutcome::Failed {
+                        src: src_file.clone(),
+                        dst: dst_file.clone(),
+                        error_msg,
+                        is_no_space,
+                        mismatches,
+                    }
+                }
+            }
+        };
+        let copy_one = |file: &(PathBuf, PathBuf)| -> FileCopyOutcome {
+            // Check cancellation before starting each file
+            if options.is_cancelled() {
+                return FileCopyOutcome::Cancelled {
+                    src: file.0.clone(),
+                    dst: file.1.clone(),
+                };
+            }
+            to_outcome(file, copy_file_internal(&file.0, &file.1, options, ctx))
         };
 
-        // Use custom thread pool only if parallelism differs from default
-        let outcomes = if options.parallel != rayon::current_num_threads() {
+        // With the io_uring engine, small files are copied a batch at a time
+        #[cfg(all(feature = "io_uring", target_os = "linux"))]
+        let batch_files = if batch::applies(options) {
+            batch::BATCH_FILES
+        } else {
+            1
+        };
+        #[cfg(not(all(feature = "io_uring", target_os = "linux")))]
+        let batch_files = 1;
+        let copy_chunk = |chunk: &[(PathBuf, PathBuf)]| -> Vec<FileCopyOutcome> {
+            #[cfg(all(feature = "io_uring", target_os = "linux"))]
+            if chunk.len() > 1 {
+                if options.is_cancelled() {
+                    return chunk.iter().map(copy_one).collect();
+                }
+                return batch::copy_batch(chunk, options, ctx)
+                    .into_iter()
+                    .zip(chunk)
+                    .map(|(result, file)| match result {
+                        Some(result) => to_outcome(file, result),
+                        // Left to the standard path
+                        None => copy_one(file),
+                    })
+                    .collect();
+            }
+            chunk.iter().map(copy_one).collect()
+        };
+
+        let do_copy = |files: &[(PathBuf, PathBuf)]| -> (Vec<FileCopyOutcome>, Vec<usize>) {
+            if !options.auto_parallel {
+                let outcomes = if batch_files == 1 {
+                    files.par_iter().map(copy_one).collect()
+                } else {
+                    files
+                        .par_chunks(batch_files)
+                        .flat_map_iter(copy_chunk)
+                        .collect()
+                };
+                return (outcomes, Vec::new());
+            }
+            // Tune how many files (or batches) are copied at once, up to `parallel`
+            let chunks: Vec<_> = files.chunks(batch_files).collect();
+            let (outcomes, levels) = run_adaptive(&chunks, options.parallel, |chunk| {
+                let outcomes = copy_chunk(chunk);
+                let written = outcomes
+                    .iter()
+                    .map(|outcome| match outcome {
+                        FileCopyOutcome::Copied { written, .. } => *written,
+                        _ => 0,
+                    })
+                    .sum();
+                (outcomes, written)
+            });
+            (outcomes.into_iter().flatten().collect(), levels)
+        };
+
+        let (outcomes, levels) = if options.parallel != rayon::current_num_threads() {
+            // Use custom thread pool only if parallelism differs from default
             let custom_pool = rayon::ThreadPoolBuilder::new()
                 .num_threads(options.parallel)
                 .build();
@@ -273,21 +471,60 @@ pub fn copy_dir(src: &Path, dst: &Path, options: &CopyOptions) -> Result<CopySta
                 }
             }
         } else {
-            // Use rayon's global pool directly
+            // Use the current pool directly: rayon's global pool, or a
+            // session's
             do_copy(&files)
         };
+        if options.auto_parallel {
+            options.verbose(&format!(
+                "parallelism levels: {}",
+                levels
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" -> ")
+            ));
+        }
+        parallel_levels = levels;
 
         // Process outcomes
         for outcome in outcomes {
             match outcome {
-                FileCopyOutcome::Copied { src, dst, bytes } => {
+                FileCopyOutcome::Copied {
+                    src,
+                    dst,
+                    bytes,
+                    written,
+                    changed,
+                    deduplicated,
+                } => {
                     files_copied += 1;
                     bytes_copied += bytes;
+                    bytes_written += written;
+                    files_changed += u64::from(changed);
+                    if deduplicated {
+                        files_deduplicated += 1;
+                        bytes_deduplicated += bytes;
+                        options.verbose(&format!(
+                            "linked {} -> {} (duplicate of an earlier file)",
+                            src.display(),
+                            dst.display()
+                        ));
+                    } else {
+                        options.verbose(&format!(
+                            "copied {} -> {} ({} bytes)",
+                            src.display(),
+                            dst.display(),
+                            bytes
+                        ));
+                    }
+                }
+                FileCopyOutcome::Linked { src, dst } => {
+                    files_linked += 1;
                     options.verbose(&format!(
-                        "copied {} -> {} ({} bytes)",
+                        "linked {} -> {} (unchanged in link-dest)",
                         src.display(),
-                        dst.display(),
-                        bytes
+                        dst.display()
                     ));
                 }
                 FileCopyOutcome::Skipped { src, dst } => {
@@ -298,23 +535,47 @@ pub fn copy_dir(src: &Path, dst: &Path, options: &CopyOptions) -> Result<CopySta
                         dst.display()
                     ));
                 }
+                FileCopyOutcome::Unchanged { src, dst } => {
+                    files_skipped += 1;
+                    options.verbose(&format!(
+                        "skipped {} -> {} (unchanged in compare-dest)",
+                        src.display(),
+                        dst.display()
+                    ));
+                }
                 FileCopyOutcome::Failed {
                     src,
                     dst,
                     error_msg,
                     is_no_space,
+                    mismatches,
                 } => {
                     failed_count += 1;
+                    if mismatches.is_empty() {
+                        unverified_failures += 1;
+                    }
+                    verify_mismatches.extend(mismatches);
                     options.verbose(&format!(
                         "failed {} -> {}: {}",
                         src.display(),
                         dst.display(),
                         error_msg
                     ));
+                    remaining.push_failed(&src, &ctx.final_path(&dst), EntryKind::File);
                     if is_no_space && no_space_error.is_none() {
                         no_space_error = Some((dst, error_msg));
                     }
                 }
+                FileCopyOutcome::Cancelled { src, dst } => {
+                    remaining.push_pending(&src, &ctx.final_path(&dst), EntryKind::File);
+                }
+            }
+        }
+
+        // Symlinks are only created once every file is done
+        if options.preserve_symlinks {
+            for (src_link, dst_link) in &symlinks {
+                remaining.push_pending(src_link, &ctx.final_path(dst_link), EntryKind::Symlink);
             }
         }
 
@@ -325,6 +586,7 @@ pub fn copy_dir(src: &Path, dst: &Path, options: &CopyOptions) -> Result<CopySta
                 bytes_copied,
                 files_skipped,
                 dirs_created,
+                remaining_work: Box::new(remaining),
             });
         }
 
@@ -335,8 +597,16 @@ pub fn copy_dir(src: &Path, dst: &Path, options: &CopyOptions) -> Result<CopySta
                 bytes_copied,
                 failed_files: failed_count,
                 total_files,
-                remaining: total_files - files_copied as usize,
+                remaining: total_files - (files_copied + files_linked) as usize,
                 path: failed_path.clone(),
+                remaining_work: Box::new(remaining),
+            });
+        }
+
+        // Every failure was a copy that read back wrong
+        if failed_count > 0 && unverified_failures == 0 {
+            return Err(Error::VerificationFailed {
+                mismatches: verify_mismatches,
             });
         }
 
@@ -355,100 +625,47 @@ pub fn copy_dir(src: &Path, dst: &Path, options: &CopyOptions) -> Result<CopySta
 
     if options.preserve_symlinks && total_symlinks > 0 {
         let mut symlink_failures = 0usize;
+        let mut failed_symlinks: Vec<&(PathBuf, PathBuf)> = Vec::new();
 
-        for (src_link, dst_link) in &symlinks {
+        for (i, (src_link, dst_link)) in symlinks.iter().enumerate() {
             if options.is_cancelled() {
+                let mut remaining = RemainingWork::new(&ctx.final_path(dst));
+                for (src_link, dst_link) in &symlinks[i..] {
+                    remaining.push_pending(src_link, &ctx.final_path(dst_link), EntryKind::Symlink);
+                }
+                for (src_link, dst_link) in failed_symlinks {
+                    remaining.push_failed(src_link, &ctx.final_path(dst_link), EntryKind::Symlink);
+                }
                 return Err(Error::Cancelled {
                     files_copied,
                     bytes_copied,
                     files_skipped,
                     dirs_created,
+                    remaining_work: Box::new(remaining),
                 });
             }
 
-            match fs::read_link(src_link) {
-                Ok(target) => {
-                    // Check for escaping symlinks (contains ".." components)
-                    if target.is_relative() && is_escaping_symlink(&target) {
-                        if options.block_escaping_symlinks {
-                            options.warn(&format!(
-                                "Blocking escaping symlink {} -> {}",
-                                src_link.display(),
-                                target.display()
-                            ));
-                            symlinks_skipped += 1;
-                            continue; // Skip this symlink entirely
-                        } else if options.warn_escaping_symlinks {
-                            options.warn(&format!(
-                                "Symlink {} -> {} uses relative path escaping upward",
-                                src_link.display(),
-                                target.display()
-                            ));
-                        }
+            match copy_symlink(src_link, dst_link, options, ctx) {
+                Ok(SymlinkCopy::Blocked) => symlinks_skipped += 1,
+                Ok(outcome) => {
+                    let copied = matches!(outcome, SymlinkCopy::Copied);
+                    if copied {
+                        symlinks_copied += 1;
+                    } else {
+                        symlinks_skipped += 1;
                     }
-
-                    // Handle existing destination based on on_conflict
-                    let dst_exists = dst_link.exists() || is_symlink(dst_link);
-                    if dst_exists {
-                        match options.on_conflict {
-                            OnConflict::Skip | OnConflict::UpdateNewer => {
-                                // For symlinks, UpdateNewer behaves like Skip (no mtime comparison)
-                                symlinks_skipped += 1;
-                                continue;
-                            }
-                            OnConflict::Error => {
-                                return Err(Error::AlreadyExists(dst_link.clone()));
-                            }
-                            OnConflict::Overwrite => {
-                                // Convert to extended-length path format on Windows for long path support
-                                let safe_dst_link = safe_path(dst_link);
-                                // Remove existing file/symlink/dir before creating symlink
-                                if is_symlink(dst_link) || dst_link.is_file() {
-                                    if let Err(e) = fs::remove_file(&safe_dst_link) {
-                                        options.warn(&format!(
-                                            "Failed to remove existing file {}: {}",
-                                            dst_link.display(),
-                                            e
-                                        ));
-                                        symlink_failures += 1;
-                                        continue;
-                                    }
-                                } else if dst_link.is_dir() {
-                                    if let Err(e) = fs::remove_dir_all(&safe_dst_link) {
-                                        options.warn(&format!(
-                                            "Failed to remove existing directory {}: {}",
-                                            dst_link.display(),
-                                            e
-                                        ));
-                                        symlink_failures += 1;
-                                        continue;
-                                    }
-                                }
-                            }
+                    if ctx.removes_source() {
+                        if let Err(e) = remove_moved_source(src_link, dst_link, copied, options) {
+                            options.warn(&e.to_string());
+                            symlink_failures += 1;
                         }
                     }
-
-                    // Create symlink (convert to extended-length path format on Windows for long path support)
-                    let safe_dst_link = safe_path(dst_link);
-                    if let Err(e) = symlink(&target, &safe_dst_link) {
-                        options.warn(&format!(
-                            "Failed to create symlink {} -> {}: {}",
-                            dst_link.display(),
-                            target.display(),
-                            e
-                        ));
-                        symlink_failures += 1;
-                    } else {
-                        symlinks_copied += 1;
-                    }
                 }
+                Err(e @ Error::AlreadyExists(_)) => return Err(e),
                 Err(e) => {
-                    options.warn(&format!(
-                        "Failed to read symlink {}: {}",
-                        src_link.display(),
-                        e
-                    ));
+                    options.warn(&e.to_string());
                     symlink_failures += 1;
+                    failed_symlinks.push(&symlinks[i]);
                 }
             }
         }
@@ -461,20 +678,164 @@ pub fn copy_dir(src: &Path, dst: &Path, options: &CopyOptions) -> Result<CopySta
         }
     }
 
+    // Source directories are removed bottom-up once everything in them has moved;
+    // anything left behind (skipped entries) keeps its directory in place
+    if ctx.removes_source() {
+        for dir in dirs.iter().rev() {
+            let _ = fs::remove_dir(&dir.src);
+        }
+    }
+
     Ok(CopyStats {
         files_copied,
+        files_linked,
         files_skipped,
         symlinks_copied,
         symlinks_skipped,
         dirs_created,
         bytes_copied,
+        bytes_written,
+        renamed: 0,
+        files_changed,
+        files_deduplicated,
+        bytes_deduplicated,
+        digest: None,
+        parallel_levels,
         duration: start_time.elapsed(),
     })
 }
 
+/// Outcome of recreating one symlink.
+pub(crate) enum SymlinkCopy {
+    /// The symlink was created
+    Copied,
+    /// The destination exists and was kept
+    Skipped,
+    /// The symlink escapes upward and `block_escaping_symlinks` is set
+    Blocked,
+}
+
+/// Recreate the symlink `src_link` at `dst_link`, applying the conflict policy.
+///
+/// A conflict under [`OnConflict::Error`] fails with [`Error::AlreadyExists`].
+pub(crate) fn copy_symlink(
+    src_link: &Path,
+    dst_link: &Path,
+    options: &CopyOptions,
+    ctx: &CopyContext,
+) -> Result<SymlinkCopy> {
+    let failed = |what: String, e: std::io::Error| {
+        Error::Io(std::io::Error::new(e.kind(), format!("{what}: {e}")))
+    };
+    let target = fs::read_link(src_link)
+        .map_err(|e| failed(format!("Failed to read symlink {}", src_link.display()), e))?;
+
+    // Check for escaping symlinks (contains ".." components)
+    if target.is_relative() && is_escaping_symlink(&target) {
+        if options.block_escaping_symlinks {
+            options.warn(&format!(
+                "Blocking escaping symlink {} -> {}",
+                src_link.display(),
+                target.display()
+            ));
+            return Ok(SymlinkCopy::Blocked);
+        } else if options.warn_escaping_symlinks {
+            options.warn(&format!(
+                "Symlink {} -> {} uses relative path escaping upward",
+                src_link.display(),
+                target.display()
+            ));
+        }
+    }
+
+    // Handle existing destination based on on_conflict
+    // Convert to extended-length path format on Windows for long path support
+    let safe_dst_link = safe_path(dst_link);
+    let dst_exists = dst_link.exists() || is_symlink(dst_link);
+    if dst_exists {
+        match options.on_conflict {
+            // For symlinks, UpdateNewer behaves like Skip (no mtime comparison)
+            OnConflict::Skip | OnConflict::UpdateNewer => return Ok(SymlinkCopy::Skipped),
+            OnConflict::Error => return Err(Error::AlreadyExists(dst_link.to_path_buf())),
+            OnConflict::Overwrite => {
+                // Remove existing file/symlink/dir before creating symlink
+                if is_symlink(dst_link) || dst_link.is_file() {
+                    if let Some(mode) = &options.backup {
+                        make_backup(&safe_dst_link, ctx.relative(dst_link), mode, ctx.rollback())
+                            .map_err(|e| {
+                            failed(
+                                format!("Failed to back up existing file {}", dst_link.display()),
+                                e,
+                            )
+                        })?;
+                    }
+                    remove_replaced(&safe_dst_link, ctx).map_err(|e| {
+                        failed(
+                            format!("Failed to remove existing file {}", dst_link.display()),
+                            e,
+                        )
+                    })?;
+                } else if dst_link.is_dir() {
+                    remove_replaced(&safe_dst_link, ctx).map_err(|e| {
+                        failed(
+                            format!("Failed to remove existing directory {}", dst_link.display()),
+                            e,
+                        )
+                    })?;
+                }
+            }
+        }
+    }
+
+    symlink(&target, &safe_dst_link).map_err(|e| {
+        failed(
+            format!(
+                "Failed to create symlink {} -> {}",
+                dst_link.display(),
+                target.display()
+            ),
+            e,
+        )
+    })?;
+    if !dst_exists {
+        ctx.record_created(dst_link);
+    }
+    Ok(SymlinkCopy::Copied)
+}
+
+/// Recreate the symlink `src` at `dst` on its own, as a plan item.
+pub(crate) fn copy_symlink_with_stats(
+    src: &Path,
+    dst: &Path,
+    options: &CopyOptions,
+) -> Result<CopyStats> {
+    let start = Instant::now();
+    let copied = matches!(
+        copy_symlink(src, dst, options, &CopyContext::for_file(dst))?,
+        SymlinkCopy::Copied
+    );
+    Ok(CopyStats {
+        symlinks_copied: u64::from(copied),
+        symlinks_skipped: u64::from(!copied),
+        duration: start.elapsed(),
+        ..CopyStats::default()
+    })
+}
+
+/// Remove an entry about to be replaced by a symlink.
+///
+/// In transactional runs the entry is set aside so it can be restored.
+fn remove_replaced(path: &Path, ctx: &CopyContext) -> std::io::Result<()> {
+    match ctx.rollback() {
+        Some(rollback) => rollback.set_aside(path),
+        None if path.is_dir() && !is_symlink(path) => fs::remove_dir_all(path),
+        None => fs::remove_file(path),
+    }
+}
+
 /// Recursively collect all directories, files, and symlinks to copy
 #[allow(clippy::too_many_arguments)]
-fn collect_entries(
+pub(crate) fn collect_entries(
     src: &Path,
     dst: &Path,
     dirs: &mut Vec<DirEntry>,
@@ -584,12 +945,15 @@ fn collect_entries(
 mod tests {
     use super::*;
     use crate::CopyBuilder;
+    use crate::copy::digest::hash_file;
+    use crate::copy::{Journal, Manifest, ManifestFormat};
     use crate::error::Error;
+    use crate::options::{BackupMode, DedupMode};
     use std::fs;
-    use std::sync::Mutex;
     use std::sync::atomic::Ordering;
     #[cfg(unix)]
     use std::sync::atomic::{AtomicBool, AtomicUsize};
+    use std::sync::{Arc, Mutex};
     use tempfile::tempdir;
 
     #[test]
@@ -679,6 +1043,356 @@ mod tests {
         assert!(is_symlink(&dst.join("link")));
     }
 
+    #[test]
+    fn test_copy_dir_with_verify() {
+        let src_dir = tempdir().unwrap();
+        let dst_dir = tempdir().unwrap();
+        let dst = dst_dir.path().join("out");
+
+        fs::create_dir(src_dir.path().join("sub")).unwrap();
+        fs::write(src_dir.path().join("a.txt"), "a").unwrap();
+        fs::write(src_dir.path().join("sub/b.txt"), "b".repeat(70_000)).unwrap();
+
+        let options = CopyOptions::default().with_verify();
+        let stats = copy_dir(src_dir.path(), &dst, &options).unwrap();
+
+        assert_eq!(stats.files_copied, 2);
+        assert_eq!(
+            fs::read_to_string(dst.join("sub/b.txt")).unwrap(),
+            "b".repeat(70_000)
+        );
+    }
+
+    #[test]
+    fn test_copy_dir_writes_manifest() {
+        let src_dir = tempdir().unwrap();
+        let dst_dir = tempdir().unwrap();
+        let dst = dst_dir.path().join("out");
+
+        fs::create_dir(src_dir.path().join("sub")).unwrap();
+        fs::write(src_dir.path().join("a.txt"), "abc").unwrap();
+        fs::write(src_dir.path().join("sub/b.txt"), "b".repeat(70_000)).unwrap();
+
+        let manifest = Arc::new(Manifest::new(
+            dst_dir.path().join("SHA256SUMS"),
+            ManifestFormat::Sha256Sums,
+        ));
+        let options = CopyOptions::default().with_manifest(manifest.clone());
+        copy_dir(src_dir.path(), &dst, &options).unwrap();
+        manifest.finish().unwrap();
+
+        let text = fs::read_to_string(dst_dir.path().join("SHA256SUMS")).unwrap();
+        let b_digest = hash_file(&src_dir.path().join("sub/b.txt")).unwrap();
+        assert_eq!(
+            text,
+            format!(
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  out/a.txt\n\
+                 {b_digest}  out/sub/b.txt\n"
+            )
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_dir_dedup_hard_links_duplicates() {
+        use std::os::unix::fs::MetadataExt;
+
+        let src_dir = tempdir().unwrap();
+        let dst_dir = tempdir().unwrap();
+        let dst = dst_dir.path().join("out");
+
+        fs::create_dir(src_dir.path().join("sub")).unwrap();
+        fs::write(src_dir.path().join("a.bin"), "shard").unwrap();
+        fs::write(src_dir.path().join("sub/b.bin"), "shard").unwrap();
+        fs::write(src_dir.path().join("c.bin"), "other").unwrap();
+        // Linked files share their timestamps, so the sources must agree
+        let mtime = filetime::FileTime::from_unix_time(1_700_000_000, 0);
+        filetime::set_file_mtime(src_dir.path().join("a.bin"), mtime).unwrap();
+        filetime::set_file_mtime(src_dir.path().join("sub/b.bin"), mtime).unwrap();
+
+        // One file at a time, so the first copy is indexed before its duplicate
+        let options = CopyOptions::default()
+            .with_parallel(1)
+            .with_dedup(DedupMode::HardLink);
+        let stats = copy_dir(src_dir.path(), &dst, &options).unwrap();
+
+        assert_eq!(stats.files_copied, 3);
+        assert_eq!(stats.files_deduplicated, 1);
+        assert_eq!(stats.bytes_deduplicated, 5);
+        assert_eq!(stats.bytes_written, 10);
+        let ino = |name: &str| fs::metadata(dst.join(name)).unwrap().ino();
+        assert_eq!(ino("a.bin"), ino("sub/b.bin"));
+        assert_ne!(ino("a.bin"), ino("c.bin"));
+        assert_eq!(fs::read_to_string(dst.join("sub/b.bin")).unwrap(), "shard");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_dir_dedup_keeps_differing_metadata() {
+        use std::os::unix::fs::MetadataExt;
+
+        let src_dir = tempdir().unwrap();
+        let dst_dir = tempdir().unwrap();
+        let dst = dst_dir.path().join("out");
+
+        fs::write(src_dir.path().join("a.bin"), "shard").unwrap();
+        fs::write(src_dir.path().join("b.bin"), "shard").unwrap();
+        let older = filetime::FileTime::from_unix_time(1_600_000_000, 0);
+        let newer = filetime::FileTime::from_unix_time(1_700_000_000, 0);
+        filetime::set_file_mtime(src_dir.path().join("a.bin"), older).unwrap();
+        filetime::set_file_mtime(src_dir.path().join("b.bin"), newer).unwrap();
+
+        let options = CopyOptions::default()
+            .with_parallel(1)
+            .with_dedup(DedupMode::HardLink);
+        copy_dir(src_dir.path(), &dst, &options).unwrap();
+
+        let meta = |name: &str| fs::metadata(dst.join(name)).unwrap();
+        assert_ne!(meta("a.bin").ino(), meta("b.bin").ino());
+        assert_eq!(meta("a.bin").mtime(), 1_600_000_000);
+        assert_eq!(meta("b.bin").mtime(), 1_700_000_000);
+    }
+
+    #[test]
+    fn test_copy_dir_journal_skips_completed_files() {
+        let src_dir = tempdir().unwrap();
+        let dst_dir = tempdir().unwrap();
+        let dst = dst_dir.path().join("out");
+        let journal_path = dst_dir.path().join("journal");
+
+        fs::write(src_dir.path().join("a.txt"), "a").unwrap();
+        fs::write(src_dir.path().join("b.txt"), "b").unwrap();
+        let journal = Arc::new(Journal::open(&journal_path).unwrap());
+        let options = CopyOptions::default().with_journal(journal);
+        copy_dir(src_dir.path(), &dst, &options).unwrap();
+        drop(options);
+
+        // The removed copy is not noticed: journaled files are not checked
+        fs::remove_file(dst.join("a.txt")).unwrap();
+        fs::write(src_dir.path().join("b.txt"), "b2").unwrap();
+        let journal = Arc::new(Journal::open(&journal_path).unwrap());
+        assert_eq!(journal.completed(), 2);
+        let options = CopyOptions::default()
+            .with_on_conflict(OnConflict::Overwrite)
+            .with_journal(journal);
+        let stats = copy_dir(src_dir.path(), &dst, &options).unwrap();
+
+        assert_eq!(stats.files_copied, 1);
+        assert_eq!(stats.files_skipped, 1);
+        assert!(!dst.join("a.txt").exists());
+        assert_eq!(fs::read_to_string(dst.join("b.txt")).unwrap(), "b2");
+    }
+
+    #[test]
+    fn test_copy_dir_transactional_removes_created_entries() {
+        let src_dir = tempdir().unwrap();
+        let dst_dir = tempdir().unwrap();
+
+        fs::create_dir(src_dir.path().join("sub")).unwrap();
+        fs::write(src_dir.path().join("a.txt"), "a").unwrap();
+        fs::write(src_dir.path().join("b.txt"), "b").unwrap();
+        fs::write(src_dir.path().join("sub/c.txt"), "c").unwrap();
+        fs::write(dst_dir.path().join("b.txt"), "existing").unwrap();
+
+        let options = CopyOptions::default()
+            .with_on_conflict(OnConflict::Error)
+            .with_transactional();
+        let err = copy_dir(src_dir.path(), dst_dir.path(), &options).unwrap_err();
+
+        let Error::RolledBack { error, report } = err else {
+            panic!("expected RolledBack, got {err:?}");
+        };
+        assert!(matches!(*error, Error::PartialCopy { failed: 1, .. }));
+        assert_eq!(report.removed.len(), 3);
+        assert!(report.restored.is_empty());
+        assert!(report.failed.is_empty());
+
+        let left: Vec<_> = fs::read_dir(dst_dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(left, vec!["b.txt"]);
+        assert_eq!(
+            fs::read_to_string(dst_dir.path().join("b.txt")).unwrap(),
+            "existing"
+        );
+    }
+
+    #[test]
+    fn test_copy_dir_transactional_restores_replaced_files() {
+        let src_dir = tempdir().unwrap();
+        let dst_dir = tempdir().unwrap();
+        let dst = dst_dir.path().join("copied");
+
+        fs::write(src_dir.path().join("a.txt"), "new").unwrap();
+        fs::write(src_dir.path().join("d"), "file").unwrap();
+        fs::create_dir_all(dst.join("d")).unwrap();
+        fs::write(dst.join("a.txt"), "old").unwrap();
+
+        // Replacing the directory `d` with a file fails
+        let options = CopyOptions::default()
+            .with_on_conflict(OnConflict::Overwrite)
+            .with_transactional();
+        let err = copy_dir(src_dir.path(), &dst, &options).unwrap_err();
+
+        let Error::RolledBack { report, .. } = err else {
+            panic!("expected RolledBack, got {err:?}");
+        };
+        assert_eq!(report.restored, vec![dst.join("a.txt")]);
+        assert_eq!(fs::read_to_string(dst.join("a.txt")).unwrap(), "old");
+        assert_eq!(fs::read_dir(&dst).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_copy_dir_transactional_rolls_back_backups() {
+        let src_dir = tempdir().unwrap();
+        let dst_dir = tempdir().unwrap();
+        let dst = dst_dir.path().join("copied");
+        let backups = dst_dir.path().join("backups");
+
+        fs::write(src_dir.path().join("a.txt"), "new").unwrap();
+        fs::write(src_dir.path().join("d"), "file").unwrap();
+        fs::create_dir_all(dst.join("d")).unwrap();
+        fs::write(dst.join("a.txt"), "old").unwrap();
+        fs::write(dst.join("a.txt~"), "older").unwrap();
+
+        // Replacing the directory `d` with a file fails
+        for mode in [
+            BackupMode::Simple {
+                suffix: "~".to_owned(),
+            },
+            BackupMode::Numbered,
+            BackupMode::Directory(backups.clone()),
+        ] {
+            let options = CopyOptions::default()
+                .with_on_conflict(OnConflict::Overwrite)
+                .with_backup(mode)
+                .with_transactional();
+            let err = copy_dir(src_dir.path(), &dst, &options).unwrap_err();
+            assert!(matches!(err, Error::RolledBack { .. }), "{err:?}");
+
+            assert_eq!(fs::read_to_string(dst.join("a.txt")).unwrap(), "old");
+            assert_eq!(fs::read_to_string(dst.join("a.txt~")).unwrap(), "older");
+            assert_eq!(fs::read_dir(&dst).unwrap().count(), 3);
+            assert!(!backups.exists());
+        }
+    }
+
+    #[test]
+    fn test_copy_dir_transactional_success_cleans_up() {
+        let src_dir = tempdir().unwrap();
+        let dst_dir = tempdir().unwrap();
+
+        fs::write(src_dir.path().join("a.txt"), "new").unwrap();
+        fs::write(dst_dir.path().join("a.txt"), "old").unwrap();
+
+        let options = CopyOptions::default()
+            .with_on_conflict(OnConflict::Overwrite)
+            .with_transactional();
+        copy_dir(src_dir.path(), dst_dir.path(), &options).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dst_dir.path().join("a.txt")).unwrap(),
+            "new"
+        );
+        assert_eq!(fs::read_dir(dst_dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_dir_overwrite_symlinks_with_backup() {
+        use std::os::unix::fs::symlink;
+
+        let src_dir = tempdir().unwrap();
+        let dst_dir = tempdir().unwrap();
+        let backup_dir = tempdir().unwrap();
+
+        let src_file = src_dir.path().join("file.txt");
+        fs::write(&src_file, "content").unwrap();
+        symlink(&src_file, src_dir.path().join("link")).unwrap();
+
+        let dst = dst_dir.path().join("copied");
+        fs::create_dir_all(&dst).unwrap();
+        fs::write(dst.join("link"), "old content").unwrap();
+        fs::write(dst.join("file.txt"), "old file").unwrap();
+
+        let options = CopyOptions::default()
+            .with_on_conflict(OnConflict::Overwrite)
+            .with_backup(BackupMode::Directory(backup_dir.path().to_path_buf()));
+        copy_dir(src_dir.path(), &dst, &options).unwrap();
+
+        assert!(is_symlink(&dst.join("link")));
+        assert_eq!(
+            fs::read_to_string(backup_dir.path().join("link")).unwrap(),
+            "old content"
+        );
+        assert_eq!(
+            fs::read_to_string(backup_dir.path().join("file.txt")).unwrap(),
+            "old file"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_dir_link_dest_links_unchanged_files() {
+        use std::os::unix::fs::MetadataExt;
+
+        let src_dir = tempdir().unwrap();
+        let snapshots = tempdir().unwrap();
+        fs::create_dir(src_dir.path().join("sub")).unwrap();
+        fs::write(src_dir.path().join("sub/same.txt"), "unchanged").unwrap();
+        fs::write(src_dir.path().join("changed.txt"), "v1").unwrap();
+
+        let first = snapshots.path().join("day1");
+        copy_dir(src_dir.path(), &first, &CopyOptions::default()).unwrap();
+
+        fs::write(src_dir.path().join("changed.txt"), "v2 longer").unwrap();
+
+        let second = snapshots.path().join("day2");
+        let options = CopyOptions::default().with_link_dest(&first);
+        let stats = copy_dir(src_dir.path(), &second, &options).unwrap();
+
+        assert_eq!(stats.files_linked, 1);
+        assert_eq!(stats.files_copied, 1);
+        assert_eq!(stats.bytes_copied, 9);
+
+        let linked = fs::metadata(second.join("sub/same.txt")).unwrap();
+        let reference = fs::metadata(first.join("sub/same.txt")).unwrap();
+        assert_eq!(linked.ino(), reference.ino());
+        assert_eq!(
+            fs::read_to_string(second.join("changed.txt")).unwrap(),
+            "v2 longer"
+        );
+        assert_eq!(fs::read_to_string(first.join("changed.txt")).unwrap(), "v1");
+    }
+
+    #[test]
+    fn test_copy_dir_compare_dest_copies_only_delta() {
+        let previous = tempdir().unwrap();
+        let current = tempdir().unwrap();
+        let patch = tempdir().unwrap();
+
+        for root in [previous.path(), current.path()] {
+            fs::create_dir(root.join("lib")).unwrap();
+            fs::write(root.join("lib/stable.so"), "stable").unwrap();
+        }
+        fs::write(previous.path().join("app.bin"), "1.4").unwrap();
+        fs::write(current.path().join("app.bin"), "1.5").unwrap();
+        fs::write(current.path().join("new.txt"), "new").unwrap();
+
+        let dst = patch.path().join("out");
+        let options = CopyOptions::default()
+            .with_compare_dest(previous.path())
+            .with_checksum();
+        let stats = copy_dir(current.path(), &dst, &options).unwrap();
+
+        assert_eq!(stats.files_copied, 2);
+        assert_eq!(stats.files_skipped, 1);
+        assert!(!dst.join("lib/stable.so").exists());
+        assert_eq!(fs::read_to_string(dst.join("app.bin")).unwrap(), "1.5");
+        assert_eq!(fs::read_to_string(dst.join("new.txt")).unwrap(), "new");
+    }
+
     #[cfg(unix)]
     #[test]
     fn test_copy_dir_skip_existing_symlinks() {
@@ -1089,6 +1803,65 @@ mod tests {
         assert_eq!(options.parallel, 100);
     }
 
+    #[test]
+    fn test_copy_dir_with_auto_parallel() {
+        let src_dir = tempdir().unwrap();
+        let dst_dir = tempdir().unwrap();
+        let src = src_dir.path().join("src");
+        let dst = dst_dir.path().join("dst");
+        for i in 0..100 {
+            let sub = src.join(format!("d{}", i % 7));
+            fs::create_dir_all(&sub).unwrap();
+            fs::write(sub.join(format!("f{i}.txt")), vec![b'x'; i * 100]).unwrap();
+        }
+
+        let options = CopyOptions::default().with_auto_parallel(12);
+        assert_eq!(options.parallel, 12);
+        let stats = copy_dir(&src, &dst, &options).unwrap();
+        assert_eq!(stats.files_copied, 100);
+        assert!(!stats.parallel_levels.is_empty());
+        assert!(
+            stats
+                .parallel_levels
+                .iter()
+                .all(|&level| (1..=12).contains(&level))
+        );
+        for i in 0..100 {
+            let rel = format!("d{}/f{i}.txt", i % 7);
+            assert_eq!(fs::read(dst.join(&rel)).unwrap(), vec![b'x'; i * 100]);
+        }
+
+        // Fixed parallelism reports no levels
+        let stats = copy_dir(
+            &src,
+            dst_dir.path().join("fixed").as_path(),
+            &CopyOptions::default(),
+        )
+        .unwrap();
+        assert!(stats.parallel_levels.is_empty());
+    }
+
+    #[test]
+    fn test_copy_dir_with_metadata_limit() {
+        let src_dir = tempdir().unwrap();
+        let dst_dir = tempdir().unwrap();
+        let src = src_dir.path().join("src");
+        for i in 0..20 {
+            let sub = src.join(format!("d{}", i % 2));
+            fs::create_dir_all(&sub).unwrap();
+            fs::write(sub.join(format!("f{i}")), "x").unwrap();
+        }
+
+        // 3 directories and 20 files take over 120 operations; at 200 per
+        // second, that is about half a second once the first 20 are spent
+        let options = CopyOptions::default().with_metadata_limit(200);
+        let start = Instant::now();
+        let stats = copy_dir(&src, &dst_dir.path().join("dst"), &options).unwrap();
+        assert!(start.elapsed() >= std::time::Duration::from_millis(350));
+        assert_eq!(stats.files_copied, 20);
+        assert_eq!(stats.dirs_created, 3);
+    }
+
     #[test]
     fn test_copy_dir_returns_stats() {
         let src_dir = tempdir().unwrap();
@@ -1207,8 +1980,16 @@ mod tests {
 
         // Should be cancelled before any files are copied
         match result {
-            Err(Error::Cancelled { files_copied, .. }) => {
+            Err(Error::Cancelled {
+                files_copied,
+                remaining_work,
+                ..
+            }) => {
                 assert_eq!(files_copied, 0);
+                // The whole tree remains
+                assert_eq!(remaining_work.pending.len(), 1);
+                assert_eq!(remaining_work.pending[0].source, src_dir.path());
+                assert_eq!(remaining_work.pending[0].kind, EntryKind::Dir);
             }
             Ok(stats) => {
                 // Might have started before cancellation was checked
@@ -1246,6 +2027,7 @@ mod tests {
             Err(Error::Cancelled {
                 files_copied,
                 bytes_copied,
+                remaining_work,
                 ..
             }) => {
                 // Some files may have been copied before cancellation
@@ -1255,6 +2037,10 @@ mod tests {
                 if files_copied > 0 {
                     assert!(bytes_copied > 0);
                 }
+                // Every file was either copied or is listed as remaining
+                if remaining_work.pending.first().map(|e| e.kind) != Some(EntryKind::Dir) {
+                    assert_eq!(files_copied as usize + remaining_work.len(), 100);
+                }
             }
             Ok(stats) => {
                 // Race condition: copy might complete before cancel fires
@@ -3,38 +3,135 @@
 //! This module provides functions for copying individual files with
 //! atomic writes, TOCTOU safety, and optional reflink support.
 
+use super::CopyStats;
 use crate::error::{Error, Result};
-use crate::options::{CopyOptions, OnConflict};
+#[cfg(all(feature = "io_uring", target_os = "linux"))]
+use crate::options::CopyEngine;
+use crate::options::{CopyOptions, DedupMode, OnConflict, SourceChangePolicy};
 use crate::utils::path::safe_path;
 use std::fs::{self, File};
-use std::io;
+use std::io::{self, Seek, SeekFrom};
 use std::path::Path;
-
+use std::time::Instant;
+use tempfile::TempPath;
+
+use super::backup::make_backup;
+use super::clean::temp_builder;
+use super::compare::EntryKind;
+use super::context::CopyContext;
+use super::dedup::{dedup_mode_for, dedup_to_temp};
+use super::delta::patch_from_base;
+use super::digest::{Digest, Hasher, hash_file};
+use super::journal::{SourceStamp, source_stamp};
+use super::mv::remove_moved_source;
+use super::partial::{CHECKPOINT_INTERVAL, Partial};
+use super::reference::{MatchRule, find_match};
 #[cfg(all(feature = "reflink", any(target_os = "linux", target_os = "macos")))]
 use super::reflink;
-use super::utils::{copy_file_contents, is_source_newer, preserve_timestamps};
+use super::remaining::RemainingWork;
+#[cfg(all(feature = "io_uring", target_os = "linux"))]
+use super::uring;
+use super::utils::{
+    copy_file_contents, copy_file_contents_limited, copy_file_ranges, copy_range_limited,
+    is_source_newer, is_source_unchanged, preserve_timestamps,
+};
+use super::verify::verify_copy;
 
 /// Result of a single file copy operation (internal use)
 #[derive(Debug, Clone, Copy)]
 pub(crate) struct FileCopyResult {
     /// Whether the file was actually copied (false = skipped)
     pub copied: bool,
-    /// Number of bytes copied (0 if skipped)
+    /// Whether the destination was hard-linked from a `link_dest` snapshot
+    pub linked: bool,
+    /// Whether the file was skipped as unchanged in a `compare_dest` tree
+    pub unchanged: bool,
+    /// Number of bytes copied (0 if skipped or linked)
     pub bytes: u64,
+    /// Number of bytes actually written (less than `bytes` for delta updates)
+    pub written: u64,
+    /// Whether the source changed while it was copied (kept under `SourceChangePolicy::Warn`)
+    pub changed: bool,
+    /// Digest of the source computed while copying it, if it was hashed
+    pub digest: Option<Digest>,
+    /// Whether the file was linked to an identical file written earlier in the run
+    pub deduplicated: bool,
+    /// Size and mtime of the source as it was copied, for the journal
+    pub source: Option<SourceStamp>,
 }
 
 impl FileCopyResult {
-    pub(crate) fn copied(bytes: u64) -> Self {
+    /// Copied `bytes` of data, of which only `written` had to be written
+    pub(crate) fn copied(bytes: u64, written: u64) -> Self {
         Self {
             copied: true,
+            linked: false,
+            unchanged: false,
             bytes,
+            written,
+            changed: false,
+            digest: None,
+            deduplicated: false,
+            source: None,
+        }
+    }
+
+    pub(crate) fn linked() -> Self {
+        Self {
+            copied: true,
+            linked: true,
+            unchanged: false,
+            bytes: 0,
+            written: 0,
+            changed: false,
+            digest: None,
+            deduplicated: false,
+            source: None,
         }
     }
 
     pub(crate) fn skipped() -> Self {
         Self {
             copied: false,
+            linked: false,
+            unchanged: false,
             bytes: 0,
+            written: 0,
+            changed: false,
+            digest: None,
+            deduplicated: false,
+            source: None,
+        }
+    }
+
+    pub(crate) fn unchanged() -> Self {
+        Self {
+            unchanged: true,
+            ..Self::skipped()
+        }
+    }
+
+    /// The same result, recording `src_meta` as the source that was copied.
+    pub(crate) fn with_source(self, src_meta: &fs::Metadata) -> Self {
+        Self {
+            source: source_stamp(src_meta),
+            ..self
+        }
+    }
+
+    /// Statistics for this single file.
+    pub(crate) fn to_stats(self) -> CopyStats {
+        CopyStats {
+            files_copied: u64::from(self.copied && !self.linked),
+            files_linked: u64::from(self.linked),
+            files_skipped: u64::from(!self.copied),
+            bytes_copied: self.bytes,
+            bytes_written: self.written,
+            files_changed: u64::from(self.changed),
+            files_deduplicated: u64::from(self.deduplicated),
+            bytes_deduplicated: if self.deduplicated { self.bytes } else { 0 },
+            digest: self.digest,
+            ..CopyStats::default()
         }
     }
 }
@@ -62,17 +159,94 @@ impl FileCopyResult {
 /// - IO operations fail ([`Error::Io`])
 /// - Temp file creation fails ([`Error::TempFile`])
 /// - Atomic rename fails ([`Error::Persist`])
+/// - Backing up the replaced destination fails ([`Error::Backup`])
+/// - The copy read back differs from the source ([`Error::VerificationFailed`],
+///   with [`verify`](CopyOptions::verify))
 #[must_use = "returns false if file was skipped, check the result"]
 pub fn copy_file(src: &Path, dst: &Path, options: &CopyOptions) -> Result<bool> {
-    copy_file_internal(src, dst, options).map(|r| r.copied)
+    let ctx = CopyContext::for_file(dst);
+    copy_file_internal(src, dst, options, &ctx).map(|r| r.copied)
+}
+
+/// Copy a single file atomically and return statistics
+///
+/// Behaves like [`copy_file`], but reports the outcome as [`CopyStats`] so
+/// callers can tell copied, linked, and skipped files apart.
+///
+/// # Errors
+///
+/// Same as [`copy_file`].
+pub fn copy_file_with_stats(src: &Path, dst: &Path, options: &CopyOptions) -> Result<CopyStats> {
+    let start = Instant::now();
+    let ctx = CopyContext::for_file(dst);
+    let mut stats = copy_file_internal(src, dst, options, &ctx)?.to_stats();
+    stats.duration = start.elapsed();
+    Ok(stats)
 }
 
 /// Internal file copy that returns detailed result including bytes copied.
 /// Used by copy_dir to collect statistics.
+///
+/// When `ctx` is a move, the source is removed once the destination holds it.
 pub(crate) fn copy_file_internal(
     src: &Path,
     dst: &Path,
     options: &CopyOptions,
+    ctx: &CopyContext,
+) -> Result<FileCopyResult> {
+    let result = copy_file_once(src, dst, options, ctx).map_err(|e| match e {
+        Error::Cancelled { .. } => e.with_remaining_work(RemainingWork::single(
+            src,
+            &ctx.final_path(dst),
+            EntryKind::File,
+        )),
+        e => e,
+    })?;
+    finish_file(src, dst, result, options, ctx)
+}
+
+/// Record a file this run is done with in the manifest, the dedup index and
+/// the journal, and remove its source when moving.
+pub(crate) fn finish_file(
+    src: &Path,
+    dst: &Path,
+    mut result: FileCopyResult,
+    options: &CopyOptions,
+    ctx: &CopyContext,
+) -> Result<FileCopyResult> {
+    if result.copied && options.hashes_files() {
+        // Reflinked, delta-updated, and linked files may not have been hashed yet
+        let digest = match result.digest {
+            Some(digest) => digest,
+            None => hash_file(src)?,
+        };
+        if let Some(manifest) = &options.manifest {
+            manifest.record(&ctx.final_path(dst), &fs::metadata(dst)?, digest)?;
+        }
+        if options.dedup.is_some() && !result.linked && !result.deduplicated {
+            ctx.dedup().insert(result.bytes, digest, dst);
+        }
+        result.digest = Some(digest);
+    }
+    // Copies a failed transaction rolls back must not be journaled
+    if let Some(journal) = &options.journal {
+        if result.copied && !result.changed && ctx.uses_journal() && ctx.rollback().is_none() {
+            if let Some(stamp) = result.source {
+                journal.record(src, dst, stamp)?;
+            }
+        }
+    }
+    if ctx.removes_source() && !result.unchanged {
+        remove_moved_source(src, dst, result.copied, options)?;
+    }
+    Ok(result)
+}
+
+fn copy_file_once(
+    src: &Path,
+    dst: &Path,
+    options: &CopyOptions,
+    ctx: &CopyContext,
 ) -> Result<FileCopyResult> {
     // Early cancellation check before any file operations
     if options.is_cancelled() {
@@ -81,22 +255,46 @@ pub(crate) fn copy_file_internal(
             bytes_copied: 0,
             files_skipped: 0,
             dirs_created: 0,
+            remaining_work: Box::default(),
         });
     }
 
     // Get source metadata early - single stat call for all checks
-    let src_meta = fs::metadata(src)?;
+    options.throttle_metadata(1);
+    let mut src_meta = fs::metadata(src)?;
 
     // Check if source is a directory - give friendly error
     if src_meta.is_dir() {
         return Err(Error::IsADirectory(src.to_path_buf()));
     }
 
-    let file_len = src_meta.len();
+    // Files an earlier run journaled are done unless the source changed
+    if let Some(journal) = &options.journal {
+        if ctx.uses_journal() && journal.is_completed(src, dst, &src_meta) {
+            return Ok(FileCopyResult::skipped());
+        }
+    }
+
+    let mut file_len = src_meta.len();
+
+    // Files unchanged in a compare_dest tree are not part of the delta
+    if !options.compare_dest.is_empty()
+        && find_match(
+            &options.compare_dest,
+            ctx.relative(dst),
+            src,
+            &src_meta,
+            MatchRule::from_options(options),
+        )
+        .is_some()
+    {
+        return Ok(FileCopyResult::unchanged());
+    }
 
     // Handle conflict based on options
     // Use ONE symlink_metadata call to detect existence and type (avoid redundant stat calls)
-    match fs::symlink_metadata(dst) {
+    options.throttle_metadata(1);
+    let dst_meta = match fs::symlink_metadata(dst) {
         Ok(dst_meta) => {
             // Destination exists
             match options.on_conflict {
@@ -119,11 +317,90 @@ pub(crate) fn copy_file_internal(
                     }
                 }
             }
+            Some(dst_meta)
         }
         Err(e) if e.kind() == io::ErrorKind::NotFound => {
             // Destination doesn't exist - proceed with copy
+            None
         }
         Err(e) => return Err(e.into()),
+    };
+    let dst_exists = dst_meta.is_some();
+
+    // Hard-link unchanged files from an earlier snapshot instead of copying
+    if !options.link_dest.is_empty() {
+        if let Some(reference) = find_match(
+            &options.link_dest,
+            ctx.relative(dst),
+            src,
+            &src_meta,
+            MatchRule::from_options(options),
+        ) {
+            options.throttle_metadata(1);
+            match link_to_temp(&reference, dst) {
+                Ok(temp) => {
+                    let digest = verify_before_persist(src, &temp, dst, None, options)?;
+                    return if persist_temp(temp, dst, options, ctx, dst_exists)? {
+                        Ok(FileCopyResult {
+                            digest,
+                            ..FileCopyResult::linked()
+                        }
+                        .with_source(&src_meta))
+                    } else {
+                        Ok(FileCopyResult::skipped())
+                    };
+                }
+                Err(e) => {
+                    // e.g. snapshot on another device - fall back to a copy
+                    options.verbose(&format!(
+                        "cannot link {} from {}: {}",
+                        dst.display(),
+                        reference.display(),
+                        e
+                    ));
+                }
+            }
+        }
+    }
+
+    // Link duplicates of files already written by this run instead of copying
+    if let Some(mode) = options.dedup.filter(|_| file_len > 0) {
+        if ctx.dedup().has_size(file_len) {
+            let digest = hash_file(src)?;
+            if let Some(earlier) = ctx.dedup().find(file_len, digest) {
+                let mode = dedup_mode_for(mode, &earlier, &src_meta, options);
+                options.throttle_metadata(1);
+                match dedup_to_temp(mode, &earlier, dst) {
+                    Ok(temp) => {
+                        // A reflink is a new file that gets the source's metadata
+                        if mode == DedupMode::Reflink {
+                            apply_cloned_metadata(&src_meta, &temp, options);
+                            if options.fsync {
+                                File::open(&temp)?.sync_all()?;
+                            }
+                        }
+                        verify_before_persist(src, &temp, dst, Some(digest), options)?;
+                        if !persist_temp(temp, dst, options, ctx, dst_exists)? {
+                            return Ok(FileCopyResult::skipped());
+                        }
+                        return Ok(FileCopyResult {
+                            digest: Some(digest),
+                            deduplicated: true,
+                            ..FileCopyResult::copied(file_len, 0)
+                        }
+                        .with_source(&src_meta));
+                    }
+                    Err(e) => {
+                        options.verbose(&format!(
+                            "cannot link {} to duplicate {}: {}",
+                            dst.display(),
+                            earlier.display(),
+                            e
+                        ));
+                    }
+                }
+            }
+        }
     }
 
     // Try reflink first (instant copy on CoW filesystems like Btrfs, XFS, APFS)
@@ -134,33 +411,50 @@ pub(crate) fn copy_file_internal(
     {
         // Reflink succeeded - it preserves source attributes by default
         // We need to handle preserve_* options appropriately
+        apply_cloned_metadata(&src_meta, dst, options);
 
-        if options.preserve_permissions {
-            // Reflink already copied permissions, but re-set to ensure consistency
-            let _ = fs::set_permissions(dst, src_meta.permissions());
-        } else {
-            // Reset to default permissions (apply umask)
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::PermissionsExt;
-                // Set 0o666 which will automatically apply umask
-                let _ = fs::set_permissions(dst, fs::Permissions::from_mode(0o666));
-            }
+        // reflink() creates the destination, so it did not exist before
+        ctx.record_created(dst);
+
+        // Make the clone durable like a copied file, before a move removes
+        // the source (which then also syncs the directory entry)
+        if options.fsync {
+            File::open(safe_path(dst))?.sync_all()?;
         }
 
-        if options.preserve_timestamps {
-            let _ = preserve_timestamps(&src_meta, dst);
+        // The clone did not replace anything, so a bad one is just removed
+        let digest = if options.verify {
+            let verified = verify_copy(src, dst, dst, None);
+            if verified.is_err() {
+                let _ = fs::remove_file(safe_path(dst));
+            }
+            Some(verified?)
         } else {
-            // Reset to current time
-            let now = filetime::FileTime::now();
-            let _ = filetime::set_file_times(dst, now, now);
-        }
+            None
+        };
 
-        return Ok(FileCopyResult::copied(file_len));
+        // Reflinked data shares extents, nothing is written
+        return Ok(FileCopyResult {
+            digest,
+            ..FileCopyResult::copied(file_len, 0)
+        }
+        .with_source(&src_meta));
     }
 
     // Open source file
-    let src_file = File::open(src)?;
+    let mut src_file = File::open(src)?;
+
+    // Large files are copied into a partial file that an interrupted run can
+    // resume, unless they get a delta update; a transaction cannot leave one
+    let delta_applies = dst_meta.as_ref().is_some_and(|meta| {
+        meta.is_file() && options.delta_threshold.is_some_and(|t| file_len >= t)
+    });
+    if !delta_applies
+        && ctx.rollback().is_none()
+        && options.resume_threshold.is_some_and(|t| file_len >= t)
+    {
+        return copy_resumable(src, dst, src_file, src_meta, options, ctx, dst_exists);
+    }
 
     // Create temp file in destination directory for atomic rename
     let dst_parent = dst.parent().unwrap_or(Path::new("."));
@@ -170,19 +464,22 @@ pub(crate) fn copy_file_internal(
     let safe_dst_parent = safe_path(dst_parent);
 
     // Create temp file with appropriate permissions
+    options.throttle_metadata(1);
     let temp_file = if options.preserve_permissions {
         // Use default tempfile creation (0o600), will set source permissions later
-        tempfile::NamedTempFile::new_in(&safe_dst_parent).map_err(|e| Error::TempFile {
-            path: dst_parent.to_path_buf(),
-            source: e,
-        })?
+        temp_builder()
+            .tempfile_in(&safe_dst_parent)
+            .map_err(|e| Error::TempFile {
+                path: dst_parent.to_path_buf(),
+                source: e,
+            })?
     } else {
         // Use tempfile::Builder to set default permissions at creation time
         // This avoids an extra chmod syscall
         #[cfg(unix)]
         {
             use std::os::unix::fs::PermissionsExt;
-            tempfile::Builder::new()
+            temp_builder()
                 .permissions(fs::Permissions::from_mode(0o666))
                 .tempfile_in(&safe_dst_parent)
                 .map_err(|e| Error::TempFile {
@@ -192,83 +489,318 @@ pub(crate) fn copy_file_internal(
         }
         #[cfg(not(unix))]
         {
-            tempfile::NamedTempFile::new_in(&safe_dst_parent).map_err(|e| Error::TempFile {
-                path: dst_parent.to_path_buf(),
-                source: e,
-            })?
+            temp_builder()
+                .tempfile_in(&safe_dst_parent)
+                .map_err(|e| Error::TempFile {
+                    path: dst_parent.to_path_buf(),
+                    source: e,
+                })?
         }
     };
 
-    // Copy file contents using best available method (zero-copy on Linux)
-    // Pass cancel token to allow mid-file cancellation
-    let cancel_check = options.cancel_token.as_deref();
-    let bytes_copied = copy_file_contents(&src_file, temp_file.as_file(), file_len, cancel_check)
-        .map_err(|e| {
-        if e.kind() == io::ErrorKind::Interrupted {
-            // Mid-file cancellation - return Cancelled error with partial progress
-            Error::Cancelled {
-                files_copied: 0,
-                bytes_copied: 0,
-                files_skipped: 0,
-                dirs_created: 0,
+    let mut retries_left = match options.on_source_change {
+        SourceChangePolicy::Retry { attempts } => attempts,
+        SourceChangePolicy::Warn | SourceChangePolicy::Fail => 0,
+    };
+    let mut changed = false;
+
+    let (bytes_copied, bytes_written, digest, synced) = loop {
+        // Hash the source as it is copied, to check the copy against later
+        let mut hasher = options.hashes_files().then(Hasher::default);
+
+        // Large files replacing an existing file only get their changed blocks written
+        let delta_base = dst_meta.as_ref().filter(|meta| {
+            meta.is_file() && options.delta_threshold.is_some_and(|t| file_len >= t)
+        });
+        let delta_written = match delta_base {
+            Some(base_meta) => try_delta_update(
+                &src_file,
+                dst,
+                base_meta.len(),
+                temp_file.as_file(),
+                file_len,
+                options,
+            )?,
+            None => None,
+        };
+
+        let (copied, written, synced) = match delta_written {
+            Some(written) => {
+                // Only the changed blocks were read, so nothing was hashed
+                hasher = None;
+                (file_len, written, false)
             }
-        } else {
-            Error::Io(e)
+            None => {
+                let (bytes, synced) = copy_with_engine(
+                    &src_file,
+                    temp_file.as_file(),
+                    file_len,
+                    options,
+                    hasher.as_mut(),
+                )
+                .map_err(copy_error)?;
+                (bytes, bytes, synced)
+            }
+        };
+        let digest = hasher.map(Hasher::finalize);
+
+        // A source written, truncated or grown mid-copy leaves a torn copy
+        if is_source_unchanged(&src_meta, &src_file, copied)? {
+            break (copied, written, digest, synced);
         }
-    })?;
+        match handle_source_change(src, options, &mut retries_left)? {
+            SourceChange::Keep => {
+                changed = true;
+                break (copied, written, digest, synced);
+            }
+            SourceChange::Retry => {
+                src_file = File::open(src)?;
+                src_meta = src_file.metadata()?;
+                file_len = src_meta.len();
+                temp_file.as_file().set_len(0)?;
+                temp_file.as_file().seek(SeekFrom::Start(0))?;
+            }
+        }
+    };
 
     // Ensure data is on disk before rename
-    if options.fsync {
+    if options.fsync && !synced {
         temp_file.as_file().sync_all()?;
     }
 
     // Set source file permissions if preserving
     if options.preserve_permissions {
         let perms = src_meta.permissions();
+        options.throttle_metadata(1);
         fs::set_permissions(temp_file.path(), perms)?;
     }
 
-    // Convert destination to extended-length path format on Windows for the persist operation
-    // This is necessary when the destination path is very long (>260 chars on Windows)
-    let safe_dst = safe_path(dst);
+    let digest = verify_before_persist(src, temp_file.path(), dst, digest, options)?;
+    if !install_copy(
+        temp_file.into_temp_path(),
+        src,
+        &src_meta,
+        dst,
+        options,
+        ctx,
+        dst_exists,
+    )? {
+        return Ok(FileCopyResult::skipped());
+    }
 
-    // Atomic rename
-    // - Overwrite/UpdateNewer mode: use persist() to overwrite any file created in the TOCTOU window
-    // - Skip/Error mode: use persist_noclobber() to detect race conditions
-    let persist_result = if options.on_conflict == OnConflict::Overwrite
-        || options.on_conflict == OnConflict::UpdateNewer
-    {
-        temp_file.persist(&safe_dst).map_err(|e| Error::Persist {
-            path: dst.to_path_buf(),
-            source: e.error,
-        })
-    } else {
-        match temp_file.persist_noclobber(&safe_dst) {
-            Ok(f) => Ok(f),
-            Err(e) => {
-                if e.error.kind() == std::io::ErrorKind::AlreadyExists {
-                    // Destination was created by another process
-                    if options.on_conflict == OnConflict::Skip {
-                        return Ok(FileCopyResult::skipped());
-                    } else {
-                        return Err(Error::AlreadyExists(dst.to_path_buf()));
-                    }
-                } else {
-                    Err(Error::Persist {
-                        path: dst.to_path_buf(),
-                        source: e.error,
-                    })
+    Ok(FileCopyResult {
+        changed,
+        digest,
+        ..FileCopyResult::copied(bytes_copied, bytes_written)
+    }
+    .with_source(&src_meta))
+}
+
+/// Copy the first `len` bytes of `src` into `dst` with the configured engine.
+///
+/// Files of at least [`parallel_chunk_threshold`](CopyOptions::parallel_chunk_threshold)
+/// bytes are copied in concurrent ranges. Returns the bytes copied and
+/// whether `dst` was also synced, which the io_uring engine does along with
+/// the last writes when `options.fsync` is set. Falls back to
+/// [`copy_file_contents`] where io_uring is unavailable.
+fn copy_with_engine(
+    src: &File,
+    dst: &File,
+    len: u64,
+    options: &CopyOptions,
+    hasher: Option<&mut Hasher>,
+) -> io::Result<(u64, bool)> {
+    // Pass cancel token to allow mid-file cancellation
+    let cancel_check = options.cancel_token.as_deref();
+
+    // Huge files are split into ranges that idle threads of the current pool
+    // copy concurrently; building a pool per file would cost more than it saves
+    if options.parallel > 1 && options.parallel_chunk_threshold.is_some_and(|t| len >= t) {
+        return copy_file_ranges(
+            src,
+            dst,
+            len,
+            options.parallel_chunk_size,
+            options.bwlimit.as_deref(),
+            cancel_check,
+            hasher,
+        )
+        .map(|n| (n, false));
+    }
+
+    // Under a bandwidth limit, the data is copied in paced slices
+    if let Some(limiter) = options.bwlimit.as_deref() {
+        return copy_file_contents_limited(src, dst, len, limiter, cancel_check, hasher)
+            .map(|n| (n, false));
+    }
+
+    #[cfg(all(feature = "io_uring", target_os = "linux"))]
+    if options.engine == CopyEngine::IoUring {
+        let mut hasher = hasher;
+        if let Some(result) = uring::copy_file_contents(
+            src,
+            dst,
+            len,
+            options.fsync,
+            cancel_check,
+            hasher.as_deref_mut(),
+        ) {
+            return result;
+        }
+        return copy_file_contents(src, dst, len, cancel_check, hasher).map(|n| (n, false));
+    }
+
+    // Copy file contents using best available method (zero-copy on Linux)
+    copy_file_contents(src, dst, len, cancel_check, hasher).map(|n| (n, false))
+}
+
+/// Copy `src` through a resumable partial file next to `dst`.
+///
+/// The copy continues from the last checkpoint of an earlier, interrupted
+/// copy of the same unchanged source. Checkpoints always sync the partial
+/// file, so the recorded offset never runs ahead of the data on disk.
+fn copy_resumable(
+    src: &Path,
+    dst: &Path,
+    mut src_file: File,
+    mut src_meta: fs::Metadata,
+    options: &CopyOptions,
+    ctx: &CopyContext,
+    dst_exists: bool,
+) -> Result<FileCopyResult> {
+    let partial = Partial::for_dst(dst);
+    let (file, mut offset) = partial.open(&src_meta).map_err(|e| Error::TempFile {
+        path: dst.parent().unwrap_or(Path::new(".")).to_path_buf(),
+        source: e,
+    })?;
+    if offset > 0 {
+        options.verbose(&format!("resuming {} at byte {}", dst.display(), offset));
+    }
+    let mut resumed_from = offset;
+    let mut file_len = src_meta.len();
+
+    let mut retries_left = match options.on_source_change {
+        SourceChangePolicy::Retry { attempts } => attempts,
+        SourceChangePolicy::Warn | SourceChangePolicy::Fail => 0,
+    };
+    let mut changed = false;
+
+    loop {
+        while offset < file_len {
+            let chunk = (file_len - offset).min(CHECKPOINT_INTERVAL);
+            // The skipped prefix was not read, so the source is hashed later if needed
+            let copied = match options.bwlimit.as_deref() {
+                Some(limiter) => copy_range_limited(
+                    &src_file,
+                    &file,
+                    offset,
+                    chunk,
+                    limiter,
+                    options.cancel_token.as_deref(),
+                ),
+                None => {
+                    src_file.seek(SeekFrom::Start(offset))?;
+                    (&file).seek(SeekFrom::Start(offset))?;
+                    copy_file_contents(
+                        &src_file,
+                        &file,
+                        chunk,
+                        options.cancel_token.as_deref(),
+                        None,
+                    )
                 }
             }
+            .map_err(copy_error)?;
+            offset += copied;
+            if copied < chunk {
+                break;
+            }
+            partial.checkpoint(&file, &src_meta, offset)?;
         }
-    };
 
-    persist_result?;
+        // A source written, truncated or grown mid-copy leaves a torn copy
+        if is_source_unchanged(&src_meta, &src_file, offset)? {
+            break;
+        }
+        match handle_source_change(src, options, &mut retries_left)? {
+            SourceChange::Keep => {
+                changed = true;
+                break;
+            }
+            SourceChange::Retry => {
+                src_file = File::open(src)?;
+                src_meta = src_file.metadata()?;
+                file_len = src_meta.len();
+                partial.restart(&file)?;
+                offset = 0;
+                resumed_from = 0;
+            }
+        }
+    }
+
+    if options.fsync {
+        file.sync_all()?;
+    }
+    if options.preserve_permissions {
+        options.throttle_metadata(1);
+        file.set_permissions(src_meta.permissions())?;
+    }
+    drop(file);
+
+    let temp = partial.finish()?;
+    let digest = verify_before_persist(src, &temp, dst, None, options)?;
+    if !install_copy(temp, src, &src_meta, dst, options, ctx, dst_exists)? {
+        return Ok(FileCopyResult::skipped());
+    }
+
+    Ok(FileCopyResult {
+        changed,
+        digest,
+        ..FileCopyResult::copied(offset, offset - resumed_from)
+    }
+    .with_source(&src_meta))
+}
+
+/// Check the finished copy `temp` against `src` before it replaces `dst`.
+///
+/// Returns the source digest when [`verify`](CopyOptions::verify) is on, and
+/// `digest` otherwise. A mismatching copy is discarded with `temp`, so an
+/// existing `dst` is kept.
+pub(crate) fn verify_before_persist(
+    src: &Path,
+    temp: &Path,
+    dst: &Path,
+    digest: Option<Digest>,
+    options: &CopyOptions,
+) -> Result<Option<Digest>> {
+    if !options.verify {
+        return Ok(digest);
+    }
+    verify_copy(src, temp, dst, digest).map(Some)
+}
+
+/// Move a finished copy into place at `dst` and give it the source's metadata.
+///
+/// Returns `Ok(false)` if `dst` appeared meanwhile and is skipped.
+#[cfg_attr(not(windows), allow(unused_variables))]
+fn install_copy(
+    temp: TempPath,
+    src: &Path,
+    src_meta: &fs::Metadata,
+    dst: &Path,
+    options: &CopyOptions,
+    ctx: &CopyContext,
+    dst_exists: bool,
+) -> Result<bool> {
+    if !persist_temp(temp, dst, options, ctx, dst_exists)? {
+        return Ok(false);
+    }
 
     // Preserve timestamps after successful copy
     if options.preserve_timestamps {
         // Ignore timestamp errors - they're not critical
-        let _ = preserve_timestamps(&src_meta, dst);
+        options.throttle_metadata(1);
+        let _ = preserve_timestamps(src_meta, dst);
     }
 
     // Preserve Windows file attributes (hidden, system, etc.)
@@ -277,7 +809,213 @@ pub(crate) fn copy_file_internal(
         crate::win_attrs::copy_attributes(src, dst);
     }
 
-    Ok(FileCopyResult::copied(bytes_copied))
+    Ok(true)
+}
+
+/// How to go on after the source changed while it was being copied
+#[derive(Debug)]
+enum SourceChange {
+    /// Keep the (possibly inconsistent) copy
+    Keep,
+    /// Copy the file again
+    Retry,
+}
+
+/// Apply `options.on_source_change` to a source that changed mid-copy.
+///
+/// `retries_left` counts down the attempts of [`SourceChangePolicy::Retry`].
+fn handle_source_change(
+    src: &Path,
+    options: &CopyOptions,
+    retries_left: &mut u32,
+) -> Result<SourceChange> {
+    match options.on_source_change {
+        SourceChangePolicy::Warn => {
+            options.warn(&format!(
+                "Source changed while being copied, the copy may be inconsistent: {}",
+                src.display()
+            ));
+            Ok(SourceChange::Keep)
+        }
+        SourceChangePolicy::Retry { .. } if *retries_left > 0 => {
+            *retries_left -= 1;
+            options.verbose(&format!(
+                "{} changed while being copied, copying again",
+                src.display()
+            ));
+            Ok(SourceChange::Retry)
+        }
+        SourceChangePolicy::Retry { .. } | SourceChangePolicy::Fail => {
+            Err(Error::SourceChanged(src.to_path_buf()))
+        }
+    }
+}
+
+/// Map an error from copying file contents, turning mid-file cancellation
+/// into [`Error::Cancelled`].
+fn copy_error(e: io::Error) -> Error {
+    if e.kind() == io::ErrorKind::Interrupted {
+        // Mid-file cancellation - return Cancelled error with partial progress
+        Error::Cancelled {
+            files_copied: 0,
+            bytes_copied: 0,
+            files_skipped: 0,
+            dirs_created: 0,
+            remaining_work: Box::default(),
+        }
+    } else {
+        Error::Io(e)
+    }
+}
+
+/// Build the temp file from the existing destination, writing only the blocks
+/// that differ from the source.
+///
+/// Returns the number of bytes written, or `None` if the delta update could
+/// not be done and the temp file was reset for a full copy.
+fn try_delta_update(
+    src_file: &File,
+    dst: &Path,
+    base_len: u64,
+    temp: &File,
+    file_len: u64,
+    options: &CopyOptions,
+) -> Result<Option<u64>> {
+    let cancel_check = options.cancel_token.as_deref();
+    let result = File::open(safe_path(dst)).and_then(|base| {
+        patch_from_base(
+            src_file,
+            &base,
+            temp,
+            file_len,
+            base_len,
+            options.delta_block_size,
+            options.bwlimit.as_deref(),
+            cancel_check,
+        )
+    });
+
+    match result {
+        Ok(written) => Ok(Some(written)),
+        Err(e) if e.kind() == io::ErrorKind::Interrupted => Err(copy_error(e)),
+        Err(e) => {
+            // e.g. a filesystem without cloning (ext4, tmpfs, NFS before 4.2)
+            options.verbose(&format!(
+                "delta update of {} failed ({}), copying in full",
+                dst.display(),
+                e
+            ));
+            temp.set_len(0)?;
+            (&*temp).seek(SeekFrom::Start(0))?;
+            (&*src_file).seek(SeekFrom::Start(0))?;
+            Ok(None)
+        }
+    }
+}
+
+/// Hard-link `reference` to a fresh temporary name next to `dst`.
+fn link_to_temp(reference: &Path, dst: &Path) -> io::Result<TempPath> {
+    let dst_parent = dst.parent().unwrap_or(Path::new("."));
+    temp_builder()
+        .make_in(safe_path(dst_parent), |path| fs::hard_link(reference, path))
+        .map(tempfile::NamedTempFile::into_temp_path)
+}
+
+/// Set the metadata of a file cloned (reflinked) from `src` as a copy would.
+fn apply_cloned_metadata(src_meta: &fs::Metadata, path: &Path, options: &CopyOptions) {
+    if options.preserve_permissions {
+        // Reflink already copied permissions, but re-set to ensure consistency
+        options.throttle_metadata(1);
+        let _ = fs::set_permissions(path, src_meta.permissions());
+    } else {
+        // Reset to default permissions (apply umask)
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            // Set 0o666 which will automatically apply umask
+            options.throttle_metadata(1);
+            let _ = fs::set_permissions(path, fs::Permissions::from_mode(0o666));
+        }
+    }
+
+    options.throttle_metadata(1);
+    if options.preserve_timestamps {
+        let _ = preserve_timestamps(src_meta, path);
+    } else {
+        // Reset to current time
+        let now = filetime::FileTime::now();
+        let _ = filetime::set_file_times(path, now, now);
+    }
+}
+
+/// Atomically move a finished temporary file into place at `dst`.
+///
+/// Returns `Ok(false)` if the file was skipped because the destination
+/// appeared concurrently under [`OnConflict::Skip`].
+fn persist_temp(
+    temp: TempPath,
+    dst: &Path,
+    options: &CopyOptions,
+    ctx: &CopyContext,
+    dst_exists: bool,
+) -> Result<bool> {
+    // Convert destination to extended-length path format on Windows for the persist operation
+    // This is necessary when the destination path is very long (>260 chars on Windows)
+    let safe_dst = safe_path(dst);
+    options.throttle_metadata(1);
+
+    // Atomic rename
+    // - Overwrite/UpdateNewer mode: use persist() to overwrite any file created in the TOCTOU window
+    // - Skip/Error mode: use persist_noclobber() to detect race conditions
+    if options.on_conflict == OnConflict::Overwrite
+        || options.on_conflict == OnConflict::UpdateNewer
+    {
+        if dst_exists {
+            ctx.keep_original(&safe_dst).map_err(|e| Error::Backup {
+                path: dst.to_path_buf(),
+                source: e,
+            })?;
+        }
+
+        // Link the old version to its backup name before replacing it, so that
+        // at every point either the old or the new content is reachable
+        if let Some(mode) = options.backup.as_ref().filter(|_| dst_exists) {
+            make_backup(&safe_dst, ctx.relative(dst), mode, ctx.rollback()).map_err(|e| {
+                Error::Backup {
+                    path: dst.to_path_buf(),
+                    source: e,
+                }
+            })?;
+        }
+
+        temp.persist(&safe_dst).map_err(|e| Error::Persist {
+            path: dst.to_path_buf(),
+            source: e.error,
+        })?;
+        if !dst_exists {
+            ctx.record_created(dst);
+        }
+        Ok(true)
+    } else {
+        match temp.persist_noclobber(&safe_dst) {
+            Ok(()) => {
+                ctx.record_created(dst);
+                Ok(true)
+            }
+            Err(e) if e.error.kind() == io::ErrorKind::AlreadyExists => {
+                // Destination was created by another process
+                if options.on_conflict == OnConflict::Skip {
+                    Ok(false)
+                } else {
+                    Err(Error::AlreadyExists(dst.to_path_buf()))
+                }
+            }
+            Err(e) => Err(Error::Persist {
+                path: dst.to_path_buf(),
+                source: e.error,
+            }),
+        }
+    }
 }
 
 // =============================================================================
@@ -287,6 +1025,7 @@ pub(crate) fn copy_file_internal(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::options::BackupMode;
     use std::fs;
     use tempfile::tempdir;
 
@@ -344,6 +1083,204 @@ mod tests {
         assert_eq!(fs::read_to_string(&dst_file).unwrap(), "new content");
     }
 
+    #[test]
+    fn test_copy_file_overwrite_with_backup() {
+        let src_dir = tempdir().unwrap();
+        let dst_dir = tempdir().unwrap();
+
+        let src_file = src_dir.path().join("test.txt");
+        let dst_file = dst_dir.path().join("test.txt");
+
+        fs::write(&src_file, "new content").unwrap();
+        fs::write(&dst_file, "old content").unwrap();
+
+        let options = CopyOptions::default()
+            .with_on_conflict(OnConflict::Overwrite)
+            .with_backup(BackupMode::default());
+        assert!(copy_file(&src_file, &dst_file, &options).unwrap());
+
+        assert_eq!(fs::read_to_string(&dst_file).unwrap(), "new content");
+        assert_eq!(
+            fs::read_to_string(dst_dir.path().join("test.txt~")).unwrap(),
+            "old content"
+        );
+    }
+
+    #[test]
+    fn test_copy_file_backup_not_made_when_skipped() {
+        let src_dir = tempdir().unwrap();
+        let dst_dir = tempdir().unwrap();
+
+        let src_file = src_dir.path().join("test.txt");
+        let dst_file = dst_dir.path().join("test.txt");
+
+        fs::write(&src_file, "new content").unwrap();
+        fs::write(&dst_file, "old content").unwrap();
+
+        let options = CopyOptions::default().with_backup(BackupMode::Numbered);
+        assert!(!copy_file(&src_file, &dst_file, &options).unwrap());
+        assert!(!dst_dir.path().join("test.txt.~1~").exists());
+    }
+
+    #[test]
+    fn test_source_change_policies() {
+        let src = Path::new("live.log");
+
+        let warn = CopyOptions::default();
+        let mut retries = 0;
+        assert!(matches!(
+            handle_source_change(src, &warn, &mut retries),
+            Ok(SourceChange::Keep)
+        ));
+
+        let fail = CopyOptions::default().with_on_source_change(SourceChangePolicy::Fail);
+        assert!(matches!(
+            handle_source_change(src, &fail, &mut retries),
+            Err(Error::SourceChanged(_))
+        ));
+
+        let retry =
+            CopyOptions::default().with_on_source_change(SourceChangePolicy::Retry { attempts: 1 });
+        let mut retries = 1;
+        assert!(matches!(
+            handle_source_change(src, &retry, &mut retries),
+            Ok(SourceChange::Retry)
+        ));
+        assert!(matches!(
+            handle_source_change(src, &retry, &mut retries),
+            Err(Error::SourceChanged(_))
+        ));
+    }
+
+    #[test]
+    fn test_copy_file_in_parallel_chunks() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("big.bin");
+        let dst = dir.path().join("copy.bin");
+        let data: Vec<u8> = (0..1_000_000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&src, &data).unwrap();
+
+        let options = CopyOptions::default()
+            .with_parallel_chunks(1024)
+            .with_parallel_chunk_size(64 * 1024)
+            .with_verify();
+        let stats = copy_file_with_stats(&src, &dst, &options).unwrap();
+        assert_eq!(stats.bytes_copied, 1_000_000);
+        assert_eq!(fs::read(&dst).unwrap(), data);
+    }
+
+    #[test]
+    fn test_copy_with_bwlimit_is_paced_across_files() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir(&src).unwrap();
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        for i in 0..4 {
+            fs::write(src.join(format!("{i}.bin")), &data).unwrap();
+        }
+
+        // 400 KB at 1 MiB/s: about 0.3s once the bucket's first 100 KiB is spent
+        let options = CopyOptions::default()
+            .with_parallel(4)
+            .with_bwlimit(1024 * 1024)
+            .with_verify();
+        let start = Instant::now();
+        let stats = crate::copy_dir(&src, &dir.path().join("dst"), &options).unwrap();
+        assert!(start.elapsed() >= std::time::Duration::from_millis(250));
+        assert_eq!(stats.files_copied, 4);
+        for i in 0..4 {
+            assert_eq!(
+                fs::read(dir.path().join(format!("dst/{i}.bin"))).unwrap(),
+                data
+            );
+        }
+
+        // Huge files copied in parallel chunks share the same budget
+        let start = Instant::now();
+        let options = options
+            .with_parallel_chunks(1024)
+            .with_parallel_chunk_size(16 * 1024);
+        let big = dir.path().join("big.bin");
+        fs::write(
+            &big,
+            [data.as_slice(), data.as_slice(), data.as_slice()].concat(),
+        )
+        .unwrap();
+        copy_file(&big, &dir.path().join("big.copy"), &options).unwrap();
+        assert!(start.elapsed() >= std::time::Duration::from_millis(200));
+        assert_eq!(
+            fs::read(dir.path().join("big.copy")).unwrap().len(),
+            300_000
+        );
+    }
+
+    #[test]
+    fn test_copy_file_delta_writes_changed_blocks() {
+        let src_dir = tempdir().unwrap();
+        let dst_dir = tempdir().unwrap();
+
+        let src_file = src_dir.path().join("disk.img");
+        let dst_file = dst_dir.path().join("disk.img");
+
+        let old = vec![0xAAu8; 64 * 1024];
+        let mut new = old.clone();
+        new[20_000] = 0x55;
+        fs::write(&dst_file, &old).unwrap();
+        fs::write(&src_file, &new).unwrap();
+
+        let options = CopyOptions::default()
+            .with_on_conflict(OnConflict::Overwrite)
+            .with_delta(1024)
+            .with_delta_block_size(4096);
+        let stats = crate::copy_file_with_stats(&src_file, &dst_file, &options).unwrap();
+
+        assert_eq!(fs::read(&dst_file).unwrap(), new);
+        assert_eq!(stats.files_copied, 1);
+        assert_eq!(stats.bytes_copied, new.len() as u64);
+        // Without cloning, the delta update is skipped for a full copy
+        let expected = if supports_clone(dst_dir.path()) {
+            4096
+        } else {
+            new.len() as u64
+        };
+        assert_eq!(stats.bytes_written, expected);
+    }
+
+    /// Whether files in `dir` can be cloned, as delta updates need.
+    fn supports_clone(dir: &Path) -> bool {
+        let base = dir.join(".clone-probe-base");
+        let out = dir.join(".clone-probe-out");
+        fs::write(&base, "probe").unwrap();
+        let supported = crate::copy::delta::clone_file(
+            &File::open(&base).unwrap(),
+            &File::create(&out).unwrap(),
+        )
+        .is_ok();
+        fs::remove_file(base).unwrap();
+        fs::remove_file(out).unwrap();
+        supported
+    }
+
+    #[test]
+    fn test_copy_file_delta_below_threshold_copies_in_full() {
+        let src_dir = tempdir().unwrap();
+        let dst_dir = tempdir().unwrap();
+
+        let src_file = src_dir.path().join("small.bin");
+        let dst_file = dst_dir.path().join("small.bin");
+
+        fs::write(&dst_file, vec![1u8; 8192]).unwrap();
+        fs::write(&src_file, vec![2u8; 8192]).unwrap();
+
+        let options = CopyOptions::default()
+            .with_on_conflict(OnConflict::Overwrite)
+            .with_delta(1024 * 1024);
+        let stats = crate::copy_file_with_stats(&src_file, &dst_file, &options).unwrap();
+
+        assert_eq!(fs::read(&dst_file).unwrap(), vec![2u8; 8192]);
+        assert_eq!(stats.bytes_written, 8192);
+    }
+
     #[test]
     fn test_copy_file_overwrite_dir_with_file() {
         // Test that overwriting a directory with a file is NOT allowed
@@ -901,4 +1838,64 @@ mod tests {
         assert!(result.is_ok());
         assert!(dst_file.exists());
     }
+
+    #[test]
+    fn test_copy_file_resumes_partial_copy() {
+        use std::io::Write;
+
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("disk.img");
+        let dst = dir.path().join("copy.img");
+        let data: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&src, &data).unwrap();
+
+        // What an interrupted run leaves: a checkpointed prefix of the source
+        let partial = Partial::for_dst(&dst);
+        let (mut file, _) = partial.open(&fs::metadata(&src).unwrap()).unwrap();
+        file.write_all(&data[..400]).unwrap();
+        partial
+            .checkpoint(&file, &fs::metadata(&src).unwrap(), 400)
+            .unwrap();
+        drop(file);
+
+        let options = CopyOptions::default().with_resume(1);
+        let stats = copy_file_with_stats(&src, &dst, &options).unwrap();
+
+        assert_eq!(stats.bytes_copied, 1000);
+        assert_eq!(stats.bytes_written, 600);
+        assert_eq!(fs::read(&dst).unwrap(), data);
+        let leftovers: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(leftovers.len(), 2, "{leftovers:?}");
+    }
+
+    #[test]
+    fn test_copy_file_verify_mismatch_keeps_destination() {
+        let src_dir = tempdir().unwrap();
+        let reference = tempdir().unwrap();
+        let dst_dir = tempdir().unwrap();
+        let src = src_dir.path().join("a.txt");
+        let stale = reference.path().join("a.txt");
+        let dst = dst_dir.path().join("a.txt");
+        fs::write(&src, "new!").unwrap();
+        fs::write(&dst, "old").unwrap();
+
+        // A reference that passes the size and mtime check but differs
+        fs::write(&stale, "bad!").unwrap();
+        let mtime = filetime::FileTime::from_unix_time(1_700_000_000, 0);
+        filetime::set_file_mtime(&src, mtime).unwrap();
+        filetime::set_file_mtime(&stale, mtime).unwrap();
+
+        let options = CopyOptions::default()
+            .with_on_conflict(OnConflict::Overwrite)
+            .with_link_dest(reference.path())
+            .with_verify();
+        let err = copy_file(&src, &dst, &options).unwrap_err();
+
+        assert!(matches!(err, Error::VerificationFailed { .. }), "{err:?}");
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "old");
+        assert_eq!(fs::read_dir(dst_dir.path()).unwrap().count(), 1);
+    }
 }
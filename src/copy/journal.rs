@@ -0,0 +1,308 @@
+//! Completion journal for resuming interrupted copies.
+//!
+//! A [`Journal`] is an append-only log of the files a run has copied, with the
+//! size and mtime their source had. A later run skips journaled files whose
+//! source is unchanged without looking at the destination at all.
+
+use std::collections::HashMap;
+use std::fs::{self, File, Metadata, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::UNIX_EPOCH;
+
+/// First line of every journal file.
+const HEADER: &str = "# parcopy journal v1";
+
+/// Number of entries appended between two syncs of the journal file.
+const SYNC_EVERY: usize = 1024;
+
+/// Source size and mtime (nanoseconds since the Unix epoch) of a copied file.
+pub(crate) type SourceStamp = (u64, i128);
+
+/// An on-disk journal of completed file copies.
+///
+/// Set it with [`CopyOptions::with_journal`](crate::CopyOptions::with_journal).
+/// Every file a run copies is appended with the source's size and mtime, and
+/// the file is synced every 1024 entries and by [`sync`](Self::sync). When
+/// the journal is opened again, files it lists whose source size and mtime
+/// are unchanged are skipped without a `stat` of the destination, whatever
+/// the conflict policy; everything else is copied as usual.
+///
+/// Moves and atomic publishes ignore the journal, and transactional copies
+/// only read it, since their destination files may not survive the run. A
+/// torn last line, as left by a crash, is ignored.
+///
+/// # Example
+///
+/// ```no_run
+/// use parcopy::{CopyOptions, Journal, copy_dir};
+/// use std::path::Path;
+/// use std::sync::Arc;
+///
+/// let journal = Arc::new(Journal::open("/var/tmp/dataset.journal")?);
+/// let options = CopyOptions::default().with_journal(journal.clone());
+/// let result = copy_dir(Path::new("dataset"), Path::new("/mnt/nfs/dataset"), &options);
+/// journal.sync()?;
+/// result?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    completed: HashMap<(PathBuf, PathBuf), SourceStamp>,
+    writer: Mutex<JournalWriter>,
+}
+
+#[derive(Debug)]
+struct JournalWriter {
+    file: BufWriter<File>,
+    unsynced: usize,
+}
+
+impl Journal {
+    /// Open the journal at `path`, creating it if it does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or opened for appending.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let (completed, torn) = match fs::read_to_string(&path) {
+            Ok(text) => {
+                // A line without its newline may have been cut anywhere, even mid-path
+                let end = text.rfind('\n').map_or(0, |i| i + 1);
+                (parse_journal(&text[..end]), end < text.len())
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (HashMap::new(), false),
+            // Paths are written escaped, so this is not a journal
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} is not a parcopy journal", path.display()),
+                ));
+            }
+            Err(e) => return Err(e),
+        };
+
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "{HEADER}")?;
+        } else if torn {
+            // Start after the torn line rather than completing it
+            writeln!(file)?;
+        }
+
+        Ok(Self {
+            path,
+            completed,
+            writer: Mutex::new(JournalWriter {
+                file: BufWriter::new(file),
+                unsynced: 0,
+            }),
+        })
+    }
+
+    /// Path of the journal file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of completed files read from the journal when it was opened.
+    #[must_use]
+    pub fn completed(&self) -> usize {
+        self.completed.len()
+    }
+
+    /// Write out and sync every entry appended so far.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the journal file cannot be written or synced.
+    pub fn sync(&self) -> io::Result<()> {
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        writer.file.flush()?;
+        writer.file.get_ref().sync_data()?;
+        writer.unsynced = 0;
+        Ok(())
+    }
+
+    /// Whether `src` was copied to `dst` by an earlier run and has not changed since.
+    pub(crate) fn is_completed(&self, src: &Path, dst: &Path, src_meta: &Metadata) -> bool {
+        source_stamp(src_meta).is_some_and(|stamp| self.lists(src, dst, stamp))
+    }
+
+    /// Whether `src`, as described by `stamp`, was copied to `dst` by an earlier run.
+    pub(crate) fn lists(&self, src: &Path, dst: &Path, stamp: SourceStamp) -> bool {
+        if self.completed.is_empty() {
+            return false;
+        }
+        let (Ok(src), Ok(dst)) = (std::path::absolute(src), std::path::absolute(dst)) else {
+            return false;
+        };
+        self.completed.get(&(src, dst)) == Some(&stamp)
+    }
+
+    /// Append that `src`, as described by `stamp`, has been copied to `dst`.
+    pub(crate) fn record(&self, src: &Path, dst: &Path, stamp: SourceStamp) -> io::Result<()> {
+        let (size, mtime) = stamp;
+        // Paths that are not valid UTF-8 are not journaled, so they are always checked
+        let src = std::path::absolute(src)?;
+        let dst = std::path::absolute(dst)?;
+        let (Some(src), Some(dst)) = (src.to_str(), dst.to_str()) else {
+            return Ok(());
+        };
+        let line = format!("{size}\t{mtime}\t{}\t{}\n", escape(src), escape(dst));
+
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        writer.file.write_all(line.as_bytes())?;
+        writer.unsynced += 1;
+        if writer.unsynced >= SYNC_EVERY {
+            writer.file.flush()?;
+            writer.file.get_ref().sync_data()?;
+            writer.unsynced = 0;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Journal {
+    fn drop(&mut self) {
+        let _ = self.sync();
+    }
+}
+
+/// Size and mtime of a source file, as journaled.
+pub(crate) fn source_stamp(meta: &Metadata) -> Option<SourceStamp> {
+    let mtime = meta.modified().ok()?;
+    let nanos = match mtime.duration_since(UNIX_EPOCH) {
+        Ok(d) => i128::try_from(d.as_nanos()).ok()?,
+        Err(e) => -i128::try_from(e.duration().as_nanos()).ok()?,
+    };
+    Some((meta.len(), nanos))
+}
+
+/// Read the entries of a journal, skipping lines that cannot be parsed.
+fn parse_journal(text: &str) -> HashMap<(PathBuf, PathBuf), SourceStamp> {
+    let mut completed = HashMap::new();
+    for line in text.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split('\t');
+        let (Some(size), Some(mtime), Some(src), Some(dst), None) = (
+            fields.next(),
+            fields.next(),
+            fields.next(),
+            fields.next(),
+            fields.next(),
+        ) else {
+            continue;
+        };
+        let (Ok(size), Ok(mtime), Some(src), Some(dst)) =
+            (size.parse(), mtime.parse(), unescape(src), unescape(dst))
+        else {
+            continue;
+        };
+        completed.insert((PathBuf::from(src), PathBuf::from(dst)), (size, mtime));
+    }
+    completed
+}
+
+/// Escape the separators of a journal line in a path.
+fn escape(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Undo [`escape`], or `None` if the field is malformed.
+fn unescape(field: &str) -> Option<String> {
+    let mut out = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next()? {
+            '\\' => out.push('\\'),
+            't' => out.push('\t'),
+            'n' => out.push('\n'),
+            'r' => out.push('\r'),
+            _ => return None,
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_journal_remembers_completed_files() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("odd\tname.txt");
+        let dst = dir.path().join("copy.txt");
+        let other = dir.path().join("other.txt");
+        fs::write(&src, "abc").unwrap();
+        fs::write(&other, "xyz").unwrap();
+        let path = dir.path().join("journal");
+
+        let journal = Journal::open(&path).unwrap();
+        assert_eq!(journal.completed(), 0);
+        journal
+            .record(
+                &src,
+                &dst,
+                source_stamp(&fs::metadata(&src).unwrap()).unwrap(),
+            )
+            .unwrap();
+        drop(journal);
+
+        let journal = Journal::open(&path).unwrap();
+        let meta = fs::metadata(&src).unwrap();
+        assert_eq!(journal.completed(), 1);
+        assert!(journal.is_completed(&src, &dst, &meta));
+        assert!(!journal.is_completed(&other, &dst, &meta));
+
+        fs::write(&src, "abcd").unwrap();
+        assert!(!journal.is_completed(&src, &dst, &fs::metadata(&src).unwrap()));
+    }
+
+    #[test]
+    fn test_journal_ignores_torn_lines() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("journal");
+        fs::write(
+            &path,
+            format!("{HEADER}\n3\t1700000000000000000\t/a\t/b\n7\t12"),
+        )
+        .unwrap();
+
+        let journal = Journal::open(&path).unwrap();
+        assert_eq!(journal.completed(), 1);
+        let src = dir.path().join("src");
+        fs::write(&src, "abc").unwrap();
+        let meta = fs::metadata(&src).unwrap();
+        journal
+            .record(&src, &src, source_stamp(&meta).unwrap())
+            .unwrap();
+        drop(journal);
+
+        let journal = Journal::open(&path).unwrap();
+        assert_eq!(journal.completed(), 2);
+        assert!(journal.is_completed(&src, &src, &meta));
+    }
+}
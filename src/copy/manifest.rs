@@ -0,0 +1,510 @@
+//! Checksum manifests of copied files.
+//!
+//! A [`Manifest`] collects the digest of every file a run copies, computed
+//! while the file is copied wherever possible, and writes them out once the
+//! run is done.
+
+use std::fmt::Write as _;
+use std::fs::{self, Metadata};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::clean::temp_builder;
+use super::digest::Digest;
+
+/// File format of a [`Manifest`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ManifestFormat {
+    /// `<sha256>  <path>` lines, as written and checked by `sha256sum`
+    #[default]
+    Sha256Sums,
+    /// One JSON object per line with `path`, `size`, `mtime`, and `sha256`
+    Jsonl,
+}
+
+/// One file listed in a [`Manifest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// Path of the file, relative to the manifest's directory when inside it
+    pub path: PathBuf,
+    /// Size in bytes
+    pub size: u64,
+    /// Modification time of the copy
+    pub mtime: SystemTime,
+    /// SHA-256 digest of the contents
+    pub digest: Digest,
+}
+
+/// A checksum manifest of the files copied by one or more runs.
+///
+/// Set it with [`CopyOptions::with_manifest`](crate::CopyOptions::with_manifest)
+/// and call [`finish`](Self::finish) once every copy is done. Files that are
+/// copied or hard-linked into the destination are listed; skipped files and
+/// files moved by renaming are not.
+///
+/// Nothing is written until `finish`, which writes the manifest atomically,
+/// sorted by path. Paths are relative to the directory holding the manifest
+/// when the file is inside it, so a manifest placed at the destination root
+/// can be checked with `sha256sum -c` from there.
+///
+/// # Example
+///
+/// ```no_run
+/// use parcopy::{CopyOptions, Manifest, ManifestFormat, copy_dir};
+/// use std::path::Path;
+/// use std::sync::Arc;
+///
+/// let manifest = Arc::new(Manifest::new("/data/set/SHA256SUMS", ManifestFormat::Sha256Sums));
+/// let options = CopyOptions::default().with_manifest(manifest.clone());
+/// copy_dir(Path::new("set"), Path::new("/data/set"), &options)?;
+/// manifest.finish()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct Manifest {
+    path: PathBuf,
+    format: ManifestFormat,
+    entries: Mutex<Vec<ManifestEntry>>,
+}
+
+impl Manifest {
+    /// Prepare a manifest to be written to `path` in the given format.
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>, format: ManifestFormat) -> Self {
+        Self {
+            path: path.into(),
+            format,
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Path the manifest is written to.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Format of the manifest.
+    #[must_use]
+    pub fn format(&self) -> ManifestFormat {
+        self.format
+    }
+
+    /// Add the file at `path` with the given metadata and digest.
+    pub(crate) fn record(&self, path: &Path, meta: &Metadata, digest: Digest) -> io::Result<()> {
+        let path = std::path::absolute(path)?;
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(ManifestEntry {
+                path,
+                size: meta.len(),
+                mtime: meta.modified()?,
+                digest,
+            });
+        Ok(())
+    }
+
+    /// The entries recorded so far, sorted by path.
+    ///
+    /// Paths are made relative to the manifest's directory where possible.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the manifest's directory cannot be resolved.
+    pub fn entries(&self) -> io::Result<Vec<ManifestEntry>> {
+        let base = std::path::absolute(self.dir())?;
+        let mut entries = self
+            .entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        for entry in &mut entries {
+            if let Ok(rel) = entry.path.strip_prefix(&base) {
+                entry.path = rel.to_path_buf();
+            }
+        }
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(entries)
+    }
+
+    /// Write the manifest to its path and sync it.
+    ///
+    /// It is written to a temp file next to the path first and renamed into
+    /// place, so readers never see a partial manifest. May be called again
+    /// after more files were copied to rewrite it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing or renaming the manifest fails.
+    pub fn finish(&self) -> io::Result<()> {
+        let entries = self.entries()?;
+        let dir = self.dir();
+        fs::create_dir_all(dir)?;
+        let mut file = temp_builder().tempfile_in(dir)?;
+
+        let mut out = io::BufWriter::new(file.as_file_mut());
+        for entry in &entries {
+            out.write_all(format_entry(entry, self.format).as_bytes())?;
+        }
+        out.flush()?;
+        drop(out);
+        file.as_file().sync_all()?;
+
+        file.persist(&self.path).map_err(|e| e.error)?;
+        Ok(())
+    }
+
+    /// Directory the manifest is written to.
+    fn dir(&self) -> &Path {
+        match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        }
+    }
+}
+
+/// Render one manifest line, including its newline.
+fn format_entry(entry: &ManifestEntry, format: ManifestFormat) -> String {
+    let path = portable_path(&entry.path);
+    match format {
+        ManifestFormat::Sha256Sums => {
+            // Like sha256sum, flag names with special characters and escape them
+            if path.contains(['\\', '\n', '\r']) {
+                let escaped = path
+                    .replace('\\', "\\\\")
+                    .replace('\n', "\\n")
+                    .replace('\r', "\\r");
+                format!("\\{}  {escaped}\n", entry.digest)
+            } else {
+                format!("{}  {path}\n", entry.digest)
+            }
+        }
+        ManifestFormat::Jsonl => format!(
+            "{{\"path\":{},\"size\":{},\"mtime\":{},\"sha256\":\"{}\"}}\n",
+            json_string(&path),
+            entry.size,
+            unix_time(entry.mtime),
+            entry.digest
+        ),
+    }
+}
+
+/// Render `path` with `/` separators on every platform.
+fn portable_path(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    if cfg!(windows) {
+        path.replace('\\', "/")
+    } else {
+        path.into_owned()
+    }
+}
+
+/// Seconds since the Unix epoch with nanosecond precision, as a JSON number.
+fn unix_time(time: SystemTime) -> String {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => format!("{}.{:09}", d.as_secs(), d.subsec_nanos()),
+        Err(e) => {
+            let d = e.duration();
+            format!("-{}.{:09}", d.as_secs(), d.subsec_nanos())
+        }
+    }
+}
+
+/// Quote `s` as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if u32::from(c) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// A file listed in a manifest being read back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ListedFile {
+    /// Path as written in the manifest
+    pub path: PathBuf,
+    /// Expected digest
+    pub digest: Digest,
+}
+
+/// Parse a manifest in either format, detected line by line.
+///
+/// On failure, returns the 1-based line number and what is wrong with it.
+pub(crate) fn parse_manifest(text: &str) -> std::result::Result<Vec<ListedFile>, (usize, String)> {
+    let mut files = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            continue;
+        }
+        let parsed = if line.trim_start().starts_with('{') {
+            parse_jsonl_line(line)
+        } else {
+            parse_sha256sums_line(line)
+        };
+        files.push(parsed.map_err(|reason| (index + 1, reason))?);
+    }
+    Ok(files)
+}
+
+/// Parse a `<sha256>  <path>` line, as written by `sha256sum`.
+fn parse_sha256sums_line(line: &str) -> std::result::Result<ListedFile, String> {
+    let (escaped, line) = match line.strip_prefix('\\') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    let (hex, rest) = line
+        .split_at_checked(64)
+        .ok_or_else(|| "expected a SHA-256 digest".to_owned())?;
+    let digest = Digest::from_hex(hex).ok_or_else(|| format!("invalid digest `{hex}`"))?;
+    // A space and then a space (text mode) or `*` (binary mode)
+    let name = rest
+        .strip_prefix("  ")
+        .or_else(|| rest.strip_prefix(" *"))
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| "expected two spaces and a path after the digest".to_owned())?;
+    let name = if escaped {
+        unescape_sha256sums(name)?
+    } else {
+        name.to_owned()
+    };
+    Ok(ListedFile {
+        path: PathBuf::from(name),
+        digest,
+    })
+}
+
+/// Undo the `\\`, `\n`, and `\r` escapes of `sha256sum`.
+fn unescape_sha256sums(name: &str) -> std::result::Result<String, String> {
+    let mut out = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => out.push('\\'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            other => return Err(format!("invalid escape `\\{}`", other.unwrap_or(' '))),
+        }
+    }
+    Ok(out)
+}
+
+/// Parse a JSONL line in the shape [`format_entry`] writes.
+fn parse_jsonl_line(line: &str) -> std::result::Result<ListedFile, String> {
+    let rest = line
+        .trim()
+        .strip_prefix("{\"path\":\"")
+        .ok_or_else(|| "expected an object starting with `path`".to_owned())?;
+    let (path, rest) = unquote_json(rest)?;
+    let (size, mtime, hex) = rest
+        .strip_suffix("\"}")
+        .and_then(|rest| rest.strip_prefix(",\"size\":"))
+        .and_then(|rest| rest.split_once(",\"mtime\":"))
+        .and_then(|(size, rest)| {
+            let (mtime, hex) = rest.split_once(",\"sha256\":\"")?;
+            Some((size, mtime, hex))
+        })
+        .ok_or_else(|| "expected `size`, `mtime`, and `sha256` after `path`".to_owned())?;
+    let is_number = |s: &str, extra: &[char]| {
+        !s.is_empty() && s.chars().all(|c| c.is_ascii_digit() || extra.contains(&c))
+    };
+    if !is_number(size, &[]) || !is_number(mtime, &['-', '.']) {
+        return Err("`size` and `mtime` must be numbers".to_owned());
+    }
+    let digest = Digest::from_hex(hex).ok_or_else(|| format!("invalid digest `{hex}`"))?;
+    Ok(ListedFile {
+        path: PathBuf::from(path),
+        digest,
+    })
+}
+
+/// Undo the escapes of [`json_string`] up to the closing quote.
+///
+/// `s` starts after the opening quote. Returns the string and what follows
+/// the closing quote.
+fn unquote_json(s: &str) -> std::result::Result<(String, &str), String> {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        let unescaped = match c {
+            '"' => return Ok((out, &s[i + 1..])),
+            '\\' => match chars.next().map(|(_, e)| e) {
+                Some('"') => '"',
+                Some('\\') => '\\',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('t') => '\t',
+                Some('u') => {
+                    let hex = chars.as_str().get(..4).unwrap_or_default();
+                    let code = u32::from_str_radix(hex, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| "invalid \\u escape".to_owned())?;
+                    chars.nth(3);
+                    code
+                }
+                _ => return Err("invalid escape in string".to_owned()),
+            },
+            c => c,
+        };
+        out.push(unescaped);
+    }
+    Err("unterminated string".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::copy::digest::hash_file;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_manifest_sha256sums_relative_and_sorted() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub/b.txt"), "b").unwrap();
+        fs::write(dir.path().join("a.txt"), "abc").unwrap();
+
+        let manifest = Manifest::new(dir.path().join("SHA256SUMS"), ManifestFormat::Sha256Sums);
+        for name in ["sub/b.txt", "a.txt"] {
+            let path = dir.path().join(name);
+            manifest
+                .record(
+                    &path,
+                    &fs::metadata(&path).unwrap(),
+                    hash_file(&path).unwrap(),
+                )
+                .unwrap();
+        }
+        manifest.finish().unwrap();
+
+        let text = fs::read_to_string(dir.path().join("SHA256SUMS")).unwrap();
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  a.txt"
+        );
+        assert!(lines[1].ends_with("  sub/b.txt"));
+    }
+
+    #[test]
+    fn test_manifest_jsonl_entry() {
+        let entry = ManifestEntry {
+            path: PathBuf::from("dir/we\"ird.txt"),
+            size: 3,
+            mtime: UNIX_EPOCH + std::time::Duration::new(1_700_000_000, 5),
+            digest: Digest::from_bytes([0; 32]),
+        };
+
+        let line = format_entry(&entry, ManifestFormat::Jsonl);
+
+        assert_eq!(
+            line,
+            format!(
+                "{{\"path\":\"dir/we\\\"ird.txt\",\"size\":3,\"mtime\":1700000000.000000005,\"sha256\":\"{}\"}}\n",
+                "0".repeat(64)
+            )
+        );
+    }
+
+    #[test]
+    fn test_manifest_escapes_special_names() {
+        let entry = ManifestEntry {
+            path: PathBuf::from("line\nbreak"),
+            size: 0,
+            mtime: UNIX_EPOCH,
+            digest: Digest::from_bytes([0; 32]),
+        };
+
+        let line = format_entry(&entry, ManifestFormat::Sha256Sums);
+
+        assert_eq!(line, format!("\\{}  line\\nbreak\n", "0".repeat(64)));
+    }
+
+    #[test]
+    fn test_parse_manifest_roundtrips_both_formats() {
+        for format in [ManifestFormat::Sha256Sums, ManifestFormat::Jsonl] {
+            let entries = [
+                ManifestEntry {
+                    path: PathBuf::from("plain.txt"),
+                    size: 1,
+                    mtime: UNIX_EPOCH,
+                    digest: Digest::from_bytes([1; 32]),
+                },
+                ManifestEntry {
+                    path: PathBuf::from("odd\\na\"me\n\u{e9}"),
+                    size: 2,
+                    mtime: UNIX_EPOCH,
+                    digest: Digest::from_bytes([2; 32]),
+                },
+            ];
+            let text: String = entries.iter().map(|e| format_entry(e, format)).collect();
+
+            let files = parse_manifest(&text).unwrap();
+
+            let parsed: Vec<_> = files.iter().map(|f| (f.path.clone(), f.digest)).collect();
+            let expected: Vec<_> = entries.iter().map(|e| (e.path.clone(), e.digest)).collect();
+            assert_eq!(parsed, expected, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_manifest_accepts_binary_mode_and_unicode_escapes() {
+        let digest = "0".repeat(64);
+        let text = format!(
+            "{digest} *bin.dat\n\n{{\"path\":\"\\u0001\u{e9}\",\"size\":1,\"mtime\":0.000000000,\"sha256\":\"{digest}\"}}\n"
+        );
+
+        let files = parse_manifest(&text).unwrap();
+
+        assert_eq!(files[0].path, Path::new("bin.dat"));
+        assert_eq!(files[1].path, Path::new("\u{1}\u{e9}"));
+    }
+
+    #[test]
+    fn test_parse_manifest_rejects_other_jsonl_shapes() {
+        let digest = "0".repeat(64);
+        for line in [
+            format!("{{\"sha256\":\"{digest}\",\"path\":\"a\"}}"),
+            format!("{{\"path\":\"a\",\"size\":1,\"mtime\":0,\"sha256\":\"{digest}\",\"x\":1}}"),
+            format!(
+                "{{\"path\":\"a\",\"size\":1,\"mtime\":0,\"x\":\"y\",\"sha256\":\"{digest}\"}}"
+            ),
+        ] {
+            assert!(parse_manifest(&line).is_err(), "{line}");
+        }
+    }
+
+    #[test]
+    fn test_parse_manifest_reports_line() {
+        let digest = "0".repeat(64);
+        let text = format!("{digest}  ok\n{{\"path\":\"no-digest\"}}\n");
+
+        let (line, reason) = parse_manifest(&text).unwrap_err();
+
+        assert_eq!(line, 2);
+        assert!(reason.contains("sha256"), "{reason}");
+    }
+}
@@ -4,11 +4,52 @@
 //! safety guarantees including atomic writes, TOCTOU protection, and
 //! parallel operations.
 
+mod adaptive;
+mod backup;
+#[cfg(all(feature = "io_uring", target_os = "linux"))]
+mod batch;
+mod clean;
+mod compare;
+mod context;
+mod dedup;
+mod delta;
+mod digest;
 mod dir;
 mod file;
+mod journal;
+mod manifest;
+mod mv;
+mod partial;
+mod publish;
+mod reference;
 mod reflink;
+mod remaining;
+mod rollback;
+mod scrub;
+mod throttle;
+#[cfg(all(feature = "io_uring", target_os = "linux"))]
+mod uring;
 mod utils;
+mod verify;
 
 // Re-export public API
+pub use clean::{
+    CleanReport, StaleTemp, TEMP_FILE_PREFIX, TempKind, clean_temp_files, find_stale_temp_files,
+};
+pub use compare::{Difference, EntryKind, TreeDiff, TreeDifference, compare_trees};
+pub use digest::Digest;
+pub(crate) use dir::copy_symlink_with_stats;
 pub use dir::{CopyStats, copy_dir};
-pub use file::copy_file;
+pub use file::{copy_file, copy_file_with_stats};
+pub use journal::Journal;
+pub use manifest::{Manifest, ManifestEntry, ManifestFormat};
+pub use mv::{move_dir, move_file};
+pub(crate) use reference::{MatchRule, find_match as find_reference_match};
+pub use remaining::{RemainingEntry, RemainingWork};
+pub use rollback::RollbackReport;
+pub use scrub::{ManifestCheck, verify_manifest};
+pub use throttle::RateLimiter;
+#[cfg(all(feature = "io_uring", target_os = "linux"))]
+pub(crate) use uring::is_available as io_uring_available;
+pub use verify::VerifyMismatch;
+pub(crate) use verify::describe_mismatches;
@@ -0,0 +1,504 @@
+//! Move operations.
+//!
+//! A move renames the source in place when source and destination are on the
+//! same filesystem. Otherwise it is a copy with the usual atomic guarantees that
+//! removes each source entry only once its destination has been persisted (and
+//! synced, with `fsync`). Sources whose destination already holds the same
+//! contents are removed too, so re-running an interrupted move resumes it.
+
+use crate::error::{Error, Result};
+use crate::options::{CopyOptions, OnConflict};
+use crate::utils::path::safe_path;
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::Path;
+use std::time::Instant;
+
+use super::backup::make_backup;
+use super::clean::sweep_stale_temps;
+use super::context::CopyContext;
+use super::dir::{CopyStats, copy_tree};
+use super::file::copy_file_internal;
+use super::reference::{MatchRule, is_unchanged};
+use super::utils::is_source_newer;
+
+/// Move a single file
+///
+/// Renames `src` to `dst` when both are on the same filesystem. Otherwise the
+/// file is copied atomically as by [`copy_file`](crate::copy_file), and `src`
+/// is removed once the copy is in place. Conflicts follow `on_conflict`; a
+/// skipped file stays at its source unless the destination already holds the
+/// same contents.
+///
+/// A symlink source is renamed as-is, but copied through (like `copy_file`)
+/// when it has to cross filesystems.
+///
+/// # Returns
+///
+/// Returns [`CopyStats`] with `renamed` set for a rename, or the copy
+/// statistics otherwise.
+///
+/// # Errors
+///
+/// Same as [`copy_file`](crate::copy_file), plus [`Error::RemoveSource`] if
+/// the source cannot be removed after it was copied.
+pub fn move_file(src: &Path, dst: &Path, options: &CopyOptions) -> Result<CopyStats> {
+    let start = Instant::now();
+
+    let src_meta = fs::symlink_metadata(src)?;
+    if src_meta.is_dir() {
+        return Err(Error::IsADirectory(src.to_path_buf()));
+    }
+
+    let mut stats = if !options.is_cancelled() && try_rename(src, dst, &src_meta, options)? {
+        CopyStats {
+            renamed: 1,
+            ..CopyStats::default()
+        }
+    } else {
+        let ctx = CopyContext::for_file(dst).moving();
+        copy_file_internal(src, dst, options, &ctx)?.to_stats()
+    };
+    stats.duration = start.elapsed();
+    Ok(stats)
+}
+
+/// Move a directory tree
+///
+/// Renames `src` to `dst` when `dst` does not exist and both are on the same
+/// filesystem. Otherwise the tree is copied as by [`copy_dir`](crate::copy_dir)
+/// into the destination in place, removing each source file and symlink once
+/// its destination is in place and then every source directory left empty.
+/// Symlinks are always moved as links.
+///
+/// If the move is interrupted, running it again with the same options finishes
+/// it: entries already moved are gone from the source, and entries copied but
+/// not yet removed are recognized by comparing their contents with the
+/// destination and removed.
+///
+/// # Errors
+///
+/// Same as [`copy_dir`](crate::copy_dir), plus:
+/// - `dst` is `src` or lies inside it ([`Error::MoveIntoSelf`])
+/// - [`atomic_publish`](CopyOptions::atomic_publish) or
+///   [`transactional`](CopyOptions::transactional) is set
+///   ([`Error::UnsupportedMoveOption`])
+/// - A source entry cannot be removed after it was copied ([`Error::RemoveSource`])
+pub fn move_dir(src: &Path, dst: &Path, options: &CopyOptions) -> Result<CopyStats> {
+    let start = Instant::now();
+
+    // Sources are removed as the copy goes, so there is nothing to stage or undo
+    if options.atomic_publish {
+        return Err(Error::UnsupportedMoveOption("atomic_publish"));
+    }
+    if options.transactional {
+        return Err(Error::UnsupportedMoveOption("transactional"));
+    }
+
+    let src_meta = match fs::symlink_metadata(src) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(Error::SourceNotFound(src.to_path_buf()));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if !src_meta.is_dir() {
+        return Err(Error::NotADirectory(src.to_path_buf()));
+    }
+    if is_inside(dst, src) {
+        return Err(Error::MoveIntoSelf {
+            src: src.to_path_buf(),
+            dst: dst.to_path_buf(),
+        });
+    }
+
+    sweep_stale_temps(dst, options);
+    if !options.is_cancelled() && try_rename(src, dst, &src_meta, options)? {
+        return Ok(CopyStats {
+            renamed: 1,
+            duration: start.elapsed(),
+            ..CopyStats::default()
+        });
+    }
+
+    // Following symlinks would remove files outside the tree being moved
+    let options = CopyOptions {
+        preserve_symlinks: true,
+        ..options.clone()
+    };
+    copy_tree(src, dst, &options, &CopyContext::new(dst).moving())
+}
+
+/// Move `src` to `dst` with a rename if possible.
+///
+/// Returns `false` if the entry has to be copied instead: across filesystems,
+/// with reference trees (which decide per file), or when an existing
+/// destination must not be replaced.
+fn try_rename(src: &Path, dst: &Path, src_meta: &Metadata, options: &CopyOptions) -> Result<bool> {
+    if !options.link_dest.is_empty() || !options.compare_dest.is_empty() {
+        return Ok(false);
+    }
+
+    let safe_src = safe_path(src);
+    let safe_dst = safe_path(dst);
+
+    let renamed = match fs::symlink_metadata(&safe_dst) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            if src_meta.is_dir() {
+                fs::rename(&safe_src, &safe_dst)
+            } else {
+                // Link, then unlink: unlike rename, linking never replaces a
+                // destination that appeared in the meantime
+                match fs::hard_link(&safe_src, &safe_dst) {
+                    Ok(()) => {
+                        remove_moved_source(src, dst, true, options)?;
+                        Ok(())
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+        }
+        Ok(dst_meta) => {
+            let replace = !src_meta.is_dir()
+                && !dst_meta.is_dir()
+                && same_device(src_meta, &dst_meta)
+                && match options.on_conflict {
+                    OnConflict::Overwrite => true,
+                    OnConflict::UpdateNewer => is_source_newer(src_meta, &dst_meta),
+                    OnConflict::Skip | OnConflict::Error => false,
+                };
+            if !replace {
+                return Ok(false);
+            }
+
+            if let Some(mode) = &options.backup {
+                let ctx = CopyContext::for_file(dst);
+                make_backup(&safe_dst, ctx.relative(dst), mode, None).map_err(|e| {
+                    Error::Backup {
+                        path: dst.to_path_buf(),
+                        source: e,
+                    }
+                })?;
+            }
+            fs::rename(&safe_src, &safe_dst)
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    match renamed {
+        Ok(()) => {
+            if options.fsync {
+                sync_parent(dst)?;
+                sync_parent(src)?;
+            }
+            options.verbose(&format!("renamed {} -> {}", src.display(), dst.display()));
+            Ok(true)
+        }
+        Err(e) => {
+            options.verbose(&format!(
+                "cannot rename {} -> {} ({}), copying instead",
+                src.display(),
+                dst.display(),
+                e
+            ));
+            Ok(false)
+        }
+    }
+}
+
+/// Remove the source file or symlink `src` after moving it to `dst`.
+///
+/// `moved` tells whether this run just put `dst` in place. Otherwise `src` is
+/// only removed if `dst` already holds the same contents, as left by an
+/// interrupted move; size and mtime alone could match a different file.
+pub(crate) fn remove_moved_source(
+    src: &Path,
+    dst: &Path,
+    moved: bool,
+    options: &CopyOptions,
+) -> Result<()> {
+    if !moved && !already_moved(src, dst, options) {
+        return Ok(());
+    }
+
+    // The destination's directory entry must be durable before the source goes
+    if options.fsync {
+        sync_parent(dst)?;
+    }
+    fs::remove_file(safe_path(src)).map_err(|e| Error::RemoveSource {
+        path: src.to_path_buf(),
+        source: e,
+    })
+}
+
+/// Whether `dst` already holds the contents (or link target) of `src`.
+fn already_moved(src: &Path, dst: &Path, options: &CopyOptions) -> bool {
+    options.throttle_metadata(2);
+    let (Ok(src_meta), Ok(dst_meta)) = (fs::symlink_metadata(src), fs::symlink_metadata(dst))
+    else {
+        return false;
+    };
+
+    if src_meta.file_type().is_symlink() {
+        return dst_meta.file_type().is_symlink()
+            && matches!(
+                (fs::read_link(src), fs::read_link(dst)),
+                (Ok(a), Ok(b)) if a == b
+            );
+    }
+    let rule = MatchRule {
+        checksum: true,
+        permissions: false,
+    };
+    is_unchanged(src, &src_meta, dst, &dst_meta, rule)
+}
+
+/// Whether `dst` is `src` itself or lies inside it.
+fn is_inside(dst: &Path, src: &Path) -> bool {
+    let Ok(src) = fs::canonicalize(src) else {
+        return false;
+    };
+
+    // Resolve the longest existing prefix of `dst`; the rest does not exist yet
+    let mut existing = dst;
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(resolved) = fs::canonicalize(existing) {
+            let full = missing
+                .iter()
+                .rev()
+                .fold(resolved, |path, name| path.join(name));
+            return full.starts_with(&src);
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = if parent.as_os_str().is_empty() {
+                    Path::new(".")
+                } else {
+                    parent
+                };
+            }
+            _ => return false,
+        }
+    }
+}
+
+#[cfg(unix)]
+fn same_device(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev()
+}
+
+#[cfg(not(unix))]
+fn same_device(_a: &Metadata, _b: &Metadata) -> bool {
+    true
+}
+
+/// Flush the directory containing `path`, making renames and unlinks in it durable.
+#[cfg(unix)]
+pub(crate) fn sync_parent(path: &Path) -> io::Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::File::open(parent)?.sync_all()
+}
+
+#[cfg(not(unix))]
+pub(crate) fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::BackupMode;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_move_file_renames() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("a.txt");
+        let dst = dir.path().join("b.txt");
+        fs::write(&src, "hello").unwrap();
+
+        let stats = move_file(&src, &dst, &CopyOptions::default()).unwrap();
+
+        assert_eq!(stats.renamed, 1);
+        assert!(!src.exists());
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_move_file_skip_keeps_source() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("a.txt");
+        let dst = dir.path().join("b.txt");
+        fs::write(&src, "new content").unwrap();
+        fs::write(&dst, "old").unwrap();
+
+        let stats = move_file(&src, &dst, &CopyOptions::default()).unwrap();
+
+        assert_eq!(stats.files_skipped, 1);
+        assert_eq!(fs::read_to_string(&src).unwrap(), "new content");
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "old");
+    }
+
+    #[test]
+    fn test_move_file_overwrite_with_backup() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("a.txt");
+        let dst = dir.path().join("b.txt");
+        fs::write(&src, "new").unwrap();
+        fs::write(&dst, "old").unwrap();
+
+        let options = CopyOptions::default()
+            .with_on_conflict(OnConflict::Overwrite)
+            .with_backup(BackupMode::default());
+        let stats = move_file(&src, &dst, &options).unwrap();
+
+        assert_eq!(stats.renamed, 1);
+        assert!(!src.exists());
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "new");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("b.txt~")).unwrap(),
+            "old"
+        );
+    }
+
+    #[test]
+    fn test_move_dir_renames_tree() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("sub/f.txt"), "data").unwrap();
+
+        let stats = move_dir(&src, &dst, &CopyOptions::default()).unwrap();
+
+        assert_eq!(stats.renamed, 1);
+        assert!(!src.exists());
+        assert_eq!(fs::read_to_string(dst.join("sub/f.txt")).unwrap(), "data");
+    }
+
+    #[test]
+    fn test_move_dir_merges_into_existing_destination() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::create_dir_all(src.join("keep")).unwrap();
+        fs::create_dir_all(dst.join("keep")).unwrap();
+        fs::write(src.join("sub/f.txt"), "data").unwrap();
+        fs::write(src.join("keep/conflict.txt"), "mine").unwrap();
+        fs::write(dst.join("keep/conflict.txt"), "theirs").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("sub/f.txt", src.join("link")).unwrap();
+
+        let stats = move_dir(&src, &dst, &CopyOptions::default()).unwrap();
+
+        assert_eq!(stats.renamed, 0);
+        assert_eq!(stats.files_copied, 1);
+        assert_eq!(stats.files_skipped, 1);
+        assert_eq!(fs::read_to_string(dst.join("sub/f.txt")).unwrap(), "data");
+        assert!(!src.join("sub").exists());
+        // The skipped file stays behind, and so does its directory
+        assert_eq!(
+            fs::read_to_string(src.join("keep/conflict.txt")).unwrap(),
+            "mine"
+        );
+        assert_eq!(
+            fs::read_to_string(dst.join("keep/conflict.txt")).unwrap(),
+            "theirs"
+        );
+        #[cfg(unix)]
+        {
+            assert!(fs::symlink_metadata(src.join("link")).is_err());
+            assert_eq!(
+                fs::read_link(dst.join("link")).unwrap(),
+                Path::new("sub/f.txt")
+            );
+        }
+    }
+
+    #[test]
+    fn test_move_dir_resumes_interrupted_move() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(&src).unwrap();
+        fs::create_dir_all(&dst).unwrap();
+        fs::write(src.join("done.txt"), "copied before the interruption").unwrap();
+        fs::write(src.join("todo.txt"), "not copied yet").unwrap();
+
+        // An interrupted run copied done.txt but did not get to remove it
+        crate::copy_file(
+            &src.join("done.txt"),
+            &dst.join("done.txt"),
+            &CopyOptions::default(),
+        )
+        .unwrap();
+
+        let stats = move_dir(&src, &dst, &CopyOptions::default()).unwrap();
+
+        assert_eq!(stats.files_copied, 1);
+        assert_eq!(stats.files_skipped, 1);
+        assert!(!src.exists());
+        assert_eq!(
+            fs::read_to_string(dst.join("todo.txt")).unwrap(),
+            "not copied yet"
+        );
+    }
+
+    #[test]
+    fn test_move_dir_keeps_source_skipped_with_same_size_and_mtime() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(&src).unwrap();
+        fs::create_dir_all(&dst).unwrap();
+        fs::write(src.join("f.txt"), "mine").unwrap();
+        fs::write(dst.join("f.txt"), "ours").unwrap();
+        let mtime = filetime::FileTime::from_unix_time(1_600_000_000, 0);
+        filetime::set_file_mtime(src.join("f.txt"), mtime).unwrap();
+        filetime::set_file_mtime(dst.join("f.txt"), mtime).unwrap();
+
+        let stats = move_dir(&src, &dst, &CopyOptions::default()).unwrap();
+
+        assert_eq!(stats.files_skipped, 1);
+        assert_eq!(fs::read_to_string(src.join("f.txt")).unwrap(), "mine");
+        assert_eq!(fs::read_to_string(dst.join("f.txt")).unwrap(), "ours");
+    }
+
+    #[test]
+    fn test_move_dir_rejects_atomic_and_transactional() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("f.txt"), "data").unwrap();
+
+        for options in [
+            CopyOptions::default().with_atomic_publish(),
+            CopyOptions::default().with_transactional(),
+        ] {
+            let result = move_dir(&src, &dst, &options);
+            assert!(matches!(result, Err(Error::UnsupportedMoveOption(_))));
+        }
+        assert!(src.join("f.txt").exists());
+        assert!(!dst.exists());
+    }
+
+    #[test]
+    fn test_move_dir_into_itself_fails() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+
+        let result = move_dir(&src, &src.join("nested/deeper"), &CopyOptions::default());
+
+        assert!(matches!(result, Err(Error::MoveIntoSelf { .. })));
+        assert!(src.exists());
+    }
+}
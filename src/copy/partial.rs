@@ -0,0 +1,209 @@
+//! Resumable partial copies of large files.
+//!
+//! A large file is copied into a named partial file next to its destination
+//! instead of an anonymous temp file. After every checkpoint the partial file
+//! is synced and a sidecar records the source identity and the offset up to
+//! which the partial file holds the source. If the copy is interrupted, the
+//! next copy of the same, unchanged source continues from that offset.
+
+use std::ffi::OsString;
+use std::fs::{self, File, Metadata, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tempfile::TempPath;
+
+use crate::utils::path::safe_path;
+
+use super::clean::temp_builder;
+
+/// Bytes copied between two checkpoints of a partial file.
+pub(crate) const CHECKPOINT_INTERVAL: u64 = 128 * 1024 * 1024;
+
+/// Suffix of partial file names; the sidecar adds [`STATE_SUFFIX`].
+pub(crate) const PARTIAL_SUFFIX: &str = ".parcopy-partial";
+
+/// Suffix of the sidecar next to a partial file.
+pub(crate) const STATE_SUFFIX: &str = ".state";
+
+/// First line of every sidecar.
+const STATE_HEADER: &str = "# parcopy partial v1";
+
+/// Identity of a source file: size, mtime (nanoseconds since the Unix
+/// epoch), and inode (0 where there is none).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SourceIdentity {
+    size: u64,
+    mtime: i128,
+    inode: u64,
+}
+
+impl SourceIdentity {
+    fn of(meta: &Metadata) -> Option<Self> {
+        let mtime = meta.modified().ok()?;
+        let mtime = match mtime.duration_since(UNIX_EPOCH) {
+            Ok(d) => i128::try_from(d.as_nanos()).ok()?,
+            Err(e) => -i128::try_from(e.duration().as_nanos()).ok()?,
+        };
+        #[cfg(unix)]
+        let inode = std::os::unix::fs::MetadataExt::ino(meta);
+        #[cfg(not(unix))]
+        let inode = 0;
+        Some(Self {
+            size: meta.len(),
+            mtime,
+            inode,
+        })
+    }
+}
+
+/// The partial file and sidecar of one destination.
+#[derive(Debug)]
+pub(crate) struct Partial {
+    path: PathBuf,
+    state_path: PathBuf,
+}
+
+impl Partial {
+    /// Partial file for `dst`: `.NAME.parcopy-partial` in the same directory.
+    pub(crate) fn for_dst(dst: &Path) -> Self {
+        let parent = dst.parent().unwrap_or(Path::new("."));
+        let mut name = OsString::from(".");
+        name.push(dst.file_name().unwrap_or(dst.as_os_str()));
+        name.push(PARTIAL_SUFFIX);
+        let path = safe_path(&parent.join(&name));
+        name.push(STATE_SUFFIX);
+        let state_path = safe_path(&parent.join(name));
+        Self { path, state_path }
+    }
+
+    /// Open the partial file and return the offset to continue from.
+    ///
+    /// The offset is that of the last checkpoint if the sidecar matches
+    /// `src_meta` and the partial file holds that much data, and 0 otherwise,
+    /// in which case the partial file is emptied.
+    pub(crate) fn open(&self, src_meta: &Metadata) -> io::Result<(File, u64)> {
+        let offset = self.checkpointed_offset(src_meta).unwrap_or(0);
+        if offset == 0 {
+            remove_if_exists(&self.state_path)?;
+        }
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.path)?;
+        // Drop anything written after the checkpoint; it may be torn
+        file.set_len(offset)?;
+        Ok((file, offset))
+    }
+
+    /// Offset of the last checkpoint, if it is still valid for `src_meta`.
+    fn checkpointed_offset(&self, src_meta: &Metadata) -> Option<u64> {
+        let text = fs::read_to_string(&self.state_path).ok()?;
+        let mut lines = text.lines();
+        if lines.next()? != STATE_HEADER {
+            return None;
+        }
+        let mut fields = lines.next()?.split(' ');
+        let recorded = SourceIdentity {
+            size: fields.next()?.parse().ok()?,
+            mtime: fields.next()?.parse().ok()?,
+            inode: fields.next()?.parse().ok()?,
+        };
+        let offset: u64 = fields.next()?.parse().ok()?;
+        if fields.next().is_some() || Some(recorded) != SourceIdentity::of(src_meta) {
+            return None;
+        }
+        let held = fs::metadata(&self.path).ok()?.len();
+        (offset <= held && offset <= recorded.size).then_some(offset)
+    }
+
+    /// Sync the partial file and record that it holds the first `offset` bytes.
+    ///
+    /// The sidecar is replaced atomically, so a crash leaves either the old
+    /// or the new checkpoint.
+    pub(crate) fn checkpoint(
+        &self,
+        file: &File,
+        src_meta: &Metadata,
+        offset: u64,
+    ) -> io::Result<()> {
+        let Some(identity) = SourceIdentity::of(src_meta) else {
+            return Ok(());
+        };
+        file.sync_data()?;
+
+        let parent = self.state_path.parent().unwrap_or(Path::new("."));
+        let mut state = temp_builder().tempfile_in(parent)?;
+        writeln!(state, "{STATE_HEADER}")?;
+        writeln!(
+            state,
+            "{} {} {} {offset}",
+            identity.size, identity.mtime, identity.inode
+        )?;
+        state.as_file().sync_data()?;
+        state.persist(&self.state_path).map_err(|e| e.error)?;
+        Ok(())
+    }
+
+    /// Forget the checkpoint and empty the partial file, to copy from the start.
+    pub(crate) fn restart(&self, file: &File) -> io::Result<()> {
+        remove_if_exists(&self.state_path)?;
+        file.set_len(0)
+    }
+
+    /// Finish the copy: drop the sidecar and hand over the partial file to
+    /// be persisted (or removed if it is dropped).
+    pub(crate) fn finish(self) -> io::Result<TempPath> {
+        remove_if_exists(&self.state_path)?;
+        Ok(TempPath::from_path(self.path))
+    }
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_partial_resumes_from_checkpoint() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src.bin");
+        fs::write(&src, vec![7u8; 100]).unwrap();
+        let src_meta = fs::metadata(&src).unwrap();
+        let partial = Partial::for_dst(&dir.path().join("dst.bin"));
+        assert!(partial.path.ends_with(".dst.bin.parcopy-partial"));
+
+        let (mut file, offset) = partial.open(&src_meta).unwrap();
+        assert_eq!(offset, 0);
+        file.write_all(&[7u8; 60]).unwrap();
+        partial.checkpoint(&file, &src_meta, 40).unwrap();
+        drop(file);
+
+        // Data past the checkpoint is dropped
+        let (file, offset) = partial.open(&src_meta).unwrap();
+        assert_eq!(offset, 40);
+        assert_eq!(file.metadata().unwrap().len(), 40);
+        drop(file);
+
+        // A changed source starts over
+        fs::write(&src, vec![8u8; 90]).unwrap();
+        let (file, offset) = partial.open(&fs::metadata(&src).unwrap()).unwrap();
+        assert_eq!(offset, 0);
+        assert_eq!(file.metadata().unwrap().len(), 0);
+        assert!(!partial.state_path.exists());
+
+        let temp = partial.finish().unwrap();
+        let path = temp.to_path_buf();
+        drop(temp);
+        assert!(!path.exists());
+    }
+}
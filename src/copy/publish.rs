@@ -0,0 +1,319 @@
+//! Atomic publishing of directory copies.
+//!
+//! With [`CopyOptions::atomic_publish`], the tree is built in a hidden staging
+//! directory next to the destination. Once every entry has been copied and
+//! synced, the staged tree is swapped into place in a single step and the old
+//! tree is removed.
+
+use crate::error::{Error, Result};
+use crate::options::CopyOptions;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Instant;
+
+use super::compare::EntryKind;
+use super::context::CopyContext;
+use super::dir::{CopyStats, copy_tree};
+use super::mv::sync_parent;
+use super::remaining::RemainingWork;
+use super::utils::symlink;
+
+/// Prefix of staging directories created next to the destination
+pub(crate) const STAGING_PREFIX: &str = ".parcopy-staging-";
+
+/// Copy `src` into a staging tree and publish it at `dst` atomically.
+///
+/// Files already in `dst` are hard-linked into the staging tree first, so the
+/// conflict policy sees them as in a regular copy. If anything fails, the
+/// staging tree is removed and `dst` is left untouched.
+pub(crate) fn copy_dir_atomic(src: &Path, dst: &Path, options: &CopyOptions) -> Result<CopyStats> {
+    let start = Instant::now();
+
+    if !src.exists() {
+        return Err(Error::SourceNotFound(src.to_path_buf()));
+    }
+    if !src.is_dir() {
+        return Err(Error::NotADirectory(src.to_path_buf()));
+    }
+
+    let replace = match fs::symlink_metadata(dst) {
+        Ok(meta) if meta.is_dir() => true,
+        // Swapping would replace a file or symlink with the tree
+        Ok(_) => return Err(Error::NotADirectory(dst.to_path_buf())),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => false,
+        Err(e) => return Err(e.into()),
+    };
+
+    let publish_error = |source| Error::Publish {
+        path: dst.to_path_buf(),
+        source,
+    };
+
+    let parent = match dst.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(parent)?;
+    let staging = tempfile::Builder::new()
+        .prefix(STAGING_PREFIX)
+        .tempdir_in(parent)
+        .map_err(publish_error)?;
+    let tree = staging.path().join("tree");
+
+    if replace {
+        seed_tree(dst, &tree).map_err(publish_error)?;
+    }
+
+    // The staging tree is dropped on failure, so the whole tree remains
+    let remaining = || RemainingWork::single(src, dst, EntryKind::Dir);
+    let mut stats = copy_tree(
+        src,
+        &tree,
+        options,
+        &CopyContext::new(&tree).published_at(dst),
+    )
+    .map_err(|e| e.with_remaining_work(remaining()))?;
+
+    if options.is_cancelled() {
+        return Err(Error::Cancelled {
+            files_copied: stats.files_copied,
+            bytes_copied: stats.bytes_copied,
+            files_skipped: stats.files_skipped,
+            dirs_created: stats.dirs_created,
+            remaining_work: Box::new(remaining()),
+        });
+    }
+
+    if options.fsync {
+        sync_dirs(&tree).map_err(publish_error)?;
+    }
+
+    publish(&tree, dst, staging.path(), replace).map_err(publish_error)?;
+
+    if options.fsync {
+        sync_parent(dst).map_err(publish_error)?;
+    }
+
+    options.verbose(&format!("Published {}", dst.display()));
+
+    // The staging directory now holds the old tree, if there was one
+    let staging_path = staging.path().to_path_buf();
+    if let Err(e) = staging.close() {
+        options.warn(&format!(
+            "Failed to remove old tree {}: {e}",
+            staging_path.display()
+        ));
+    }
+
+    stats.duration = start.elapsed();
+    Ok(stats)
+}
+
+/// Recreate the tree at `existing` under `staged`, hard-linking its files.
+fn seed_tree(existing: &Path, staged: &Path) -> io::Result<()> {
+    fs::create_dir(staged)?;
+    fs::set_permissions(staged, fs::metadata(existing)?.permissions())?;
+
+    for entry in fs::read_dir(existing)? {
+        let entry = entry?;
+        let from = entry.path();
+        let to = staged.join(entry.file_name());
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            seed_tree(&from, &to)?;
+        } else if file_type.is_symlink() {
+            symlink(&fs::read_link(&from)?, &to)?;
+        } else {
+            fs::hard_link(&from, &to)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Flush every directory of the staged tree, making its entries durable.
+#[cfg(unix)]
+fn sync_dirs(dir: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            sync_dirs(&entry.path())?;
+        }
+    }
+    fs::File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dirs(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Move the staged tree to `dst`.
+///
+/// An existing `dst` is swapped with the staged tree in one step where the
+/// platform supports it. Otherwise it is first renamed into `staging`, which
+/// leaves a short window in which `dst` does not exist.
+fn publish(staged: &Path, dst: &Path, staging: &Path, replace: bool) -> io::Result<()> {
+    if !replace {
+        return fs::rename(staged, dst);
+    }
+    if exchange(staged, dst)? {
+        return Ok(());
+    }
+
+    let old = staging.join("old");
+    fs::rename(dst, &old)?;
+    if let Err(e) = fs::rename(staged, dst) {
+        // Put the old tree back rather than leave the destination missing
+        let _ = fs::rename(&old, dst);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Atomically swap `a` and `b` with `renameat2(RENAME_EXCHANGE)`.
+///
+/// Returns `false` if the kernel or filesystem does not support it.
+#[cfg(target_os = "linux")]
+fn exchange(a: &Path, b: &Path) -> io::Result<bool> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let a = CString::new(a.as_os_str().as_bytes())?;
+    let b = CString::new(b.as_os_str().as_bytes())?;
+
+    // SAFETY: Both paths are valid NUL-terminated strings that outlive the call
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_renameat2,
+            libc::AT_FDCWD,
+            a.as_ptr(),
+            libc::AT_FDCWD,
+            b.as_ptr(),
+            libc::RENAME_EXCHANGE,
+        )
+    };
+
+    if result == 0 {
+        return Ok(true);
+    }
+    let err = io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::ENOSYS | libc::EINVAL | libc::EOPNOTSUPP) => Ok(false),
+        _ => Err(err),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn exchange(_a: &Path, _b: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OnConflict;
+    use crate::copy::{Manifest, ManifestFormat};
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    fn staging_dirs(parent: &Path) -> Vec<String> {
+        fs::read_dir(parent)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with(STAGING_PREFIX))
+            .collect()
+    }
+
+    #[test]
+    fn test_atomic_publish_new_destination() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("a.txt"), "a").unwrap();
+        fs::write(src.join("sub/b.txt"), "b").unwrap();
+
+        let stats = copy_dir_atomic(&src, &dst, &CopyOptions::default()).unwrap();
+
+        assert_eq!(stats.files_copied, 2);
+        assert_eq!(fs::read_to_string(dst.join("sub/b.txt")).unwrap(), "b");
+        assert!(staging_dirs(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn test_atomic_publish_replaces_existing_tree() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(&src).unwrap();
+        fs::create_dir_all(&dst).unwrap();
+        fs::write(src.join("a.txt"), "new").unwrap();
+        fs::write(dst.join("a.txt"), "old").unwrap();
+        fs::write(dst.join("keep.txt"), "keep").unwrap();
+
+        let options = CopyOptions::default().with_on_conflict(OnConflict::Overwrite);
+        copy_dir_atomic(&src, &dst, &options).unwrap();
+
+        assert_eq!(fs::read_to_string(dst.join("a.txt")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(dst.join("keep.txt")).unwrap(), "keep");
+        assert!(staging_dirs(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn test_atomic_publish_failure_leaves_destination() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(&src).unwrap();
+        fs::create_dir_all(&dst).unwrap();
+        fs::write(src.join("a.txt"), "new").unwrap();
+        fs::write(src.join("b.txt"), "new").unwrap();
+        fs::write(dst.join("a.txt"), "old").unwrap();
+
+        let options = CopyOptions::default().with_on_conflict(OnConflict::Error);
+        assert!(copy_dir_atomic(&src, &dst, &options).is_err());
+
+        assert_eq!(fs::read_to_string(dst.join("a.txt")).unwrap(), "old");
+        assert!(!dst.join("b.txt").exists());
+        assert!(staging_dirs(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn test_atomic_publish_manifest_lists_published_paths() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("a.txt"), "abc").unwrap();
+
+        let manifest = Arc::new(Manifest::new(dst.join("SHA256SUMS"), ManifestFormat::Jsonl));
+        let options = CopyOptions::default().with_manifest(manifest.clone());
+        copy_dir_atomic(&src, &dst, &options).unwrap();
+
+        let entries = manifest.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path, Path::new("a.txt"));
+        assert_eq!(entries[0].size, 3);
+
+        manifest.finish().unwrap();
+        let text = fs::read_to_string(dst.join("SHA256SUMS")).unwrap();
+        assert!(text.starts_with(r#"{"path":"a.txt","size":3,"#));
+    }
+
+    #[test]
+    fn test_atomic_publish_rejects_file_destination() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(&dst, "file").unwrap();
+
+        let result = copy_dir_atomic(&src, &dst, &CopyOptions::default());
+
+        assert!(matches!(result, Err(Error::NotADirectory(_))));
+        assert_eq!(fs::read_to_string(&dst).unwrap(), "file");
+    }
+}
@@ -0,0 +1,179 @@
+//! Matching source files against reference trees.
+//!
+//! Reference trees are earlier copies of the same source (for example the
+//! previous day's snapshot). A source file "matches" a reference when the file
+//! at the same relative path in the reference tree is unchanged.
+
+use crate::options::CopyOptions;
+use filetime::FileTime;
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Buffer size for content comparison
+const COMPARE_BUF_SIZE: usize = 64 * 1024;
+
+/// Find the first reference root holding an unchanged copy of `src` at `rel`.
+///
+/// A candidate matches when it is a regular file of the same size and either
+/// has the same mtime or, with `rule.checksum`, the same contents.
+pub(crate) fn find_match(
+    roots: &[PathBuf],
+    rel: &Path,
+    src: &Path,
+    src_meta: &Metadata,
+    rule: MatchRule,
+) -> Option<PathBuf> {
+    roots.iter().map(|root| root.join(rel)).find(|candidate| {
+        fs::symlink_metadata(candidate)
+            .is_ok_and(|meta| is_unchanged(src, src_meta, candidate, &meta, rule))
+    })
+}
+
+/// What has to agree for a reference file to count as unchanged.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct MatchRule {
+    /// Compare contents instead of mtime
+    pub checksum: bool,
+    /// Require equal permissions (a hard link shares them with the reference)
+    pub permissions: bool,
+}
+
+impl MatchRule {
+    pub(crate) fn from_options(options: &CopyOptions) -> Self {
+        Self {
+            checksum: options.checksum,
+            permissions: options.preserve_permissions,
+        }
+    }
+}
+
+/// Whether `candidate` is an unchanged copy of `src` under `rule`.
+pub(crate) fn is_unchanged(
+    src: &Path,
+    src_meta: &Metadata,
+    candidate: &Path,
+    candidate_meta: &Metadata,
+    rule: MatchRule,
+) -> bool {
+    if !candidate_meta.is_file() || candidate_meta.len() != src_meta.len() {
+        return false;
+    }
+    if rule.permissions && candidate_meta.permissions() != src_meta.permissions() {
+        return false;
+    }
+    if rule.checksum {
+        same_contents(src, candidate).unwrap_or(false)
+    } else {
+        FileTime::from_last_modification_time(candidate_meta)
+            == FileTime::from_last_modification_time(src_meta)
+    }
+}
+
+/// Compare two files byte by byte.
+pub(crate) fn same_contents(a: &Path, b: &Path) -> io::Result<bool> {
+    let mut a = File::open(a)?;
+    let mut b = File::open(b)?;
+    let mut buf_a = vec![0u8; COMPARE_BUF_SIZE];
+    let mut buf_b = vec![0u8; COMPARE_BUF_SIZE];
+
+    loop {
+        let n = read_full(&mut a, &mut buf_a)?;
+        let m = read_full(&mut b, &mut buf_b)?;
+        if n != m || buf_a[..n] != buf_b[..m] {
+            return Ok(false);
+        }
+        if n == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// Fill `buf` as far as possible, returning fewer bytes only at EOF.
+fn read_full(file: &mut File, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match file.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_find_match_by_size_and_mtime() {
+        let src_dir = tempdir().unwrap();
+        let reference = tempdir().unwrap();
+        let src = src_dir.path().join("a.txt");
+        let candidate = reference.path().join("a.txt");
+        fs::write(&src, "same").unwrap();
+        fs::write(&candidate, "same").unwrap();
+
+        let mtime = FileTime::from_unix_time(1_700_000_000, 0);
+        filetime::set_file_mtime(&src, mtime).unwrap();
+        filetime::set_file_mtime(&candidate, mtime).unwrap();
+
+        let roots = vec![reference.path().to_path_buf()];
+        let meta = fs::metadata(&src).unwrap();
+        let rule = MatchRule::from_options(&CopyOptions::default());
+        assert_eq!(
+            find_match(&roots, Path::new("a.txt"), &src, &meta, rule),
+            Some(candidate.clone())
+        );
+
+        // A different mtime is not a match unless contents are compared
+        filetime::set_file_mtime(&candidate, FileTime::from_unix_time(1, 0)).unwrap();
+        assert_eq!(
+            find_match(&roots, Path::new("a.txt"), &src, &meta, rule),
+            None
+        );
+        let rule = MatchRule::from_options(&CopyOptions::default().with_checksum());
+        assert_eq!(
+            find_match(&roots, Path::new("a.txt"), &src, &meta, rule),
+            Some(candidate)
+        );
+    }
+
+    #[test]
+    fn test_find_match_uses_first_matching_root() {
+        let src_dir = tempdir().unwrap();
+        let older = tempdir().unwrap();
+        let newer = tempdir().unwrap();
+        let src = src_dir.path().join("a.txt");
+        fs::write(&src, "current").unwrap();
+        fs::write(newer.path().join("a.txt"), "changed").unwrap();
+        fs::write(older.path().join("a.txt"), "current").unwrap();
+
+        let roots = vec![newer.path().to_path_buf(), older.path().to_path_buf()];
+        let meta = fs::metadata(&src).unwrap();
+        let rule = MatchRule::from_options(&CopyOptions::default().with_checksum());
+        assert_eq!(
+            find_match(&roots, Path::new("a.txt"), &src, &meta, rule),
+            Some(older.path().join("a.txt"))
+        );
+    }
+
+    #[test]
+    fn test_same_contents() {
+        let dir = tempdir().unwrap();
+        let a = dir.path().join("a");
+        let b = dir.path().join("b");
+        let c = dir.path().join("c");
+        fs::write(&a, vec![7u8; COMPARE_BUF_SIZE + 10]).unwrap();
+        fs::write(&b, vec![7u8; COMPARE_BUF_SIZE + 10]).unwrap();
+        let mut different = vec![7u8; COMPARE_BUF_SIZE + 10];
+        different[COMPARE_BUF_SIZE + 5] = 0;
+        fs::write(&c, different).unwrap();
+
+        assert!(same_contents(&a, &b).unwrap());
+        assert!(!same_contents(&a, &c).unwrap());
+    }
+}
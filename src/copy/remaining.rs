@@ -0,0 +1,245 @@
+//! Work left undone by a cancelled or failed run.
+//!
+//! [`Error::Cancelled`](crate::Error::Cancelled) and
+//! [`Error::NoSpace`](crate::Error::NoSpace) carry a [`RemainingWork`] listing
+//! the entries the run did not finish, so callers can retry exactly those.
+
+use crate::error::Result;
+use crate::plan_execute::{CopyPlan, CopyPolicy, PlannedItemKind, RuntimeOptions, plan_item};
+use std::path::{Path, PathBuf};
+
+use super::compare::EntryKind;
+
+/// One entry of a [`RemainingWork`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemainingEntry {
+    /// Source path
+    pub source: PathBuf,
+    /// Destination the source was to be copied to
+    pub destination: PathBuf,
+    /// Kind of the entry: a file, a symlink, or a directory whose whole
+    /// tree remains
+    pub kind: EntryKind,
+}
+
+/// Entries a cancelled or failed run did not finish.
+///
+/// Entries are listed with their final destination, also for atomically
+/// published runs. When a directory copy stops before its files are reached,
+/// or its partial result is discarded (atomic publish, transactional
+/// copies), the whole tree is listed as a single [`EntryKind::Dir`] entry.
+///
+/// Use [`to_plan`](Self::to_plan) to copy exactly the remaining entries, or
+/// [`entries`](Self::entries) to hand them to something else. For a
+/// cancelled move, the listed sources have not been removed yet.
+///
+/// # Example
+///
+/// ```no_run
+/// use parcopy::{CopyOptions, CopyPolicy, RuntimeOptions, copy_dir, execute_plan};
+/// use std::path::Path;
+///
+/// let options = CopyOptions::default();
+/// if let Err(error) = copy_dir(Path::new("data"), Path::new("/mnt/nfs/data"), &options) {
+///     if let Some(remaining) = error.remaining_work() {
+///         let plan = remaining.to_plan(CopyPolicy::from(&options), RuntimeOptions::from(&options))?;
+///         execute_plan(&plan, None);
+///     }
+/// }
+/// # Ok::<(), parcopy::Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RemainingWork {
+    /// Destination root of the run
+    pub destination_root: PathBuf,
+    /// Entries the run did not get to, or was copying when it stopped
+    pub pending: Vec<RemainingEntry>,
+    /// Entries whose copy failed
+    pub failed: Vec<RemainingEntry>,
+}
+
+impl RemainingWork {
+    /// Empty remaining work for a run into `destination_root`.
+    pub(crate) fn new(destination_root: &Path) -> Self {
+        Self {
+            destination_root: destination_root.to_path_buf(),
+            ..Self::default()
+        }
+    }
+
+    /// A single pending entry, for a run into `destination`.
+    pub(crate) fn single(source: &Path, destination: &Path, kind: EntryKind) -> Self {
+        let mut remaining = Self::new(destination);
+        remaining.push_pending(source, destination, kind);
+        remaining
+    }
+
+    pub(crate) fn push_pending(&mut self, source: &Path, destination: &Path, kind: EntryKind) {
+        self.pending.push(RemainingEntry {
+            source: source.to_path_buf(),
+            destination: destination.to_path_buf(),
+            kind,
+        });
+    }
+
+    pub(crate) fn push_failed(&mut self, source: &Path, destination: &Path, kind: EntryKind) {
+        self.failed.push(RemainingEntry {
+            source: source.to_path_buf(),
+            destination: destination.to_path_buf(),
+            kind,
+        });
+    }
+
+    /// Whether nothing remains.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty() && self.failed.is_empty()
+    }
+
+    /// Number of remaining entries, pending and failed.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.pending.len() + self.failed.len()
+    }
+
+    /// All remaining entries, pending ones first.
+    pub fn entries(&self) -> impl Iterator<Item = &RemainingEntry> {
+        self.pending.iter().chain(&self.failed)
+    }
+
+    /// Plan copying every remaining entry to its destination.
+    ///
+    /// Each entry is classified against the filesystem as it is now, as
+    /// [`plan_copy`](crate::plan_copy) would, so entries completed since
+    /// are planned according to `policy` (skipped, by default).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::SourceNotFound`](crate::Error::SourceNotFound) if the
+    /// source of an entry no longer exists, or [`Error::Io`](crate::Error::Io)
+    /// if it cannot be read.
+    pub fn to_plan(&self, policy: CopyPolicy, runtime: RuntimeOptions) -> Result<CopyPlan> {
+        let items = self
+            .entries()
+            .map(|entry| {
+                let kind = match entry.kind {
+                    EntryKind::Dir => PlannedItemKind::Directory,
+                    EntryKind::Symlink => PlannedItemKind::Symlink,
+                    EntryKind::File | EntryKind::Other => PlannedItemKind::File,
+                };
+                plan_item(
+                    &entry.source,
+                    &entry.destination,
+                    &self.destination_root,
+                    kind,
+                    &policy,
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(CopyPlan {
+            destination_root: self.destination_root.clone(),
+            policy,
+            runtime,
+            items,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::plan_execute::{ItemOutcome, PlanAction, PlanReason, execute_plan};
+    use crate::{CopyOptions, copy_dir};
+    use std::fs;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, LazyLock};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_remaining_work_to_plan() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(&src).unwrap();
+        fs::create_dir_all(&dst).unwrap();
+        fs::write(src.join("a.txt"), "a").unwrap();
+        fs::write(src.join("b.txt"), "b").unwrap();
+        fs::write(dst.join("b.txt"), "b").unwrap();
+
+        let mut remaining = RemainingWork::new(&dst);
+        remaining.push_pending(&src.join("a.txt"), &dst.join("a.txt"), EntryKind::File);
+        remaining.push_failed(&src.join("b.txt"), &dst.join("b.txt"), EntryKind::File);
+        assert_eq!(remaining.len(), 2);
+
+        let plan = remaining
+            .to_plan(CopyPolicy::default(), RuntimeOptions::default())
+            .unwrap();
+        assert_eq!(plan.destination_root, dst);
+        let actions: Vec<_> = plan
+            .items
+            .iter()
+            .map(|item| (item.action, item.reason))
+            .collect();
+        assert_eq!(
+            actions,
+            [
+                (PlanAction::Copy, PlanReason::NotExists),
+                (PlanAction::Skip, PlanReason::Exists)
+            ]
+        );
+
+        fs::remove_file(src.join("a.txt")).unwrap();
+        let err = remaining
+            .to_plan(CopyPolicy::default(), RuntimeOptions::default())
+            .unwrap_err();
+        assert!(matches!(err, Error::SourceNotFound(_)), "{err}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cancelled_copy_resumes_from_remaining_work() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("file.txt"), "data").unwrap();
+        // Both links escape upward, so the first one warns and cancels the run
+        std::os::unix::fs::symlink("../one", src.join("link1")).unwrap();
+        std::os::unix::fs::symlink("../two", src.join("link2")).unwrap();
+
+        static CANCEL: LazyLock<Arc<AtomicBool>> = LazyLock::new(Arc::default);
+        let options = CopyOptions::default()
+            .with_parallel(1)
+            .with_cancel_token(CANCEL.clone())
+            .with_warn_handler(|_| CANCEL.store(true, Ordering::Relaxed));
+
+        let err = copy_dir(&src, &dst, &options).unwrap_err();
+        let remaining = err.remaining_work().unwrap();
+        assert!(
+            matches!(
+                err,
+                Error::Cancelled {
+                    files_copied: 1,
+                    ..
+                }
+            ),
+            "{err}"
+        );
+        assert_eq!(remaining.destination_root, dst);
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining.pending[0].kind, EntryKind::Symlink);
+        let pending = remaining.pending[0].destination.clone();
+        assert!(fs::symlink_metadata(&pending).is_err());
+
+        CANCEL.store(false, Ordering::Relaxed);
+        let plan = remaining
+            .to_plan(CopyPolicy::from(&options), RuntimeOptions::default())
+            .unwrap();
+        assert_eq!(plan.items[0].kind, PlannedItemKind::Symlink);
+        let report = execute_plan(&plan, None);
+        assert_eq!(report.items[0].outcome, ItemOutcome::Copied);
+        assert!(fs::symlink_metadata(&pending).unwrap().is_symlink());
+    }
+}
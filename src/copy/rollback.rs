@@ -0,0 +1,316 @@
+//! Rollback of transactional directory copies.
+//!
+//! With [`CopyOptions::transactional`](crate::CopyOptions::transactional), a
+//! run records every entry it creates and sets aside every entry it replaces.
+//! If the run fails, the created entries are removed and the replaced ones are
+//! put back; if it succeeds, the set-aside originals are deleted.
+
+use crate::error::{Error, Result};
+use crate::options::CopyOptions;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use super::compare::EntryKind;
+use super::context::CopyContext;
+use super::dir::{CopyStats, copy_tree};
+use super::remaining::RemainingWork;
+
+/// Prefix of the hidden names replaced entries are kept under during a run
+pub(crate) const ROLLBACK_PREFIX: &str = ".parcopy-rollback-";
+
+/// What a failed transactional run undid.
+///
+/// Carried by [`Error::RolledBack`].
+#[derive(Debug, Default)]
+pub struct RollbackReport {
+    /// Files, symlinks, and directories created by the run that were removed
+    pub removed: Vec<PathBuf>,
+    /// Replaced entries whose previous version was put back
+    pub restored: Vec<PathBuf>,
+    /// Entries that could not be rolled back
+    pub failed: Vec<(PathBuf, io::Error)>,
+}
+
+/// A change made to the destination by the current run.
+#[derive(Debug)]
+enum Change {
+    /// A file or symlink that did not exist before
+    Created(PathBuf),
+    /// A directory that did not exist before
+    CreatedDir(PathBuf),
+    /// An existing entry, kept at `saved` until the run ends
+    Replaced { path: PathBuf, saved: PathBuf },
+}
+
+/// Journal of the changes made by a transactional run.
+#[derive(Debug, Default)]
+pub(crate) struct Rollback {
+    changes: Mutex<Vec<Change>>,
+}
+
+impl Rollback {
+    fn push(&self, change: Change) {
+        self.changes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(change);
+    }
+
+    /// Record a file or symlink created by the run.
+    pub(crate) fn created(&self, path: &Path) {
+        self.push(Change::Created(path.to_path_buf()));
+    }
+
+    /// Record a directory created by the run.
+    pub(crate) fn created_dir(&self, path: &Path) {
+        self.push(Change::CreatedDir(path.to_path_buf()));
+    }
+
+    /// Create `path` and its missing parents, recording each one.
+    pub(crate) fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        let missing: Vec<&Path> = path
+            .ancestors()
+            .take_while(|dir| !dir.as_os_str().is_empty() && fs::symlink_metadata(dir).is_err())
+            .collect();
+
+        for dir in missing.into_iter().rev() {
+            match fs::create_dir(dir) {
+                Ok(()) => self.created_dir(dir),
+                // Created concurrently by someone else, so not ours to remove
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Keep a hard link to the file at `path` before it is replaced.
+    pub(crate) fn keep_original(&self, path: &Path) -> io::Result<()> {
+        let saved = hidden_sibling(path, |saved| fs::hard_link(path, saved))?;
+        self.push(Change::Replaced {
+            path: path.to_path_buf(),
+            saved,
+        });
+        Ok(())
+    }
+
+    /// Move the entry at `path` aside instead of removing it.
+    pub(crate) fn set_aside(&self, path: &Path) -> io::Result<()> {
+        let saved = hidden_sibling(path, |saved| fs::rename(path, saved))?;
+        self.push(Change::Replaced {
+            path: path.to_path_buf(),
+            saved,
+        });
+        Ok(())
+    }
+
+    /// Delete the originals kept for a run that succeeded.
+    pub(crate) fn commit(self) {
+        let changes = self
+            .changes
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        for change in changes {
+            if let Change::Replaced { saved, .. } = change {
+                let _ = remove_entry(&saved);
+            }
+        }
+    }
+
+    /// Undo every change of a failed run, newest first.
+    pub(crate) fn roll_back(self) -> RollbackReport {
+        let changes = self
+            .changes
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut report = RollbackReport::default();
+
+        // Directories are created before anything inside them, so undoing in
+        // reverse order empties each directory before removing it
+        for change in changes.into_iter().rev() {
+            let (path, result, restored) = match change {
+                Change::Created(path) => {
+                    // Already gone, e.g. removed after failing verification
+                    let result = match fs::remove_file(&path) {
+                        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+                        result => result,
+                    };
+                    (path, result, false)
+                }
+                Change::CreatedDir(path) => {
+                    let result = fs::remove_dir(&path);
+                    (path, result, false)
+                }
+                Change::Replaced { path, saved } => {
+                    let result = restore(&saved, &path);
+                    (path, result, true)
+                }
+            };
+            match result {
+                Ok(()) if restored => report.restored.push(path),
+                Ok(()) => report.removed.push(path),
+                Err(e) => report.failed.push((path, e)),
+            }
+        }
+
+        report
+    }
+}
+
+/// Copy `src` to `dst`, undoing every change to `dst` if the copy fails.
+///
+/// The failure is returned as [`Error::RolledBack`], wrapping the original
+/// error together with the [`RollbackReport`].
+pub(crate) fn copy_dir_transactional(
+    src: &Path,
+    dst: &Path,
+    options: &CopyOptions,
+) -> Result<CopyStats> {
+    let ctx = CopyContext::new(dst).transactional();
+    let result = copy_tree(src, dst, options, &ctx);
+    let Some(rollback) = ctx.into_rollback() else {
+        return result;
+    };
+
+    match result {
+        Ok(stats) => {
+            rollback.commit();
+            Ok(stats)
+        }
+        Err(error) => {
+            let report = rollback.roll_back();
+            for path in &report.removed {
+                options.verbose(&format!("rolled back {} (removed)", path.display()));
+            }
+            for path in &report.restored {
+                options.verbose(&format!("rolled back {} (restored)", path.display()));
+            }
+            for (path, e) in &report.failed {
+                options.warn(&format!("Failed to roll back {}: {e}", path.display()));
+            }
+            // Everything was undone, so the whole tree remains
+            let error = error.with_remaining_work(RemainingWork::single(src, dst, EntryKind::Dir));
+            Err(Error::RolledBack {
+                error: Box::new(error),
+                report,
+            })
+        }
+    }
+}
+
+/// Run `place` on a free hidden path next to `path` and return that path.
+fn hidden_sibling(path: &Path, place: impl Fn(&Path) -> io::Result<()>) -> io::Result<PathBuf> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    tempfile::Builder::new()
+        .prefix(ROLLBACK_PREFIX)
+        .make_in(dir, |saved| place(saved))?
+        .into_temp_path()
+        .keep()
+        .map_err(|e| e.error)
+}
+
+/// Put the entry kept at `saved` back at `path`.
+fn restore(saved: &Path, path: &Path) -> io::Result<()> {
+    // A directory cannot be renamed over the entry that replaced it
+    if fs::symlink_metadata(saved)?.is_dir() {
+        match remove_entry(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    fs::rename(saved, path)?;
+
+    // Renaming onto another link to the same file leaves both names in place
+    if fs::symlink_metadata(saved).is_ok() {
+        fs::remove_file(saved)?;
+    }
+    Ok(())
+}
+
+/// Remove a file, symlink, or directory tree.
+fn remove_entry(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_roll_back_removes_created_entries() {
+        let dir = tempdir().unwrap();
+        let sub = dir.path().join("sub");
+        let file = sub.join("a.txt");
+
+        let rollback = Rollback::default();
+        fs::create_dir(&sub).unwrap();
+        rollback.created_dir(&sub);
+        fs::write(&file, "a").unwrap();
+        rollback.created(&file);
+
+        let report = rollback.roll_back();
+
+        assert_eq!(report.removed, vec![file, sub.clone()]);
+        assert!(report.failed.is_empty());
+        assert!(!sub.exists());
+    }
+
+    #[test]
+    fn test_roll_back_restores_replaced_file() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("a.txt");
+        fs::write(&file, "old").unwrap();
+
+        let rollback = Rollback::default();
+        rollback.keep_original(&file).unwrap();
+        fs::remove_file(&file).unwrap();
+        fs::write(&file, "new").unwrap();
+
+        let report = rollback.roll_back();
+
+        assert_eq!(report.restored, vec![file.clone()]);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "old");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_roll_back_restores_directory_set_aside() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("entry");
+        fs::create_dir(&path).unwrap();
+        fs::write(path.join("inner.txt"), "inner").unwrap();
+
+        let rollback = Rollback::default();
+        rollback.set_aside(&path).unwrap();
+        fs::write(&path, "replacement").unwrap();
+
+        let report = rollback.roll_back();
+
+        assert_eq!(report.restored, vec![path.clone()]);
+        assert_eq!(fs::read_to_string(path.join("inner.txt")).unwrap(), "inner");
+    }
+
+    #[test]
+    fn test_commit_deletes_kept_originals() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("a.txt");
+        fs::write(&file, "old").unwrap();
+
+        let rollback = Rollback::default();
+        rollback.keep_original(&file).unwrap();
+        rollback.commit();
+
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "old");
+    }
+}
@@ -0,0 +1,286 @@
+//! Checking a tree against a checksum manifest.
+//!
+//! [`verify_manifest`] re-hashes every file listed in a manifest written by
+//! [`Manifest`](crate::Manifest) (or by `sha256sum`) and reports files whose
+//! contents changed since, files that are gone, and files that were never
+//! listed.
+
+use crate::error::{Error, Result};
+use crate::options::CopyOptions;
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::digest::{Digest, hash_from_storage};
+use super::dir::collect_entries;
+use super::manifest::{ListedFile, parse_manifest};
+use super::utils::run_in_pool;
+use super::verify::VerifyMismatch;
+
+/// Result of [`verify_manifest`].
+///
+/// Paths are as listed in the manifest, except for `unexpected` files, which
+/// are relative to the checked root. Nothing is modified or removed.
+#[derive(Debug, Default)]
+pub struct ManifestCheck {
+    /// Listed files whose contents no longer match their digest
+    pub corrupted: Vec<VerifyMismatch>,
+    /// Listed files that no longer exist
+    pub missing: Vec<PathBuf>,
+    /// Files under the root that the manifest does not list
+    pub unexpected: Vec<PathBuf>,
+    /// Listed files that could not be read
+    pub unreadable: Vec<(PathBuf, io::Error)>,
+    /// Number of listed files hashed
+    pub files_checked: u64,
+    /// Bytes hashed
+    pub bytes_checked: u64,
+    /// Duration of the check
+    pub duration: Duration,
+}
+
+impl ManifestCheck {
+    /// Whether every listed file was read and matched, and nothing else was found.
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.corrupted.is_empty()
+            && self.missing.is_empty()
+            && self.unexpected.is_empty()
+            && self.unreadable.is_empty()
+    }
+}
+
+/// Outcome of checking one listed file.
+enum Checked {
+    Matched(u64),
+    Corrupted(VerifyMismatch, u64),
+    Missing,
+    Unreadable(io::Error),
+}
+
+/// Check the tree at `root` against the checksum manifest at `manifest`.
+///
+/// SHA256SUMS manifests and JSONL manifests as written by a
+/// [`Manifest`](crate::Manifest) are accepted. Relative paths in the
+/// manifest are resolved against `root`. Listed files are hashed in parallel
+/// on a pool of `options.parallel` threads, reading from storage rather than
+/// the page cache where the platform allows, so that bit rot is detected.
+///
+/// Regular files under `root` that the manifest does not list are reported
+/// as unexpected; the tree is walked with the traversal rules `copy_dir`
+/// applies for the same `options`. The manifest itself is never unexpected.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The manifest cannot be read ([`Error::Io`])
+/// - The manifest cannot be parsed ([`Error::InvalidManifest`])
+/// - `root` does not exist ([`Error::SourceNotFound`])
+/// - `root` is not a directory ([`Error::NotADirectory`])
+/// - Walking `root` fails ([`Error::SymlinkLoop`], [`Error::MaxDepthExceeded`], [`Error::Io`])
+///
+/// Listed files that are missing or cannot be read are reported in the
+/// returned [`ManifestCheck`] instead.
+///
+/// # Example
+///
+/// ```no_run
+/// use parcopy::{CopyOptions, verify_manifest};
+/// use std::path::Path;
+///
+/// let check = verify_manifest(
+///     Path::new("/mnt/archive/SHA256SUMS"),
+///     Path::new("/mnt/archive"),
+///     &CopyOptions::default(),
+/// )?;
+/// for mismatch in &check.corrupted {
+///     println!("corrupted: {}", mismatch.path.display());
+/// }
+/// # Ok::<(), parcopy::Error>(())
+/// ```
+pub fn verify_manifest(
+    manifest: &Path,
+    root: &Path,
+    options: &CopyOptions,
+) -> Result<ManifestCheck> {
+    let start = Instant::now();
+
+    let text = fs::read_to_string(manifest)?;
+    let listed = parse_manifest(&text).map_err(|(line, reason)| Error::InvalidManifest {
+        path: manifest.to_path_buf(),
+        line,
+        reason,
+    })?;
+
+    if !root.exists() {
+        return Err(Error::SourceNotFound(root.to_path_buf()));
+    }
+    if !root.is_dir() {
+        return Err(Error::NotADirectory(root.to_path_buf()));
+    }
+
+    let results = run_in_pool(options, || {
+        listed
+            .par_iter()
+            .map(|file| check_file(&file.path, &root.join(&file.path), file.digest))
+            .collect::<Vec<_>>()
+    });
+
+    let mut check = ManifestCheck::default();
+    for (file, result) in listed.iter().zip(results) {
+        match result {
+            Checked::Matched(bytes) => {
+                check.files_checked += 1;
+                check.bytes_checked += bytes;
+            }
+            Checked::Corrupted(mismatch, bytes) => {
+                check.files_checked += 1;
+                check.bytes_checked += bytes;
+                check.corrupted.push(mismatch);
+            }
+            Checked::Missing => check.missing.push(file.path.clone()),
+            Checked::Unreadable(e) => check.unreadable.push((file.path.clone(), e)),
+        }
+    }
+
+    check.unexpected = unlisted_files(manifest, root, &listed, options)?;
+    check.duration = start.elapsed();
+    Ok(check)
+}
+
+/// Hash one listed file and compare it with its expected digest.
+fn check_file(listed: &Path, path: &Path, expected: Digest) -> Checked {
+    let len = match fs::metadata(path) {
+        Ok(meta) => meta.len(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Checked::Missing,
+        Err(e) => return Checked::Unreadable(e),
+    };
+    match hash_from_storage(path) {
+        Ok(actual) if actual == expected => Checked::Matched(len),
+        Ok(actual) => Checked::Corrupted(
+            VerifyMismatch {
+                path: listed.to_path_buf(),
+                expected,
+                actual,
+            },
+            len,
+        ),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Checked::Missing,
+        Err(e) => Checked::Unreadable(e),
+    }
+}
+
+/// Regular files under `root` not listed in the manifest, relative to `root`.
+fn unlisted_files(
+    manifest: &Path,
+    root: &Path,
+    listed: &[ListedFile],
+    options: &CopyOptions,
+) -> Result<Vec<PathBuf>> {
+    // Compare canonical paths so `./a` and `a` are the same file
+    let root = fs::canonicalize(root)?;
+    let mut known: HashSet<PathBuf> = listed
+        .iter()
+        .filter_map(|file| fs::canonicalize(root.join(&file.path)).ok())
+        .collect();
+    if let Ok(manifest) = fs::canonicalize(manifest) {
+        known.insert(manifest);
+    }
+
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    let mut symlinks = Vec::new();
+    let mut visited = HashSet::new();
+    collect_entries(
+        &root,
+        &root,
+        &mut dirs,
+        &mut files,
+        &mut symlinks,
+        options,
+        0,
+        &mut visited,
+    )?;
+
+    let mut unexpected: Vec<PathBuf> = files
+        .into_iter()
+        .filter(|(path, _)| {
+            !fs::canonicalize(path).is_ok_and(|canonical| known.contains(&canonical))
+        })
+        .filter_map(|(path, _)| path.strip_prefix(&root).ok().map(Path::to_path_buf))
+        .collect();
+    unexpected.sort();
+    Ok(unexpected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Manifest, ManifestFormat, copy_dir};
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    /// Copy a small tree to `dst` and write its manifest inside it.
+    fn copy_with_manifest(base: &Path, format: ManifestFormat) -> (PathBuf, PathBuf) {
+        let src = base.join("src");
+        let dst = base.join("dst");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("a.txt"), "alpha").unwrap();
+        fs::write(src.join("sub/b.txt"), "beta").unwrap();
+
+        let manifest = Arc::new(Manifest::new(dst.join("SHA256SUMS"), format));
+        let options = CopyOptions::default().with_manifest(manifest.clone());
+        copy_dir(&src, &dst, &options).unwrap();
+        manifest.finish().unwrap();
+        (dst.clone(), dst.join("SHA256SUMS"))
+    }
+
+    #[test]
+    fn test_verify_manifest_clean_tree() {
+        for format in [ManifestFormat::Sha256Sums, ManifestFormat::Jsonl] {
+            let dir = tempdir().unwrap();
+            let (root, manifest) = copy_with_manifest(dir.path(), format);
+
+            let check = verify_manifest(&manifest, &root, &CopyOptions::default()).unwrap();
+
+            assert!(check.is_clean(), "{format:?}: {check:?}");
+            assert_eq!(check.files_checked, 2);
+            assert_eq!(check.bytes_checked, 9);
+        }
+    }
+
+    #[test]
+    fn test_verify_manifest_reports_rot_missing_and_unexpected() {
+        let dir = tempdir().unwrap();
+        let (root, manifest) = copy_with_manifest(dir.path(), ManifestFormat::Sha256Sums);
+        fs::write(root.join("a.txt"), "alpha!").unwrap();
+        fs::remove_file(root.join("sub/b.txt")).unwrap();
+        fs::write(root.join("sub/new.txt"), "new").unwrap();
+
+        let check = verify_manifest(&manifest, &root, &CopyOptions::default()).unwrap();
+
+        assert!(!check.is_clean());
+        assert_eq!(check.corrupted.len(), 1);
+        assert_eq!(check.corrupted[0].path, Path::new("a.txt"));
+        assert_eq!(check.missing, vec![PathBuf::from("sub/b.txt")]);
+        assert_eq!(check.unexpected, vec![PathBuf::from("sub/new.txt")]);
+        assert_eq!(check.files_checked, 1);
+    }
+
+    #[test]
+    fn test_verify_manifest_rejects_malformed_line() {
+        let dir = tempdir().unwrap();
+        let manifest = dir.path().join("SHA256SUMS");
+        fs::write(&manifest, "\nnot a digest  a.txt\n").unwrap();
+
+        let err = verify_manifest(&manifest, dir.path(), &CopyOptions::default()).unwrap_err();
+
+        assert!(
+            matches!(err, Error::InvalidManifest { line: 2, .. }),
+            "{err}"
+        );
+    }
+}
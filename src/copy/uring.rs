@@ -0,0 +1,351 @@
+//! io_uring copy engine (Linux, `io_uring` feature).
+//!
+//! Each worker thread keeps its own ring. A file is copied in windows of
+//! chunks: every chunk is a read linked to the write of the same buffer, and
+//! the window reaching the end of the file also carries the fsync, so a small
+//! file is read, written and synced with a single `io_uring_enter`. Small files
+//! of directory copies are batched across files instead, with their opens,
+//! stats and renames (see [`batch`](super::batch)).
+//!
+//! If the kernel does not offer io_uring (or the needed opcodes), or the ring
+//! cannot be created (e.g. blocked by seccomp), [`copy_file_contents`] returns
+//! `None` and the caller uses the standard `copy_file_range` path.
+
+use io_uring::{IoUring, Probe, opcode, squeue, types};
+use std::cell::RefCell;
+use std::fs::File;
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::AsRawFd;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::digest::Hasher;
+
+/// Size of each chunk read and written
+const CHUNK_SIZE: usize = 256 * 1024;
+
+/// Chunks in flight per file
+const WINDOW: usize = 8;
+
+/// Submission queue entries: a read and a write per chunk, plus the fsync
+const QUEUE_DEPTH: u32 = 2 * WINDOW as u32 + 1;
+
+/// `user_data` of the fsync entry
+const FSYNC: u64 = u64::MAX;
+
+struct Engine {
+    ring: IoUring,
+    buffers: Vec<Vec<u8>>,
+}
+
+thread_local! {
+    static ENGINE: RefCell<Option<Engine>> = const { RefCell::new(None) };
+}
+
+/// Whether io_uring can be used on this system.
+///
+/// Checked once per process by setting up a ring and probing for the read,
+/// write and fsync operations.
+pub(crate) fn is_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        let Ok(ring) = IoUring::new(QUEUE_DEPTH) else {
+            return false;
+        };
+        let mut probe = Probe::new();
+        ring.submitter().register_probe(&mut probe).is_ok()
+            && [opcode::Read::CODE, opcode::Write::CODE, opcode::Fsync::CODE]
+                .into_iter()
+                .all(|code| probe.is_supported(code))
+    })
+}
+
+/// Copy the first `len` bytes of `src` into `dst` through io_uring.
+///
+/// Both files are accessed at explicit offsets from the start. If `fsync` is
+/// set, `dst` is synced with the last writes; the returned flag tells whether
+/// that happened, so the caller only syncs when it did not. Returns the bytes
+/// copied, fewer than `len` if `src` ends early.
+///
+/// Returns `None` if io_uring cannot be used, before anything is written.
+pub(crate) fn copy_file_contents(
+    src: &File,
+    dst: &File,
+    len: u64,
+    fsync: bool,
+    cancel_check: Option<&AtomicBool>,
+    hasher: Option<&mut Hasher>,
+) -> Option<io::Result<(u64, bool)>> {
+    if !is_available() {
+        return None;
+    }
+    ENGINE.with(|engine| {
+        let mut engine = engine.try_borrow_mut().ok()?;
+        if engine.is_none() {
+            *engine = Some(Engine {
+                ring: IoUring::new(QUEUE_DEPTH).ok()?,
+                buffers: (0..WINDOW).map(|_| vec![0u8; CHUNK_SIZE]).collect(),
+            });
+        }
+        let result = engine
+            .as_mut()?
+            .copy(src, dst, len, fsync, cancel_check, hasher);
+        if result.is_err() {
+            // Entries of a failed submission may still be in flight, and
+            // their completions would be taken for those of the next file
+            *engine = None;
+        }
+        Some(result)
+    })
+}
+
+impl Engine {
+    fn copy(
+        &mut self,
+        src: &File,
+        dst: &File,
+        len: u64,
+        fsync: bool,
+        cancel_check: Option<&AtomicBool>,
+        mut hasher: Option<&mut Hasher>,
+    ) -> io::Result<(u64, bool)> {
+        let src_fd = types::Fd(src.as_raw_fd());
+        let dst_fd = types::Fd(dst.as_raw_fd());
+        let mut offset = 0u64;
+
+        while offset < len {
+            if cancel_check.is_some_and(|cancel| cancel.load(Ordering::Relaxed)) {
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+            }
+
+            // Queue a read and a linked write for each chunk of the window
+            let mut chunks = Vec::with_capacity(WINDOW);
+            let mut entries = Vec::with_capacity(2 * WINDOW + 1);
+            let mut end = offset;
+            for (i, buf) in self.buffers.iter_mut().enumerate() {
+                if end >= len {
+                    break;
+                }
+                let want = usize::try_from(len - end).map_or(CHUNK_SIZE, |n| n.min(CHUNK_SIZE));
+                let user_data = 2 * i as u64;
+                #[allow(clippy::cast_possible_truncation)] // want <= CHUNK_SIZE
+                entries.push(
+                    opcode::Read::new(src_fd, buf.as_mut_ptr(), want as u32)
+                        .offset(end)
+                        .build()
+                        .flags(squeue::Flags::IO_LINK)
+                        .user_data(user_data),
+                );
+                #[allow(clippy::cast_possible_truncation)]
+                entries.push(
+                    opcode::Write::new(dst_fd, buf.as_ptr(), want as u32)
+                        .offset(end)
+                        .build()
+                        .user_data(user_data + 1),
+                );
+                chunks.push((end, want));
+                end += want as u64;
+            }
+            // The last window syncs once all of its writes are done
+            let sync_here = fsync && end >= len;
+            if sync_here {
+                entries.push(
+                    opcode::Fsync::new(dst_fd)
+                        .build()
+                        .flags(squeue::Flags::IO_DRAIN)
+                        .user_data(FSYNC),
+                );
+            }
+
+            let results = self.submit(&entries)?;
+            let result = |user_data: u64| {
+                results
+                    .iter()
+                    .find(|(data, _)| *data == user_data)
+                    .map_or(-libc::EIO, |(_, res)| *res)
+            };
+
+            let mut next = end;
+            // Data written outside the ring lands after the fsync
+            let mut rewritten = false;
+            for (i, &(chunk_offset, want)) in chunks.iter().enumerate() {
+                let read = result(2 * i as u64);
+                if read < 0 {
+                    return Err(io::Error::from_raw_os_error(-read));
+                }
+                let read = read.unsigned_abs() as usize;
+                let data = &self.buffers[i][..read];
+                if let Some(hasher) = hasher.as_deref_mut() {
+                    hasher.update(data);
+                }
+
+                if read < want {
+                    // A short read cancels the linked write: write what was
+                    // read and go on from there (the rest of the window is
+                    // read again)
+                    dst.write_all_at(data, chunk_offset)?;
+                    next = chunk_offset + read as u64;
+                    if read == 0 {
+                        // The source ended early; drop anything written beyond
+                        dst.set_len(next)?;
+                        return Ok((next, false));
+                    }
+                    break;
+                }
+
+                let written = result(2 * i as u64 + 1);
+                if written < 0 {
+                    return Err(io::Error::from_raw_os_error(-written));
+                }
+                let written = written.unsigned_abs() as usize;
+                if written < read {
+                    dst.write_all_at(&data[written..], chunk_offset + written as u64)?;
+                    rewritten = true;
+                }
+            }
+
+            if sync_here && next == end {
+                let synced = result(FSYNC);
+                if synced < 0 {
+                    return Err(io::Error::from_raw_os_error(-synced));
+                }
+                return Ok((len, !rewritten));
+            }
+            offset = next;
+        }
+
+        Ok((offset, false))
+    }
+
+    /// Submit `entries` and wait for all of their completions.
+    ///
+    /// Returns the `(user_data, result)` of every entry.
+    fn submit(&mut self, entries: &[squeue::Entry]) -> io::Result<Vec<(u64, i32)>> {
+        // SAFETY: Every entry points into `self.buffers` or at descriptors
+        // owned by the caller, and the buffers are leaked below if an entry
+        // may still be in flight.
+        let results = unsafe { submit_all(&mut self.ring, entries) };
+        if results.is_err() {
+            // The kernel may still write into the buffers, so they must never
+            // be reused or freed; the caller drops the ring
+            std::mem::forget(std::mem::take(&mut self.buffers));
+        }
+        results
+    }
+}
+
+/// Submit `entries` to `ring` and wait for all of their completions.
+///
+/// Returns the `(user_data, result)` of every entry.
+///
+/// # Safety
+///
+/// Everything the entries point to must stay valid until they complete. If
+/// this returns an error, some of them may still be in flight: that memory
+/// must then never be reused or freed, and the ring must be dropped.
+pub(crate) unsafe fn submit_all(
+    ring: &mut IoUring,
+    entries: &[squeue::Entry],
+) -> io::Result<Vec<(u64, i32)>> {
+    // SAFETY: Upheld by the caller
+    unsafe {
+        ring.submission()
+            .push_multiple(entries)
+            .map_err(|_| io::Error::other("io_uring submission queue is full"))?;
+    }
+
+    let mut results = Vec::with_capacity(entries.len());
+    while results.len() < entries.len() {
+        match ring.submit_and_wait(entries.len() - results.len()) {
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+        results.extend(ring.completion().map(|cqe| (cqe.user_data(), cqe.result())));
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::copy::digest::hash_file;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_uring_copies_file_contents() {
+        if !is_available() {
+            return;
+        }
+        let dir = tempdir().unwrap();
+        for len in [0, 1, CHUNK_SIZE, CHUNK_SIZE * WINDOW + 12345] {
+            let src_path = dir.path().join(format!("src-{len}"));
+            let dst_path = dir.path().join(format!("dst-{len}"));
+            let data: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            fs::write(&src_path, &data).unwrap();
+
+            let src = File::open(&src_path).unwrap();
+            let dst = File::create(&dst_path).unwrap();
+            let mut hasher = Hasher::default();
+            let (copied, synced) =
+                copy_file_contents(&src, &dst, len as u64, true, None, Some(&mut hasher))
+                    .unwrap()
+                    .unwrap();
+            assert_eq!(copied, len as u64);
+            assert_eq!(synced, len > 0);
+            assert_eq!(fs::read(&dst_path).unwrap(), data);
+            assert_eq!(hasher.finalize(), hash_file(&src_path).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_uring_stops_at_early_end_of_source() {
+        if !is_available() {
+            return;
+        }
+        let dir = tempdir().unwrap();
+        let src_path = dir.path().join("src");
+        let dst_path = dir.path().join("dst");
+        fs::write(&src_path, vec![7u8; CHUNK_SIZE + 100]).unwrap();
+
+        let src = File::open(&src_path).unwrap();
+        let dst = File::create(&dst_path).unwrap();
+        // The source is shorter than expected, as if truncated mid-copy
+        let (copied, synced) =
+            copy_file_contents(&src, &dst, 3 * CHUNK_SIZE as u64, true, None, None)
+                .unwrap()
+                .unwrap();
+        assert_eq!(copied, CHUNK_SIZE as u64 + 100);
+        assert!(!synced);
+        assert_eq!(fs::read(&dst_path).unwrap(), vec![7u8; CHUNK_SIZE + 100]);
+    }
+
+    #[test]
+    fn test_copy_dir_with_io_uring_engine() {
+        use crate::{CopyEngine, CopyOptions, copy_dir};
+
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("small.txt"), "small").unwrap();
+        fs::write(
+            src.join("sub/large.bin"),
+            vec![3u8; 3 * CHUNK_SIZE * WINDOW],
+        )
+        .unwrap();
+
+        let options = CopyOptions::default()
+            .with_engine(CopyEngine::IoUring)
+            .with_verify();
+        let stats = copy_dir(&src, &dst, &options).unwrap();
+        assert_eq!(stats.files_copied, 2);
+        assert_eq!(fs::read(dst.join("small.txt")).unwrap(), b"small");
+        assert_eq!(
+            fs::read(dst.join("sub/large.bin")).unwrap(),
+            fs::read(src.join("sub/large.bin")).unwrap()
+        );
+    }
+}
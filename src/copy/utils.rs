@@ -4,6 +4,8 @@
 //! copy operations, including symlink handling, timestamp preservation,
 //! and platform-specific utilities.
 
+use super::digest::{Hasher, HashingReader};
+use super::throttle::RateLimiter;
 use filetime::{FileTime, set_file_times};
 use std::fs::{self, Metadata};
 use std::io;
@@ -23,19 +25,26 @@ use std::sync::atomic::{AtomicBool, Ordering};
 ///
 /// If `cancel_check` is provided, the operation checks for cancellation between
 /// chunks (128MB boundaries). If cancelled, returns `io::ErrorKind::Interrupted`.
+///
+/// # Hashing
+///
+/// If `hasher` is provided, it is fed the copied bytes. The userspace copy
+/// hashes data as it streams through; after a `copy_file_range` copy, the
+/// copied range of the source is read again to hash it.
 pub(crate) fn copy_file_contents(
     src: &std::fs::File,
     dst: &std::fs::File,
     len: u64,
     cancel_check: Option<&AtomicBool>,
+    hasher: Option<&mut Hasher>,
 ) -> io::Result<u64> {
     #[cfg(target_os = "linux")]
     {
-        copy_file_range_all(src, dst, len, cancel_check)
+        copy_file_range_all(src, dst, len, cancel_check, hasher)
     }
     #[cfg(not(target_os = "linux"))]
     {
-        copy_file_contents_chunked(src, dst, len, cancel_check)
+        copy_file_contents_chunked(src, dst, len, cancel_check, hasher)
     }
 }
 
@@ -49,6 +58,7 @@ fn copy_file_range_all(
     dst: &std::fs::File,
     len: u64,
     cancel_check: Option<&AtomicBool>,
+    hasher: Option<&mut Hasher>,
 ) -> io::Result<u64> {
     use std::os::unix::io::AsRawFd;
 
@@ -94,7 +104,7 @@ fn copy_file_range_all(
                         | Some(libc::EOPNOTSUPP)
                 )
             {
-                return copy_file_contents_chunked(src, dst, len, cancel_check);
+                return copy_file_contents_chunked(src, dst, len, cancel_check, hasher);
             }
             return Err(err);
         }
@@ -109,9 +119,264 @@ fn copy_file_range_all(
         remaining = remaining.saturating_sub(bytes_copied);
     }
 
+    // The data never entered userspace, so hash what was copied separately
+    if let Some(hasher) = hasher {
+        hash_range(src, copied, hasher)?;
+    }
+
+    Ok(copied)
+}
+
+/// Hash the first `len` bytes of `file` without moving its cursor.
+fn hash_range(file: &std::fs::File, len: u64, hasher: &mut Hasher) -> io::Result<()> {
+    let mut buf = vec![0u8; 1024 * 1024];
+    let mut offset = 0u64;
+    while offset < len {
+        let want = buf
+            .len()
+            .min(usize::try_from(len - offset).unwrap_or(usize::MAX));
+        let n = read_at(file, &mut buf[..want], offset)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        offset += n as u64;
+    }
+    Ok(())
+}
+
+/// Copy the first `len` bytes of `src` into `dst` in concurrent ranges.
+///
+/// The range `[0, len)` is split into chunks of `chunk_size` bytes, which the
+/// current rayon pool copies at explicit offsets: with `copy_file_range` on
+/// Linux (falling back to `pread`/`pwrite` where it is not supported), and
+/// with positioned reads and writes elsewhere. `dst` is sized to `len` first.
+///
+/// If `src` ends early, `dst` is cut to the bytes copied before the first
+/// gap, and that count is returned. If `hasher` is provided, the copied bytes
+/// of `src` are read again afterwards to hash them in order. With a
+/// `limiter`, each chunk is copied as by [`copy_range_limited`].
+///
+/// # Cancellation
+///
+/// If `cancel_check` is provided, each chunk checks for cancellation before it
+/// starts. If cancelled, returns `io::ErrorKind::Interrupted`.
+pub(crate) fn copy_file_ranges(
+    src: &std::fs::File,
+    dst: &std::fs::File,
+    len: u64,
+    chunk_size: u64,
+    limiter: Option<&RateLimiter>,
+    cancel_check: Option<&AtomicBool>,
+    hasher: Option<&mut Hasher>,
+) -> io::Result<u64> {
+    use rayon::prelude::*;
+
+    let chunk_size = chunk_size.max(1);
+    dst.set_len(len)?;
+
+    let chunks: Vec<(u64, u64)> = (0..len.div_ceil(chunk_size))
+        .map(|i| {
+            let start = i * chunk_size;
+            (start, chunk_size.min(len - start))
+        })
+        .collect();
+    let copied = chunks
+        .par_iter()
+        .map(|&(start, want)| {
+            if cancel_check.is_some_and(|cancel| cancel.load(Ordering::Relaxed)) {
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+            }
+            match limiter {
+                Some(limiter) => copy_range_limited(src, dst, start, want, limiter, cancel_check),
+                None => copy_range(src, dst, start, want),
+            }
+        })
+        .collect::<io::Result<Vec<u64>>>()?;
+
+    // Everything up to the first chunk cut short by the end of the source
+    let mut total = 0;
+    for (&(_, want), &got) in chunks.iter().zip(&copied) {
+        total += got;
+        if got < want {
+            dst.set_len(total)?;
+            break;
+        }
+    }
+
+    if let Some(hasher) = hasher {
+        hash_range(src, total, hasher)?;
+    }
+    Ok(total)
+}
+
+/// Copy `len` bytes at `offset` from `src` to the same offset in `dst`.
+///
+/// Returns the bytes copied, fewer than `len` if `src` ends early.
+fn copy_range(src: &std::fs::File, dst: &std::fs::File, offset: u64, len: u64) -> io::Result<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::io::AsRawFd;
+
+        let mut copied = 0u64;
+        while copied < len {
+            let mut off_in = libc::loff_t::try_from(offset + copied)
+                .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+            let mut off_out = off_in;
+            let want = usize::try_from(len - copied).unwrap_or(usize::MAX);
+            // SAFETY: Both descriptors are valid for the lifetime of the
+            // borrowed files, and the offsets point at live locals; explicit
+            // offsets leave the file cursors untouched.
+            let result = unsafe {
+                libc::copy_file_range(
+                    src.as_raw_fd(),
+                    &raw mut off_in,
+                    dst.as_raw_fd(),
+                    &raw mut off_out,
+                    want,
+                    0,
+                )
+            };
+            if result < 0 {
+                let err = io::Error::last_os_error();
+                if matches!(
+                    err.raw_os_error(),
+                    Some(libc::EXDEV | libc::ENOSYS | libc::EINVAL | libc::EOPNOTSUPP)
+                ) {
+                    return copy_range_buffered(src, dst, offset + copied, len - copied)
+                        .map(|n| copied + n);
+                }
+                return Err(err);
+            }
+            if result == 0 {
+                break;
+            }
+            copied += result.unsigned_abs() as u64;
+        }
+        Ok(copied)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        copy_range_buffered(src, dst, offset, len)
+    }
+}
+
+/// Copy `len` bytes at `offset` like [`copy_range`], paced by `limiter`.
+///
+/// The range is copied in slices of an eighth of a second's worth of bytes
+/// (between 4 KiB and 1 MiB), each taking its size from `limiter` first, so
+/// every thread sharing the limiter stays within its rate.
+///
+/// # Cancellation
+///
+/// If `cancel_check` is provided, it is checked before and while waiting for
+/// each slice. If cancelled, returns `io::ErrorKind::Interrupted`.
+pub(crate) fn copy_range_limited(
+    src: &std::fs::File,
+    dst: &std::fs::File,
+    offset: u64,
+    len: u64,
+    limiter: &RateLimiter,
+    cancel_check: Option<&AtomicBool>,
+) -> io::Result<u64> {
+    let slice = (limiter.rate() / 8).clamp(4 * 1024, RANGE_BUFFER_SIZE as u64);
+    let mut copied = 0u64;
+    while copied < len {
+        let want = slice.min(len - copied);
+        limiter.acquire_cancellable(want, cancel_check)?;
+        let n = copy_range(src, dst, offset + copied, want)?;
+        copied += n;
+        if n < want {
+            break;
+        }
+    }
+    Ok(copied)
+}
+
+/// Copy the first `len` bytes of `src` into `dst`, paced by `limiter`.
+///
+/// See [`copy_range_limited`]. If `hasher` is provided, the copied bytes of
+/// `src` are read again afterwards to hash them.
+pub(crate) fn copy_file_contents_limited(
+    src: &std::fs::File,
+    dst: &std::fs::File,
+    len: u64,
+    limiter: &RateLimiter,
+    cancel_check: Option<&AtomicBool>,
+    hasher: Option<&mut Hasher>,
+) -> io::Result<u64> {
+    let copied = copy_range_limited(src, dst, 0, len, limiter, cancel_check)?;
+    if let Some(hasher) = hasher {
+        hash_range(src, copied, hasher)?;
+    }
     Ok(copied)
 }
 
+/// Copy a range with positioned reads and writes through a buffer.
+fn copy_range_buffered(
+    src: &std::fs::File,
+    dst: &std::fs::File,
+    offset: u64,
+    len: u64,
+) -> io::Result<u64> {
+    let mut buf =
+        vec![0u8; usize::try_from(len).map_or(RANGE_BUFFER_SIZE, |n| n.min(RANGE_BUFFER_SIZE))];
+    let mut copied = 0u64;
+    while copied < len {
+        let want = buf
+            .len()
+            .min(usize::try_from(len - copied).unwrap_or(usize::MAX));
+        let n = read_at(src, &mut buf[..want], offset + copied)?;
+        if n == 0 {
+            break;
+        }
+        write_all_at(dst, &buf[..n], offset + copied)?;
+        copied += n as u64;
+    }
+    Ok(copied)
+}
+
+/// Size of the buffer for positioned range copies
+const RANGE_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Read into `buf` from `offset`, without moving the file's cursor.
+pub(crate) fn read_at(file: &std::fs::File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    loop {
+        #[cfg(unix)]
+        let result = std::os::unix::fs::FileExt::read_at(file, buf, offset);
+        #[cfg(windows)]
+        let result = std::os::windows::fs::FileExt::seek_read(file, buf, offset);
+        match result {
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            result => return result,
+        }
+    }
+}
+
+/// Write all of `buf` at `offset`, without moving the file's cursor.
+pub(crate) fn write_all_at(
+    file: &std::fs::File,
+    mut buf: &[u8],
+    mut offset: u64,
+) -> io::Result<()> {
+    while !buf.is_empty() {
+        #[cfg(unix)]
+        let result = std::os::unix::fs::FileExt::write_at(file, buf, offset);
+        #[cfg(windows)]
+        let result = std::os::windows::fs::FileExt::seek_write(file, buf, offset);
+        match result {
+            Ok(0) => return Err(io::Error::from(io::ErrorKind::WriteZero)),
+            Ok(n) => {
+                buf = &buf[n..];
+                offset += n as u64;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
 /// Chunked file copy for non-Linux platforms and fallback.
 ///
 /// Copies file in 128MB chunks, checking for cancellation between chunks.
@@ -120,11 +385,12 @@ fn copy_file_contents_chunked(
     dst: &std::fs::File,
     len: u64,
     cancel_check: Option<&AtomicBool>,
+    hasher: Option<&mut Hasher>,
 ) -> io::Result<u64> {
     use std::io::{BufReader, Read};
 
     const CHUNK_SIZE: u64 = 128 * 1024 * 1024; // 128MB
-    let mut reader = BufReader::new(src);
+    let mut reader = HashingReader::new(BufReader::new(src), hasher);
     let mut remaining = len;
     let mut copied: u64 = 0;
 
@@ -217,6 +483,25 @@ pub(crate) struct DirEntry {
     pub dst: std::path::PathBuf,
 }
 
+/// Run `f` on a thread pool sized by `options.parallel`.
+///
+/// Falls back to the global pool if a dedicated pool cannot be built.
+pub(crate) fn run_in_pool<T: Send>(
+    options: &crate::CopyOptions,
+    f: impl FnOnce() -> T + Send,
+) -> T {
+    if options.parallel == rayon::current_num_threads() {
+        return f();
+    }
+    match rayon::ThreadPoolBuilder::new()
+        .num_threads(options.parallel)
+        .build()
+    {
+        Ok(pool) => pool.install(f),
+        Err(_) => f(),
+    }
+}
+
 // =============================================================================
 // Metadata and timestamp utilities
 // =============================================================================
@@ -232,6 +517,32 @@ pub(crate) fn is_source_newer(src_meta: &Metadata, dst_meta: &Metadata) -> bool
     }
 }
 
+/// Whether a source file is unchanged since `before` was taken.
+///
+/// Compares size, mtime and (on Unix) ctime of the open `file` with `before`,
+/// and checks that all `copied` bytes of the expected size could be read.
+pub(crate) fn is_source_unchanged(
+    before: &Metadata,
+    file: &std::fs::File,
+    copied: u64,
+) -> io::Result<bool> {
+    let after = file.metadata()?;
+    if copied != before.len() || after.len() != before.len() {
+        return Ok(false);
+    }
+    if after.modified().ok() != before.modified().ok() {
+        return Ok(false);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if (after.ctime(), after.ctime_nsec()) != (before.ctime(), before.ctime_nsec()) {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
 /// Preserve file timestamps (mtime and atime)
 pub(crate) fn preserve_timestamps(src_meta: &Metadata, dst: &Path) -> io::Result<()> {
     let mtime = FileTime::from_last_modification_time(src_meta);
@@ -249,6 +560,33 @@ mod tests {
     use std::fs;
     use tempfile::tempdir;
 
+    #[test]
+    fn test_copy_file_ranges() {
+        let dir = tempdir().unwrap();
+        let src_path = dir.path().join("src");
+        let dst_path = dir.path().join("dst");
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&src_path, &data).unwrap();
+
+        let src = fs::File::open(&src_path).unwrap();
+        let dst = fs::File::create(&dst_path).unwrap();
+        let mut hasher = Hasher::default();
+        let copied =
+            copy_file_ranges(&src, &dst, 100_000, 4096, None, None, Some(&mut hasher)).unwrap();
+        assert_eq!(copied, 100_000);
+        assert_eq!(fs::read(&dst_path).unwrap(), data);
+        assert_eq!(
+            hasher.finalize(),
+            super::super::digest::hash_file(&src_path).unwrap()
+        );
+
+        // A source shorter than expected leaves only what was copied
+        let dst = fs::File::create(&dst_path).unwrap();
+        let copied = copy_file_ranges(&src, &dst, 150_000, 4096, None, None, None).unwrap();
+        assert_eq!(copied, 100_000);
+        assert_eq!(fs::read(&dst_path).unwrap(), data);
+    }
+
     #[test]
     fn test_is_symlink() {
         let dir = tempdir().unwrap();
@@ -297,6 +635,29 @@ mod tests {
         assert!(!is_source_newer(&meta1, &meta2));
     }
 
+    #[test]
+    fn test_is_source_unchanged() {
+        use std::io::Write;
+
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("live.log");
+        fs::write(&path, "line 1\n").unwrap();
+
+        let before = fs::metadata(&path).unwrap();
+        let file = fs::File::open(&path).unwrap();
+        assert!(is_source_unchanged(&before, &file, before.len()).unwrap());
+        // A short read means the copy is incomplete
+        assert!(!is_source_unchanged(&before, &file, before.len() - 1).unwrap());
+
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"line 2\n")
+            .unwrap();
+        assert!(!is_source_unchanged(&before, &file, before.len()).unwrap());
+    }
+
     #[test]
     fn test_get_dir_key() {
         let dir = tempdir().unwrap();
@@ -0,0 +1,123 @@
+//! Read-back verification of copied files.
+//!
+//! With [`CopyOptions::verify`](crate::CopyOptions::verify), each copy is read
+//! back before it replaces the destination and its digest is compared with
+//! the digest of the source.
+
+use crate::error::{Error, Result};
+use std::path::{Path, PathBuf};
+
+use super::digest::{Digest, hash_file, hash_from_storage};
+
+/// A copied file whose contents read back differ from its source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyMismatch {
+    /// Destination file that failed verification (it was left as it was)
+    pub path: PathBuf,
+    /// Digest of the source
+    pub expected: Digest,
+    /// Digest of the destination as read back
+    pub actual: Digest,
+}
+
+/// Check that `copy`, the copy of `src` made for `dst`, holds the same
+/// contents as `src`, and return the digest of `src`.
+///
+/// `source_digest` is the digest computed while copying, if any; otherwise
+/// the source is hashed now. Nothing is removed: the caller discards a
+/// mismatching copy before it replaces `dst`.
+pub(crate) fn verify_copy(
+    src: &Path,
+    copy: &Path,
+    dst: &Path,
+    source_digest: Option<Digest>,
+) -> Result<Digest> {
+    let expected = match source_digest {
+        Some(digest) => digest,
+        None => hash_file(src)?,
+    };
+    let actual = hash_from_storage(copy)?;
+    if actual == expected {
+        return Ok(expected);
+    }
+
+    Err(Error::VerificationFailed {
+        mismatches: vec![VerifyMismatch {
+            path: dst.to_path_buf(),
+            expected,
+            actual,
+        }],
+    })
+}
+
+/// Describe verification failures for [`Error::VerificationFailed`].
+pub(crate) fn describe_mismatches(mismatches: &[VerifyMismatch]) -> String {
+    match mismatches {
+        [] => "Verification failed".to_owned(),
+        [one] => format!(
+            "Verification failed for {}: expected sha256 {}, read back {}",
+            one.path.display(),
+            one.expected,
+            one.actual
+        ),
+        [first, ..] => format!(
+            "Verification failed for {} files, including {}",
+            mismatches.len(),
+            first.path.display()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_verify_copy_matching() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::write(&src, "same").unwrap();
+        fs::write(&dst, "same").unwrap();
+
+        let digest = verify_copy(&src, &dst, &dst, None).unwrap();
+        assert_eq!(digest, hash_file(&src).unwrap());
+    }
+
+    #[test]
+    fn test_verify_copy_mismatch_reports_destination() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let copy = dir.path().join("copy");
+        let dst = dir.path().join("dst");
+        fs::write(&src, "good").unwrap();
+        fs::write(&copy, "bad!").unwrap();
+
+        let err = verify_copy(&src, &copy, &dst, None).unwrap_err();
+
+        let Error::VerificationFailed { mismatches } = &err else {
+            panic!("expected VerificationFailed, got {err:?}");
+        };
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].path, dst);
+        assert_ne!(mismatches[0].expected, mismatches[0].actual);
+        assert!(copy.exists());
+    }
+
+    #[test]
+    fn test_verify_copy_uses_digest_from_copy() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::write(&src, "now").unwrap();
+        fs::write(&dst, "now").unwrap();
+
+        // The source changed after it was hashed during the copy
+        let copied = hash_file(&dst).unwrap();
+        fs::write(&src, "later").unwrap();
+
+        verify_copy(&src, &dst, &dst, Some(copied)).unwrap();
+    }
+}
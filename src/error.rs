@@ -7,13 +7,14 @@
 //!
 //! | Category | Errors |
 //! |----------|--------|
-//! | IO | [`Error::Io`], [`Error::TempFile`], [`Error::Persist`] |
-//! | Validation | [`Error::SourceNotFound`], [`Error::NotADirectory`], [`Error::IsADirectory`] |
+//! | IO | [`Error::Io`], [`Error::TempFile`], [`Error::Persist`], [`Error::Backup`] |
+//! | Validation | [`Error::SourceNotFound`], [`Error::NotADirectory`], [`Error::IsADirectory`], [`Error::InvalidManifest`], [`Error::PlanDrift`], [`Error::UnsupportedMoveOption`] |
 //! | Conflict | [`Error::AlreadyExists`] |
 //! | Partial | [`Error::PartialCopy`], [`Error::PartialSymlinks`], [`Error::NoSpace`] |
 //! | Safety | [`Error::SymlinkLoop`], [`Error::MaxDepthExceeded`] |
 //! | Control | [`Error::Cancelled`] |
 
+use crate::copy::{RemainingWork, RollbackReport, VerifyMismatch, describe_mismatches};
 use std::io;
 use std::path::PathBuf;
 use thiserror::Error;
@@ -38,6 +39,9 @@ pub enum ErrorCode {
     Cancelled,
     PartialCopy,
     SymlinkLoop,
+    SourceChanged,
+    VerificationFailed,
+    PlanDrift,
     IoError,
     Internal,
 }
@@ -55,7 +59,7 @@ pub struct ErrorCodeSpec {
     pub remediation: &'static str,
 }
 
-const ERROR_CODE_SPECS: [ErrorCodeSpec; 10] = [
+const ERROR_CODE_SPECS: [ErrorCodeSpec; 13] = [
     ErrorCodeSpec {
         code: ErrorCode::InvalidInput,
         meaning: "User input or invocation is invalid.",
@@ -104,6 +108,24 @@ const ERROR_CODE_SPECS: [ErrorCodeSpec; 10] = [
         typical_triggers: "Circular symlink graph detected.",
         remediation: "Remove/fix the loop or adjust symlink policy.",
     },
+    ErrorCodeSpec {
+        code: ErrorCode::SourceChanged,
+        meaning: "A source file changed while it was being copied.",
+        typical_triggers: "Live files written, truncated or replaced during the copy.",
+        remediation: "Quiesce the writer or use a retry source-change policy and rerun.",
+    },
+    ErrorCodeSpec {
+        code: ErrorCode::VerificationFailed,
+        meaning: "File contents read back differ from their source or checksum manifest.",
+        typical_triggers: "Faulty storage, network filesystems or controllers corrupting data, bit rot at rest.",
+        remediation: "Check the destination storage and rerun; mismatching copies are discarded.",
+    },
+    ErrorCodeSpec {
+        code: ErrorCode::PlanDrift,
+        meaning: "The filesystem no longer matches a saved copy plan.",
+        typical_triggers: "Source modified, removed, or destination created after planning.",
+        remediation: "Plan again, review the new plan, and apply it.",
+    },
     ErrorCodeSpec {
         code: ErrorCode::IoError,
         meaning: "Generic I/O error.",
@@ -131,6 +153,9 @@ impl ErrorCode {
             Self::Cancelled => "cancelled",
             Self::PartialCopy => "partial_copy",
             Self::SymlinkLoop => "symlink_loop",
+            Self::SourceChanged => "source_changed",
+            Self::VerificationFailed => "verification_failed",
+            Self::PlanDrift => "plan_drift",
             Self::IoError => "io_error",
             Self::Internal => "internal",
         }
@@ -148,14 +173,17 @@ impl ErrorCode {
             Self::Cancelled => ERROR_CODE_SPECS[5],
             Self::PartialCopy => ERROR_CODE_SPECS[6],
             Self::SymlinkLoop => ERROR_CODE_SPECS[7],
-            Self::IoError => ERROR_CODE_SPECS[8],
-            Self::Internal => ERROR_CODE_SPECS[9],
+            Self::SourceChanged => ERROR_CODE_SPECS[8],
+            Self::VerificationFailed => ERROR_CODE_SPECS[9],
+            Self::PlanDrift => ERROR_CODE_SPECS[10],
+            Self::IoError => ERROR_CODE_SPECS[11],
+            Self::Internal => ERROR_CODE_SPECS[12],
         }
     }
 
     /// Returns all stable error codes in canonical reference order.
     #[must_use]
-    pub const fn all() -> [ErrorCode; 10] {
+    pub const fn all() -> [ErrorCode; 13] {
         [
             ErrorCode::InvalidInput,
             ErrorCode::SourceNotFound,
@@ -165,6 +193,9 @@ impl ErrorCode {
             ErrorCode::Cancelled,
             ErrorCode::PartialCopy,
             ErrorCode::SymlinkLoop,
+            ErrorCode::SourceChanged,
+            ErrorCode::VerificationFailed,
+            ErrorCode::PlanDrift,
             ErrorCode::IoError,
             ErrorCode::Internal,
         ]
@@ -179,7 +210,7 @@ impl std::fmt::Display for ErrorCode {
 
 /// Returns stable reference metadata for all known error codes.
 #[must_use]
-pub const fn error_code_specs() -> &'static [ErrorCodeSpec; 10] {
+pub const fn error_code_specs() -> &'static [ErrorCodeSpec; 13] {
     &ERROR_CODE_SPECS
 }
 
@@ -275,7 +306,8 @@ pub enum Error {
     ///
     /// Free up space on the destination and re-run the copy operation.
     /// Successfully copied files will be skipped by default (OnConflict::Skip),
-    /// and the copy will resume from where it left off.
+    /// and the copy will resume from where it left off. Alternatively, copy
+    /// only the entries listed in `remaining_work`.
     #[error(
         "No space left on device: {files_copied} of {total_files} files copied, {remaining} remaining. Re-run to resume."
     )]
@@ -292,6 +324,8 @@ pub enum Error {
         remaining: usize,
         /// The path where the no-space error occurred
         path: PathBuf,
+        /// Entries that failed or were not copied
+        remaining_work: Box<RemainingWork>,
     },
 
     /// Source path does not exist
@@ -328,6 +362,113 @@ pub enum Error {
         source: std::io::Error,
     },
 
+    /// Failed to back up a destination file before replacing it
+    ///
+    /// The destination is left unchanged when this error occurs.
+    #[error("Failed to back up {path}: {source}")]
+    Backup {
+        /// Destination path that was about to be replaced
+        path: PathBuf,
+        /// Underlying error
+        source: std::io::Error,
+    },
+
+    /// Failed to remove a source entry after moving it
+    ///
+    /// The destination already holds the entry, so re-running the move
+    /// removes the leftover source.
+    #[error("Failed to remove moved source {path}: {source}")]
+    RemoveSource {
+        /// Source path that could not be removed
+        path: PathBuf,
+        /// Underlying error
+        source: std::io::Error,
+    },
+
+    /// Copied files read back from the destination differ from their source
+    ///
+    /// Copies are checked before they replace their destination, so each
+    /// mismatching destination file was left as it was.
+    #[error("{}", describe_mismatches(mismatches))]
+    VerificationFailed {
+        /// Files that failed verification
+        mismatches: Vec<VerifyMismatch>,
+    },
+
+    /// A checksum manifest could not be parsed
+    #[error("Invalid manifest {path} at line {line}: {reason}")]
+    InvalidManifest {
+        /// Manifest file
+        path: PathBuf,
+        /// 1-based line number of the offending entry
+        line: usize,
+        /// What is wrong with the entry
+        reason: String,
+    },
+
+    /// A planned item no longer matches the filesystem
+    ///
+    /// Raised by [`execute_plan`](crate::execute_plan) for an item whose
+    /// source or destination changed since it was planned; the item is not
+    /// run.
+    #[error("Plan no longer matches {path}: {reason}")]
+    PlanDrift {
+        /// Source of the item
+        path: PathBuf,
+        /// What changed
+        reason: String,
+    },
+
+    /// Failed to stage or swap in an atomically published directory
+    ///
+    /// The destination is left unchanged when this error occurs.
+    #[error("Failed to publish {path}: {source}")]
+    Publish {
+        /// Destination directory being published
+        path: PathBuf,
+        /// Underlying error
+        source: std::io::Error,
+    },
+
+    /// A transactional copy failed and its changes to the destination were undone
+    ///
+    /// Entries created by the run were removed and entries it replaced were
+    /// restored; `report` lists them, along with any entry that could not be
+    /// rolled back. The error code is that of the original `error`.
+    #[error("{error} (rolled back: {} removed, {} restored, {} failed)", report.removed.len(), report.restored.len(), report.failed.len())]
+    RolledBack {
+        /// Error that made the run fail
+        error: Box<Error>,
+        /// What was rolled back
+        report: RollbackReport,
+    },
+
+    /// Destination lies inside the source directory being moved
+    #[error("Cannot move {src} into itself: {dst}")]
+    MoveIntoSelf {
+        /// Source directory
+        src: PathBuf,
+        /// Destination inside the source
+        dst: PathBuf,
+    },
+
+    /// A directory move was asked for an option that moves do not support
+    ///
+    /// [`move_dir`](crate::move_dir) neither stages nor rolls back, so it
+    /// rejects [`atomic_publish`](crate::CopyOptions::atomic_publish) and
+    /// [`transactional`](crate::CopyOptions::transactional) rather than
+    /// ignoring them.
+    #[error("Moves do not support {0}")]
+    UnsupportedMoveOption(&'static str),
+
+    /// Source file changed while it was being copied
+    ///
+    /// Raised under [`SourceChangePolicy::Fail`](crate::SourceChangePolicy::Fail),
+    /// or once the retries of [`SourceChangePolicy::Retry`](crate::SourceChangePolicy::Retry)
+    /// are exhausted. The destination is left unchanged.
+    #[error("Source file changed while being copied: {0}")]
+    SourceChanged(PathBuf),
+
     /// Symlink loop detected (would cause infinite recursion)
     #[error("Symlink loop detected: {0}")]
     SymlinkLoop(PathBuf),
@@ -344,9 +485,9 @@ pub enum Error {
     /// Operation was cancelled via cancellation token
     ///
     /// This error carries partial statistics so the caller knows what
-    /// was completed before cancellation. Re-running with
-    /// [`OnConflict::Skip`](crate::OnConflict::Skip) (the default)
-    /// will resume where the cancelled operation left off.
+    /// was completed before cancellation, and the entries that were not.
+    /// Re-running with [`OnConflict::Skip`](crate::OnConflict::Skip) (the
+    /// default) will resume where the cancelled operation left off.
     #[error("Operation cancelled ({files_copied} files copied, {bytes_copied} bytes)")]
     Cancelled {
         /// Number of files successfully copied before cancellation
@@ -357,6 +498,8 @@ pub enum Error {
         files_skipped: u64,
         /// Number of directories created before cancellation
         dirs_created: u64,
+        /// Entries that were not copied or failed
+        remaining_work: Box<RemainingWork>,
     },
 }
 
@@ -376,18 +519,54 @@ impl Error {
 
         match self {
             Self::Io(error) => io_code(error),
-            Self::TempFile { source, .. } | Self::Persist { source, .. } => io_code(source),
+            Self::TempFile { source, .. }
+            | Self::Persist { source, .. }
+            | Self::Backup { source, .. }
+            | Self::RemoveSource { source, .. }
+            | Self::Publish { source, .. } => io_code(source),
+            Self::RolledBack { error, .. } => error.code(),
             Self::PartialCopy { .. } | Self::PartialSymlinks { .. } => ErrorCode::PartialCopy,
             Self::NoSpace { .. } => ErrorCode::NoSpace,
             Self::SourceNotFound(_) => ErrorCode::SourceNotFound,
-            Self::NotADirectory(_) | Self::IsADirectory(_) | Self::MaxDepthExceeded { .. } => {
-                ErrorCode::InvalidInput
-            }
+            Self::NotADirectory(_)
+            | Self::IsADirectory(_)
+            | Self::MaxDepthExceeded { .. }
+            | Self::MoveIntoSelf { .. }
+            | Self::UnsupportedMoveOption(_)
+            | Self::InvalidManifest { .. } => ErrorCode::InvalidInput,
             Self::AlreadyExists(_) => ErrorCode::AlreadyExists,
             Self::SymlinkLoop(_) => ErrorCode::SymlinkLoop,
+            Self::SourceChanged(_) => ErrorCode::SourceChanged,
+            Self::VerificationFailed { .. } => ErrorCode::VerificationFailed,
+            Self::PlanDrift { .. } => ErrorCode::PlanDrift,
             Self::Cancelled { .. } => ErrorCode::Cancelled,
         }
     }
+
+    /// The work left undone, for a cancelled run or one that ran out of space.
+    ///
+    /// For [`Error::RolledBack`], this is the remaining work of the original
+    /// error. Returns `None` for other errors.
+    #[must_use]
+    pub fn remaining_work(&self) -> Option<&RemainingWork> {
+        match self {
+            Self::Cancelled { remaining_work, .. } | Self::NoSpace { remaining_work, .. } => {
+                Some(remaining_work)
+            }
+            Self::RolledBack { error, .. } => error.remaining_work(),
+            _ => None,
+        }
+    }
+
+    /// Replace the remaining work of a cancelled or out-of-space error.
+    pub(crate) fn with_remaining_work(mut self, remaining: RemainingWork) -> Self {
+        if let Self::Cancelled { remaining_work, .. } | Self::NoSpace { remaining_work, .. } =
+            &mut self
+        {
+            **remaining_work = remaining;
+        }
+        self
+    }
 }
 
 #[cfg(test)]
@@ -430,6 +609,7 @@ mod tests {
             total_files: 8,
             remaining: 3,
             path: PathBuf::from("/dest/file.txt"),
+            remaining_work: Box::default(),
         };
         let msg = format!("{}", error);
         assert!(msg.contains("No space left on device"));
@@ -461,6 +641,7 @@ mod tests {
             total_files: 2,
             remaining: 1,
             path: PathBuf::from("/full"),
+            remaining_work: Box::default(),
         };
         assert_eq!(no_space.code(), ErrorCode::NoSpace);
     }
@@ -487,6 +668,12 @@ mod tests {
         assert_eq!(ErrorCode::Cancelled.as_str(), "cancelled");
         assert_eq!(ErrorCode::PartialCopy.as_str(), "partial_copy");
         assert_eq!(ErrorCode::SymlinkLoop.as_str(), "symlink_loop");
+        assert_eq!(ErrorCode::SourceChanged.as_str(), "source_changed");
+        assert_eq!(
+            ErrorCode::VerificationFailed.as_str(),
+            "verification_failed"
+        );
+        assert_eq!(ErrorCode::PlanDrift.as_str(), "plan_drift");
         assert_eq!(ErrorCode::IoError.as_str(), "io_error");
         assert_eq!(ErrorCode::Internal.as_str(), "internal");
     }
@@ -563,6 +750,7 @@ mod tests {
             bytes_copied: 1024,
             files_skipped: 2,
             dirs_created: 1,
+            remaining_work: Box::default(),
         };
         assert_eq!(err.code(), ErrorCode::Cancelled);
     }
@@ -582,6 +770,41 @@ mod tests {
         assert_eq!(err.code(), ErrorCode::InvalidInput);
     }
 
+    #[test]
+    fn test_error_code_mapping_invalid_manifest() {
+        let err = Error::InvalidManifest {
+            path: PathBuf::from("/SHA256SUMS"),
+            line: 3,
+            reason: "missing `path`".to_owned(),
+        };
+        assert_eq!(err.code(), ErrorCode::InvalidInput);
+    }
+
+    #[test]
+    fn test_error_code_mapping_unsupported_move_option() {
+        let err = Error::UnsupportedMoveOption("atomic_publish");
+        assert_eq!(err.code(), ErrorCode::InvalidInput);
+    }
+
+    #[test]
+    fn test_error_code_mapping_rolled_back_uses_original() {
+        let err = Error::RolledBack {
+            error: Box::new(Error::PartialCopy {
+                failed: 1,
+                total: 3,
+            }),
+            report: RollbackReport {
+                removed: vec![PathBuf::from("/dst/a")],
+                ..RollbackReport::default()
+            },
+        };
+        assert_eq!(err.code(), ErrorCode::PartialCopy);
+        assert_eq!(
+            err.to_string(),
+            "Failed to copy 1 of 3 files (rolled back: 1 removed, 0 restored, 0 failed)"
+        );
+    }
+
     #[test]
     fn test_error_display_variants() {
         let err = Error::PartialCopy {
@@ -619,6 +842,7 @@ mod tests {
             bytes_copied: 100,
             files_skipped: 1,
             dirs_created: 0,
+            remaining_work: Box::default(),
         };
         assert!(format!("{err}").contains("3 files copied"));
 
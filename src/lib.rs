@@ -102,7 +102,8 @@
 //! |---------|-------------|
 //! | `progress` | Progress bar support with indicatif |
 //! | `tracing` | Structured logging with tracing crate |
-//! | `serde` | Serialize/Deserialize for [`CopyOptions`] |
+//! | `serde` | Serialize/Deserialize for [`CopyOptions`] and [`CopyPlan`] |
+//! | `io_uring` | [`CopyEngine::IoUring`] on Linux |
 //! | `full` | Enable all optional features |
 //!
 //! ## NFS Optimization
@@ -119,6 +120,7 @@ mod copy;
 mod error;
 mod options;
 mod plan_execute;
+mod session;
 mod utils;
 
 #[cfg(feature = "progress")]
@@ -128,13 +130,21 @@ mod progress;
 mod win_attrs;
 
 pub use builder::CopyBuilder;
-pub use copy::{CopyStats, copy_dir, copy_file};
+pub use copy::{
+    CleanReport, CopyStats, Difference, Digest, EntryKind, Journal, Manifest, ManifestCheck,
+    ManifestEntry, ManifestFormat, RateLimiter, RemainingEntry, RemainingWork, RollbackReport,
+    StaleTemp, TEMP_FILE_PREFIX, TempKind, TreeDiff, TreeDifference, VerifyMismatch,
+    clean_temp_files, compare_trees, copy_dir, copy_file, copy_file_with_stats,
+    find_stale_temp_files, move_dir, move_file, verify_manifest,
+};
 pub use error::{Error, ErrorCode, ErrorCodeSpec, Result, error_code_specs, is_no_space_error};
-pub use options::{CopyOptions, OnConflict};
+pub use options::{BackupMode, CopyEngine, CopyOptions, DedupMode, OnConflict, SourceChangePolicy};
 pub use plan_execute::{
-    CopyEvent, CopyPlan, CopyPolicy, CopyReport, EventHandler, ItemOutcome, ItemReport, PlanAction,
-    PlanReason, PlannedItem, PlannedItemKind, RuntimeOptions, execute_plan, plan_copy,
+    CopyEvent, CopyPlan, CopyPolicy, CopyReport, EventHandler, ItemOutcome, ItemReport,
+    PLAN_SCHEMA_VERSION, PlanAction, PlanReason, PlannedItem, PlannedItemKind, RuntimeOptions,
+    execute_plan, plan_copy, plan_item,
 };
+pub use session::CopySession;
 
 #[cfg(feature = "progress")]
 #[cfg_attr(docsrs, doc(cfg(feature = "progress")))]
@@ -15,8 +15,17 @@
 //!     .with_max_depth(100);
 //! ```
 
+use crate::copy::{Journal, Manifest, RateLimiter};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Default block size for delta updates (1 MiB)
+const DEFAULT_DELTA_BLOCK_SIZE: usize = 1024 * 1024;
+
+/// Default chunk size for parallel copies of large files (64 MiB)
+const DEFAULT_PARALLEL_CHUNK_SIZE: u64 = 64 * 1024 * 1024;
 
 /// Behavior when destination file already exists.
 ///
@@ -56,6 +65,116 @@ pub enum OnConflict {
     UpdateNewer,
 }
 
+/// How to keep the previous version of a destination file that is replaced.
+///
+/// Backups are only made when [`OnConflict::Overwrite`] or
+/// [`OnConflict::UpdateNewer`] actually replaces an existing file or symlink.
+/// The old version is linked to its backup name before the new content is
+/// renamed into place, so at least one version is always present on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BackupMode {
+    /// Keep the old file as `name` + `suffix`, replacing any older backup
+    /// (like `cp --backup=simple`).
+    Simple {
+        /// Suffix appended to the file name (default: `~`)
+        suffix: String,
+    },
+    /// Keep every old version as `name.~N~` with an increasing `N`
+    /// (like `cp --backup=numbered`).
+    Numbered,
+    /// Move old files into a separate directory that mirrors the
+    /// destination tree, replacing any older backup there.
+    Directory(PathBuf),
+}
+
+impl Default for BackupMode {
+    fn default() -> Self {
+        Self::Simple {
+            suffix: "~".to_string(),
+        }
+    }
+}
+
+/// What to do when a source file changes while it is being copied.
+///
+/// A change is detected when the source size, mtime or ctime differs before
+/// and after the copy, or when fewer bytes than expected could be read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SourceChangePolicy {
+    /// Keep the copy, report a warning and count it in
+    /// [`CopyStats::files_changed`](crate::CopyStats::files_changed) (default).
+    #[default]
+    Warn,
+    /// Fail the file with [`Error::SourceChanged`](crate::Error::SourceChanged).
+    Fail,
+    /// Copy the file again, up to `attempts` more times, then fail.
+    Retry {
+        /// Number of additional copy attempts
+        attempts: u32,
+    },
+}
+
+/// How a file identical to one already written in the same run is stored.
+///
+/// Used by [`CopyOptions::dedup`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DedupMode {
+    /// Hard-link the duplicate to the earlier copy.
+    ///
+    /// Both names share one inode, so they also share permissions and
+    /// timestamps. A duplicate whose source differs from the earlier one in
+    /// a preserved permission or mtime is reflinked instead, or written
+    /// normally where reflinks are not available.
+    HardLink,
+    /// Reflink the duplicate to the earlier copy, sharing its data extents
+    /// (requires the `reflink` feature and a filesystem with CoW support).
+    ///
+    /// The duplicate keeps its own metadata. Where reflinks are not
+    /// available, duplicates are written normally.
+    Reflink,
+}
+
+/// How file contents are moved from source to destination.
+///
+/// Used by [`CopyOptions::engine`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CopyEngine {
+    /// One worker thread per file, copying with `copy_file_range` on Linux
+    /// and buffered reads and writes elsewhere.
+    #[default]
+    Standard,
+    /// Submit file operations in batches through a per-thread io_uring
+    /// (Linux, requires the `io_uring` feature).
+    ///
+    /// Cuts the per-file syscalls that dominate copies of many small files on
+    /// fast NVMe and NFS. Directory copies stat, open, read, write, sync and
+    /// rename files of up to 64 KiB in batches of 32, leaving files whose
+    /// destination exists (unless skipped) to the standard path; larger files
+    /// have their reads, writes and fsync batched. Where io_uring is
+    /// unavailable (other platforms, older kernels, or blocked by seccomp),
+    /// the [`Standard`](Self::Standard) engine is used.
+    IoUring,
+}
+
+impl CopyEngine {
+    /// Whether this engine can be used here, rather than falling back to
+    /// [`Standard`](Self::Standard).
+    #[must_use]
+    pub fn is_available(self) -> bool {
+        match self {
+            Self::Standard => true,
+            #[cfg(all(feature = "io_uring", target_os = "linux"))]
+            Self::IoUring => crate::copy::io_uring_available(),
+            #[cfg(not(all(feature = "io_uring", target_os = "linux")))]
+            Self::IoUring => false,
+        }
+    }
+}
+
 /// Options for copy operations.
 ///
 /// Use [`Default::default()`] to get sensible defaults, then customize
@@ -66,6 +185,7 @@ pub enum OnConflict {
 /// | Field | Default | Description |
 /// |-------|---------|-------------|
 /// | `parallel` | 16 | Concurrent operations |
+/// | `auto_parallel` | `false` | Copy `parallel` files at once |
 /// | `on_conflict` | `Skip` | Skip existing files |
 /// | `preserve_permissions` | `true` | Copy file permissions |
 /// | `preserve_dir_permissions` | `true` | Copy directory permissions |
@@ -77,6 +197,27 @@ pub enum OnConflict {
 /// | `block_escaping_symlinks` | `false` | Block symlinks with `..` |
 /// | `max_depth` | `None` | No depth limit |
 /// | `cancel_token` | `None` | No cancellation support |
+/// | `backup` | `None` | Replaced files are not backed up |
+/// | `link_dest` | empty | No reference snapshots to hard-link from |
+/// | `compare_dest` | empty | No reference trees to compare against |
+/// | `checksum` | `false` | Match reference files by size and mtime |
+/// | `delta_threshold` | `None` | Always rewrite replaced files in full |
+/// | `delta_block_size` | 1 MiB | Block size for delta updates |
+/// | `resume_threshold` | `None` | Interrupted copies start over |
+/// | `on_source_change` | `Warn` | Warn when a source changes mid-copy |
+/// | `atomic_publish` | `false` | Copy directories into the destination in place |
+/// | `transactional` | `false` | Keep what a failed directory copy created |
+/// | `verify` | `false` | Trust copies without reading them back |
+/// | `digests` | `false` | Do not hash copied files |
+/// | `manifest` | `None` | No checksum manifest |
+/// | `dedup` | `None` | Write every file, even duplicates |
+/// | `journal` | `None` | No completion journal |
+/// | `clean_stale_temps` | `None` | Leave temp files of earlier runs alone |
+/// | `engine` | `Standard` | Copy file contents with `copy_file_range` |
+/// | `parallel_chunk_threshold` | `None` | Copy each file on a single thread |
+/// | `parallel_chunk_size` | 64 MiB | Chunk size for parallel copies of large files |
+/// | `bwlimit` | `None` | No bandwidth limit |
+/// | `metadata_limit` | `None` | No metadata operation limit |
 ///
 /// # Example
 ///
@@ -97,6 +238,16 @@ pub struct CopyOptions {
     /// can overwhelm the server. Adjust based on your storage backend.
     pub parallel: usize,
 
+    /// Tune the number of files copied at once (default: `false`)
+    ///
+    /// Directory copies measure throughput and per-file latency while copying
+    /// files and adjust how many are copied at once, between 1 and
+    /// [`parallel`](Self::parallel): one more while throughput holds up, a
+    /// quarter fewer when it drops or latency grows without a gain. The
+    /// levels chosen are reported in
+    /// [`CopyStats::parallel_levels`](crate::CopyStats::parallel_levels).
+    pub auto_parallel: bool,
+
     /// Behavior when destination file already exists
     pub on_conflict: OnConflict,
 
@@ -176,6 +327,176 @@ pub struct CopyOptions {
     #[cfg_attr(feature = "serde", serde(skip))]
     pub cancel_token: Option<Arc<AtomicBool>>,
 
+    /// Keep the previous version of replaced destination files (default: `None`)
+    ///
+    /// See [`BackupMode`] for the available naming schemes.
+    pub backup: Option<BackupMode>,
+
+    /// Earlier snapshots to hard-link unchanged files from (default: empty)
+    ///
+    /// When the file at the same relative path in one of these directories
+    /// is unchanged, it is hard-linked into the destination instead of being
+    /// copied, like `rsync --link-dest`. Directories are tried in order.
+    pub link_dest: Vec<PathBuf>,
+
+    /// Reference trees whose unchanged files are not copied (default: empty)
+    ///
+    /// When the file at the same relative path in one of these directories
+    /// is unchanged, the source file is skipped, so the destination only
+    /// receives the delta, like `rsync --compare-dest`.
+    pub compare_dest: Vec<PathBuf>,
+
+    /// Compare file contents instead of size and mtime when matching
+    /// reference files (default: `false`)
+    pub checksum: bool,
+
+    /// Minimum size for block-level delta updates (default: `None`, disabled)
+    ///
+    /// When a file of at least this many bytes replaces an existing regular
+    /// file (under [`OnConflict::Overwrite`] or [`OnConflict::UpdateNewer`]),
+    /// the old destination is cloned into the temp file and only the blocks
+    /// that differ from the source are written. Cloning uses Linux `FICLONE`
+    /// (Btrfs, XFS, NFS 4.2); where the destination cannot be cloned, the
+    /// file is copied in full.
+    pub delta_threshold: Option<u64>,
+
+    /// Block size in bytes for delta updates (default: 1 MiB)
+    pub delta_block_size: usize,
+
+    /// Minimum size for resumable copies (default: `None`, disabled)
+    ///
+    /// Files of at least this many bytes are copied into a partial file
+    /// `.NAME.parcopy-partial` next to the destination, which is synced every
+    /// 128 MiB while a sidecar records the source's size, mtime, and inode
+    /// and the offset reached. If the copy is interrupted (cancelled, out of
+    /// space, crashed), the partial file is kept and the next copy of the
+    /// unchanged source continues from that offset. Transactional copies and
+    /// files getting a [delta update](Self::delta_threshold) are not resumable.
+    pub resume_threshold: Option<u64>,
+
+    /// What to do when a source file changes while it is being copied
+    /// (default: [`SourceChangePolicy::Warn`])
+    pub on_source_change: SourceChangePolicy,
+
+    /// Publish directory copies atomically (default: `false`)
+    ///
+    /// [`copy_dir`](crate::copy_dir) builds the whole tree in a hidden staging
+    /// directory next to the destination and swaps it into place once every
+    /// file is copied and synced, so readers see either the old tree or the
+    /// new one, never a mix. Files already in the destination are hard-linked
+    /// into the staging tree first, so conflict policies apply as usual.
+    pub atomic_publish: bool,
+
+    /// Undo a failed directory copy (default: `false`)
+    ///
+    /// When [`copy_dir`](crate::copy_dir) fails, every file, symlink, and
+    /// directory the run created is removed and every entry it replaced is
+    /// restored; the failure is returned as
+    /// [`Error::RolledBack`](crate::Error::RolledBack). Pre-existing content
+    /// is left alone. Backups written for [`backup`](Self::backup) are kept.
+    pub transactional: bool,
+
+    /// Read back each copied file and compare it with the source (default: `false`)
+    ///
+    /// The source is hashed (SHA-256) while it is copied, and the copy is
+    /// hashed before it replaces the destination, bypassing the page cache
+    /// where possible. A mismatch fails the file with
+    /// [`Error::VerificationFailed`](crate::Error::VerificationFailed) and
+    /// discards the bad copy, keeping any existing destination.
+    pub verify: bool,
+
+    /// Compute the SHA-256 digest of each copied file (default: `false`)
+    ///
+    /// The digest is computed from the bytes as they are copied where
+    /// possible, and reported in
+    /// [`CopyStats::digest`](crate::CopyStats::digest) for single-file copies
+    /// and in [`ItemReport::digest`](crate::ItemReport::digest). Files are
+    /// also hashed whenever [`verify`](Self::verify) or
+    /// [`manifest`](Self::manifest) is set.
+    pub digests: bool,
+
+    /// Checksum manifest to list every copied file in (default: `None`)
+    ///
+    /// Each file copied or hard-linked into the destination is added with its
+    /// SHA-256 digest, computed from the bytes as they are copied where
+    /// possible. The manifest is shared, so one manifest can cover several
+    /// runs; call [`Manifest::finish`] to write it.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub manifest: Option<Arc<Manifest>>,
+
+    /// Link duplicates of files already written in the same run (default: `None`)
+    ///
+    /// A file whose size matches a file copied earlier in the run is hashed
+    /// (SHA-256) before it is copied; if the contents match, it is linked to
+    /// the earlier copy as set by [`DedupMode`] instead of written again. The
+    /// bytes not written are counted in
+    /// [`CopyStats::bytes_deduplicated`](crate::CopyStats::bytes_deduplicated).
+    /// Identical files copied at the same time may both be written.
+    pub dedup: Option<DedupMode>,
+
+    /// Journal of completed files to resume from (default: `None`)
+    ///
+    /// Files the journal lists as copied from an unchanged source are skipped
+    /// without a `stat` of the destination, and every file copied is added
+    /// to it. See [`Journal`] for when the journal is not used.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub journal: Option<Arc<Journal>>,
+
+    /// Remove temp files of earlier runs from the destination first (default: `None`)
+    ///
+    /// Before a directory is copied or moved into an existing destination,
+    /// temporary files that crashed runs left under it and that were last
+    /// modified at least this long ago are removed, as by
+    /// [`clean_temp_files`](crate::clean_temp_files), except that partial
+    /// files of resumable copies are kept for the copy to resume from.
+    /// Failures to remove a file are reported as warnings and do not fail
+    /// the copy.
+    pub clean_stale_temps: Option<Duration>,
+
+    /// How file contents are copied (default: [`CopyEngine::Standard`])
+    ///
+    /// Reflinks, delta updates, and resumable copies of large files are
+    /// used as usual with either engine; the engine copies everything else.
+    pub engine: CopyEngine,
+
+    /// Minimum size for copying a file in parallel chunks (default: `None`, disabled)
+    ///
+    /// Files of at least this many bytes are split into
+    /// [`parallel_chunk_size`](Self::parallel_chunk_size) ranges that the
+    /// threads of the current rayon pool (a directory copy's or a
+    /// [`CopySession`](crate::CopySession)'s, otherwise the global pool) copy
+    /// concurrently into the same temp file, with
+    /// `copy_file_range` at explicit offsets on Linux and positioned reads
+    /// and writes elsewhere. This multiplies the throughput of a single huge
+    /// file on parallel filesystems and NFS with `nconnect`. Reflinks, delta
+    /// updates, and resumable copies take precedence.
+    pub parallel_chunk_threshold: Option<u64>,
+
+    /// Chunk size in bytes for parallel copies of large files (default: 64 MiB)
+    pub parallel_chunk_size: u64,
+
+    /// Bandwidth limit in bytes per second shared by every worker (default: `None`)
+    ///
+    /// File data is copied in slices of at most 1 MiB that each wait for
+    /// their share of the limit, covering `copy_file_range`, parallel chunks,
+    /// resumable copies, and the blocks written by delta updates. Reflinks
+    /// and hard links move no data and are not limited. The io_uring engine
+    /// is not used while a limit is set. The limiter is shared, so options
+    /// cloned from one another, and runs given them, share one budget.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub bwlimit: Option<Arc<RateLimiter>>,
+
+    /// Limit on metadata operations per second shared by every worker (default: `None`)
+    ///
+    /// Each file's stats, temp file creation, link, rename, chmod, and
+    /// utimes, and each directory's existence check, creation, and
+    /// permission copy wait for a token first. This keeps a parallel
+    /// small-file copy from overloading the metadata path of an NFS server.
+    /// Like [`bwlimit`](Self::bwlimit), the limiter is shared by options
+    /// cloned from one another.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub metadata_limit: Option<Arc<RateLimiter>>,
+
     /// Callback for warnings (optional)
     ///
     /// If not set and `tracing` feature is enabled, warnings are logged via tracing.
@@ -197,6 +518,7 @@ impl Default for CopyOptions {
     fn default() -> Self {
         Self {
             parallel: 16,
+            auto_parallel: false,
             on_conflict: OnConflict::Skip,
             preserve_permissions: true,
             preserve_dir_permissions: true,
@@ -208,6 +530,27 @@ impl Default for CopyOptions {
             preserve_timestamps: true,
             preserve_windows_attributes: true,
             cancel_token: None,
+            backup: None,
+            link_dest: Vec::new(),
+            compare_dest: Vec::new(),
+            checksum: false,
+            delta_threshold: None,
+            delta_block_size: DEFAULT_DELTA_BLOCK_SIZE,
+            resume_threshold: None,
+            on_source_change: SourceChangePolicy::Warn,
+            atomic_publish: false,
+            transactional: false,
+            verify: false,
+            digests: false,
+            manifest: None,
+            dedup: None,
+            journal: None,
+            clean_stale_temps: None,
+            engine: CopyEngine::Standard,
+            parallel_chunk_threshold: None,
+            parallel_chunk_size: DEFAULT_PARALLEL_CHUNK_SIZE,
+            bwlimit: None,
+            metadata_limit: None,
             warn_handler: None,
             verbose_handler: None,
         }
@@ -252,6 +595,25 @@ impl CopyOptions {
         self
     }
 
+    /// Tune the number of files copied at once, up to `max`
+    ///
+    /// Sets [`parallel`](Self::parallel) to `max` (at least 1). See
+    /// [`auto_parallel`](Self::auto_parallel).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use parcopy::CopyOptions;
+    ///
+    /// let options = CopyOptions::default().with_auto_parallel(64);
+    /// ```
+    #[must_use]
+    pub fn with_auto_parallel(mut self, max: usize) -> Self {
+        self.parallel = max.max(1);
+        self.auto_parallel = true;
+        self
+    }
+
     /// Set the conflict behavior
     #[must_use]
     pub fn with_on_conflict(mut self, on_conflict: OnConflict) -> Self {
@@ -336,6 +698,288 @@ impl CopyOptions {
         self
     }
 
+    /// Back up destination files before they are replaced
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use parcopy::{BackupMode, CopyOptions, OnConflict};
+    ///
+    /// let options = CopyOptions::default()
+    ///     .with_on_conflict(OnConflict::Overwrite)
+    ///     .with_backup(BackupMode::Numbered);
+    /// ```
+    #[must_use]
+    pub fn with_backup(mut self, mode: BackupMode) -> Self {
+        self.backup = Some(mode);
+        self
+    }
+
+    /// Add a reference snapshot to hard-link unchanged files from
+    ///
+    /// May be called several times; earlier snapshots take precedence.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use parcopy::CopyOptions;
+    ///
+    /// let options = CopyOptions::default()
+    ///     .with_link_dest("/backups/2026-10-17");
+    /// ```
+    #[must_use]
+    pub fn with_link_dest(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.link_dest.push(dir.into());
+        self
+    }
+
+    /// Add a reference tree whose unchanged files are skipped
+    ///
+    /// May be called several times.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use parcopy::CopyOptions;
+    ///
+    /// let options = CopyOptions::default()
+    ///     .with_compare_dest("/releases/1.4.0");
+    /// ```
+    #[must_use]
+    pub fn with_compare_dest(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.compare_dest.push(dir.into());
+        self
+    }
+
+    /// Match reference files by contents instead of size and mtime
+    #[must_use]
+    pub fn with_checksum(mut self) -> Self {
+        self.checksum = true;
+        self
+    }
+
+    /// Update large replaced files block by block
+    ///
+    /// Files of at least `min_size` bytes that replace an existing file only
+    /// have their changed blocks written. See
+    /// [`delta_threshold`](Self::delta_threshold).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use parcopy::{CopyOptions, OnConflict};
+    ///
+    /// let options = CopyOptions::default()
+    ///     .with_on_conflict(OnConflict::Overwrite)
+    ///     .with_delta(64 * 1024 * 1024);
+    /// ```
+    #[must_use]
+    pub fn with_delta(mut self, min_size: u64) -> Self {
+        self.delta_threshold = Some(min_size);
+        self
+    }
+
+    /// Set the block size for delta updates
+    ///
+    /// Value is clamped to at least 1.
+    #[must_use]
+    pub fn with_delta_block_size(mut self, size: usize) -> Self {
+        self.delta_block_size = size.max(1);
+        self
+    }
+
+    /// Make copies of large files resumable after an interruption
+    ///
+    /// Files of at least `min_size` bytes keep a partial copy that the next
+    /// run continues from. See [`resume_threshold`](Self::resume_threshold).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use parcopy::CopyOptions;
+    ///
+    /// let options = CopyOptions::default().with_resume(1024 * 1024 * 1024);
+    /// ```
+    #[must_use]
+    pub fn with_resume(mut self, min_size: u64) -> Self {
+        self.resume_threshold = Some(min_size);
+        self
+    }
+
+    /// Set what happens when a source file changes while it is being copied
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use parcopy::{CopyOptions, SourceChangePolicy};
+    ///
+    /// let options = CopyOptions::default()
+    ///     .with_on_source_change(SourceChangePolicy::Retry { attempts: 3 });
+    /// ```
+    #[must_use]
+    pub fn with_on_source_change(mut self, policy: SourceChangePolicy) -> Self {
+        self.on_source_change = policy;
+        self
+    }
+
+    /// Publish directory copies atomically via a staging directory
+    ///
+    /// See [`atomic_publish`](Self::atomic_publish).
+    #[must_use]
+    pub fn with_atomic_publish(mut self) -> Self {
+        self.atomic_publish = true;
+        self
+    }
+
+    /// Roll back everything a failed directory copy changed
+    ///
+    /// See [`transactional`](Self::transactional).
+    #[must_use]
+    pub fn with_transactional(mut self) -> Self {
+        self.transactional = true;
+        self
+    }
+
+    /// Read back and check every copied file
+    ///
+    /// See [`verify`](Self::verify).
+    #[must_use]
+    pub fn with_verify(mut self) -> Self {
+        self.verify = true;
+        self
+    }
+
+    /// Compute the digest of every copied file
+    ///
+    /// See [`digests`](Self::digests).
+    #[must_use]
+    pub fn with_digests(mut self) -> Self {
+        self.digests = true;
+        self
+    }
+
+    /// List every copied file in a checksum manifest
+    ///
+    /// See [`manifest`](Self::manifest).
+    #[must_use]
+    pub fn with_manifest(mut self, manifest: Arc<Manifest>) -> Self {
+        self.manifest = Some(manifest);
+        self
+    }
+
+    /// Link duplicate files to their first copy in the run
+    ///
+    /// See [`dedup`](Self::dedup).
+    #[must_use]
+    pub fn with_dedup(mut self, mode: DedupMode) -> Self {
+        self.dedup = Some(mode);
+        self
+    }
+
+    /// Skip files a completion journal lists as copied, and journal new copies
+    ///
+    /// See [`journal`](Self::journal).
+    #[must_use]
+    pub fn with_journal(mut self, journal: Arc<Journal>) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Remove temp files older than `min_age` that earlier runs left in the
+    /// destination
+    ///
+    /// See [`clean_stale_temps`](Self::clean_stale_temps).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use parcopy::CopyOptions;
+    /// use std::time::Duration;
+    ///
+    /// let options = CopyOptions::default().with_clean_stale_temps(Duration::from_secs(24 * 3600));
+    /// ```
+    #[must_use]
+    pub fn with_clean_stale_temps(mut self, min_age: Duration) -> Self {
+        self.clean_stale_temps = Some(min_age);
+        self
+    }
+
+    /// Set how file contents are copied
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use parcopy::{CopyEngine, CopyOptions};
+    ///
+    /// let options = CopyOptions::default().with_engine(CopyEngine::IoUring);
+    /// ```
+    #[must_use]
+    pub fn with_engine(mut self, engine: CopyEngine) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    /// Copy files of at least `min_size` bytes in parallel chunks
+    ///
+    /// See [`parallel_chunk_threshold`](Self::parallel_chunk_threshold).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use parcopy::CopyOptions;
+    ///
+    /// let options = CopyOptions::default().with_parallel_chunks(1024 * 1024 * 1024);
+    /// ```
+    #[must_use]
+    pub fn with_parallel_chunks(mut self, min_size: u64) -> Self {
+        self.parallel_chunk_threshold = Some(min_size);
+        self
+    }
+
+    /// Set the chunk size for parallel copies of large files
+    ///
+    /// Value is clamped to at least 1.
+    #[must_use]
+    pub fn with_parallel_chunk_size(mut self, size: u64) -> Self {
+        self.parallel_chunk_size = size.max(1);
+        self
+    }
+
+    /// Limit the bandwidth of file data to `bytes_per_second` (like `rsync --bwlimit`)
+    ///
+    /// See [`bwlimit`](Self::bwlimit).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use parcopy::CopyOptions;
+    ///
+    /// // 200 MiB/s across all workers
+    /// let options = CopyOptions::default().with_bwlimit(200 * 1024 * 1024);
+    /// ```
+    #[must_use]
+    pub fn with_bwlimit(mut self, bytes_per_second: u64) -> Self {
+        self.bwlimit = Some(Arc::new(RateLimiter::new(bytes_per_second)));
+        self
+    }
+
+    /// Limit metadata operations to `ops_per_second` across all workers
+    ///
+    /// See [`metadata_limit`](Self::metadata_limit).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use parcopy::CopyOptions;
+    ///
+    /// let options = CopyOptions::default().with_metadata_limit(2000);
+    /// ```
+    #[must_use]
+    pub fn with_metadata_limit(mut self, ops_per_second: u64) -> Self {
+        self.metadata_limit = Some(Arc::new(RateLimiter::new(ops_per_second)));
+        self
+    }
+
     /// Check if the operation has been cancelled.
     ///
     /// Returns `false` if no cancellation token is set.
@@ -345,6 +989,19 @@ impl CopyOptions {
             .is_some_and(|t| t.load(Ordering::Relaxed))
     }
 
+    /// Whether copied files are hashed, for verification, a manifest, reporting, or dedup.
+    pub(crate) fn hashes_files(&self) -> bool {
+        self.verify || self.digests || self.manifest.is_some() || self.dedup.is_some()
+    }
+
+    /// Wait until `ops` metadata operations fit within [`metadata_limit`](Self::metadata_limit).
+    pub(crate) fn throttle_metadata(&self, ops: u64) {
+        if let Some(limiter) = &self.metadata_limit {
+            // A cancelled wait ends early; the copy notices the cancellation next
+            let _ = limiter.acquire_cancellable(ops, self.cancel_token.as_deref());
+        }
+    }
+
     pub(crate) fn warn(&self, msg: &str) {
         if let Some(handler) = self.warn_handler {
             handler(msg);
@@ -3,16 +3,33 @@
 //! This module provides a two-stage workflow:
 //! 1. Build a [`CopyPlan`] from sources and destination.
 //! 2. Execute the plan and receive a structured [`CopyReport`].
-
-use crate::{CopyOptions, CopyStats, Error, ErrorCode, OnConflict, Result, copy_dir, copy_file};
+//!
+//! With the `serde` feature, a [`CopyPlan`] can be saved and executed later,
+//! e.g. once it has been reviewed. Its serialized form carries
+//! [`PLAN_SCHEMA_VERSION`], and [`execute_plan`] checks every item against
+//! the filesystem before running it, so a plan runs as planned or not at all.
+
+use crate::copy::{MatchRule, copy_symlink_with_stats, find_reference_match};
+use crate::{
+    BackupMode, CopyEngine, CopyOptions, CopyStats, DedupMode, Digest, Error, ErrorCode, Journal,
+    Manifest, OnConflict, RateLimiter, Result, SourceChangePolicy, VerifyMismatch, copy_dir,
+    copy_file_with_stats,
+};
+use std::borrow::Cow;
 use std::fs::Metadata;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Version of the serialized [`CopyPlan`] schema.
+///
+/// Deserializing a plan written with another version fails.
+pub const PLAN_SCHEMA_VERSION: u32 = 1;
 
 /// Copy behavior policy (semantic behavior, not runtime tuning).
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CopyPolicy {
     pub on_conflict: OnConflict,
     pub preserve_permissions: bool,
@@ -24,11 +41,28 @@ pub struct CopyPolicy {
     pub warn_escaping_symlinks: bool,
     pub block_escaping_symlinks: bool,
     pub max_depth: Option<usize>,
+    pub backup: Option<BackupMode>,
+    pub link_dest: Vec<PathBuf>,
+    pub compare_dest: Vec<PathBuf>,
+    pub checksum: bool,
+    pub delta_threshold: Option<u64>,
+    pub delta_block_size: usize,
+    pub resume_threshold: Option<u64>,
+    pub on_source_change: SourceChangePolicy,
+    pub atomic_publish: bool,
+    pub transactional: bool,
+    pub verify: bool,
+    pub dedup: Option<DedupMode>,
 }
 
 impl Default for CopyPolicy {
     fn default() -> Self {
-        let options = CopyOptions::default();
+        Self::from(&CopyOptions::default())
+    }
+}
+
+impl From<&CopyOptions> for CopyPolicy {
+    fn from(options: &CopyOptions) -> Self {
         Self {
             on_conflict: options.on_conflict,
             preserve_permissions: options.preserve_permissions,
@@ -40,37 +74,128 @@ impl Default for CopyPolicy {
             warn_escaping_symlinks: options.warn_escaping_symlinks,
             block_escaping_symlinks: options.block_escaping_symlinks,
             max_depth: options.max_depth,
+            backup: options.backup.clone(),
+            link_dest: options.link_dest.clone(),
+            compare_dest: options.compare_dest.clone(),
+            checksum: options.checksum,
+            delta_threshold: options.delta_threshold,
+            delta_block_size: options.delta_block_size,
+            resume_threshold: options.resume_threshold,
+            on_source_change: options.on_source_change,
+            atomic_publish: options.atomic_publish,
+            transactional: options.transactional,
+            verify: options.verify,
+            dedup: options.dedup,
         }
     }
 }
 
 /// Runtime/performance controls for execution.
+///
+/// The cancellation token, manifest, and journal are not serialized; set
+/// them again on a deserialized plan.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RuntimeOptions {
     pub parallel: usize,
+    /// Tune the number of files copied at once, up to `parallel`
+    pub auto_parallel: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub cancel_token: Option<Arc<AtomicBool>>,
+    /// Checksum manifest to list copied files in
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub manifest: Option<Arc<Manifest>>,
+    /// Report the SHA-256 digest of each copied file item
+    pub digests: bool,
+    /// Completion journal to skip finished files with and record copies in
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub journal: Option<Arc<Journal>>,
+    /// Minimum age of leftover temp files to remove from the destination first
+    pub clean_stale_temps: Option<Duration>,
+    /// How file contents are copied
+    pub engine: CopyEngine,
+    /// Minimum size for copying a file in parallel chunks
+    pub parallel_chunk_threshold: Option<u64>,
+    /// Chunk size for parallel copies of large files
+    pub parallel_chunk_size: u64,
+    /// Bandwidth limit in bytes per second shared by every item
+    pub bwlimit: Option<u64>,
+    /// Limit on metadata operations per second shared by every item
+    pub metadata_limit: Option<u64>,
 }
 
 impl Default for RuntimeOptions {
     fn default() -> Self {
         Self {
             parallel: 16,
+            auto_parallel: false,
             cancel_token: None,
+            manifest: None,
+            digests: false,
+            journal: None,
+            clean_stale_temps: None,
+            engine: CopyEngine::Standard,
+            parallel_chunk_threshold: None,
+            parallel_chunk_size: CopyOptions::default().parallel_chunk_size,
+            bwlimit: None,
+            metadata_limit: None,
+        }
+    }
+}
+
+impl From<&CopyOptions> for RuntimeOptions {
+    fn from(options: &CopyOptions) -> Self {
+        Self {
+            parallel: options.parallel,
+            auto_parallel: options.auto_parallel,
+            cancel_token: options.cancel_token.clone(),
+            manifest: options.manifest.clone(),
+            digests: options.digests,
+            journal: options.journal.clone(),
+            clean_stale_temps: options.clean_stale_temps,
+            engine: options.engine,
+            parallel_chunk_threshold: options.parallel_chunk_threshold,
+            parallel_chunk_size: options.parallel_chunk_size,
+            bwlimit: options.bwlimit.as_ref().map(|limiter| limiter.rate()),
+            metadata_limit: options
+                .metadata_limit
+                .as_ref()
+                .map(|limiter| limiter.rate()),
         }
     }
 }
 
 /// Planned action for one item.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum PlanAction {
     Copy,
     Skip,
     Overwrite,
     Error,
+    /// Hard-link an unchanged file from a `link_dest` snapshot.
+    Link,
+}
+
+impl PlanAction {
+    /// Stable wire-format value, as used in serialized plans.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Copy => "copy",
+            Self::Skip => "skip",
+            Self::Overwrite => "overwrite",
+            Self::Error => "error",
+            Self::Link => "link",
+        }
+    }
 }
 
 /// Reason for a planned action.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum PlanReason {
     NotExists,
     Exists,
@@ -78,17 +203,44 @@ pub enum PlanReason {
     Filtered,
     PolicyBlocked,
     InvalidInput,
+    /// The file is unchanged in a `link_dest` snapshot.
+    LinkDestMatch,
+    /// The file is unchanged in a `compare_dest` tree.
+    CompareDestMatch,
+}
+
+impl PlanReason {
+    /// Stable wire-format value, as used in serialized plans.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::NotExists => "not_exists",
+            Self::Exists => "exists",
+            Self::NewerOrSame => "newer_or_same",
+            Self::Filtered => "filtered",
+            Self::PolicyBlocked => "policy_blocked",
+            Self::InvalidInput => "invalid_input",
+            Self::LinkDestMatch => "link_dest_match",
+            Self::CompareDestMatch => "compare_dest_match",
+        }
+    }
 }
 
 /// Source item kind in a plan.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum PlannedItemKind {
     File,
     Directory,
+    /// A symlink recreated as-is (only planned for the remaining work of a
+    /// run that preserves symlinks, see [`RemainingWork`](crate::RemainingWork))
+    Symlink,
 }
 
 /// One planned copy item.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PlannedItem {
     pub source: PathBuf,
     pub destination: PathBuf,
@@ -96,10 +248,20 @@ pub struct PlannedItem {
     pub action: PlanAction,
     pub reason: PlanReason,
     pub estimated_bytes: u64,
+    /// Modification time of the source when the item was planned
+    pub source_mtime: Option<SystemTime>,
 }
 
 /// A reusable copy plan generated by [`plan_copy`].
+///
+/// With the `serde` feature, plans serialize with a `schema_version` field
+/// set to [`PLAN_SCHEMA_VERSION`].
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "SerializedPlan", try_from = "SerializedPlan")
+)]
 pub struct CopyPlan {
     pub destination_root: PathBuf,
     pub policy: CopyPolicy,
@@ -107,10 +269,55 @@ pub struct CopyPlan {
     pub items: Vec<PlannedItem>,
 }
 
+/// Serialized form of a [`CopyPlan`], tagged with its schema version.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedPlan {
+    schema_version: u32,
+    destination_root: PathBuf,
+    policy: CopyPolicy,
+    runtime: RuntimeOptions,
+    items: Vec<PlannedItem>,
+}
+
+#[cfg(feature = "serde")]
+impl From<CopyPlan> for SerializedPlan {
+    fn from(plan: CopyPlan) -> Self {
+        Self {
+            schema_version: PLAN_SCHEMA_VERSION,
+            destination_root: plan.destination_root,
+            policy: plan.policy,
+            runtime: plan.runtime,
+            items: plan.items,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SerializedPlan> for CopyPlan {
+    type Error = String;
+
+    fn try_from(plan: SerializedPlan) -> std::result::Result<Self, String> {
+        if plan.schema_version != PLAN_SCHEMA_VERSION {
+            return Err(format!(
+                "unsupported plan schema version {} (expected {PLAN_SCHEMA_VERSION})",
+                plan.schema_version
+            ));
+        }
+        Ok(Self {
+            destination_root: plan.destination_root,
+            policy: plan.policy,
+            runtime: plan.runtime,
+            items: plan.items,
+        })
+    }
+}
+
 /// Outcome for one item in [`CopyReport`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ItemOutcome {
     Copied,
+    Linked,
     Skipped,
     Failed,
 }
@@ -124,6 +331,10 @@ pub struct ItemReport {
     pub bytes_copied: Option<u64>,
     pub error_code: Option<ErrorCode>,
     pub error_message: Option<String>,
+    /// Files of this item whose copies failed read-back verification
+    pub verify_mismatches: Vec<VerifyMismatch>,
+    /// SHA-256 digest of a copied or linked file item, when files are hashed
+    pub digest: Option<Digest>,
 }
 
 /// Structured execution report returned by [`execute_plan`].
@@ -157,6 +368,8 @@ pub enum CopyEvent {
         source: PathBuf,
         destination: PathBuf,
         bytes_copied: u64,
+        /// SHA-256 digest of a file item, when files are hashed
+        digest: Option<Digest>,
     },
     ItemSkipped {
         source: PathBuf,
@@ -221,22 +434,20 @@ pub fn plan_copy(
 
         let destination_path =
             resolve_destination_path(&source, &destination, destination_is_dir, multi_source)?;
-        let (action, reason) =
-            classify_plan_action(&source_metadata, &destination_path, policy.on_conflict);
         let kind = if source_metadata.is_dir() {
             PlannedItemKind::Directory
         } else {
             PlannedItemKind::File
         };
 
-        items.push(PlannedItem {
+        items.push(planned_item(
             source,
-            destination: destination_path,
+            &source_metadata,
+            destination_path,
+            &destination,
             kind,
-            action,
-            reason,
-            estimated_bytes: source_metadata.len(),
-        });
+            &policy,
+        ));
     }
 
     Ok(CopyPlan {
@@ -247,14 +458,98 @@ pub fn plan_copy(
     })
 }
 
+/// Plan copying `source`, of the given kind, to exactly `destination`.
+///
+/// `root` is the destination root of the plan, which the
+/// [`link_dest`](CopyPolicy::link_dest) and
+/// [`compare_dest`](CopyPolicy::compare_dest) trees mirror. The item is
+/// classified as by [`plan_copy`].
+///
+/// # Errors
+///
+/// Returns [`Error::SourceNotFound`] if `source` does not exist, or
+/// [`Error::Io`] if it cannot be read.
+pub fn plan_item(
+    source: &Path,
+    destination: &Path,
+    root: &Path,
+    kind: PlannedItemKind,
+    policy: &CopyPolicy,
+) -> Result<PlannedItem> {
+    let source_metadata = source_metadata(source, kind).map_err(|io| {
+        if io.kind() == std::io::ErrorKind::NotFound {
+            Error::SourceNotFound(source.to_path_buf())
+        } else {
+            Error::Io(io)
+        }
+    })?;
+    Ok(planned_item(
+        source.to_path_buf(),
+        &source_metadata,
+        destination.to_path_buf(),
+        root,
+        kind,
+        policy,
+    ))
+}
+
+fn planned_item(
+    source: PathBuf,
+    source_metadata: &Metadata,
+    destination: PathBuf,
+    root: &Path,
+    kind: PlannedItemKind,
+    policy: &CopyPolicy,
+) -> PlannedItem {
+    let (action, reason) = match kind {
+        PlannedItemKind::Symlink => classify_symlink(&destination, policy.on_conflict),
+        _ => classify_plan_action(&source, source_metadata, &destination, root, policy),
+    };
+    PlannedItem {
+        source,
+        destination,
+        kind,
+        action,
+        reason,
+        estimated_bytes: source_metadata.len(),
+        source_mtime: source_metadata.modified().ok(),
+    }
+}
+
+/// Metadata of a planned source: of the link itself for symlink items.
+fn source_metadata(source: &Path, kind: PlannedItemKind) -> std::io::Result<Metadata> {
+    match kind {
+        PlannedItemKind::Symlink => source.symlink_metadata(),
+        PlannedItemKind::File | PlannedItemKind::Directory => source.metadata(),
+    }
+}
+
 /// Execute a copy plan and return a structured report.
 ///
 /// This function continues through all planned items and records failures
 /// item-by-item in the returned report.
-pub fn execute_plan(plan: &CopyPlan, mut handler: Option<&mut dyn EventHandler>) -> CopyReport {
+///
+/// Before an item runs, its source and destination are checked again. An
+/// item whose source is gone, changed kind, size, or mtime, or whose
+/// destination now calls for another action than planned is not run and
+/// fails with [`Error::PlanDrift`].
+pub fn execute_plan(plan: &CopyPlan, handler: Option<&mut dyn EventHandler>) -> CopyReport {
+    execute_plan_with(plan, handler, &copy_options_from_plan(plan), None)
+}
+
+/// Execute `plan` with `options`, copying each item in `pool` if one is given.
+pub(crate) fn execute_plan_with(
+    plan: &CopyPlan,
+    mut handler: Option<&mut dyn EventHandler>,
+    options: &CopyOptions,
+    pool: Option<&rayon::ThreadPool>,
+) -> CopyReport {
     let mut report = CopyReport::default();
     let start = Instant::now();
-    let options = copy_options_from_plan(plan);
+    let run = |copy: &(dyn Fn() -> Result<CopyStats> + Sync)| match pool {
+        Some(pool) => pool.install(copy),
+        None => copy(),
+    };
 
     for item in &plan.items {
         emit_event(&mut handler, CopyEvent::ItemPlanned { item: item.clone() });
@@ -266,70 +561,120 @@ pub fn execute_plan(plan: &CopyPlan, mut handler: Option<&mut dyn EventHandler>)
             },
         );
 
+        if let Some(reason) = plan_drift(item, &plan.policy, &plan.destination_root) {
+            let error = Error::PlanDrift {
+                path: item.source.clone(),
+                reason,
+            };
+            let code = error.code();
+            report.items.push(ItemReport {
+                source: item.source.clone(),
+                destination: item.destination.clone(),
+                outcome: ItemOutcome::Failed,
+                bytes_copied: None,
+                error_code: Some(code),
+                error_message: Some(error.to_string()),
+                verify_mismatches: Vec::new(),
+                digest: None,
+            });
+            emit_event(
+                &mut handler,
+                CopyEvent::ItemFailed {
+                    source: item.source.clone(),
+                    destination: item.destination.clone(),
+                    error_code: code,
+                    error_message: error.to_string(),
+                },
+            );
+            continue;
+        }
+
         match item.kind {
-            PlannedItemKind::File => match copy_file(&item.source, &item.destination, &options) {
-                Ok(true) => {
-                    report.stats.files_copied += 1;
-                    report.stats.bytes_copied += item.estimated_bytes;
-                    report.items.push(ItemReport {
-                        source: item.source.clone(),
-                        destination: item.destination.clone(),
-                        outcome: ItemOutcome::Copied,
-                        bytes_copied: Some(item.estimated_bytes),
-                        error_code: None,
-                        error_message: None,
-                    });
-                    emit_event(
-                        &mut handler,
-                        CopyEvent::ItemCompleted {
+            PlannedItemKind::File | PlannedItemKind::Symlink => {
+                let result = if item.kind == PlannedItemKind::Symlink {
+                    copy_symlink_with_stats(&item.source, &item.destination, options)
+                } else {
+                    let options = item_options(options, item, &plan.destination_root);
+                    run(&|| copy_file_with_stats(&item.source, &item.destination, &options))
+                };
+                match result {
+                    Ok(stats) if stats.files_skipped == 0 && stats.symlinks_skipped == 0 => {
+                        let outcome = if stats.files_linked > 0 {
+                            ItemOutcome::Linked
+                        } else {
+                            ItemOutcome::Copied
+                        };
+                        let bytes_copied = stats.bytes_copied;
+                        let digest = stats.digest;
+                        report.stats = merge_stats(report.stats, stats);
+                        report.items.push(ItemReport {
                             source: item.source.clone(),
                             destination: item.destination.clone(),
-                            bytes_copied: item.estimated_bytes,
-                        },
-                    );
-                }
-                Ok(false) => {
-                    report.stats.files_skipped += 1;
-                    report.items.push(ItemReport {
-                        source: item.source.clone(),
-                        destination: item.destination.clone(),
-                        outcome: ItemOutcome::Skipped,
-                        bytes_copied: None,
-                        error_code: None,
-                        error_message: None,
-                    });
-                    emit_event(
-                        &mut handler,
-                        CopyEvent::ItemSkipped {
+                            outcome,
+                            bytes_copied: Some(bytes_copied),
+                            error_code: None,
+                            error_message: None,
+                            verify_mismatches: Vec::new(),
+                            digest,
+                        });
+                        emit_event(
+                            &mut handler,
+                            CopyEvent::ItemCompleted {
+                                source: item.source.clone(),
+                                destination: item.destination.clone(),
+                                bytes_copied,
+                                digest,
+                            },
+                        );
+                    }
+                    Ok(stats) => {
+                        report.stats = merge_stats(report.stats, stats);
+                        report.items.push(ItemReport {
                             source: item.source.clone(),
                             destination: item.destination.clone(),
-                            reason: item.reason,
-                        },
-                    );
-                }
-                Err(error) => {
-                    let code = error.code();
-                    report.items.push(ItemReport {
-                        source: item.source.clone(),
-                        destination: item.destination.clone(),
-                        outcome: ItemOutcome::Failed,
-                        bytes_copied: None,
-                        error_code: Some(code),
-                        error_message: Some(error.to_string()),
-                    });
-                    emit_event(
-                        &mut handler,
-                        CopyEvent::ItemFailed {
+                            outcome: ItemOutcome::Skipped,
+                            bytes_copied: None,
+                            error_code: None,
+                            error_message: None,
+                            verify_mismatches: Vec::new(),
+                            digest: None,
+                        });
+                        emit_event(
+                            &mut handler,
+                            CopyEvent::ItemSkipped {
+                                source: item.source.clone(),
+                                destination: item.destination.clone(),
+                                reason: item.reason,
+                            },
+                        );
+                    }
+                    Err(error) => {
+                        let code = error.code();
+                        report.items.push(ItemReport {
                             source: item.source.clone(),
                             destination: item.destination.clone(),
-                            error_code: code,
-                            error_message: error.to_string(),
-                        },
-                    );
+                            outcome: ItemOutcome::Failed,
+                            bytes_copied: None,
+                            error_code: Some(code),
+                            error_message: Some(error.to_string()),
+                            verify_mismatches: verify_mismatches(&error),
+                            digest: None,
+                        });
+                        emit_event(
+                            &mut handler,
+                            CopyEvent::ItemFailed {
+                                source: item.source.clone(),
+                                destination: item.destination.clone(),
+                                error_code: code,
+                                error_message: error.to_string(),
+                            },
+                        );
+                    }
                 }
-            },
+            }
             PlannedItemKind::Directory => {
-                match copy_dir(&item.source, &item.destination, &options) {
+                let options = item_options(options, item, &plan.destination_root);
+                match run(&|| copy_dir(&item.source, &item.destination, &options)) {
                     Ok(stats) => {
                         report.stats = merge_stats(report.stats, stats.clone());
                         report.items.push(ItemReport {
@@ -339,6 +684,8 @@ pub fn execute_plan(plan: &CopyPlan, mut handler: Option<&mut dyn EventHandler>)
                             bytes_copied: Some(stats.bytes_copied),
                             error_code: None,
                             error_message: None,
+                            verify_mismatches: Vec::new(),
+                            digest: None,
                         });
                         emit_event(
                             &mut handler,
@@ -346,6 +693,7 @@ pub fn execute_plan(plan: &CopyPlan, mut handler: Option<&mut dyn EventHandler>)
                                 source: item.source.clone(),
                                 destination: item.destination.clone(),
                                 bytes_copied: stats.bytes_copied,
+                                digest: None,
                             },
                         );
                     }
@@ -358,6 +706,8 @@ pub fn execute_plan(plan: &CopyPlan, mut handler: Option<&mut dyn EventHandler>)
                             bytes_copied: None,
                             error_code: Some(code),
                             error_message: Some(error.to_string()),
+                            verify_mismatches: verify_mismatches(&error),
+                            digest: None,
                         });
                         emit_event(
                             &mut handler,
@@ -378,6 +728,88 @@ pub fn execute_plan(plan: &CopyPlan, mut handler: Option<&mut dyn EventHandler>)
     report
 }
 
+/// Options for copying `item` within the plan's destination tree at `root`.
+///
+/// Reference trees mirror the whole destination tree, while the copy
+/// functions look files up relative to the destination they are given (a
+/// directory, or the parent of a file). For an item copied below `root`, the
+/// reference trees are narrowed to the matching subtree.
+fn item_options<'o>(
+    options: &'o CopyOptions,
+    item: &PlannedItem,
+    root: &Path,
+) -> Cow<'o, CopyOptions> {
+    if options.link_dest.is_empty() && options.compare_dest.is_empty() {
+        return Cow::Borrowed(options);
+    }
+    let base = match item.kind {
+        PlannedItemKind::Directory => Some(item.destination.as_path()),
+        PlannedItemKind::File | PlannedItemKind::Symlink => item.destination.parent(),
+    };
+    let Some(rel) = base
+        .and_then(|base| base.strip_prefix(root).ok())
+        .filter(|rel| !rel.as_os_str().is_empty())
+    else {
+        return Cow::Borrowed(options);
+    };
+    let mut options = options.clone();
+    for reference in options
+        .link_dest
+        .iter_mut()
+        .chain(&mut options.compare_dest)
+    {
+        *reference = reference.join(rel);
+    }
+    Cow::Owned(options)
+}
+
+/// What changed about `item` since it was planned, if anything.
+fn plan_drift(item: &PlannedItem, policy: &CopyPolicy, root: &Path) -> Option<String> {
+    let metadata = match source_metadata(&item.source, item.kind) {
+        Ok(metadata) => metadata,
+        Err(e) => return Some(format!("source can no longer be read: {e}")),
+    };
+    let kind = if metadata.is_symlink() {
+        PlannedItemKind::Symlink
+    } else if metadata.is_dir() {
+        PlannedItemKind::Directory
+    } else {
+        PlannedItemKind::File
+    };
+    if (kind == PlannedItemKind::Symlink) != (item.kind == PlannedItemKind::Symlink) {
+        return Some("source is no longer a symlink".to_owned());
+    }
+    if kind != item.kind {
+        return Some("source changed between file and directory".to_owned());
+    }
+    // A directory's size says nothing about its contents
+    if kind != PlannedItemKind::Directory && metadata.len() != item.estimated_bytes {
+        return Some(format!(
+            "source size changed from {} to {} bytes",
+            item.estimated_bytes,
+            metadata.len()
+        ));
+    }
+    if item.source_mtime.is_some() && metadata.modified().ok() != item.source_mtime {
+        return Some("source was modified".to_owned());
+    }
+
+    let (action, reason) = match kind {
+        PlannedItemKind::Symlink => classify_symlink(&item.destination, policy.on_conflict),
+        _ => classify_plan_action(&item.source, &metadata, &item.destination, root, policy),
+    };
+    if (action, reason) != (item.action, item.reason) {
+        return Some(format!(
+            "destination changed: planned {} ({}), now {} ({})",
+            item.action.as_str(),
+            item.reason.as_str(),
+            action.as_str(),
+            reason.as_str()
+        ));
+    }
+    None
+}
+
 fn resolve_destination_path(
     source: &Path,
     destination: &Path,
@@ -398,6 +830,50 @@ fn resolve_destination_path(
 }
 
 fn classify_plan_action(
+    source: &Path,
+    source_metadata: &Metadata,
+    destination: &Path,
+    root: &Path,
+    policy: &CopyPolicy,
+) -> (PlanAction, PlanReason) {
+    let rule = MatchRule {
+        checksum: policy.checksum,
+        permissions: policy.preserve_permissions,
+    };
+    // Reference trees mirror the destination root; a file copied to the
+    // root itself is looked up by its name
+    let rel = destination
+        .strip_prefix(root)
+        .ok()
+        .filter(|rel| !rel.as_os_str().is_empty())
+        .or_else(|| destination.file_name().map(Path::new))
+        .unwrap_or(destination);
+    let is_file = source_metadata.is_file();
+
+    // Files unchanged in a compare_dest tree are not part of the delta
+    if is_file
+        && !policy.compare_dest.is_empty()
+        && find_reference_match(&policy.compare_dest, rel, source, source_metadata, rule).is_some()
+    {
+        return (PlanAction::Skip, PlanReason::CompareDestMatch);
+    }
+
+    let (action, reason) = classify_conflict(source_metadata, destination, policy.on_conflict);
+
+    // Files that will be written may instead be linked from a snapshot
+    let writes = matches!(action, PlanAction::Copy | PlanAction::Overwrite);
+    if writes
+        && is_file
+        && !policy.link_dest.is_empty()
+        && find_reference_match(&policy.link_dest, rel, source, source_metadata, rule).is_some()
+    {
+        return (PlanAction::Link, PlanReason::LinkDestMatch);
+    }
+
+    (action, reason)
+}
+
+fn classify_conflict(
     source_metadata: &Metadata,
     destination: &Path,
     on_conflict: OnConflict,
@@ -426,7 +902,20 @@ fn classify_plan_action(
     }
 }
 
-fn copy_options_from_plan(plan: &CopyPlan) -> CopyOptions {
+/// Classify recreating a symlink at `destination`, which is never linked or
+/// compared and treats [`OnConflict::UpdateNewer`] like [`OnConflict::Skip`].
+fn classify_symlink(destination: &Path, on_conflict: OnConflict) -> (PlanAction, PlanReason) {
+    if destination.symlink_metadata().is_err() {
+        return (PlanAction::Copy, PlanReason::NotExists);
+    }
+    match on_conflict {
+        OnConflict::Skip | OnConflict::UpdateNewer => (PlanAction::Skip, PlanReason::Exists),
+        OnConflict::Overwrite => (PlanAction::Overwrite, PlanReason::Exists),
+        OnConflict::Error => (PlanAction::Error, PlanReason::Exists),
+    }
+}
+
+pub(crate) fn copy_options_from_plan(plan: &CopyPlan) -> CopyOptions {
     let mut options = CopyOptions::default()
         .with_parallel(plan.runtime.parallel)
         .with_on_conflict(plan.policy.on_conflict);
@@ -440,10 +929,47 @@ fn copy_options_from_plan(plan: &CopyPlan) -> CopyOptions {
     options.warn_escaping_symlinks = plan.policy.warn_escaping_symlinks;
     options.block_escaping_symlinks = plan.policy.block_escaping_symlinks;
     options.max_depth = plan.policy.max_depth;
+    options.backup = plan.policy.backup.clone();
+    options.link_dest = plan.policy.link_dest.clone();
+    options.compare_dest = plan.policy.compare_dest.clone();
+    options.checksum = plan.policy.checksum;
+    options.delta_threshold = plan.policy.delta_threshold;
+    options.delta_block_size = plan.policy.delta_block_size;
+    options.resume_threshold = plan.policy.resume_threshold;
+    options.on_source_change = plan.policy.on_source_change;
+    options.atomic_publish = plan.policy.atomic_publish;
+    options.transactional = plan.policy.transactional;
+    options.verify = plan.policy.verify;
+    options.dedup = plan.policy.dedup;
+    options.auto_parallel = plan.runtime.auto_parallel;
     options.cancel_token = plan.runtime.cancel_token.clone();
+    options.manifest = plan.runtime.manifest.clone();
+    options.digests = plan.runtime.digests;
+    options.journal = plan.runtime.journal.clone();
+    options.clean_stale_temps = plan.runtime.clean_stale_temps;
+    options.engine = plan.runtime.engine;
+    options.parallel_chunk_threshold = plan.runtime.parallel_chunk_threshold;
+    options.parallel_chunk_size = plan.runtime.parallel_chunk_size;
+    options.bwlimit = plan
+        .runtime
+        .bwlimit
+        .map(|rate| Arc::new(RateLimiter::new(rate)));
+    options.metadata_limit = plan
+        .runtime
+        .metadata_limit
+        .map(|rate| Arc::new(RateLimiter::new(rate)));
     options
 }
 
+/// Verification failures carried by `error`, if it has any.
+fn verify_mismatches(error: &Error) -> Vec<VerifyMismatch> {
+    match error {
+        Error::VerificationFailed { mismatches } => mismatches.clone(),
+        Error::RolledBack { error, .. } => verify_mismatches(error),
+        _ => Vec::new(),
+    }
+}
+
 fn emit_event(handler: &mut Option<&mut dyn EventHandler>, event: CopyEvent) {
     if let Some(handler) = handler.as_deref_mut() {
         handler.on_event(&event);
@@ -452,11 +978,18 @@ fn emit_event(handler: &mut Option<&mut dyn EventHandler>, event: CopyEvent) {
 
 fn merge_stats(mut a: CopyStats, b: CopyStats) -> CopyStats {
     a.files_copied += b.files_copied;
+    a.files_linked += b.files_linked;
     a.files_skipped += b.files_skipped;
     a.symlinks_copied += b.symlinks_copied;
     a.symlinks_skipped += b.symlinks_skipped;
     a.dirs_created += b.dirs_created;
     a.bytes_copied += b.bytes_copied;
+    a.bytes_written += b.bytes_written;
+    a.renamed += b.renamed;
+    a.files_changed += b.files_changed;
+    a.files_deduplicated += b.files_deduplicated;
+    a.bytes_deduplicated += b.bytes_deduplicated;
+    a.parallel_levels.extend(b.parallel_levels);
     a
 }
 
@@ -554,6 +1087,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_execute_plan_reports_file_digest() {
+        let src_dir = tempfile::TempDir::new().expect("create source temp dir");
+        let dst_dir = tempfile::TempDir::new().expect("create destination temp dir");
+        let source = src_dir.path().join("abc.txt");
+        std::fs::write(&source, "abc").expect("write source");
+
+        let runtime = RuntimeOptions {
+            digests: true,
+            ..RuntimeOptions::default()
+        };
+        let plan = plan_copy(
+            vec![source],
+            dst_dir.path().join("abc.txt"),
+            CopyPolicy::default(),
+            runtime,
+        )
+        .expect("plan copy should succeed");
+
+        let mut digests = Vec::new();
+        let mut collector = |event: &CopyEvent| {
+            if let CopyEvent::ItemCompleted { digest, .. } = event {
+                digests.push(*digest);
+            }
+        };
+        let report = execute_plan(&plan, Some(&mut collector));
+
+        let expected = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        let digest = report.items[0].digest.expect("digest reported");
+        assert_eq!(digest.to_string(), expected);
+        assert_eq!(digests, vec![Some(digest)]);
+    }
+
     #[test]
     fn test_execute_plan_reports_failed_item() {
         let src_dir = tempfile::TempDir::new().expect("create source temp dir");
@@ -577,6 +1143,46 @@ mod tests {
         assert_eq!(report.items[0].error_code, Some(ErrorCode::AlreadyExists));
     }
 
+    #[test]
+    fn test_execute_plan_reports_drift() {
+        let src_dir = tempfile::TempDir::new().expect("create source temp dir");
+        let dst_dir = tempfile::TempDir::new().expect("create destination temp dir");
+        let changed = src_dir.path().join("changed.txt");
+        let taken = src_dir.path().join("taken.txt");
+        let stable = src_dir.path().join("stable.txt");
+        for source in [&changed, &taken, &stable] {
+            std::fs::write(source, "planned").expect("write source");
+        }
+
+        let plan = plan_copy(
+            vec![changed, taken, stable],
+            dst_dir.path().to_path_buf(),
+            CopyPolicy::default(),
+            RuntimeOptions::default(),
+        )
+        .expect("plan copy should succeed");
+        std::fs::write(src_dir.path().join("changed.txt"), "changed since").expect("modify source");
+        std::fs::write(dst_dir.path().join("taken.txt"), "other").expect("create destination");
+
+        let report = execute_plan(&plan, None);
+        let outcomes: Vec<_> = report.items.iter().map(|item| item.outcome).collect();
+        assert_eq!(
+            outcomes,
+            [
+                ItemOutcome::Failed,
+                ItemOutcome::Failed,
+                ItemOutcome::Copied
+            ]
+        );
+        assert_eq!(report.items[0].error_code, Some(ErrorCode::PlanDrift));
+        assert_eq!(report.items[1].error_code, Some(ErrorCode::PlanDrift));
+        assert_eq!(
+            std::fs::read_to_string(dst_dir.path().join("taken.txt")).expect("read destination"),
+            "other"
+        );
+        assert!(!dst_dir.path().join("changed.txt").exists());
+    }
+
     #[test]
     fn test_plan_copy_empty_sources() {
         let result = plan_copy(
@@ -683,6 +1289,36 @@ mod tests {
         assert_eq!(plan.items[0].reason, PlanReason::Exists);
     }
 
+    #[test]
+    fn test_classify_compare_dest_match() {
+        let src_dir = tempfile::TempDir::new().expect("create source temp dir");
+        let reference = tempfile::TempDir::new().expect("create reference temp dir");
+        let dst_dir = tempfile::TempDir::new().expect("create destination temp dir");
+        let source = src_dir.path().join("f.txt");
+        std::fs::write(&source, "released").expect("write source");
+        std::fs::write(reference.path().join("f.txt"), "released").expect("write reference");
+
+        let policy = CopyPolicy {
+            compare_dest: vec![reference.path().to_path_buf()],
+            checksum: true,
+            ..CopyPolicy::default()
+        };
+        let plan = plan_copy(
+            vec![source],
+            dst_dir.path().to_path_buf(),
+            policy,
+            RuntimeOptions::default(),
+        )
+        .expect("plan copy should succeed");
+
+        assert_eq!(plan.items[0].action, PlanAction::Skip);
+        assert_eq!(plan.items[0].reason, PlanReason::CompareDestMatch);
+
+        let report = execute_plan(&plan, None);
+        assert_eq!(report.items[0].outcome, ItemOutcome::Skipped);
+        assert!(!dst_dir.path().join("f.txt").exists());
+    }
+
     #[test]
     fn test_classify_error_conflict() {
         let src_dir = tempfile::TempDir::new().unwrap();
@@ -777,6 +1413,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_execute_plan_directory_into_existing_destination_uses_link_dest() {
+        let src_dir = tempfile::TempDir::new().unwrap();
+        let snapshot = tempfile::TempDir::new().unwrap();
+        let dst_dir = tempfile::TempDir::new().unwrap();
+        let sub = src_dir.path().join("mydir");
+        std::fs::create_dir(&sub).unwrap();
+        std::fs::write(sub.join("file.txt"), "content").unwrap();
+        // The snapshot mirrors the destination root, so it holds mydir/file.txt
+        crate::copy_dir(
+            &sub,
+            &snapshot.path().join("mydir"),
+            &CopyOptions::default(),
+        )
+        .unwrap();
+
+        let policy = CopyPolicy {
+            link_dest: vec![snapshot.path().to_path_buf()],
+            ..CopyPolicy::default()
+        };
+        let plan = plan_copy(
+            vec![sub],
+            dst_dir.path().to_path_buf(),
+            policy,
+            RuntimeOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(plan.items[0].destination, dst_dir.path().join("mydir"));
+
+        let report = execute_plan(&plan, None);
+        assert!(!report.has_failures());
+        assert_eq!(report.stats.files_linked, 1);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let linked = std::fs::metadata(dst_dir.path().join("mydir/file.txt")).unwrap();
+            let reference = std::fs::metadata(snapshot.path().join("mydir/file.txt")).unwrap();
+            assert_eq!(linked.ino(), reference.ino());
+        }
+    }
+
     #[test]
     fn test_execute_plan_directory_copy_failure() {
         // Try to copy a non-existent directory (plan it manually)
@@ -791,6 +1468,7 @@ mod tests {
                 action: PlanAction::Copy,
                 reason: PlanReason::NotExists,
                 estimated_bytes: 0,
+                source_mtime: None,
             }],
         };
 
@@ -888,10 +1566,32 @@ mod tests {
             warn_escaping_symlinks: true,
             block_escaping_symlinks: true,
             max_depth: Some(5),
+            backup: Some(BackupMode::Numbered),
+            link_dest: vec![PathBuf::from("/snapshots/previous")],
+            compare_dest: vec![PathBuf::from("/releases/1.0")],
+            checksum: true,
+            delta_threshold: Some(1 << 20),
+            delta_block_size: 4096,
+            resume_threshold: Some(1 << 30),
+            on_source_change: SourceChangePolicy::Retry { attempts: 2 },
+            atomic_publish: true,
+            transactional: true,
+            verify: true,
+            dedup: Some(DedupMode::Reflink),
         };
         let runtime = RuntimeOptions {
             parallel: 4,
+            auto_parallel: true,
             cancel_token: None,
+            manifest: None,
+            digests: true,
+            journal: None,
+            clean_stale_temps: Some(Duration::from_secs(3600)),
+            engine: CopyEngine::IoUring,
+            parallel_chunk_threshold: Some(1 << 30),
+            parallel_chunk_size: 1 << 20,
+            bwlimit: Some(200 << 20),
+            metadata_limit: Some(500),
         };
         let plan = CopyPlan {
             destination_root: PathBuf::from("/dst"),
@@ -911,6 +1611,31 @@ mod tests {
         assert!(options.warn_escaping_symlinks);
         assert!(options.block_escaping_symlinks);
         assert_eq!(options.max_depth, Some(5));
+        assert_eq!(options.backup, Some(BackupMode::Numbered));
+        assert_eq!(
+            options.link_dest,
+            vec![PathBuf::from("/snapshots/previous")]
+        );
+        assert!(options.checksum);
+        assert_eq!(options.delta_threshold, Some(1 << 20));
+        assert_eq!(options.resume_threshold, Some(1 << 30));
+        assert_eq!(options.delta_block_size, 4096);
+        assert_eq!(
+            options.on_source_change,
+            SourceChangePolicy::Retry { attempts: 2 }
+        );
+        assert!(options.atomic_publish);
+        assert!(options.transactional);
+        assert!(options.verify);
+        assert_eq!(options.dedup, Some(DedupMode::Reflink));
+        assert!(options.digests);
+        assert_eq!(options.clean_stale_temps, Some(Duration::from_secs(3600)));
+        assert_eq!(options.engine, CopyEngine::IoUring);
+        assert_eq!(options.parallel_chunk_threshold, Some(1 << 30));
+        assert_eq!(options.parallel_chunk_size, 1 << 20);
+        assert_eq!(options.bwlimit.as_ref().map(|l| l.rate()), Some(200 << 20));
+        assert_eq!(options.metadata_limit.as_ref().map(|l| l.rate()), Some(500));
         assert_eq!(options.parallel, 4);
+        assert!(options.auto_parallel);
     }
 }
@@ -0,0 +1,242 @@
+//! Reusable copy sessions.
+//!
+//! [`copy_dir`] and friends build a thread pool for every call whose
+//! `parallel` differs from the global rayon pool. A [`CopySession`] builds
+//! its pool once and runs every operation in it, which saves the thread
+//! start-up cost for services that run many small copies.
+
+use crate::copy::{CopyStats, copy_dir, copy_file, copy_file_with_stats, move_dir, move_file};
+use crate::error::Result;
+use crate::options::CopyOptions;
+use crate::plan_execute::{
+    CopyPlan, CopyReport, EventHandler, copy_options_from_plan, execute_plan_with,
+};
+use std::path::Path;
+
+/// A set of copy options bound to a persistent thread pool.
+///
+/// Every operation of the session runs in the same pool of
+/// [`parallel`](CopyOptions::parallel) threads and shares the session's
+/// state:
+///
+/// - **Thread pool**: built once by [`new`](Self::new) rather than per call.
+///   With [`CopyEngine::IoUring`](crate::CopyEngine::IoUring) the pool threads
+///   also keep their io_uring rings between calls.
+/// - **Rate limiters**: the [`bwlimit`](CopyOptions::bwlimit) and
+///   [`metadata_limit`](CopyOptions::metadata_limit) limiters are shared by
+///   all operations, so the limits hold across concurrent calls too.
+/// - **Reflink cache**: whether each filesystem supports reflinks is
+///   remembered for the whole process, so later copies skip the probe.
+///
+/// A session is `Sync`: it can be shared between threads, whose operations
+/// then share the pool.
+///
+/// # Example
+///
+/// ```no_run
+/// use parcopy::{CopyOptions, CopySession};
+/// use std::path::Path;
+///
+/// let session = CopySession::new(CopyOptions::default().with_parallel(8));
+/// for job in ["a", "b", "c"] {
+///     let src = Path::new("inbox").join(job);
+///     let dst = Path::new("archive").join(job);
+///     let stats = session.copy_dir(&src, &dst)?;
+///     println!("{job}: {} files", stats.files_copied);
+/// }
+/// # Ok::<(), parcopy::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct CopySession {
+    options: CopyOptions,
+    /// `None` when the global rayon pool already has `parallel` threads
+    pool: Option<rayon::ThreadPool>,
+}
+
+impl CopySession {
+    /// Create a session, building its thread pool.
+    ///
+    /// If the global rayon pool already has [`parallel`](CopyOptions::parallel)
+    /// threads, the session uses it instead. If the pool cannot be built, a
+    /// warning is reported and each operation falls back to building its own.
+    #[must_use]
+    pub fn new(options: CopyOptions) -> Self {
+        let pool = if options.parallel == rayon::current_num_threads() {
+            None
+        } else {
+            match rayon::ThreadPoolBuilder::new()
+                .num_threads(options.parallel)
+                .thread_name(|i| format!("parcopy-{i}"))
+                .build()
+            {
+                Ok(pool) => Some(pool),
+                Err(e) => {
+                    options.warn(&format!("Failed to build the session thread pool: {e}"));
+                    None
+                }
+            }
+        };
+        Self { options, pool }
+    }
+
+    /// The options every operation of the session uses.
+    #[must_use]
+    pub fn options(&self) -> &CopyOptions {
+        &self.options
+    }
+
+    /// Copy a single file, like [`copy_file`](crate::copy_file).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`copy_file`](crate::copy_file).
+    pub fn copy_file(&self, src: &Path, dst: &Path) -> Result<bool> {
+        self.install(|| copy_file(src, dst, &self.options))
+    }
+
+    /// Copy a single file and return statistics, like
+    /// [`copy_file_with_stats`](crate::copy_file_with_stats).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`copy_file_with_stats`](crate::copy_file_with_stats).
+    pub fn copy_file_with_stats(&self, src: &Path, dst: &Path) -> Result<CopyStats> {
+        self.install(|| copy_file_with_stats(src, dst, &self.options))
+    }
+
+    /// Copy a directory tree, like [`copy_dir`](crate::copy_dir).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`copy_dir`](crate::copy_dir).
+    pub fn copy_dir(&self, src: &Path, dst: &Path) -> Result<CopyStats> {
+        self.install(|| copy_dir(src, dst, &self.options))
+    }
+
+    /// Move a single file, like [`move_file`](crate::move_file).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`move_file`](crate::move_file).
+    pub fn move_file(&self, src: &Path, dst: &Path) -> Result<CopyStats> {
+        self.install(|| move_file(src, dst, &self.options))
+    }
+
+    /// Move a directory tree, like [`move_dir`](crate::move_dir).
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`move_dir`](crate::move_dir).
+    pub fn move_dir(&self, src: &Path, dst: &Path) -> Result<CopyStats> {
+        self.install(|| move_dir(src, dst, &self.options))
+    }
+
+    /// Execute a copy plan, like [`execute_plan`](crate::execute_plan).
+    ///
+    /// The plan's policy and runtime options apply, except that items are
+    /// copied in the session's pool with its parallelism, and the session's
+    /// rate limiters, if set, replace the plan's.
+    pub fn execute_plan(
+        &self,
+        plan: &CopyPlan,
+        handler: Option<&mut dyn EventHandler>,
+    ) -> CopyReport {
+        let mut options = copy_options_from_plan(plan);
+        options.parallel = self.options.parallel;
+        options.auto_parallel = self.options.auto_parallel;
+        if self.options.bwlimit.is_some() {
+            options.bwlimit.clone_from(&self.options.bwlimit);
+        }
+        if self.options.metadata_limit.is_some() {
+            options
+                .metadata_limit
+                .clone_from(&self.options.metadata_limit);
+        }
+        execute_plan_with(plan, handler, &options, self.pool.as_ref())
+    }
+
+    /// Run `f` in the session's pool.
+    ///
+    /// The copy functions called from `f` use the pool, e.g. to copy with
+    /// options other than the session's.
+    pub fn install<T: Send>(&self, f: impl FnOnce() -> T + Send) -> T {
+        match &self.pool {
+            Some(pool) => pool.install(f),
+            None => f(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CopyPolicy, RuntimeOptions, plan_copy};
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_session_reuses_its_pool() {
+        let session = CopySession::new(CopyOptions::default().with_parallel(3));
+        let calls: Vec<_> = (0..3)
+            .map(|_| {
+                session.install(|| {
+                    let name = std::thread::current().name().map(str::to_owned);
+                    (rayon::current_num_threads(), name)
+                })
+            })
+            .collect();
+        for (threads, name) in calls {
+            assert_eq!(threads, 3);
+            assert!(name.unwrap().starts_with("parcopy-"));
+        }
+    }
+
+    #[test]
+    fn test_session_copies_repeatedly() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("sub")).unwrap();
+        fs::write(src.join("a.txt"), "a").unwrap();
+        fs::write(src.join("sub/b.txt"), "b").unwrap();
+
+        let session = CopySession::new(CopyOptions::default().with_parallel(2));
+        for i in 0..5 {
+            let dst = dir.path().join(format!("dst-{i}"));
+            let stats = session.copy_dir(&src, &dst).unwrap();
+            assert_eq!(stats.files_copied, 2);
+            assert_eq!(fs::read_to_string(dst.join("sub/b.txt")).unwrap(), "b");
+        }
+
+        let file = dir.path().join("single.txt");
+        assert!(session.copy_file(&src.join("a.txt"), &file).unwrap());
+        let moved = dir.path().join("moved.txt");
+        session.move_file(&file, &moved).unwrap();
+        assert!(!file.exists());
+        assert_eq!(fs::read_to_string(&moved).unwrap(), "a");
+    }
+
+    #[test]
+    fn test_session_executes_plan() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let dst = dir.path().join("dst");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(src.join("a.txt"), "hello").unwrap();
+
+        let session = CopySession::new(
+            CopyOptions::default()
+                .with_parallel(2)
+                .with_bwlimit(1_000_000),
+        );
+        let plan = plan_copy(
+            vec![src],
+            dst.clone(),
+            CopyPolicy::default(),
+            RuntimeOptions::default(),
+        )
+        .unwrap();
+        let report = session.execute_plan(&plan, None);
+        assert!(!report.has_failures());
+        assert_eq!(fs::read_to_string(dst.join("a.txt")).unwrap(), "hello");
+    }
+}